@@ -9,7 +9,6 @@ use pulldown_cmark::{
 };
 use pulldown_cmark_writer::Line;
 use pulldown_cmark_writer::ast::custom::{BlockNode, InlineNode};
-use pulldown_cmark_writer::ast::writer::block_to_region;
 use pulldown_cmark_writer::ast::{Block, Inline, block_to_events, writer::blocks_to_markdown};
 use pulldown_cmark_writer::text::Region;
 use std::sync::Arc;
@@ -54,15 +53,9 @@ impl BlockNode for WarningBlock {
         events
     }
     fn to_region(&self) -> Region {
-        let mut region = Region::from_str(&format!("⚠️ **{}**\n", self.title));
-        for block in &self.content {
-            let block_region = block_to_region(block);
-            for line in block_region.lines() {
-                region.push_back_line(line);
-            }
-        }
-        region.prefix_each_line("> ".to_string());
-        region
+        // no direct rendering: the writer parses `to_events` and renders the
+        // resulting blockquote, nested list included
+        Region::new()
     }
 }
 
@@ -144,19 +137,17 @@ fn main() {
     };
 
     // Table
-    let table = Block::Table(
-        vec![Alignment::None, Alignment::None],
-        vec![
-            vec![
-                vec![Inline::Text(Region::from_str("Header 1"))],
-                vec![Inline::Text(Region::from_str("Header 2"))],
-            ],
-            vec![
-                vec![Inline::Text(Region::from_str("Cell 1"))],
-                vec![Inline::Text(Region::from_str("Cell 2"))],
-            ],
+    let table = Block::Table {
+        aligns: vec![Alignment::None, Alignment::None],
+        head: vec![
+            vec![Inline::Text(Region::from_str("Header 1"))],
+            vec![Inline::Text(Region::from_str("Header 2"))],
         ],
-    );
+        rows: vec![vec![
+            vec![Inline::Text(Region::from_str("Cell 1"))],
+            vec![Inline::Text(Region::from_str("Cell 2"))],
+        ]],
+    };
 
     let blocks = vec![heading, paragraph, warning_block, list, code_block, table];
 
@@ -0,0 +1,206 @@
+//! Fenced divs, the `:::` containers of Pandoc and remark-directive.
+//!
+//! ```markdown
+//! ::: warning
+//! Back up your data first.
+//! :::
+//! ```
+//!
+//! `FencedDivs` is a built-in `Extension` that parses such containers into
+//! `Admonition` nodes holding the blocks between the fences, and writes
+//! them back in the same syntax. As HTML, the kinds GitHub has alerts for
+//! (`note`, `tip`, `important`, `warning`, `caution`) render as alerts, any
+//! other kind as a `<div>` with the kind as its class.
+//!
+//! ```ignore
+//! let mut extensions = ExtensionRegistry::new();
+//! extensions.register(FencedDivs, ParserOptions::new());
+//! let blocks = extensions.parse(&events);
+//! ```
+//!
+//! As in Pandoc, fences are lines of three or more colons, the opening one
+//! followed by the kind, either as a word or as the first class of an
+//! attribute list (`::: {.note}`). Fenced divs nest, and a fence must be
+//! the first or last line of its paragraph. After a list or a blockquote,
+//! leave a blank line before the closing fence: markdown would otherwise
+//! continue the last paragraph of the list or quote with it.
+
+use crate::Region;
+use crate::ast::ParseContext;
+use crate::ast::block::{Block, block_to_events};
+use crate::ast::custom::BlockNode;
+use crate::ast::extension::Extension;
+use crate::ast::fenced::{line_text, scan_container};
+use crate::ast::parse::{HookResult, parse_events_to_blocks_with_hook};
+use crate::ast::writer::html::{HtmlOptions, blocks_to_html};
+use crate::ast::writer::{WriteContext, WriterOptions, write_blocks};
+use pulldown_cmark::{BlockQuoteKind, CowStr, Event, Tag, TagEnd};
+use std::any::Any;
+use std::sync::Arc;
+
+/// A fenced div: its kind and the blocks inside it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Admonition {
+    /// The word after the opening fence, e.g. `note`.
+    pub kind: String,
+    pub children: Vec<Block>,
+}
+
+impl Admonition {
+    pub fn new(kind: impl Into<String>, children: impl IntoIterator<Item = Block>) -> Self {
+        Admonition {
+            kind: kind.into(),
+            children: children.into_iter().collect(),
+        }
+    }
+
+    /// The GitHub alert of the same kind, if there is one.
+    fn alert_kind(&self) -> Option<BlockQuoteKind> {
+        match self.kind.to_ascii_lowercase().as_str() {
+            "note" => Some(BlockQuoteKind::Note),
+            "tip" => Some(BlockQuoteKind::Tip),
+            "important" => Some(BlockQuoteKind::Important),
+            "warning" => Some(BlockQuoteKind::Warning),
+            "caution" => Some(BlockQuoteKind::Caution),
+            _ => None,
+        }
+    }
+
+    fn render(&self, options: &WriterOptions) -> Region {
+        let mut body = String::new();
+        write_blocks(&mut body, &self.children, options).expect("writing to a String cannot fail");
+        let blank = "\n".repeat(options.blank_lines.max(1));
+        let mut md = format!("::: {}\n", self.kind);
+        if !body.trim().is_empty() {
+            md.push_str(&blank);
+            md.push_str(body.trim_end_matches('\n'));
+            md.push('\n');
+        }
+        md.push_str(&blank);
+        md.push_str(":::");
+        Region::from_str(&md)
+    }
+}
+
+fn fence_paragraph(text: String) -> [Event<'static>; 3] {
+    [
+        Event::Start(Tag::Paragraph),
+        Event::Text(CowStr::from(text)),
+        Event::End(TagEnd::Paragraph),
+    ]
+}
+
+impl BlockNode for Admonition {
+    /// The fences as paragraphs of their own around the events of the
+    /// children, as the source reads.
+    fn to_events(&self) -> Vec<Event<'static>> {
+        let mut events = fence_paragraph(format!("::: {}", self.kind)).to_vec();
+        events.extend(self.children.iter().flat_map(block_to_events));
+        events.extend(fence_paragraph(":::".to_string()));
+        events
+    }
+
+    fn to_region(&self) -> Region {
+        self.render(&WriterOptions::default())
+    }
+
+    fn to_region_in(&self, ctx: &WriteContext) -> Region {
+        self.render(ctx.options)
+    }
+
+    fn to_html(&self) -> Option<String> {
+        let options = HtmlOptions::default();
+        Some(match self.alert_kind() {
+            Some(kind) => blocks_to_html(
+                &[Block::Alert {
+                    kind,
+                    children: self.children.clone(),
+                }],
+                &options,
+            ),
+            None => format!(
+                "<div class=\"{}\">\n{}</div>\n",
+                self.kind.replace('&', "&amp;").replace('"', "&quot;"),
+                blocks_to_html(&self.children, &options)
+            ),
+        })
+    }
+
+    fn node_eq(&self, other: &dyn BlockNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<Admonition>() == Some(self)
+    }
+}
+
+/// The `:::` syntax, parsed into `Admonition`s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FencedDivs;
+
+/// The kind of an opening fence, `::: kind` or `::: {.kind}`.
+fn opening_kind(line: &[Event]) -> Option<String> {
+    let text = line_text(line)?;
+    let text = text.trim();
+    let rest = text.trim_start_matches(':');
+    if text.len() - rest.len() < 3 {
+        return None;
+    }
+    let rest = rest.trim_end_matches(':').trim();
+    let kind = match rest.strip_prefix('{') {
+        Some(attrs) => attrs
+            .trim_end_matches('}')
+            .split_whitespace()
+            .find_map(|a| a.strip_prefix('.'))?,
+        None => rest,
+    };
+    (!kind.is_empty() && !kind.contains(char::is_whitespace)).then(|| kind.to_string())
+}
+
+impl FencedDivs {
+    fn parse_at(&self, events: &[Event], ctx: &ParseContext) -> Option<(usize, Admonition)> {
+        if ctx.parent_collects_inlines {
+            return None;
+        }
+        let (n, kind, inner) = scan_container(events, opening_kind, false)?;
+        Some((
+            n,
+            Admonition {
+                kind,
+                children: self.parse_inner(&inner),
+            },
+        ))
+    }
+
+    /// Parse the blocks between the fences, nested fenced divs included.
+    fn parse_inner(&self, events: &[Event<'static>]) -> Vec<Block> {
+        let mut hook = |evs: &[Event], _i: usize, ctx: &ParseContext| {
+            self.parse_at(evs, ctx)
+                .map(|(n, node)| (n, HookResult::Block(Block::Custom(Arc::new(node)))))
+        };
+        parse_events_to_blocks_with_hook(events, Some(&mut hook))
+    }
+}
+
+impl Extension for FencedDivs {
+    type Node = Admonition;
+
+    fn try_parse(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, Admonition)> {
+        self.parse_at(events, ctx)
+    }
+
+    fn write_markdown(&self, node: &Admonition) -> Region {
+        node.to_region()
+    }
+
+    fn write_markdown_in(&self, node: &Admonition, ctx: &WriteContext) -> Region {
+        node.to_region_in(ctx)
+    }
+
+    fn write_html(&self, node: &Admonition) -> Option<String> {
+        node.to_html()
+    }
+}
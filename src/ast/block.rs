@@ -1,11 +1,89 @@
 use crate::ast::custom::BlockNode;
 use crate::ast::inline::{Inline, inline_to_events};
+use crate::ast::writer::plaintext::inlines_text;
 use crate::text::Region;
-use pulldown_cmark::{Alignment, CodeBlockKind, CowStr, Event, HeadingLevel, Tag, TagEnd};
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, MetadataBlockKind,
+    Options, Parser, Tag, TagEnd,
+};
 use std::sync::Arc;
 
-/// Block level AST nodes.
+/// Direction of `Block::sort_by_column`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// Ready-made keys for `Block::sort_by_column`, computed from the plain
+/// text of a cell:
+///
+/// ```ignore
+/// table.sort_by_column(2, SortOrder::Descending, CellKey::numeric);
+/// ```
 #[derive(Clone, Debug)]
+pub struct CellKey {
+    number: Option<f64>,
+    text: String,
+}
+
+impl CellKey {
+    /// Compare cells by their text.
+    pub fn text(cell: &[Inline]) -> CellKey {
+        CellKey {
+            number: None,
+            text: inlines_text(cell).trim().to_string(),
+        }
+    }
+
+    /// Compare cells starting with a number, like `1,024` or `-3.5 ms`, by
+    /// that number, then by text. They come before cells without one.
+    pub fn numeric(cell: &[Inline]) -> CellKey {
+        let mut key = CellKey::text(cell);
+        let digits: String = key
+            .text
+            .char_indices()
+            .take_while(|&(i, c)| {
+                c.is_ascii_digit() || matches!(c, '.' | ',') || (i == 0 && matches!(c, '-' | '+'))
+            })
+            .map(|(_, c)| c)
+            .filter(|&c| c != ',')
+            .collect();
+        key.number = digits.parse().ok();
+        key
+    }
+}
+
+// Equality follows `Ord`, which compares numbers with `total_cmp`, so
+// that NaN equals itself and `-0.0` does not equal `0.0`.
+impl PartialEq for CellKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for CellKey {}
+
+impl PartialOrd for CellKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CellKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let number = match (self.number, other.number) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        };
+        number.then_with(|| self.text.cmp(&other.text))
+    }
+}
+
+/// Block level AST nodes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Block {
     Paragraph(Vec<Inline>),
     Heading {
@@ -16,7 +94,16 @@ pub enum Block {
         children: Vec<Inline>,
     },
     BlockQuote(Vec<Block>),
+    /// A GitHub alert: a block quote opened by `[!NOTE]`, `[!TIP]`, ...
+    Alert {
+        kind: BlockQuoteKind,
+        children: Vec<Block>,
+    },
     CodeBlock {
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::ast::serde_impl::code_block_kind")
+        )]
         kind: CodeBlockKind<'static>,
         content: Region,
     },
@@ -30,11 +117,261 @@ pub enum Block {
     FootnoteDefinition(String, Vec<Block>),
     TablePlaceholder(Vec<Alignment>),
     TableRow(Vec<Vec<crate::ast::inline::Inline>>),
-    Table(Vec<Alignment>, Vec<Vec<Vec<crate::ast::inline::Inline>>>),
+    /// The header row of a table while it is being parsed.
+    TableHead(Vec<Vec<crate::ast::inline::Inline>>),
+    /// A table: the column alignments, the header cells and the body rows.
+    /// The writer always emits a header row, padding a missing or short
+    /// one with empty cells.
+    Table {
+        aligns: Vec<Alignment>,
+        head: Vec<Vec<crate::ast::inline::Inline>>,
+        rows: Vec<Vec<Vec<crate::ast::inline::Inline>>>,
+    },
+    /// Front matter: a YAML (`---`) or TOML (`+++`) fenced metadata block.
+    /// `content` holds the text between the fences.
+    Metadata {
+        kind: MetadataBlockKind,
+        content: Region,
+    },
+    /// An HTML comment block. Holds the text between `<!--` and `-->`.
+    Comment(Region),
+    /// Markdown emitted verbatim by the writer, without any escaping. Only
+    /// the prefixes of enclosing containers are applied to its lines.
+    Raw(Region),
+    /// A link reference definition (`[id]: dest "title"`). Definitions
+    /// produce no events: the parser resolves references into the links
+    /// themselves (see `parse::link_definition_blocks` to recover them).
+    LinkDefinition {
+        id: String,
+        dest: String,
+        title: String,
+    },
     /// A user-provided custom block node.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::ast::serde_impl::custom_block")
+    )]
     Custom(Arc<dyn BlockNode + 'static>),
 }
 
+// Every field compares totally (pulldown's `CodeBlockKind`, `LinkType` and
+// `Alignment` only lack the `Eq` marker), so equality is an equivalence.
+impl Eq for Block {}
+
+impl Block {
+    /// A paragraph holding `children`; string items become text.
+    pub fn paragraph<I>(children: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Inline>,
+    {
+        Block::Paragraph(children.into_iter().map(Into::into).collect())
+    }
+
+    /// A heading of the given level without id, classes or attributes.
+    pub fn heading<I>(level: HeadingLevel, children: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Inline>,
+    {
+        Block::Heading {
+            level,
+            id: None,
+            classes: Vec::new(),
+            attrs: Vec::new(),
+            children: children.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// A fenced code block. `lang` may be empty. The content is stored with
+    /// a trailing newline, as the parser reports it.
+    pub fn code(lang: &str, src: &str) -> Self {
+        let content = if src.ends_with('\n') {
+            Region::from_str(src)
+        } else {
+            Region::from_str(&format!("{}\n", src))
+        };
+        Block::CodeBlock {
+            kind: CodeBlockKind::Fenced(CowStr::from(lang.to_string())),
+            content,
+        }
+    }
+
+    /// A blockquote around `children`.
+    pub fn block_quote(children: impl IntoIterator<Item = Block>) -> Self {
+        Block::BlockQuote(children.into_iter().collect())
+    }
+
+    /// A GitHub alert of the given kind around `children`.
+    pub fn alert(kind: BlockQuoteKind, children: impl IntoIterator<Item = Block>) -> Self {
+        Block::Alert {
+            kind,
+            children: children.into_iter().collect(),
+        }
+    }
+
+    /// A table of `rows`, the first of which is the header. Cells take one
+    /// inline each; rows shorter than the longest are padded with empty
+    /// cells.
+    ///
+    /// ```ignore
+    /// let table = Block::table_from_rows([["crate", "version"], ["serde", "1.0"]]);
+    /// ```
+    pub fn table_from_rows<R, C>(rows: R) -> Self
+    where
+        R: IntoIterator<Item = C>,
+        C: IntoIterator,
+        C::Item: Into<Inline>,
+    {
+        let mut rows: Vec<Vec<Vec<Inline>>> = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(|cell| vec![cell.into()]).collect())
+            .collect();
+        let cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+        for row in &mut rows {
+            row.resize_with(cols, Vec::new);
+        }
+        let head = if rows.is_empty() {
+            Vec::new()
+        } else {
+            rows.remove(0)
+        };
+        Block::Table {
+            aligns: vec![Alignment::None; cols],
+            head,
+            rows,
+        }
+    }
+
+    /// A table of the records of comma-separated `data`. With `has_header`
+    /// the first record is the header, otherwise the header is left empty.
+    /// Values are taken as plain text: pipes are escaped and line breaks
+    /// become spaces.
+    #[cfg(feature = "csv")]
+    pub fn table_from_csv<R: std::io::Read>(data: R, has_header: bool) -> csv::Result<Self> {
+        Block::table_from_delimited(data, b',', has_header)
+    }
+
+    /// A table of the records of tab-separated `data`, as `table_from_csv`.
+    #[cfg(feature = "csv")]
+    pub fn table_from_tsv<R: std::io::Read>(data: R, has_header: bool) -> csv::Result<Self> {
+        Block::table_from_delimited(data, b'\t', has_header)
+    }
+
+    #[cfg(feature = "csv")]
+    fn table_from_delimited<R: std::io::Read>(
+        data: R,
+        delimiter: u8,
+        has_header: bool,
+    ) -> csv::Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(data);
+        let mut records = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let cells: Vec<Inline> = record
+                .iter()
+                .map(|value| {
+                    let text = value.replace('|', "\\|");
+                    Inline::text(&text.lines().collect::<Vec<_>>().join(" "))
+                })
+                .collect();
+            records.push(cells);
+        }
+        let mut table = Block::table_from_rows(records);
+        if !has_header
+            && let Block::Table { head, rows, .. } = &mut table
+            && !head.is_empty()
+        {
+            rows.insert(0, std::mem::take(head));
+        }
+        Ok(table)
+    }
+
+    /// Sort the body rows of a table by their cell in `column`, compared by
+    /// what `key` returns for it. The sort is stable, so rows with equal
+    /// keys keep their order, which allows sorting by several columns in
+    /// turn. A row without that column gets the key of an empty cell.
+    /// Blocks other than `Block::Table` are left alone.
+    pub fn sort_by_column<K, F>(&mut self, column: usize, order: SortOrder, mut key: F)
+    where
+        K: Ord,
+        F: FnMut(&[Inline]) -> K,
+    {
+        let Block::Table { rows, .. } = self else {
+            return;
+        };
+        let mut keyed: Vec<(K, Vec<Vec<Inline>>)> = std::mem::take(rows)
+            .into_iter()
+            .map(|row| (key(row.get(column).map_or(&[], Vec::as_slice)), row))
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| match order {
+            SortOrder::Ascending => a.cmp(b),
+            SortOrder::Descending => b.cmp(a),
+        });
+        *rows = keyed.into_iter().map(|(_, row)| row).collect();
+    }
+
+    /// Clone the block, copying custom nodes instead of sharing their `Arc`
+    /// with the original, so the copy holds no state shared with it.
+    pub fn deep_clone(&self) -> Block {
+        let mut b = self.clone();
+        b.unshare_custom();
+        b
+    }
+
+    fn unshare_custom(&mut self) {
+        fn inlines(inls: &mut [Inline]) {
+            inls.iter_mut().for_each(Inline::unshare_custom);
+        }
+        fn blocks(bs: &mut [Block]) {
+            bs.iter_mut().for_each(Block::unshare_custom);
+        }
+        match self {
+            Block::Paragraph(inls) | Block::Heading { children: inls, .. } => inlines(inls),
+            Block::BlockQuote(children)
+            | Block::Alert { children, .. }
+            | Block::Item(children)
+            | Block::FootnoteDefinition(_, children) => blocks(children),
+            Block::List { items, .. } => items.iter_mut().for_each(|i| blocks(i)),
+            Block::TableRow(cells) | Block::TableHead(cells) => {
+                cells.iter_mut().for_each(|c| inlines(c))
+            }
+            Block::Table { head, rows, .. } => {
+                head.iter_mut().for_each(|c| inlines(c));
+                rows.iter_mut().flatten().for_each(|c| inlines(c))
+            }
+            Block::Custom(node) => *node = Arc::from(dyn_clone::clone_box(&**node)),
+            Block::CodeBlock { .. }
+            | Block::HtmlBlock(_)
+            | Block::Rule
+            | Block::TablePlaceholder(_)
+            | Block::Metadata { .. }
+            | Block::Comment(_)
+            | Block::Raw(_)
+            | Block::LinkDefinition { .. } => {}
+        }
+    }
+}
+
+/// Events of a table row (or the header row, for `Tag::TableHead`).
+fn table_row_events(tag: Tag<'static>, cells: &[Vec<Inline>]) -> Vec<Event<'static>> {
+    let end = tag.to_end();
+    let mut out = vec![Event::Start(tag)];
+    for cell in cells {
+        out.push(Event::Start(Tag::TableCell));
+        for inl in cell {
+            out.extend(inline_to_events(inl));
+        }
+        out.push(Event::End(TagEnd::TableCell));
+    }
+    out.push(Event::End(end));
+    out
+}
+
 /// Convert a `Block` into pulldown-cmark events (owned, 'static).
 pub fn block_to_events(b: &Block) -> Vec<Event<'static>> {
     match b {
@@ -74,6 +411,14 @@ pub fn block_to_events(b: &Block) -> Vec<Event<'static>> {
             out.push(Event::End(TagEnd::BlockQuote(None)));
             out
         }
+        Block::Alert { kind, children } => {
+            let mut out = vec![Event::Start(Tag::BlockQuote(Some(*kind)))];
+            for ch in children {
+                out.extend(block_to_events(ch));
+            }
+            out.push(Event::End(TagEnd::BlockQuote(Some(*kind))));
+            out
+        }
         Block::CodeBlock { kind, content } => {
             let mut out = vec![Event::Start(Tag::CodeBlock(kind.clone()))];
             // each line as Html/Text event is fine; we emit a single Text event
@@ -119,34 +464,54 @@ pub fn block_to_events(b: &Block) -> Vec<Event<'static>> {
                 Event::End(TagEnd::Table),
             ]
         }
-        Block::TableRow(cells) => {
-            let mut out = vec![Event::Start(Tag::TableRow)];
-            for cell in cells {
-                out.push(Event::Start(Tag::TableCell));
-                for inl in cell {
-                    out.extend(inline_to_events(inl));
-                }
-                out.push(Event::End(TagEnd::TableCell));
-            }
-            out.push(Event::End(TagEnd::TableRow));
-            out
-        }
-        Block::Table(aligns, rows) => {
+        Block::TableRow(cells) => table_row_events(Tag::TableRow, cells),
+        Block::TableHead(cells) => table_row_events(Tag::TableHead, cells),
+        Block::Table { aligns, head, rows } => {
             let mut out = vec![Event::Start(Tag::Table(aligns.clone()))];
+            out.extend(table_row_events(Tag::TableHead, head));
             for row in rows {
-                out.push(Event::Start(Tag::TableRow));
-                for cell in row {
-                    out.push(Event::Start(Tag::TableCell));
-                    for inl in cell {
-                        out.extend(inline_to_events(inl));
-                    }
-                    out.push(Event::End(TagEnd::TableCell));
-                }
-                out.push(Event::End(TagEnd::TableRow));
+                out.extend(table_row_events(Tag::TableRow, row));
             }
             out.push(Event::End(TagEnd::Table));
             out
         }
+        Block::Metadata { kind, content } => {
+            let mut text = content.apply();
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            vec![
+                Event::Start(Tag::MetadataBlock(*kind)),
+                Event::Text(CowStr::from(text)),
+                Event::End(TagEnd::MetadataBlock(*kind)),
+            ]
+        }
+        Block::Comment(body) => {
+            let html = format!("<!--{}-->\n", body.apply());
+            let mut out = vec![Event::Start(Tag::HtmlBlock)];
+            for line in html.split_inclusive('\n') {
+                out.push(Event::Html(CowStr::from(line.to_string())));
+            }
+            out.push(Event::End(TagEnd::HtmlBlock));
+            out
+        }
+        Block::Raw(r) => {
+            let src = r.apply();
+            Parser::new_ext(&src, raw_parse_options())
+                .map(|e| e.into_static())
+                .collect()
+        }
+        Block::LinkDefinition { .. } => Vec::new(),
         Block::Custom(c) => c.to_events(),
     }
 }
+
+/// Extensions enabled when raw markdown has to be turned back into events.
+pub(crate) fn raw_parse_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_MATH
+        | Options::ENABLE_GFM
+}
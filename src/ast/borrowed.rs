@@ -0,0 +1,577 @@
+//! Zero-copy AST that borrows its text from the parsed events.
+//!
+//! The owned `Block`/`Inline` tree copies every piece of text into a
+//! `Region`. When a caller only wants to inspect a document and emit it
+//! again, the types in this module keep the `CowStr`s the parser produced
+//! instead, so text borrowed from the source stays borrowed. Nodes mirror
+//! the event stream closely enough that `to_events` reproduces the events
+//! they were built from.
+//!
+//! `into_static` detaches a tree from the source buffer, and
+//! `to_owned_blocks` converts it to the owned AST for writing.
+
+use crate::ast::parse::parse_events_to_blocks;
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, LinkType,
+    MetadataBlockKind, Tag,
+};
+
+/// Block level node borrowing its text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block<'a> {
+    Paragraph(Vec<Inline<'a>>),
+    /// Inline content that sits directly in a container without a paragraph
+    /// around it, as in tight list items.
+    Plain(Vec<Inline<'a>>),
+    Heading {
+        level: HeadingLevel,
+        id: Option<CowStr<'a>>,
+        classes: Vec<CowStr<'a>>,
+        attrs: Vec<(CowStr<'a>, Option<CowStr<'a>>)>,
+        children: Vec<Inline<'a>>,
+    },
+    BlockQuote(Option<BlockQuoteKind>, Vec<Block<'a>>),
+    /// Code block content in the chunks the parser reported it in.
+    CodeBlock {
+        kind: CodeBlockKind<'a>,
+        content: Vec<CowStr<'a>>,
+    },
+    HtmlBlock(Vec<CowStr<'a>>),
+    List {
+        start: Option<u64>,
+        items: Vec<Vec<Block<'a>>>,
+    },
+    Rule,
+    FootnoteDefinition(CowStr<'a>, Vec<Block<'a>>),
+    Table {
+        aligns: Vec<Alignment>,
+        head: Vec<Vec<Inline<'a>>>,
+        rows: Vec<Vec<Vec<Inline<'a>>>>,
+    },
+    Metadata {
+        kind: MetadataBlockKind,
+        content: Vec<CowStr<'a>>,
+    },
+    /// Any other container (definition lists, ...), kept with its tag.
+    Other(Tag<'a>, Vec<Block<'a>>),
+}
+
+/// Inline level node borrowing its text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline<'a> {
+    Text(CowStr<'a>),
+    Code(CowStr<'a>),
+    InlineHtml(CowStr<'a>),
+    Html(CowStr<'a>),
+    SoftBreak,
+    HardBreak,
+    TaskListMarker(bool),
+    Emphasis(Vec<Inline<'a>>),
+    Strong(Vec<Inline<'a>>),
+    Strikethrough(Vec<Inline<'a>>),
+    Subscript(Vec<Inline<'a>>),
+    Superscript(Vec<Inline<'a>>),
+    Link {
+        link_type: LinkType,
+        dest: CowStr<'a>,
+        title: CowStr<'a>,
+        id: CowStr<'a>,
+        children: Vec<Inline<'a>>,
+    },
+    Image {
+        link_type: LinkType,
+        dest: CowStr<'a>,
+        title: CowStr<'a>,
+        id: CowStr<'a>,
+        children: Vec<Inline<'a>>,
+    },
+    FootnoteReference(CowStr<'a>),
+    InlineMath(CowStr<'a>),
+    DisplayMath(CowStr<'a>),
+}
+
+/// Build a borrowed tree from an event stream. Events are consumed by
+/// value, so passing a `Parser` directly moves its `CowStr`s into the tree
+/// without copying. An `End` closing nothing is skipped, as
+/// `parse_events_to_blocks` does.
+pub fn parse_events<'a>(events: impl IntoIterator<Item = Event<'a>>) -> Vec<Block<'a>> {
+    let mut it = events.into_iter().peekable();
+    let mut out = Vec::new();
+    // `parse_blocks` stops at the first `End` it does not open
+    while it.peek().is_some() {
+        out.extend(parse_blocks(&mut it));
+    }
+    out
+}
+
+/// Convert blocks back into the events they represent.
+pub fn blocks_to_events<'a>(blocks: &[Block<'a>]) -> Vec<Event<'a>> {
+    let mut out = Vec::new();
+    for b in blocks {
+        b.push_events(&mut out);
+    }
+    out
+}
+
+/// Convert borrowed blocks into the owned AST, e.g. to write them with
+/// `blocks_to_markdown`.
+pub fn to_owned_blocks(blocks: &[Block<'_>]) -> Vec<crate::ast::Block> {
+    parse_events_to_blocks(&blocks_to_events(blocks))
+}
+
+fn is_inline_tag(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis
+            | Tag::Strong
+            | Tag::Strikethrough
+            | Tag::Subscript
+            | Tag::Superscript
+            | Tag::Link { .. }
+            | Tag::Image { .. }
+    )
+}
+
+/// Parse blocks up to (and including) the closing `End` of the enclosing
+/// container, or to the end of the stream at top level.
+fn parse_blocks<'a>(it: &mut impl Iterator<Item = Event<'a>>) -> Vec<Block<'a>> {
+    let mut out = Vec::new();
+    let mut plain: Vec<Inline<'a>> = Vec::new();
+    while let Some(ev) = it.next() {
+        let block = match ev {
+            Event::End(_) => break,
+            Event::Start(tag) if is_inline_tag(&tag) => {
+                plain.push(parse_inline_tag(tag, it));
+                continue;
+            }
+            Event::Start(tag) => parse_block_tag(tag, it),
+            Event::Rule => Block::Rule,
+            other => {
+                if let Some(inl) = leaf_inline(other) {
+                    plain.push(inl);
+                }
+                continue;
+            }
+        };
+        if !plain.is_empty() {
+            out.push(Block::Plain(std::mem::take(&mut plain)));
+        }
+        out.push(block);
+    }
+    if !plain.is_empty() {
+        out.push(Block::Plain(plain));
+    }
+    out
+}
+
+/// Collect the text of a leaf block up to its closing `End`.
+fn parse_text<'a>(it: &mut impl Iterator<Item = Event<'a>>) -> Vec<CowStr<'a>> {
+    let mut out = Vec::new();
+    for ev in it.by_ref() {
+        match ev {
+            Event::End(_) => break,
+            Event::Text(t) | Event::Html(t) => out.push(t),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn parse_rows<'a>(it: &mut impl Iterator<Item = Event<'a>>) -> Vec<Vec<Inline<'a>>> {
+    let mut cells = Vec::new();
+    while let Some(ev) = it.next() {
+        match ev {
+            Event::Start(Tag::TableCell) => cells.push(parse_inlines(it)),
+            Event::End(_) => break,
+            _ => {}
+        }
+    }
+    cells
+}
+
+fn parse_block_tag<'a>(tag: Tag<'a>, it: &mut impl Iterator<Item = Event<'a>>) -> Block<'a> {
+    match tag {
+        Tag::Paragraph => Block::Paragraph(parse_inlines(it)),
+        Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        } => Block::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+            children: parse_inlines(it),
+        },
+        Tag::BlockQuote(kind) => Block::BlockQuote(kind, parse_blocks(it)),
+        Tag::CodeBlock(kind) => Block::CodeBlock {
+            kind,
+            content: parse_text(it),
+        },
+        Tag::HtmlBlock => Block::HtmlBlock(parse_text(it)),
+        Tag::MetadataBlock(kind) => Block::Metadata {
+            kind,
+            content: parse_text(it),
+        },
+        Tag::List(start) => {
+            let mut items = Vec::new();
+            while let Some(ev) = it.next() {
+                match ev {
+                    Event::Start(Tag::Item) => items.push(parse_blocks(it)),
+                    Event::End(_) => break,
+                    _ => {}
+                }
+            }
+            Block::List { start, items }
+        }
+        Tag::FootnoteDefinition(label) => Block::FootnoteDefinition(label, parse_blocks(it)),
+        Tag::Table(aligns) => {
+            let mut head = Vec::new();
+            let mut rows = Vec::new();
+            while let Some(ev) = it.next() {
+                match ev {
+                    Event::Start(Tag::TableHead) => head = parse_rows(it),
+                    Event::Start(Tag::TableRow) => rows.push(parse_rows(it)),
+                    Event::End(_) => break,
+                    _ => {}
+                }
+            }
+            Block::Table { aligns, head, rows }
+        }
+        other => Block::Other(other, parse_blocks(it)),
+    }
+}
+
+fn parse_inlines<'a>(it: &mut impl Iterator<Item = Event<'a>>) -> Vec<Inline<'a>> {
+    let mut out = Vec::new();
+    while let Some(ev) = it.next() {
+        match ev {
+            Event::End(_) => break,
+            Event::Start(tag) if is_inline_tag(&tag) => out.push(parse_inline_tag(tag, it)),
+            // a block inside inline content is not produced by the parser;
+            // keep its inlines rather than losing them
+            Event::Start(_) => out.extend(parse_inlines(it)),
+            other => out.extend(leaf_inline(other)),
+        }
+    }
+    out
+}
+
+fn parse_inline_tag<'a>(tag: Tag<'a>, it: &mut impl Iterator<Item = Event<'a>>) -> Inline<'a> {
+    let children = parse_inlines(it);
+    match tag {
+        Tag::Emphasis => Inline::Emphasis(children),
+        Tag::Strong => Inline::Strong(children),
+        Tag::Strikethrough => Inline::Strikethrough(children),
+        Tag::Subscript => Inline::Subscript(children),
+        Tag::Superscript => Inline::Superscript(children),
+        Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        } => Inline::Link {
+            link_type,
+            dest: dest_url,
+            title,
+            id,
+            children,
+        },
+        Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        } => Inline::Image {
+            link_type,
+            dest: dest_url,
+            title,
+            id,
+            children,
+        },
+        _ => unreachable!("parse_inline_tag called with a block tag"),
+    }
+}
+
+fn leaf_inline(ev: Event<'_>) -> Option<Inline<'_>> {
+    Some(match ev {
+        Event::Text(t) => Inline::Text(t),
+        Event::Code(t) => Inline::Code(t),
+        Event::InlineHtml(t) => Inline::InlineHtml(t),
+        Event::Html(t) => Inline::Html(t),
+        Event::SoftBreak => Inline::SoftBreak,
+        Event::HardBreak => Inline::HardBreak,
+        Event::TaskListMarker(b) => Inline::TaskListMarker(b),
+        Event::FootnoteReference(t) => Inline::FootnoteReference(t),
+        Event::InlineMath(t) => Inline::InlineMath(t),
+        Event::DisplayMath(t) => Inline::DisplayMath(t),
+        Event::Start(_) | Event::End(_) | Event::Rule => return None,
+    })
+}
+
+fn push_wrapped<'a>(
+    tag: Tag<'a>,
+    out: &mut Vec<Event<'a>>,
+    body: impl FnOnce(&mut Vec<Event<'a>>),
+) {
+    let end = tag.to_end();
+    out.push(Event::Start(tag));
+    body(out);
+    out.push(Event::End(end));
+}
+
+fn push_inlines<'a>(inls: &[Inline<'a>], out: &mut Vec<Event<'a>>) {
+    for inl in inls {
+        inl.push_events(out);
+    }
+}
+
+fn push_blocks<'a>(blocks: &[Block<'a>], out: &mut Vec<Event<'a>>) {
+    for b in blocks {
+        b.push_events(out);
+    }
+}
+
+impl<'a> Block<'a> {
+    /// The events this block represents.
+    pub fn to_events(&self) -> Vec<Event<'a>> {
+        let mut out = Vec::new();
+        self.push_events(&mut out);
+        out
+    }
+
+    fn push_events(&self, out: &mut Vec<Event<'a>>) {
+        match self {
+            Block::Paragraph(inls) => push_wrapped(Tag::Paragraph, out, |o| push_inlines(inls, o)),
+            Block::Plain(inls) => push_inlines(inls, out),
+            Block::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+                children,
+            } => {
+                let tag = Tag::Heading {
+                    level: *level,
+                    id: id.clone(),
+                    classes: classes.clone(),
+                    attrs: attrs.clone(),
+                };
+                push_wrapped(tag, out, |o| push_inlines(children, o))
+            }
+            Block::BlockQuote(kind, children) => {
+                push_wrapped(Tag::BlockQuote(*kind), out, |o| push_blocks(children, o))
+            }
+            Block::CodeBlock { kind, content } => {
+                push_wrapped(Tag::CodeBlock(kind.clone()), out, |o| {
+                    o.extend(content.iter().cloned().map(Event::Text))
+                })
+            }
+            Block::HtmlBlock(content) => push_wrapped(Tag::HtmlBlock, out, |o| {
+                o.extend(content.iter().cloned().map(Event::Html))
+            }),
+            Block::Metadata { kind, content } => {
+                push_wrapped(Tag::MetadataBlock(*kind), out, |o| {
+                    o.extend(content.iter().cloned().map(Event::Text))
+                })
+            }
+            Block::List { start, items } => push_wrapped(Tag::List(*start), out, |o| {
+                for item in items {
+                    push_wrapped(Tag::Item, o, |o| push_blocks(item, o));
+                }
+            }),
+            Block::Rule => out.push(Event::Rule),
+            Block::FootnoteDefinition(label, children) => {
+                push_wrapped(Tag::FootnoteDefinition(label.clone()), out, |o| {
+                    push_blocks(children, o)
+                })
+            }
+            Block::Table { aligns, head, rows } => {
+                let push_cells = |cells: &[Vec<Inline<'a>>], o: &mut Vec<Event<'a>>| {
+                    for cell in cells {
+                        push_wrapped(Tag::TableCell, o, |o| push_inlines(cell, o));
+                    }
+                };
+                push_wrapped(Tag::Table(aligns.clone()), out, |o| {
+                    push_wrapped(Tag::TableHead, o, |o| push_cells(head, o));
+                    for row in rows {
+                        push_wrapped(Tag::TableRow, o, |o| push_cells(row, o));
+                    }
+                })
+            }
+            Block::Other(tag, children) => {
+                push_wrapped(tag.clone(), out, |o| push_blocks(children, o))
+            }
+        }
+    }
+
+    /// Detach the block from the source it borrows from.
+    pub fn into_static(self) -> Block<'static> {
+        fn blocks(bs: Vec<Block<'_>>) -> Vec<Block<'static>> {
+            bs.into_iter().map(Block::into_static).collect()
+        }
+        fn texts(ts: Vec<CowStr<'_>>) -> Vec<CowStr<'static>> {
+            ts.into_iter().map(CowStr::into_static).collect()
+        }
+        match self {
+            Block::Paragraph(inls) => Block::Paragraph(inlines_into_static(inls)),
+            Block::Plain(inls) => Block::Plain(inlines_into_static(inls)),
+            Block::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+                children,
+            } => Block::Heading {
+                level,
+                id: id.map(CowStr::into_static),
+                classes: texts(classes),
+                attrs: attrs
+                    .into_iter()
+                    .map(|(k, v)| (k.into_static(), v.map(CowStr::into_static)))
+                    .collect(),
+                children: inlines_into_static(children),
+            },
+            Block::BlockQuote(kind, children) => Block::BlockQuote(kind, blocks(children)),
+            Block::CodeBlock { kind, content } => Block::CodeBlock {
+                kind: kind.into_static(),
+                content: texts(content),
+            },
+            Block::HtmlBlock(content) => Block::HtmlBlock(texts(content)),
+            Block::List { start, items } => Block::List {
+                start,
+                items: items.into_iter().map(blocks).collect(),
+            },
+            Block::Rule => Block::Rule,
+            Block::FootnoteDefinition(label, children) => {
+                Block::FootnoteDefinition(label.into_static(), blocks(children))
+            }
+            Block::Table { aligns, head, rows } => Block::Table {
+                aligns,
+                head: head.into_iter().map(inlines_into_static).collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(inlines_into_static).collect())
+                    .collect(),
+            },
+            Block::Metadata { kind, content } => Block::Metadata {
+                kind,
+                content: texts(content),
+            },
+            Block::Other(tag, children) => Block::Other(tag.into_static(), blocks(children)),
+        }
+    }
+}
+
+fn inlines_into_static(inls: Vec<Inline<'_>>) -> Vec<Inline<'static>> {
+    inls.into_iter().map(Inline::into_static).collect()
+}
+
+impl<'a> Inline<'a> {
+    /// The events this inline represents.
+    pub fn to_events(&self) -> Vec<Event<'a>> {
+        let mut out = Vec::new();
+        self.push_events(&mut out);
+        out
+    }
+
+    fn push_events(&self, out: &mut Vec<Event<'a>>) {
+        match self {
+            Inline::Text(t) => out.push(Event::Text(t.clone())),
+            Inline::Code(t) => out.push(Event::Code(t.clone())),
+            Inline::InlineHtml(t) => out.push(Event::InlineHtml(t.clone())),
+            Inline::Html(t) => out.push(Event::Html(t.clone())),
+            Inline::SoftBreak => out.push(Event::SoftBreak),
+            Inline::HardBreak => out.push(Event::HardBreak),
+            Inline::TaskListMarker(b) => out.push(Event::TaskListMarker(*b)),
+            Inline::Emphasis(c) => push_wrapped(Tag::Emphasis, out, |o| push_inlines(c, o)),
+            Inline::Strong(c) => push_wrapped(Tag::Strong, out, |o| push_inlines(c, o)),
+            Inline::Strikethrough(c) => {
+                push_wrapped(Tag::Strikethrough, out, |o| push_inlines(c, o))
+            }
+            Inline::Subscript(c) => push_wrapped(Tag::Subscript, out, |o| push_inlines(c, o)),
+            Inline::Superscript(c) => push_wrapped(Tag::Superscript, out, |o| push_inlines(c, o)),
+            Inline::Link {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => {
+                let tag = Tag::Link {
+                    link_type: *link_type,
+                    dest_url: dest.clone(),
+                    title: title.clone(),
+                    id: id.clone(),
+                };
+                push_wrapped(tag, out, |o| push_inlines(children, o))
+            }
+            Inline::Image {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => {
+                let tag = Tag::Image {
+                    link_type: *link_type,
+                    dest_url: dest.clone(),
+                    title: title.clone(),
+                    id: id.clone(),
+                };
+                push_wrapped(tag, out, |o| push_inlines(children, o))
+            }
+            Inline::FootnoteReference(t) => out.push(Event::FootnoteReference(t.clone())),
+            Inline::InlineMath(t) => out.push(Event::InlineMath(t.clone())),
+            Inline::DisplayMath(t) => out.push(Event::DisplayMath(t.clone())),
+        }
+    }
+
+    /// Detach the inline from the source it borrows from.
+    pub fn into_static(self) -> Inline<'static> {
+        match self {
+            Inline::Text(t) => Inline::Text(t.into_static()),
+            Inline::Code(t) => Inline::Code(t.into_static()),
+            Inline::InlineHtml(t) => Inline::InlineHtml(t.into_static()),
+            Inline::Html(t) => Inline::Html(t.into_static()),
+            Inline::SoftBreak => Inline::SoftBreak,
+            Inline::HardBreak => Inline::HardBreak,
+            Inline::TaskListMarker(b) => Inline::TaskListMarker(b),
+            Inline::Emphasis(c) => Inline::Emphasis(inlines_into_static(c)),
+            Inline::Strong(c) => Inline::Strong(inlines_into_static(c)),
+            Inline::Strikethrough(c) => Inline::Strikethrough(inlines_into_static(c)),
+            Inline::Subscript(c) => Inline::Subscript(inlines_into_static(c)),
+            Inline::Superscript(c) => Inline::Superscript(inlines_into_static(c)),
+            Inline::Link {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => Inline::Link {
+                link_type,
+                dest: dest.into_static(),
+                title: title.into_static(),
+                id: id.into_static(),
+                children: inlines_into_static(children),
+            },
+            Inline::Image {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => Inline::Image {
+                link_type,
+                dest: dest.into_static(),
+                title: title.into_static(),
+                id: id.into_static(),
+                children: inlines_into_static(children),
+            },
+            Inline::FootnoteReference(t) => Inline::FootnoteReference(t.into_static()),
+            Inline::InlineMath(t) => Inline::InlineMath(t.into_static()),
+            Inline::DisplayMath(t) => Inline::DisplayMath(t.into_static()),
+        }
+    }
+}
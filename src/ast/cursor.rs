@@ -0,0 +1,238 @@
+//! Path-addressed editing of a block tree.
+//!
+//! A `Cursor` points at one block by its path: the index of the block among
+//! its siblings at each level, from the top level down. It moves like an
+//! editor's caret (`next_sibling`, `prev_sibling`, `parent`,
+//! `first_child`) and edits
+//! around itself (`replace`, `insert_before`, `insert_after`, `delete`),
+//! leaving every other block as it was.
+//!
+//! ```ignore
+//! let mut cursor = Cursor::new(&mut blocks);
+//! cursor.next_sibling();
+//! cursor.first_child(); // into a block quote
+//! cursor.insert_before(Block::paragraph(["new"]));
+//! ```
+//!
+//! A list has no blocks of its own: entering one goes to the first block
+//! of its first item, and the path takes two indices there, the item and
+//! the block within it. Sibling moves stay within an item.
+
+use crate::ast::Block;
+
+/// One level of a path: the index of the block, and of the list item
+/// holding it when its parent is a list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Step {
+    item: Option<usize>,
+    index: usize,
+}
+
+/// A position in a block tree, borrowed mutably for editing.
+#[derive(Debug)]
+pub struct Cursor<'a> {
+    root: &'a mut Vec<Block>,
+    steps: Vec<Step>,
+}
+
+/// The child blocks of `b`, or those of its list item `item`.
+fn children_mut(b: &mut Block, item: Option<usize>) -> Option<&mut Vec<Block>> {
+    match (b, item) {
+        (
+            Block::BlockQuote(children)
+            | Block::Alert { children, .. }
+            | Block::Item(children)
+            | Block::FootnoteDefinition(_, children),
+            None,
+        ) => Some(children),
+        (Block::List { items, .. }, Some(i)) => items.get_mut(i),
+        _ => None,
+    }
+}
+
+impl<'a> Cursor<'a> {
+    /// A cursor on the first top-level block.
+    pub fn new(root: &'a mut Vec<Block>) -> Self {
+        Cursor {
+            root,
+            steps: vec![Step {
+                item: None,
+                index: 0,
+            }],
+        }
+    }
+
+    /// A cursor on the block at `path`, as returned by `Cursor::path`, or
+    /// `None` when no block is there.
+    pub fn at(root: &'a mut Vec<Block>, path: &[usize]) -> Option<Self> {
+        let mut cursor = Cursor::new(root);
+        cursor.steps.clear();
+        let mut rest = path;
+        while let [first, tail @ ..] = rest {
+            let in_list = matches!(cursor.get(), Some(Block::List { .. }));
+            let step = match (in_list, tail) {
+                (true, [index, tail @ ..]) => {
+                    rest = tail;
+                    Step {
+                        item: Some(*first),
+                        index: *index,
+                    }
+                }
+                (true, []) => return None,
+                (false, _) => {
+                    rest = tail;
+                    Step {
+                        item: None,
+                        index: *first,
+                    }
+                }
+            };
+            cursor.steps.push(step);
+            cursor.get()?;
+        }
+        (!cursor.steps.is_empty()).then_some(cursor)
+    }
+
+    /// The path of the block under the cursor, list item indices included.
+    pub fn path(&self) -> Vec<usize> {
+        self.steps
+            .iter()
+            .flat_map(|s| s.item.into_iter().chain([s.index]))
+            .collect()
+    }
+
+    /// The blocks the cursor's block is one of.
+    fn siblings_mut(&mut self) -> Option<&mut Vec<Block>> {
+        let (last, ancestors) = self.steps.split_last()?;
+        let mut blocks = &mut *self.root;
+        let items = ancestors.iter().skip(1).chain([last]).map(|s| s.item);
+        for (step, item) in ancestors.iter().zip(items) {
+            blocks = children_mut(blocks.get_mut(step.index)?, item)?;
+        }
+        Some(blocks)
+    }
+
+    fn index(&self) -> usize {
+        self.steps.last().map_or(0, |s| s.index)
+    }
+
+    /// The block under the cursor, if there is one. A cursor can point
+    /// past the last sibling, e.g. after deleting it.
+    pub fn get(&self) -> Option<&Block> {
+        let (last, ancestors) = self.steps.split_last()?;
+        let mut blocks: &Vec<Block> = self.root;
+        let items = ancestors.iter().skip(1).chain([last]).map(|s| s.item);
+        for (step, item) in ancestors.iter().zip(items) {
+            blocks = match (blocks.get(step.index)?, item) {
+                (
+                    Block::BlockQuote(children)
+                    | Block::Alert { children, .. }
+                    | Block::Item(children)
+                    | Block::FootnoteDefinition(_, children),
+                    None,
+                ) => children,
+                (Block::List { items, .. }, Some(i)) => items.get(i)?,
+                _ => return None,
+            };
+        }
+        blocks.get(last.index)
+    }
+
+    /// The block under the cursor, to change it in place.
+    pub fn get_mut(&mut self) -> Option<&mut Block> {
+        let i = self.index();
+        self.siblings_mut()?.get_mut(i)
+    }
+
+    /// Move to the next sibling. Returns false, without moving, when there
+    /// is none.
+    pub fn next_sibling(&mut self) -> bool {
+        let i = self.index();
+        let len = self.siblings_mut().map_or(0, |s| s.len());
+        if i + 1 >= len {
+            return false;
+        }
+        if let Some(last) = self.steps.last_mut() {
+            last.index += 1;
+        }
+        true
+    }
+
+    /// Move to the previous sibling, like `next_sibling`.
+    pub fn prev_sibling(&mut self) -> bool {
+        match self.steps.last_mut() {
+            Some(last) if last.index > 0 => {
+                last.index -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move to the enclosing block (the list, for a block in a list item).
+    /// Returns false at the top level.
+    pub fn parent(&mut self) -> bool {
+        if self.steps.len() < 2 {
+            return false;
+        }
+        self.steps.pop();
+        true
+    }
+
+    /// Move to the first child block of the block under the cursor.
+    /// Returns false, without moving, when it has none.
+    pub fn first_child(&mut self) -> bool {
+        let item = match self.get() {
+            Some(Block::List { .. }) => Some(0),
+            _ => None,
+        };
+        let has_child = self
+            .get_mut()
+            .and_then(|b| children_mut(b, item))
+            .is_some_and(|c| !c.is_empty());
+        if has_child {
+            self.steps.push(Step { item, index: 0 });
+        }
+        has_child
+    }
+
+    /// Put `b` in place of the block under the cursor and return the old
+    /// one. Returns `Err(b)` when the cursor points at no block.
+    pub fn replace(&mut self, b: Block) -> Result<Block, Block> {
+        match self.get_mut() {
+            Some(slot) => Ok(std::mem::replace(slot, b)),
+            None => Err(b),
+        }
+    }
+
+    /// Insert `b` before the block under the cursor, which stays under the
+    /// cursor. A cursor past the last sibling appends `b`.
+    pub fn insert_before(&mut self, b: Block) {
+        let i = self.index();
+        let Some(siblings) = self.siblings_mut() else {
+            return;
+        };
+        let at = i.min(siblings.len());
+        siblings.insert(at, b);
+        if let Some(last) = self.steps.last_mut() {
+            last.index = at + 1;
+        }
+    }
+
+    /// Insert `b` after the block under the cursor, which stays under the
+    /// cursor.
+    pub fn insert_after(&mut self, b: Block) {
+        let i = self.index();
+        if let Some(siblings) = self.siblings_mut() {
+            siblings.insert((i + 1).min(siblings.len()), b);
+        }
+    }
+
+    /// Remove the block under the cursor and return it. The cursor then
+    /// points at the following sibling, if any.
+    pub fn delete(&mut self) -> Option<Block> {
+        let i = self.index();
+        let siblings = self.siblings_mut()?;
+        (i < siblings.len()).then(|| siblings.remove(i))
+    }
+}
@@ -3,33 +3,105 @@
 //! This module defines traits that allow consumers to plug in custom
 //! inline/block nodes. We provide default marker types so the library
 //! remains compatible when the user doesn't supply custom nodes.
+//!
+//! Custom nodes are cloneable through `dyn_clone`: any node type that is
+//! `Clone` can be copied behind a trait object (`Box<dyn BlockNode>` is
+//! `Clone`), which is what `Block::deep_clone` builds on.
 
+use crate::ast::writer::WriteContext;
 use crate::{Line, Region};
+use dyn_clone::DynClone;
 use pulldown_cmark::Event;
+use std::any::Any;
 
 /// Trait describing a user-defined block node.
 ///
 /// Implementors should provide a way to convert their node into a sequence
-/// of pulldown-cmark `Event<'static>` values for serialization.
-pub trait BlockNode: std::fmt::Debug + Send + Sync {
+/// of pulldown-cmark `Event<'static>` values for serialization. Node types
+/// must be `Clone` so ASTs holding them can be deep-copied, and `'static` so
+/// they can be looked up by type (see `ast::registry`).
+pub trait BlockNode: Any + DynClone + std::fmt::Debug + Send + Sync {
     /// Convert the custom block into owned pulldown-cmark events.
     fn to_events(&self) -> Vec<Event<'static>>;
     /// Provide a direct rendering of this block as a `Region`.
     /// The writer will use this `Region` directly when
-    /// converting blocks to markdown.
+    /// converting blocks to markdown. When it is empty, the writer renders
+    /// the blocks described by `to_events` instead.
+    ///
+    /// The region must be rendered as if the node sat at the top level of
+    /// the document. When the node is nested inside a container (blockquote,
+    /// list item, footnote definition) the writer applies the container's
+    /// prefixes itself: every line holding text, suffix lines included,
+    /// receives the `> ` marker or continuation indentation of each
+    /// enclosing container. Blank lines get a bare `>` in block quotes and
+    /// stay empty in list items, keeping lines free of trailing whitespace
+    /// (footnote definitions indent them like the others). Suffix lines
+    /// stay directly after the node's own lines. Implementors should
+    /// therefore never add container prefixes on their own.
     fn to_region(&self) -> Region;
+    /// Render the block knowing where it is written: inside which
+    /// containers, list items or table cells, and with which options. The
+    /// writer calls this method; the default ignores the context and
+    /// returns `to_region`.
+    fn to_region_in(&self, _ctx: &WriteContext) -> Region {
+        self.to_region()
+    }
+    /// HTML for the block, used by `blocks_to_html`. The default returns
+    /// `None`, rendering the HTML of `to_events` instead.
+    fn to_html(&self) -> Option<String> {
+        None
+    }
+    /// Structural equality against another custom block. The default
+    /// compares the events both nodes produce; implementors can override it
+    /// with a cheaper or stricter comparison.
+    fn node_eq(&self, other: &dyn BlockNode) -> bool {
+        self.to_events() == other.to_events()
+    }
 }
 
+impl PartialEq for dyn BlockNode {
+    fn eq(&self, other: &Self) -> bool {
+        // the same node is trivially equal to itself
+        std::ptr::addr_eq(self, other) || self.node_eq(other)
+    }
+}
+
+dyn_clone::clone_trait_object!(BlockNode);
+
 /// Trait describing a user-defined inline node.
-pub trait InlineNode: std::fmt::Debug + Send + Sync {
+pub trait InlineNode: Any + DynClone + std::fmt::Debug + Send + Sync {
     /// Convert the custom inline into owned pulldown-cmark events.
     fn to_events(&self) -> Vec<Event<'static>>;
     /// Provide a direct rendering of this inline as a `Line`.
     /// The writer will use this `Line` directly when
-    /// converting inlines to markdown.
+    /// converting inlines to markdown. When it is empty, the writer renders
+    /// the inlines described by `to_events` instead.
     fn to_line(&self) -> Line;
+    /// Context-aware counterpart of `to_line`, like
+    /// `BlockNode::to_region_in`.
+    fn to_line_in(&self, _ctx: &WriteContext) -> Line {
+        self.to_line()
+    }
+    /// HTML for the inline, like `BlockNode::to_html`.
+    fn to_html(&self) -> Option<String> {
+        None
+    }
+    /// Structural equality against another custom inline. The default
+    /// compares the events both nodes produce; implementors can override it
+    /// with a cheaper or stricter comparison.
+    fn node_eq(&self, other: &dyn InlineNode) -> bool {
+        self.to_events() == other.to_events()
+    }
 }
 
+impl PartialEq for dyn InlineNode {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other) || self.node_eq(other)
+    }
+}
+
+dyn_clone::clone_trait_object!(InlineNode);
+
 /// Optional trait that allows consumers to provide a parser for custom
 /// block nodes. Implementors should decide whether the events at the
 /// current position match their node and return the number of consumed
@@ -43,6 +115,62 @@ pub trait BlockParser: Send + Sync {
     ) -> Option<(usize, crate::ast::Block)>;
 }
 
+/// Inline counterpart of `BlockParser`, for custom constructs found inside
+/// paragraphs, headings and other inline content.
+pub trait InlineParser: Send + Sync {
+    fn try_parse(
+        &self,
+        events: &[Event],
+        idx: usize,
+        ctx: &crate::ast::ParseContext,
+    ) -> Option<(usize, crate::ast::Inline)>;
+
+    /// Like `try_parse`, for parsers turning the consumed events into
+    /// several inlines, e.g. text split around the parts they recognise.
+    /// The default returns the inline of `try_parse`.
+    fn try_parse_inlines(
+        &self,
+        events: &[Event],
+        idx: usize,
+        ctx: &crate::ast::ParseContext,
+    ) -> Option<(usize, Vec<crate::ast::Inline>)> {
+        self.try_parse(events, idx, ctx)
+            .map(|(n, inline)| (n, vec![inline]))
+    }
+}
+
+/// A custom block that replays recorded events and markdown. This is what a
+/// `Block::Custom` turns into when it is deserialized, since the original
+/// node type is not known at that point.
+#[derive(Clone, Debug)]
+pub struct OpaqueBlock {
+    pub events: Vec<Event<'static>>,
+    pub region: Region,
+}
+impl BlockNode for OpaqueBlock {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        self.events.clone()
+    }
+    fn to_region(&self) -> Region {
+        self.region.clone()
+    }
+}
+
+/// Inline counterpart of `OpaqueBlock`.
+#[derive(Clone, Debug)]
+pub struct OpaqueInline {
+    pub events: Vec<Event<'static>>,
+    pub line: Line,
+}
+impl InlineNode for OpaqueInline {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        self.events.clone()
+    }
+    fn to_line(&self) -> Line {
+        self.line.clone()
+    }
+}
+
 /// Default empty marker for when no custom block node is used.
 #[derive(Clone, Debug)]
 pub struct NoBlock;
@@ -0,0 +1,83 @@
+//! Problems found while turning an event stream into blocks.
+//!
+//! `parse_events_to_blocks` is lenient and never fails. Stray inlines are
+//! wrapped in a paragraph, events that fit nowhere are dropped, tags left
+//! open are closed at the end of the stream and stray `End`s are ignored.
+//! `try_parse_events_to_blocks` and
+//! `parse_events_to_blocks_with_diagnostics` report each of those decisions
+//! as a `ParseDiagnostic` carrying the index of the offending event.
+
+use pulldown_cmark::{Tag, TagEnd};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseDiagnostic {
+    /// An `End` that matches no open tag. It was ignored.
+    UnmatchedEnd { index: usize, end: TagEnd },
+    /// An `End` for an outer tag while `expected` was still open inside it.
+    /// The inner tag was closed along with the outer one.
+    MismatchedEnd {
+        index: usize,
+        expected: TagEnd,
+        found: TagEnd,
+    },
+    /// A `Start` was never closed. `index` is the position of the `Start`;
+    /// the tag was closed at the end of the stream.
+    UnclosedTag { index: usize, tag: Tag<'static> },
+    /// Inline content appeared where blocks were expected and was wrapped
+    /// in a paragraph.
+    WrappedInline { index: usize },
+    /// An event (or the block it ended) had no place in the enclosing node
+    /// and was dropped.
+    DroppedEvent { index: usize },
+    /// A tag without a matching AST node. Its inline content was kept as a
+    /// paragraph.
+    UnsupportedTag { index: usize, tag: Tag<'static> },
+}
+
+impl ParseDiagnostic {
+    /// Index of the event the diagnostic refers to.
+    pub fn index(&self) -> usize {
+        match self {
+            ParseDiagnostic::UnmatchedEnd { index, .. }
+            | ParseDiagnostic::MismatchedEnd { index, .. }
+            | ParseDiagnostic::UnclosedTag { index, .. }
+            | ParseDiagnostic::WrappedInline { index }
+            | ParseDiagnostic::DroppedEvent { index }
+            | ParseDiagnostic::UnsupportedTag { index, .. } => *index,
+        }
+    }
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDiagnostic::UnmatchedEnd { index, end } => {
+                write!(f, "event {}: End({:?}) without an open tag", index, end)
+            }
+            ParseDiagnostic::MismatchedEnd {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "event {}: expected End({:?}), found End({:?})",
+                index, expected, found
+            ),
+            ParseDiagnostic::UnclosedTag { index, tag } => {
+                write!(f, "event {}: Start({:?}) is never closed", index, tag)
+            }
+            ParseDiagnostic::WrappedInline { index } => {
+                write!(f, "event {}: inline content wrapped in a paragraph", index)
+            }
+            ParseDiagnostic::DroppedEvent { index } => {
+                write!(f, "event {}: dropped, no place for it in the tree", index)
+            }
+            ParseDiagnostic::UnsupportedTag { index, tag } => {
+                write!(f, "event {}: unsupported tag {:?}", index, tag)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseDiagnostic {}
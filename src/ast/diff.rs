@@ -0,0 +1,876 @@
+//! Structural differences between two versions of a document.
+//!
+//! `diff_blocks` compares two block lists and returns the `Change`s that
+//! turn the old one into the new one: blocks inserted, deleted or
+//! modified, found by matching unchanged blocks as a line diff matches
+//! lines. Containers that kept their kind (block quotes, list items,
+//! footnote definitions) are compared child by child, so an edit deep in a
+//! quote is reported where it happened rather than as a new quote. With
+//! `DiffOptions::inlines`, paragraphs and headings are compared inline by
+//! inline in the same way.
+//!
+//! Changes are meant to be applied in order: the path of each refers to
+//! the document as the changes before it left it. `apply` replays them,
+//! on the old document or on another version of it, and reports a
+//! `Conflict` where that version no longer matches. `render_changes` shows
+//! them instead, as one document marked up with CriticMarkup or
+//! `<ins>`/`<del>`. `merge3` combines the changes two versions made to a
+//! common base.
+
+use crate::ast::block::block_to_events;
+use crate::ast::custom::{BlockNode, InlineNode};
+use crate::ast::visit::{Edit, VisitMut, walk_block_mut, walk_blocks_mut, walk_inline_mut};
+use crate::ast::writer::{
+    WriterOptions, block_to_markdown, blocks_to_markdown, inline_to_markdown,
+};
+use crate::ast::{Block, Inline};
+use crate::text::{Line, Region};
+use pulldown_cmark::Event;
+use std::any::Any;
+use std::fmt;
+use std::mem::discriminant;
+use std::sync::Arc;
+
+/// Where a change applies: the path of a block, as `Cursor::path` returns
+/// it, and for inline changes the path of the inline within that block
+/// (the index among its siblings at each level of nesting).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NodePath {
+    pub blocks: Vec<usize>,
+    pub inlines: Vec<usize>,
+}
+
+impl NodePath {
+    /// Whether the path points at an inline rather than a block.
+    pub fn is_inline(&self) -> bool {
+        !self.inlines.is_empty()
+    }
+}
+
+/// A block or an inline, as held by a `Change`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    Block(Block),
+    Inline(Inline),
+}
+
+/// One step from the old document to the new one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// `node` was inserted at `path`.
+    Insert { path: NodePath, node: Node },
+    /// `node`, at `path`, was deleted.
+    Delete { path: NodePath, node: Node },
+    /// The node at `path` changed from `old` to `new`.
+    Modify {
+        path: NodePath,
+        old: Node,
+        new: Node,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &NodePath {
+        match self {
+            Change::Insert { path, .. }
+            | Change::Delete { path, .. }
+            | Change::Modify { path, .. } => path,
+        }
+    }
+}
+
+/// How `diff_blocks_with` compares documents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Compare the content of paragraphs and headings inline by inline,
+    /// instead of reporting the whole block as modified.
+    pub inlines: bool,
+}
+
+/// An alignment step between an old and a new sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Delete(usize),
+    Insert(usize),
+    /// An old item that became a new one of the same kind.
+    Pair(usize, usize),
+}
+
+/// Align `old` with `new` on their longest common subsequence. Between two
+/// kept items, deleted and inserted items are paired in order while
+/// `same_kind` holds for them.
+fn align<T: PartialEq>(old: &[T], new: &[T], same_kind: impl Fn(&T, &T) -> bool) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (o, n) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    // lcs[i][j]: length of the common subsequence of o[i..] and n[j..]
+    let mut lcs = vec![vec![0usize; n.len() + 1]; o.len() + 1];
+    for i in (0..o.len()).rev() {
+        for j in (0..n.len()).rev() {
+            lcs[i][j] = if o[i] == n[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = vec![Op::Keep; prefix];
+    let (mut deleted, mut inserted) = (Vec::new(), Vec::new());
+    let flush = |ops: &mut Vec<Op>, deleted: &mut Vec<usize>, inserted: &mut Vec<usize>| {
+        let paired = deleted.len().min(inserted.len());
+        for (&d, &i) in deleted.iter().zip(inserted.iter()) {
+            if same_kind(&old[d], &new[i]) {
+                ops.push(Op::Pair(d, i));
+            } else {
+                ops.extend([Op::Delete(d), Op::Insert(i)]);
+            }
+        }
+        ops.extend(deleted.drain(..).skip(paired).map(Op::Delete));
+        ops.extend(inserted.drain(..).skip(paired).map(Op::Insert));
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < o.len() || j < n.len() {
+        if i < o.len() && j < n.len() && o[i] == n[j] {
+            flush(&mut ops, &mut deleted, &mut inserted);
+            ops.push(Op::Keep);
+            i += 1;
+            j += 1;
+        } else if j == n.len() || (i < o.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            deleted.push(prefix + i);
+            i += 1;
+        } else {
+            inserted.push(prefix + j);
+            j += 1;
+        }
+    }
+    flush(&mut ops, &mut deleted, &mut inserted);
+    ops.extend(std::iter::repeat_n(Op::Keep, suffix));
+    ops
+}
+
+/// Compare two block lists, block by block, as `diff_blocks_with` does
+/// with default options.
+pub fn diff_blocks(old: &[Block], new: &[Block]) -> Vec<Change> {
+    diff_blocks_with(old, new, &DiffOptions::default())
+}
+
+/// The changes turning `old` into `new`, in document order.
+pub fn diff_blocks_with(old: &[Block], new: &[Block], options: &DiffOptions) -> Vec<Change> {
+    let mut differ = Differ {
+        options: *options,
+        out: Vec::new(),
+    };
+    differ.blocks(&[], old, new);
+    differ.out
+}
+
+struct Differ {
+    options: DiffOptions,
+    out: Vec<Change>,
+}
+
+/// `prefix` followed by `rest`.
+fn join(prefix: &[usize], rest: &[usize]) -> Vec<usize> {
+    prefix.iter().chain(rest).copied().collect()
+}
+
+impl Differ {
+    fn blocks(&mut self, prefix: &[usize], old: &[Block], new: &[Block]) {
+        let ops = align(old, new, |a, b| discriminant(a) == discriminant(b));
+        let mut k = 0;
+        for op in ops {
+            let path = NodePath {
+                blocks: join(prefix, &[k]),
+                inlines: Vec::new(),
+            };
+            match op {
+                Op::Keep => k += 1,
+                Op::Delete(i) => self.out.push(Change::Delete {
+                    path,
+                    node: Node::Block(old[i].clone()),
+                }),
+                Op::Insert(j) => {
+                    self.out.push(Change::Insert {
+                        path,
+                        node: Node::Block(new[j].clone()),
+                    });
+                    k += 1;
+                }
+                Op::Pair(i, j) => {
+                    self.block(path.blocks, &old[i], &new[j]);
+                    k += 1;
+                }
+            }
+        }
+    }
+
+    /// Compare two blocks of the same kind at `path`.
+    fn block(&mut self, path: Vec<usize>, old: &Block, new: &Block) {
+        match (old, new) {
+            (Block::BlockQuote(a), Block::BlockQuote(b)) | (Block::Item(a), Block::Item(b)) => {
+                return self.blocks(&path, a, b);
+            }
+            (
+                Block::Alert {
+                    kind: ka,
+                    children: a,
+                },
+                Block::Alert {
+                    kind: kb,
+                    children: b,
+                },
+            ) if ka == kb => return self.blocks(&path, a, b),
+            (Block::FootnoteDefinition(la, a), Block::FootnoteDefinition(lb, b)) if la == lb => {
+                return self.blocks(&path, a, b);
+            }
+            (
+                Block::List {
+                    start: sa,
+                    items: a,
+                },
+                Block::List {
+                    start: sb,
+                    items: b,
+                },
+            ) if sa == sb && a.len() == b.len() => {
+                for (n, (a, b)) in a.iter().zip(b).enumerate() {
+                    self.blocks(&join(&path, &[n]), a, b);
+                }
+                return;
+            }
+            (Block::Paragraph(a), Block::Paragraph(b)) if self.options.inlines => {
+                return self.inlines(&path, &[], a, b);
+            }
+            (
+                Block::Heading {
+                    level: la,
+                    id: ia,
+                    classes: ca,
+                    attrs: aa,
+                    children: a,
+                },
+                Block::Heading {
+                    level: lb,
+                    id: ib,
+                    classes: cb,
+                    attrs: ab,
+                    children: b,
+                },
+            ) if self.options.inlines && (la, ia, ca, aa) == (lb, ib, cb, ab) => {
+                return self.inlines(&path, &[], a, b);
+            }
+            _ => {}
+        }
+        self.out.push(Change::Modify {
+            path: NodePath {
+                blocks: path,
+                inlines: Vec::new(),
+            },
+            old: Node::Block(old.clone()),
+            new: Node::Block(new.clone()),
+        });
+    }
+
+    fn inlines(&mut self, block: &[usize], prefix: &[usize], old: &[Inline], new: &[Inline]) {
+        let ops = align(old, new, |a, b| discriminant(a) == discriminant(b));
+        let mut k = 0;
+        for op in ops {
+            let path = NodePath {
+                blocks: block.to_vec(),
+                inlines: join(prefix, &[k]),
+            };
+            match op {
+                Op::Keep => k += 1,
+                Op::Delete(i) => self.out.push(Change::Delete {
+                    path,
+                    node: Node::Inline(old[i].clone()),
+                }),
+                Op::Insert(j) => {
+                    self.out.push(Change::Insert {
+                        path,
+                        node: Node::Inline(new[j].clone()),
+                    });
+                    k += 1;
+                }
+                Op::Pair(i, j) => {
+                    match same_shell(&old[i], &new[j]) {
+                        Some((a, b)) => self.inlines(block, &path.inlines, a, b),
+                        None => self.out.push(Change::Modify {
+                            path,
+                            old: Node::Inline(old[i].clone()),
+                            new: Node::Inline(new[j].clone()),
+                        }),
+                    }
+                    k += 1;
+                }
+            }
+        }
+    }
+}
+
+/// The children of two inlines that only differ in their children.
+fn same_shell<'a>(a: &'a Inline, b: &'a Inline) -> Option<(&'a [Inline], &'a [Inline])> {
+    match (a, b) {
+        (Inline::Emphasis(a), Inline::Emphasis(b))
+        | (Inline::Strong(a), Inline::Strong(b))
+        | (Inline::Strikethrough(a), Inline::Strikethrough(b))
+        | (Inline::Subscript(a), Inline::Subscript(b))
+        | (Inline::Superscript(a), Inline::Superscript(b)) => Some((a, b)),
+        (
+            Inline::Link {
+                link_type: ta,
+                dest: da,
+                title: tia,
+                id: ia,
+                children: a,
+            },
+            Inline::Link {
+                link_type: tb,
+                dest: db,
+                title: tib,
+                id: ib,
+                children: b,
+            },
+        )
+        | (
+            Inline::Image {
+                link_type: ta,
+                dest: da,
+                title: tia,
+                id: ia,
+                children: a,
+            },
+            Inline::Image {
+                link_type: tb,
+                dest: db,
+                title: tib,
+                id: ib,
+                children: b,
+            },
+        ) if (ta, da, tia, ia) == (tb, db, tib, ib) => Some((a, b)),
+        _ => None,
+    }
+}
+
+/// A change `apply` could not make: the document does not have what the
+/// change expects at its path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// Index of the change in the list given to `apply`.
+    pub index: usize,
+    /// Path of the change.
+    pub path: NodePath,
+    /// What the document has at the change's path, if anything.
+    pub found: Option<Box<Node>>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = &self.path;
+        write!(f, "change {} conflicts at {:?}", self.index, path.blocks)?;
+        if path.is_inline() {
+            write!(f, " inline {:?}", path.inlines)?;
+        }
+        match self.found {
+            Some(_) => write!(f, ": the node there differs"),
+            None => write!(f, ": no node there"),
+        }
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+/// How `annotate` marks changes in the document it produces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChangeMarkup {
+    /// CriticMarkup: `{++inserted++}`, `{--deleted--}` and
+    /// `{~~old~>new~~}`.
+    #[default]
+    CriticMarkup,
+    /// HTML `<ins>` and `<del>` elements.
+    Html,
+}
+
+/// A deleted block kept in an annotated document, as its markup. It is
+/// skipped by paths, since the changes after its deletion do not see it.
+#[derive(Clone, Debug)]
+struct DeletedBlock(String);
+
+impl BlockNode for DeletedBlock {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        Vec::new()
+    }
+    fn to_region(&self) -> Region {
+        Region::from_str(&self.0)
+    }
+}
+
+/// The inline counterpart of `DeletedBlock`.
+#[derive(Clone, Debug)]
+struct DeletedInline(String);
+
+impl InlineNode for DeletedInline {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        Vec::new()
+    }
+    fn to_line(&self) -> Line {
+        Line::from_str(&self.0)
+    }
+}
+
+/// The opening and closing marks of an insertion, or of a deletion.
+fn marks(markup: ChangeMarkup, inserted: bool) -> (&'static str, &'static str) {
+    match (markup, inserted) {
+        (ChangeMarkup::CriticMarkup, true) => ("{++", "++}"),
+        (ChangeMarkup::CriticMarkup, false) => ("{--", "--}"),
+        (ChangeMarkup::Html, true) => ("<ins>", "</ins>"),
+        (ChangeMarkup::Html, false) => ("<del>", "</del>"),
+    }
+}
+
+/// A block or an inline as changes see it. In an annotated document,
+/// deleted nodes stay in place but no longer count in paths.
+trait Sibling: Clone + PartialEq {
+    fn is_deleted(&self) -> bool;
+    /// The node marked as inserted.
+    fn inserted(&self, markup: ChangeMarkup) -> Self;
+    /// The node marked as deleted.
+    fn deleted(&self, markup: ChangeMarkup) -> Self;
+    /// `old` marked as replaced by `new`.
+    fn replaced(old: &Self, new: &Self, markup: ChangeMarkup) -> Self;
+}
+
+fn block_markdown(b: &Block) -> String {
+    block_to_markdown(b, &WriterOptions::default())
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+/// `b` between the marks of an insertion or a deletion.
+fn mark_block(b: &Block, markup: ChangeMarkup, inserted: bool) -> String {
+    let (open, close) = marks(markup, inserted);
+    match markup {
+        ChangeMarkup::CriticMarkup => format!("{open}{}{close}", block_markdown(b)),
+        // markdown inside an HTML block needs blank lines around it
+        ChangeMarkup::Html => format!("{open}\n\n{}\n\n{close}", block_markdown(b)),
+    }
+}
+
+impl Sibling for Block {
+    fn is_deleted(&self) -> bool {
+        match self {
+            Block::Custom(c) => {
+                let any: &dyn Any = &**c;
+                any.is::<DeletedBlock>()
+            }
+            _ => false,
+        }
+    }
+
+    fn inserted(&self, markup: ChangeMarkup) -> Self {
+        Block::Raw(Region::from_str(&mark_block(self, markup, true)))
+    }
+
+    fn deleted(&self, markup: ChangeMarkup) -> Self {
+        Block::Custom(Arc::new(DeletedBlock(mark_block(self, markup, false))))
+    }
+
+    fn replaced(old: &Self, new: &Self, markup: ChangeMarkup) -> Self {
+        let old = mark_block(old, markup, false);
+        let new = mark_block(new, markup, true);
+        Block::Raw(Region::from_str(&format!("{old}\n\n{new}")))
+    }
+}
+
+/// `inl` between the marks of an insertion or a deletion.
+fn mark_inline(inl: &Inline, markup: ChangeMarkup, inserted: bool) -> String {
+    let (open, close) = marks(markup, inserted);
+    let md = inline_to_markdown(inl, &WriterOptions::default());
+    format!("{open}{md}{close}")
+}
+
+impl Sibling for Inline {
+    fn is_deleted(&self) -> bool {
+        match self {
+            Inline::Custom(c) => {
+                let any: &dyn Any = &**c;
+                any.is::<DeletedInline>()
+            }
+            _ => false,
+        }
+    }
+
+    fn inserted(&self, markup: ChangeMarkup) -> Self {
+        Inline::Raw(Region::from_str(&mark_inline(self, markup, true)))
+    }
+
+    fn deleted(&self, markup: ChangeMarkup) -> Self {
+        Inline::Custom(Arc::new(DeletedInline(mark_inline(self, markup, false))))
+    }
+
+    fn replaced(old: &Self, new: &Self, markup: ChangeMarkup) -> Self {
+        let md = match markup {
+            ChangeMarkup::CriticMarkup => {
+                let options = WriterOptions::default();
+                format!(
+                    "{{~~{}~>{}~~}}",
+                    inline_to_markdown(old, &options),
+                    inline_to_markdown(new, &options)
+                )
+            }
+            ChangeMarkup::Html => mark_inline(old, markup, false) + &mark_inline(new, markup, true),
+        };
+        Inline::Raw(Region::from_str(&md))
+    }
+}
+
+/// Where the `i`th node that is not deleted sits in `siblings`; one past
+/// the last one is the end of `siblings`.
+fn physical<T: Sibling>(siblings: &[T], i: usize) -> Option<usize> {
+    let mut live = siblings
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| !s.is_deleted())
+        .map(|(n, _)| n);
+    match live.nth(i) {
+        Some(n) => Some(n),
+        None if siblings.iter().filter(|s| !s.is_deleted()).count() == i => Some(siblings.len()),
+        None => None,
+    }
+}
+
+/// The blocks holding the block at `path`, and its index among them. The
+/// index may be one past the end, where a block can be inserted.
+fn block_siblings<'a>(
+    blocks: &'a mut Vec<Block>,
+    path: &[usize],
+) -> Option<(&'a mut Vec<Block>, usize)> {
+    let (first, rest) = path.split_first()?;
+    let i = physical(blocks, *first)?;
+    if rest.is_empty() {
+        return Some((blocks, i));
+    }
+    match blocks.get_mut(i)? {
+        Block::BlockQuote(children)
+        | Block::Alert { children, .. }
+        | Block::Item(children)
+        | Block::FootnoteDefinition(_, children) => block_siblings(children, rest),
+        Block::List { items, .. } => match rest {
+            [item, rest @ ..] if !rest.is_empty() => block_siblings(items.get_mut(*item)?, rest),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The inlines holding the inline at `path`, and its index among them.
+fn inline_siblings<'a>(
+    inlines: &'a mut Vec<Inline>,
+    path: &[usize],
+) -> Option<(&'a mut Vec<Inline>, usize)> {
+    let (first, rest) = path.split_first()?;
+    let i = physical(inlines, *first)?;
+    if rest.is_empty() {
+        return Some((inlines, i));
+    }
+    match inlines.get_mut(i)? {
+        Inline::Emphasis(children)
+        | Inline::Strong(children)
+        | Inline::Strikethrough(children)
+        | Inline::Subscript(children)
+        | Inline::Superscript(children)
+        | Inline::Link { children, .. }
+        | Inline::Image { children, .. } => inline_siblings(children, rest),
+        _ => None,
+    }
+}
+
+/// A change to a node of type `T`.
+enum ChangeOf<'a, T> {
+    Insert(&'a T),
+    Delete(&'a T),
+    Modify(&'a T, &'a T),
+}
+
+/// Make `change` on `siblings` at `i`, marking it up when `markup` is
+/// given. On a conflict, returns what is at `i`.
+fn apply_at<T: Sibling>(
+    siblings: &mut Vec<T>,
+    i: usize,
+    change: ChangeOf<'_, T>,
+    markup: Option<ChangeMarkup>,
+) -> Result<(), Option<T>> {
+    let found = siblings.get(i).filter(|f| !f.is_deleted());
+    match change {
+        ChangeOf::Insert(node) if i <= siblings.len() => {
+            let node = match markup {
+                Some(markup) => node.inserted(markup),
+                None => node.clone(),
+            };
+            siblings.insert(i, node);
+            Ok(())
+        }
+        ChangeOf::Insert(_) => Err(None),
+        ChangeOf::Delete(node) if found == Some(node) => {
+            match markup {
+                Some(markup) => siblings[i] = node.deleted(markup),
+                None => drop(siblings.remove(i)),
+            }
+            Ok(())
+        }
+        ChangeOf::Modify(old, new) if found == Some(old) => {
+            siblings[i] = match markup {
+                Some(markup) => T::replaced(old, new, markup),
+                None => new.clone(),
+            };
+            Ok(())
+        }
+        ChangeOf::Delete(_) | ChangeOf::Modify(..) => Err(found.cloned()),
+    }
+}
+
+/// Make one change; `Err` holds what the document has at its path.
+fn apply_one(
+    blocks: &mut Vec<Block>,
+    change: &Change,
+    markup: Option<ChangeMarkup>,
+) -> Result<(), Option<Node>> {
+    let path = change.path();
+    let (siblings, i) = block_siblings(blocks, &path.blocks).ok_or(None)?;
+    if !path.is_inline() {
+        let change = match change {
+            Change::Insert {
+                node: Node::Block(b),
+                ..
+            } => ChangeOf::Insert(b),
+            Change::Delete {
+                node: Node::Block(b),
+                ..
+            } => ChangeOf::Delete(b),
+            Change::Modify {
+                old: Node::Block(old),
+                new: Node::Block(new),
+                ..
+            } => ChangeOf::Modify(old, new),
+            _ => return Err(siblings.get(i).cloned().map(Node::Block)),
+        };
+        return apply_at(siblings, i, change, markup).map_err(|found| found.map(Node::Block));
+    }
+    let inlines = match siblings.get_mut(i) {
+        Some(Block::Paragraph(children) | Block::Heading { children, .. }) => children,
+        found => return Err(found.cloned().map(Node::Block)),
+    };
+    let (siblings, i) = inline_siblings(inlines, &path.inlines).ok_or(None)?;
+    let change = match change {
+        Change::Insert {
+            node: Node::Inline(inl),
+            ..
+        } => ChangeOf::Insert(inl),
+        Change::Delete {
+            node: Node::Inline(inl),
+            ..
+        } => ChangeOf::Delete(inl),
+        Change::Modify {
+            old: Node::Inline(old),
+            new: Node::Inline(new),
+            ..
+        } => ChangeOf::Modify(old, new),
+        _ => return Err(siblings.get(i).cloned().map(Node::Inline)),
+    };
+    apply_at(siblings, i, change, markup).map_err(|found| found.map(Node::Inline))
+}
+
+fn apply_all(
+    blocks: &[Block],
+    changes: &[Change],
+    markup: Option<ChangeMarkup>,
+) -> Result<Vec<Block>, Conflict> {
+    let mut out = blocks.to_vec();
+    for (index, change) in changes.iter().enumerate() {
+        apply_one(&mut out, change, markup).map_err(|found| Conflict {
+            index,
+            path: change.path().clone(),
+            found: found.map(Box::new),
+        })?;
+    }
+    Ok(out)
+}
+
+/// Replay `changes`, in order, on a copy of `blocks`. A change made on
+/// another version of the document applies as long as the nodes it
+/// touches are where it expects them, unchanged; the first one that is
+/// not is reported as a `Conflict`, and nothing is applied.
+pub fn apply(blocks: &[Block], changes: &[Change]) -> Result<Vec<Block>, Conflict> {
+    apply_all(blocks, changes, None)
+}
+
+/// Replay `changes` on `blocks` like `apply`, but keep what they delete:
+/// the result is a single document showing every change, with insertions
+/// and deletions marked up as `markup` says, for a reviewer to read.
+/// Changed nodes become `Raw` markdown.
+pub fn annotate(
+    blocks: &[Block],
+    changes: &[Change],
+    markup: ChangeMarkup,
+) -> Result<Vec<Block>, Conflict> {
+    let mut out = apply_all(blocks, changes, Some(markup))?;
+    struct Reveal;
+    impl VisitMut for Reveal {
+        fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+            if let Block::Custom(c) = b {
+                let any: &dyn Any = &**c;
+                if let Some(DeletedBlock(md)) = any.downcast_ref() {
+                    return Edit::Replace(vec![Block::Raw(Region::from_str(md))]);
+                }
+            }
+            walk_block_mut(self, b);
+            Edit::Keep
+        }
+
+        fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+            if let Inline::Custom(c) = inl {
+                let any: &dyn Any = &**c;
+                if let Some(DeletedInline(md)) = any.downcast_ref() {
+                    return Edit::Replace(vec![Inline::Raw(Region::from_str(md))]);
+                }
+            }
+            walk_inline_mut(self, inl);
+            Edit::Keep
+        }
+    }
+    walk_blocks_mut(&mut Reveal, &mut out);
+    Ok(out)
+}
+
+/// The markdown of `annotate`'s document.
+pub fn render_changes(
+    blocks: &[Block],
+    changes: &[Change],
+    markup: ChangeMarkup,
+) -> Result<String, Conflict> {
+    Ok(blocks_to_markdown(&annotate(blocks, changes, markup)?))
+}
+
+/// Blocks both sides of a three-way merge changed differently, left for a
+/// person to resolve. It is written as git writes a conflict:
+///
+/// ```text
+/// <<<<<<< ours
+/// ...
+/// ||||||| base
+/// ...
+/// =======
+/// ...
+/// >>>>>>> theirs
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub base: Vec<Block>,
+    pub ours: Vec<Block>,
+    pub theirs: Vec<Block>,
+}
+
+impl BlockNode for MergeConflict {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        block_to_events(&Block::Raw(self.to_region()))
+    }
+
+    fn to_region(&self) -> Region {
+        let mut md = String::new();
+        for (marker, blocks) in [
+            ("<<<<<<< ours", &self.ours),
+            ("||||||| base", &self.base),
+            ("=======", &self.theirs),
+        ] {
+            md.push_str(marker);
+            md.push('\n');
+            if !blocks.is_empty() {
+                md.push_str(&blocks_to_markdown(blocks));
+            }
+        }
+        md.push_str(">>>>>>> theirs");
+        Region::from_str(&md)
+    }
+
+    fn node_eq(&self, other: &dyn BlockNode) -> bool {
+        let any: &dyn Any = other;
+        any.downcast_ref::<MergeConflict>() == Some(self)
+    }
+}
+
+/// The result of `merge3`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Merged {
+    /// The merged blocks, with a `MergeConflict` custom block where the
+    /// sides disagree.
+    pub blocks: Vec<Block>,
+    /// Number of `MergeConflict` blocks in `blocks`.
+    pub conflicts: usize,
+}
+
+/// For each item of `old`, the index of the item of `new` it is kept as.
+fn kept_as(old: &[Block], new: &[Block]) -> Vec<Option<usize>> {
+    let mut out = vec![None; old.len()];
+    let (mut i, mut j) = (0, 0);
+    for op in align(old, new, |_, _| false) {
+        match op {
+            Op::Keep => {
+                out[i] = Some(j);
+                i += 1;
+                j += 1;
+            }
+            Op::Delete(_) => i += 1,
+            Op::Insert(_) => j += 1,
+            Op::Pair(..) => unreachable!("kept_as pairs no blocks"),
+        }
+    }
+    out
+}
+
+/// Merge the changes `ours` and `theirs` made to `base`, block by block at
+/// the top level. Where only one side changed a stretch of blocks, its
+/// version is taken; where both changed it alike, that version is taken;
+/// elsewhere the three versions are kept in a `MergeConflict` block.
+pub fn merge3(base: &[Block], ours: &[Block], theirs: &[Block]) -> Merged {
+    let in_ours = kept_as(base, ours);
+    let in_theirs = kept_as(base, theirs);
+    let mut merged = Merged {
+        blocks: Vec::new(),
+        conflicts: 0,
+    };
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // the next base block both sides kept, or the end of all three
+        let stable = (i..base.len()).find_map(|s| Some((s, in_ours[s]?, in_theirs[s]?)));
+        let (s, o, t) = stable.unwrap_or((base.len(), ours.len(), theirs.len()));
+        let (b, ou, th) = (&base[i..s], &ours[j..o], &theirs[k..t]);
+        if ou == th || th == b {
+            merged.blocks.extend_from_slice(ou);
+        } else if ou == b {
+            merged.blocks.extend_from_slice(th);
+        } else {
+            merged.conflicts += 1;
+            merged.blocks.push(Block::Custom(Arc::new(MergeConflict {
+                base: b.to_vec(),
+                ours: ou.to_vec(),
+                theirs: th.to_vec(),
+            })));
+        }
+        if stable.is_none() {
+            return merged;
+        }
+        merged.blocks.push(base[s].clone());
+        (i, j, k) = (s + 1, o + 1, t + 1);
+    }
+}
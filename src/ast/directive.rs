@@ -0,0 +1,639 @@
+//! Generic directives, in the syntax of remark-directive.
+//!
+//! ```markdown
+//! Press :kbd[Ctrl]{.key} to copy.
+//!
+//! ::youtube[Intro video]{#intro vid=123}
+//!
+//! :::spoiler{.wide}
+//! He was dead all along.
+//! :::
+//! ```
+//!
+//! A directive is a name with an optional `[label]` and an optional
+//! `{attributes}` list of `#id`, `.class` and `key=value` (or
+//! `key="quoted value"`) entries. Text directives (one colon) sit in
+//! inline content, leaf directives (two colons) take a paragraph of their
+//! own, and container directives (three colons or more) hold the blocks up
+//! to a closing fence of at least as many colons. All three parse into `Directive` nodes,
+//! which users match on by `name` and write back in the same syntax.
+//!
+//! `Directives` is a built-in `Extension` for the block forms. Parsing
+//! hooks see text events whole, so text directives are picked out of a
+//! parsed tree by `text_directives`, which also looks inside container
+//! directives:
+//!
+//! ```ignore
+//! let mut extensions = ExtensionRegistry::new();
+//! extensions.register(Directives, ParserOptions::new());
+//! let mut blocks = extensions.parse(&events);
+//! text_directives(&mut blocks);
+//! ```
+//!
+//! A text directive needs a label or attributes, so `Note:this` stays text.
+//! As for fenced divs, leave a blank line before the closing fence of a
+//! container directive ending in a list or a blockquote.
+
+use crate::ast::ParseContext;
+use crate::ast::block::{Block, block_to_events};
+use crate::ast::custom::{BlockNode, InlineNode};
+use crate::ast::extension::Extension;
+use crate::ast::fenced::scan_container;
+use crate::ast::inline::Inline;
+use crate::ast::parse::{HookResult, parse_events_to_blocks, parse_events_to_blocks_with_hook};
+use crate::ast::transform::merge_text;
+use crate::ast::visit::{Edit, VisitMut, walk_block_mut, walk_blocks_mut};
+use crate::ast::writer::html::{HtmlOptions, blocks_to_html};
+use crate::ast::writer::{WriteContext, WriterOptions, block_to_markdown, write_blocks};
+use crate::{Line, Region};
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use std::any::Any;
+use std::sync::Arc;
+
+/// The three forms of a directive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DirectiveKind {
+    /// `:name[label]{attrs}`, inside inline content.
+    Text,
+    /// `::name[label]{attrs}`, a block of its own.
+    Leaf,
+    /// `:::name[label]{attrs}`, then blocks, then a closing `:::`.
+    Container,
+}
+
+/// A directive: its form, name, label, attributes and, for containers,
+/// the blocks inside it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Directive {
+    pub kind: DirectiveKind,
+    pub name: String,
+    pub label: Vec<Inline>,
+    /// The `#id` attribute.
+    pub id: Option<String>,
+    /// The `.class` attributes, in order.
+    pub classes: Vec<String>,
+    /// The other attributes, in order. A key without a value has an empty
+    /// one.
+    pub attrs: Vec<(String, String)>,
+    /// The blocks of a container directive; empty for the other forms.
+    pub children: Vec<Block>,
+}
+
+impl Directive {
+    pub fn new(kind: DirectiveKind, name: impl Into<String>) -> Self {
+        Directive {
+            kind,
+            name: name.into(),
+            label: Vec::new(),
+            id: None,
+            classes: Vec::new(),
+            attrs: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// The value of attribute `key`, the last one if it is repeated.
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The number of colons of the fences: longer for a container than
+    /// for the containers nested in it, which its closing fence would
+    /// otherwise close.
+    fn fence_len(&self) -> usize {
+        match self.kind {
+            DirectiveKind::Text => 1,
+            DirectiveKind::Leaf => 2,
+            DirectiveKind::Container => self
+                .children
+                .iter()
+                .filter_map(|b| match b {
+                    Block::Custom(c) => {
+                        let any: &dyn Any = &**c;
+                        any.downcast_ref::<Directive>()
+                            .filter(|d| d.kind == DirectiveKind::Container)
+                    }
+                    _ => None,
+                })
+                .map(|d| d.fence_len() + 1)
+                .fold(3, usize::max),
+        }
+    }
+
+    /// The opening syntax: colons, name, label and attributes.
+    fn head(&self) -> String {
+        let colons = ":".repeat(self.fence_len());
+        let mut out = format!("{colons}{}", self.name);
+        let attrs = self.attributes();
+        if !self.label.is_empty() || (attrs.is_empty() && self.kind == DirectiveKind::Text) {
+            let label = block_to_markdown(
+                &Block::Paragraph(self.label.clone()),
+                &WriterOptions::default(),
+            );
+            out.push('[');
+            out.push_str(label.trim_end_matches('\n'));
+            out.push(']');
+        }
+        if !attrs.is_empty() {
+            out.push('{');
+            out.push_str(&attrs);
+            out.push('}');
+        }
+        out
+    }
+
+    /// The attribute list, without braces.
+    fn attributes(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(id) = &self.id {
+            parts.push(format!("#{id}"));
+        }
+        parts.extend(self.classes.iter().map(|c| format!(".{c}")));
+        for (key, value) in &self.attrs {
+            parts.push(if value.is_empty() {
+                key.clone()
+            } else if value.contains('"') {
+                format!("{key}='{value}'")
+            } else {
+                format!("{key}=\"{value}\"")
+            });
+        }
+        parts.join(" ")
+    }
+
+    fn render(&self, options: &WriterOptions) -> Region {
+        if self.kind != DirectiveKind::Container {
+            return Region::from_str(&self.head());
+        }
+        let mut body = String::new();
+        write_blocks(&mut body, &self.children, options).expect("writing to a String cannot fail");
+        let blank = "\n".repeat(options.blank_lines.max(1));
+        let mut md = self.head();
+        md.push('\n');
+        if !body.trim().is_empty() {
+            md.push_str(&blank);
+            md.push_str(body.trim_end_matches('\n'));
+            md.push('\n');
+        }
+        md.push_str(&blank);
+        md.push_str(&":".repeat(self.fence_len()));
+        Region::from_str(&md)
+    }
+
+    /// The opening HTML tag: `span` for text directives, `div` otherwise,
+    /// with the name in `data-directive`.
+    fn open_tag(&self) -> String {
+        let tag = match self.kind {
+            DirectiveKind::Text => "span",
+            _ => "div",
+        };
+        let mut out = format!("<{tag} data-directive=\"{}\"", escape(&self.name));
+        if let Some(id) = &self.id {
+            out.push_str(&format!(" id=\"{}\"", escape(id)));
+        }
+        if !self.classes.is_empty() {
+            out.push_str(&format!(" class=\"{}\"", escape(&self.classes.join(" "))));
+        }
+        for (key, value) in &self.attrs {
+            out.push_str(&format!(" {}=\"{}\"", escape(key), escape(value)));
+        }
+        out.push('>');
+        out
+    }
+
+    fn label_html(&self) -> String {
+        let html = blocks_to_html(
+            &[Block::Paragraph(self.label.clone())],
+            &HtmlOptions::default(),
+        );
+        let html = html.trim_end_matches('\n');
+        let html = html.strip_prefix("<p>").unwrap_or(html);
+        html.strip_suffix("</p>").unwrap_or(html).to_string()
+    }
+
+    fn html(&self) -> String {
+        match self.kind {
+            DirectiveKind::Text => format!("{}{}</span>", self.open_tag(), self.label_html()),
+            DirectiveKind::Leaf => format!("{}{}</div>\n", self.open_tag(), self.label_html()),
+            DirectiveKind::Container => {
+                let mut out = self.open_tag();
+                out.push('\n');
+                if !self.label.is_empty() {
+                    out.push_str(&format!("<p>{}</p>\n", self.label_html()));
+                }
+                out.push_str(&blocks_to_html(&self.children, &HtmlOptions::default()));
+                out.push_str("</div>\n");
+                out
+            }
+        }
+    }
+
+    fn events(&self) -> Vec<Event<'static>> {
+        if self.kind == DirectiveKind::Text {
+            return vec![Event::Text(CowStr::from(self.head()))];
+        }
+        let mut events = paragraph(self.head()).to_vec();
+        if self.kind == DirectiveKind::Container {
+            events.extend(self.children.iter().flat_map(block_to_events));
+            events.extend(paragraph(":".repeat(self.fence_len())));
+        }
+        events
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+fn paragraph(text: String) -> [Event<'static>; 3] {
+    [
+        Event::Start(Tag::Paragraph),
+        Event::Text(CowStr::from(text)),
+        Event::End(TagEnd::Paragraph),
+    ]
+}
+
+impl BlockNode for Directive {
+    /// The opening line as a paragraph, then for containers the events of
+    /// the children and the closing fence, as the source reads.
+    fn to_events(&self) -> Vec<Event<'static>> {
+        self.events()
+    }
+
+    fn to_region(&self) -> Region {
+        self.render(&WriterOptions::default())
+    }
+
+    fn to_region_in(&self, ctx: &WriteContext) -> Region {
+        self.render(ctx.options)
+    }
+
+    fn to_html(&self) -> Option<String> {
+        Some(self.html())
+    }
+
+    fn node_eq(&self, other: &dyn BlockNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<Directive>() == Some(self)
+    }
+}
+
+impl InlineNode for Directive {
+    /// The directive as written, in one text event.
+    fn to_events(&self) -> Vec<Event<'static>> {
+        self.events()
+    }
+
+    fn to_line(&self) -> Line {
+        Line::from_str(&self.head())
+    }
+
+    fn to_html(&self) -> Option<String> {
+        Some(self.html())
+    }
+
+    fn node_eq(&self, other: &dyn InlineNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<Directive>() == Some(self)
+    }
+}
+
+/// The length of the directive name at the start of `s`: an ASCII letter,
+/// then letters, digits, `-` and `_`.
+fn name_len(s: &str) -> usize {
+    if !s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return 0;
+    }
+    s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(s.len())
+}
+
+type Attributes = (Option<String>, Vec<String>, Vec<(String, String)>);
+
+/// Parse the `{...}` list at the start of `s`, returning it with its
+/// length.
+fn attributes(s: &str) -> Option<(Attributes, usize)> {
+    let mut rest = s.strip_prefix('{')?;
+    let (mut id, mut classes, mut attrs) = (None, Vec::new(), Vec::new());
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some(((id, classes, attrs), s.len() - after.len()));
+        }
+        let (sigil, word) = match rest.strip_prefix(['#', '.']) {
+            Some(word) => (rest.chars().next(), word),
+            None => (None, rest),
+        };
+        // ids and classes end at the next sigil, keys at their value
+        let stop: &[char] = match sigil {
+            Some(_) => &['{', '}', '=', '"', '\'', '#', '.'],
+            None => &['{', '}', '=', '"', '\''],
+        };
+        let n = word
+            .find(|c: char| c.is_whitespace() || stop.contains(&c))
+            .unwrap_or(word.len());
+        if n == 0 {
+            return None;
+        }
+        let key = word[..n].to_string();
+        rest = &word[n..];
+        match sigil {
+            Some('#') => id = Some(key),
+            Some(_) => classes.push(key),
+            None => {
+                let mut value = String::new();
+                if let Some(after) = rest.strip_prefix('=') {
+                    let (v, len) = match after.chars().next()? {
+                        q @ ('"' | '\'') => {
+                            let end = after[1..].find(q)?;
+                            (&after[1..1 + end], end + 2)
+                        }
+                        _ => {
+                            let end = after
+                                .find(|c: char| c.is_whitespace() || c == '}')
+                                .unwrap_or(after.len());
+                            (&after[..end], end)
+                        }
+                    };
+                    value = v.to_string();
+                    rest = &after[len..];
+                }
+                attrs.push((key, value));
+            }
+        }
+    }
+}
+
+/// Parse the directive opening at byte `at` of the text inline
+/// `inlines[i]`, with the given number of colons. The label may hold other
+/// inlines; the attributes must sit in one text inline. Returns the
+/// directive and where it ends: the inline index and the byte offset in
+/// that inline, or the inline index past a non-text inline.
+fn inline_directive(
+    inlines: &[Inline],
+    i: usize,
+    at: usize,
+    kind: DirectiveKind,
+    colons: usize,
+) -> Option<(Directive, usize, usize)> {
+    let text = |j: usize| match inlines.get(j) {
+        Some(Inline::Text(t)) => Some(t.apply()),
+        _ => None,
+    };
+    let first = text(i)?;
+    let s = first.get(at..)?.strip_prefix(&":".repeat(colons)[..])?;
+    let n = name_len(s);
+    if n == 0 {
+        return None;
+    }
+    let mut directive = Directive::new(kind, &s[..n]);
+    let (mut j, mut pos) = (i, first.len() - s.len() + n);
+    let mut has_label = false;
+    if first[pos..].starts_with('[') {
+        has_label = true;
+        pos += 1;
+        let mut depth = 0usize;
+        let mut piece = first.clone();
+        'label: loop {
+            for (k, c) in piece[pos..].char_indices() {
+                match c {
+                    '[' => depth += 1,
+                    ']' if depth > 0 => depth -= 1,
+                    ']' => {
+                        if k > 0 {
+                            directive.label.push(Inline::text(&piece[pos..pos + k]));
+                        }
+                        pos += k + 1;
+                        break 'label;
+                    }
+                    _ => {}
+                }
+            }
+            if pos < piece.len() {
+                directive.label.push(Inline::text(&piece[pos..]));
+            }
+            // the label goes on past this text
+            loop {
+                j += 1;
+                match inlines.get(j)? {
+                    Inline::Text(t) => {
+                        piece = t.apply();
+                        pos = 0;
+                        continue 'label;
+                    }
+                    inl => directive.label.push(inl.clone()),
+                }
+            }
+        }
+    }
+    let piece = text(j)?;
+    let mut has_attrs = false;
+    if let Some(((id, classes, attrs), len)) = attributes(&piece[pos..]) {
+        (directive.id, directive.classes, directive.attrs) = (id, classes, attrs);
+        has_attrs = true;
+        pos += len;
+    }
+    if kind == DirectiveKind::Text && !has_label && !has_attrs {
+        return None;
+    }
+    Some((directive, j, pos))
+}
+
+/// Replace the text directives at this level of `inlines`, and in their
+/// labels.
+fn inline_directives(inlines: &mut Vec<Inline>) {
+    merge_text(inlines);
+    let mut i = 0;
+    while i < inlines.len() {
+        let Inline::Text(t) = &inlines[i] else {
+            i += 1;
+            continue;
+        };
+        let text = t.apply();
+        let found = text.char_indices().find_map(|(at, c)| {
+            let before = text[..at].chars().next_back();
+            if c != ':' || before.is_some_and(|b| b.is_alphanumeric() || b == ':') {
+                return None;
+            }
+            inline_directive(inlines, i, at, DirectiveKind::Text, 1).map(|m| (at, m))
+        });
+        let Some((at, (mut directive, j, pos))) = found else {
+            i += 1;
+            continue;
+        };
+        inline_directives(&mut directive.label);
+        let mut with = Vec::new();
+        if at > 0 {
+            with.push(Inline::text(&text[..at]));
+        }
+        with.push(Inline::Custom(Arc::new(directive)));
+        let rest = match &inlines[j] {
+            Inline::Text(t) => t.apply()[pos..].to_string(),
+            _ => String::new(),
+        };
+        if !rest.is_empty() {
+            with.push(Inline::text(&rest));
+        }
+        let n = with.len();
+        inlines.splice(i..=j, with);
+        // go on with the text after the directive
+        i += n - usize::from(!rest.is_empty());
+    }
+}
+
+struct TextDirectives;
+
+impl VisitMut for TextDirectives {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        match b {
+            Block::Paragraph(children) | Block::Heading { children, .. } => {
+                inline_directives(children)
+            }
+            Block::Table { head, rows, .. } => {
+                for cell in head.iter_mut().chain(rows.iter_mut().flatten()) {
+                    inline_directives(cell);
+                }
+            }
+            Block::TableRow(cells) | Block::TableHead(cells) => {
+                cells.iter_mut().for_each(inline_directives)
+            }
+            Block::Custom(node) => {
+                let any: &dyn Any = &**node;
+                if let Some(directive) = any.downcast_ref::<Directive>() {
+                    let mut directive = directive.clone();
+                    inline_directives(&mut directive.label);
+                    walk_blocks_mut(self, &mut directive.children);
+                    return Edit::Replace(vec![Block::Custom(Arc::new(directive))]);
+                }
+            }
+            _ => walk_block_mut(self, b),
+        }
+        Edit::Keep
+    }
+
+    fn visit_inline_mut(&mut self, _inl: &mut Inline) -> Edit<Inline> {
+        Edit::Keep
+    }
+}
+
+/// Replace `:name[label]{attrs}` text directives in the inline content of
+/// `blocks`, at any depth, with `Inline::Custom` `Directive`s. The labels
+/// of emphasis, links and other inline containers are searched too.
+pub fn text_directives(blocks: &mut Vec<Block>) {
+    walk_blocks_mut(&mut TextDirectives, blocks);
+}
+
+/// The `::name` and `:::name` syntax, parsed into `Directive`s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Directives;
+
+/// A directive opening at the start of paragraph `line` with `colons`
+/// colons or more, and filling the line.
+fn block_directive(line: &[Event], kind: DirectiveKind) -> Option<Directive> {
+    let mut events = vec![Event::Start(Tag::Paragraph)];
+    events.extend(line.iter().cloned());
+    events.push(Event::End(TagEnd::Paragraph));
+    let [Block::Paragraph(mut inlines)] =
+        <[Block; 1]>::try_from(parse_events_to_blocks(&events)).ok()?
+    else {
+        return None;
+    };
+    merge_text(&mut inlines);
+    let Some(Inline::Text(t)) = inlines.first_mut() else {
+        return None;
+    };
+    let text = t.apply();
+    let colons = text.len() - text.trim_start_matches(':').len();
+    let fits = match kind {
+        DirectiveKind::Leaf => colons == 2,
+        _ => colons >= 3,
+    };
+    if !fits {
+        return None;
+    }
+    // the fence may be longer than three colons
+    let trimmed = colons - colons.clamp(2, 3);
+    let colons = colons - trimmed;
+    let (mut directive, j, pos) = inline_directive(&inlines, 0, trimmed, kind, colons)?;
+    let rest = match &inlines[j] {
+        Inline::Text(t) => t.apply()[pos..].to_string(),
+        _ => String::new(),
+    };
+    if j + 1 != inlines.len() || !rest.trim().is_empty() {
+        return None;
+    }
+    inline_directives(&mut directive.label);
+    Some(directive)
+}
+
+impl Directives {
+    fn parse_at(&self, events: &[Event], ctx: &ParseContext) -> Option<(usize, Directive)> {
+        if ctx.parent_collects_inlines
+            || !matches!(events.first(), Some(Event::Start(Tag::Paragraph)))
+        {
+            return None;
+        }
+        // a leaf directive is a paragraph of one line
+        let end = events
+            .iter()
+            .position(|e| matches!(e, Event::End(TagEnd::Paragraph)))?;
+        let line = &events[1..end];
+        if !line.iter().any(|e| matches!(e, Event::SoftBreak))
+            && let Some(directive) = block_directive(line, DirectiveKind::Leaf)
+        {
+            return Some((end + 1, directive));
+        }
+        let opening = |line: &[Event]| block_directive(line, DirectiveKind::Container);
+        let (n, mut directive, inner) = scan_container(events, opening, true)?;
+        directive.children = self.parse_inner(&inner);
+        Some((n, directive))
+    }
+
+    /// Parse the blocks between the fences, nested directives included.
+    fn parse_inner(&self, events: &[Event]) -> Vec<Block> {
+        let mut hook = |evs: &[Event], _i: usize, ctx: &ParseContext| {
+            self.parse_at(evs, ctx)
+                .map(|(n, node)| (n, HookResult::Block(Block::Custom(Arc::new(node)))))
+        };
+        parse_events_to_blocks_with_hook(events, Some(&mut hook))
+    }
+}
+
+impl Extension for Directives {
+    type Node = Directive;
+
+    fn try_parse(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, Directive)> {
+        self.parse_at(events, ctx)
+    }
+
+    fn write_markdown(&self, node: &Directive) -> Region {
+        BlockNode::to_region(node)
+    }
+
+    fn write_markdown_in(&self, node: &Directive, ctx: &WriteContext) -> Region {
+        BlockNode::to_region_in(node, ctx)
+    }
+
+    fn write_html(&self, node: &Directive) -> Option<String> {
+        BlockNode::to_html(node)
+    }
+}
+
+/// Parse `events` with `Directives` and `text_directives`, the block forms
+/// first.
+pub fn parse_directives(events: &[Event]) -> Vec<Block> {
+    let mut blocks = Directives.parse_inner(events);
+    text_directives(&mut blocks);
+    blocks
+}
@@ -0,0 +1,393 @@
+//! Whole-document representation.
+//!
+//! A `Document` splits the pieces of a markdown file that are not tied to a
+//! position in the block flow out of `blocks`: front matter, footnote
+//! definitions, and link reference definitions. Features that need a
+//! document-wide view (collecting references, placing footnotes, front
+//! matter) can work on these directly.
+
+use crate::Region;
+use crate::ast::block::Block;
+use crate::ast::inline::Inline;
+use crate::ast::label_key;
+use crate::ast::parse::{link_definition_blocks, parse_events_to_blocks};
+use crate::ast::writer::{
+    WriterOptions, blocks_to_markdown_with_unique_definitions, render_link_definition,
+};
+use pulldown_cmark::{Event, LinkType, MetadataBlockKind, Options, Parser};
+
+/// A link reference definition (`[id]: dest "title"`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkDefinition {
+    pub id: String,
+    pub dest: String,
+    pub title: String,
+}
+
+impl From<LinkDefinition> for Block {
+    fn from(def: LinkDefinition) -> Self {
+        Block::LinkDefinition {
+            id: def.id,
+            dest: def.dest,
+            title: def.title,
+        }
+    }
+}
+
+/// Two reference definitions of one id with different targets, found by
+/// `Document::dedupe_definitions`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefinitionConflict {
+    /// The definition that was kept, the first of its id.
+    pub kept: LinkDefinition,
+    /// A later definition of the same id, dropped.
+    pub dropped: LinkDefinition,
+}
+
+/// A parsed markdown document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Document {
+    /// Body blocks, without front matter and top-level footnote definitions.
+    pub blocks: Vec<Block>,
+    /// Link reference definitions, in source order.
+    pub link_definitions: Vec<LinkDefinition>,
+    /// Top-level footnote definitions as `(label, children)`, in source order.
+    pub footnotes: Vec<(String, Vec<Block>)>,
+    /// Front matter, if the document starts with a metadata block.
+    pub metadata: Option<(MetadataBlockKind, Region)>,
+}
+
+impl Document {
+    /// Split a block list into a document. The first metadata block becomes
+    /// the front matter, and top-level footnote and link definitions move
+    /// to `footnotes` and `link_definitions`. Definitions for reference-style
+    /// links without a `Block::LinkDefinition` are recovered from the links.
+    pub fn from_blocks(blocks: Vec<Block>) -> Self {
+        let mut doc = Document::default();
+        let mut inferred = Vec::new();
+        collect_link_definitions(&blocks, &mut inferred);
+        for b in blocks {
+            match b {
+                Block::Metadata { kind, content } if doc.metadata.is_none() => {
+                    doc.metadata = Some((kind, content));
+                }
+                Block::FootnoteDefinition(label, children) => {
+                    doc.footnotes.push((label, children));
+                }
+                Block::LinkDefinition { id, dest, title } => {
+                    // the first definition of a label wins, as in markdown
+                    if doc.link_definition(&id).is_none() {
+                        doc.link_definitions
+                            .push(LinkDefinition { id, dest, title });
+                    }
+                }
+                other => doc.blocks.push(other),
+            }
+        }
+        for def in inferred {
+            if doc.link_definition(&def.id).is_none() {
+                doc.link_definitions.push(def);
+            }
+        }
+        doc
+    }
+
+    /// Flatten the document back into blocks: front matter first, then the
+    /// body, footnote definitions and link definitions.
+    pub fn into_blocks(self) -> Vec<Block> {
+        let mut out = Vec::new();
+        if let Some((kind, content)) = self.metadata {
+            out.push(Block::Metadata { kind, content });
+        }
+        out.extend(self.blocks);
+        out.extend(
+            self.footnotes
+                .into_iter()
+                .map(|(label, children)| Block::FootnoteDefinition(label, children)),
+        );
+        out.extend(self.link_definitions.into_iter().map(Block::from));
+        out
+    }
+
+    /// Point every reference-style link and image at its definition in
+    /// `link_definitions`, updating `dest` and `title`, so edits to the
+    /// definitions carry over to the links. Returns the ids that have no
+    /// definition (each once); those links are left alone.
+    pub fn resolve_references(&mut self) -> Vec<String> {
+        let mut missing = Vec::new();
+        resolve_in_blocks(&mut self.blocks, &self.link_definitions, &mut missing);
+        for (_, children) in &mut self.footnotes {
+            resolve_in_blocks(children, &self.link_definitions, &mut missing);
+        }
+        missing
+    }
+
+    /// Gather the reference definitions of the document into
+    /// `link_definitions`, once per id, before writing it. Definitions are
+    /// taken from `link_definitions` first, then from the reference links
+    /// of the body and footnotes; the first one of an id wins, as in
+    /// markdown, and every link is pointed at it. Returns the definitions
+    /// dropped because an earlier one of the same id has another target.
+    pub fn dedupe_definitions(&mut self) -> Vec<DefinitionConflict> {
+        let mut found = std::mem::take(&mut self.link_definitions);
+        collect_link_definitions(&self.blocks, &mut found);
+        for (_, children) in &self.footnotes {
+            collect_link_definitions(children, &mut found);
+        }
+        let mut conflicts = Vec::new();
+        for def in found {
+            match self.link_definition(&def.id) {
+                None => self.link_definitions.push(def),
+                Some(kept) if kept.dest != def.dest || kept.title != def.title => {
+                    let conflict = DefinitionConflict {
+                        kept: kept.clone(),
+                        dropped: def,
+                    };
+                    if !conflicts.contains(&conflict) {
+                        conflicts.push(conflict);
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        self.resolve_references();
+        conflicts
+    }
+
+    /// Look up a link definition by id, ignoring case as markdown does.
+    pub fn link_definition(&self, id: &str) -> Option<&LinkDefinition> {
+        self.link_definitions
+            .iter()
+            .find(|d| labels_match(&d.id, id))
+    }
+}
+
+fn labels_match(a: &str, b: &str) -> bool {
+    label_key(a) == label_key(b)
+}
+
+fn collect_link_definitions(blocks: &[Block], out: &mut Vec<LinkDefinition>) {
+    for b in blocks {
+        match b {
+            Block::Paragraph(inls) | Block::Heading { children: inls, .. } => {
+                collect_inline_definitions(inls, out)
+            }
+            Block::BlockQuote(children)
+            | Block::Alert { children, .. }
+            | Block::Item(children)
+            | Block::FootnoteDefinition(_, children) => collect_link_definitions(children, out),
+            Block::List { items, .. } => {
+                for item in items {
+                    collect_link_definitions(item, out);
+                }
+            }
+            Block::TableRow(cells) | Block::TableHead(cells) => {
+                for cell in cells {
+                    collect_inline_definitions(cell, out);
+                }
+            }
+            Block::Table { head, rows, .. } => {
+                for cell in head.iter().chain(rows.iter().flatten()) {
+                    collect_inline_definitions(cell, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_inline_definitions(inls: &[Inline], out: &mut Vec<LinkDefinition>) {
+    for inl in inls {
+        match inl {
+            Inline::Link {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            }
+            | Inline::Image {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => {
+                let is_reference = matches!(
+                    link_type,
+                    LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut
+                );
+                if is_reference && !id.is_empty() {
+                    out.push(LinkDefinition {
+                        id: id.clone(),
+                        dest: dest.clone(),
+                        title: title.clone(),
+                    });
+                }
+                collect_inline_definitions(children, out);
+            }
+            Inline::Emphasis(c)
+            | Inline::Strong(c)
+            | Inline::Strikethrough(c)
+            | Inline::Superscript(c)
+            | Inline::Subscript(c) => collect_inline_definitions(c, out),
+            _ => {}
+        }
+    }
+}
+
+fn resolve_in_blocks(blocks: &mut [Block], defs: &[LinkDefinition], missing: &mut Vec<String>) {
+    for b in blocks {
+        match b {
+            Block::Paragraph(inls) | Block::Heading { children: inls, .. } => {
+                resolve_in_inlines(inls, defs, missing)
+            }
+            Block::BlockQuote(children)
+            | Block::Alert { children, .. }
+            | Block::Item(children)
+            | Block::FootnoteDefinition(_, children) => resolve_in_blocks(children, defs, missing),
+            Block::List { items, .. } => {
+                for item in items {
+                    resolve_in_blocks(item, defs, missing);
+                }
+            }
+            Block::TableRow(cells) | Block::TableHead(cells) => {
+                for cell in cells {
+                    resolve_in_inlines(cell, defs, missing);
+                }
+            }
+            Block::Table { head, rows, .. } => {
+                for cell in head.iter_mut().chain(rows.iter_mut().flatten()) {
+                    resolve_in_inlines(cell, defs, missing);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn resolve_in_inlines(inls: &mut [Inline], defs: &[LinkDefinition], missing: &mut Vec<String>) {
+    for inl in inls {
+        match inl {
+            Inline::Link {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            }
+            | Inline::Image {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => {
+                let is_reference = matches!(
+                    link_type,
+                    LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut
+                );
+                if is_reference && !id.is_empty() {
+                    match defs.iter().find(|d| labels_match(&d.id, id)) {
+                        Some(def) => {
+                            *dest = def.dest.clone();
+                            *title = def.title.clone();
+                        }
+                        None if !missing.iter().any(|m| labels_match(m, id)) => {
+                            missing.push(id.clone())
+                        }
+                        None => {}
+                    }
+                }
+                resolve_in_inlines(children, defs, missing);
+            }
+            Inline::Emphasis(c)
+            | Inline::Strong(c)
+            | Inline::Strikethrough(c)
+            | Inline::Superscript(c)
+            | Inline::Subscript(c) => resolve_in_inlines(c, defs, missing),
+            _ => {}
+        }
+    }
+}
+
+/// Parse an event stream into a document. Only definitions used by a
+/// reference-style link can be recovered from events; use
+/// `parse_markdown_to_document` to keep unused ones as well.
+pub fn parse_events_to_document<'a>(events: &[Event<'a>]) -> Document {
+    Document::from_blocks(parse_events_to_blocks(events))
+}
+
+/// Parse markdown source into a document, taking the link definitions
+/// straight from the parser so unused definitions are kept too.
+pub fn parse_markdown_to_document(src: &str, options: Options) -> Document {
+    parse_parser_to_document(Parser::new_ext(src, options))
+}
+
+/// Parse the events of `parser` into a document, keeping every link
+/// definition of the source (see `parse_markdown_to_document`).
+pub fn parse_parser_to_document(parser: Parser<'_>) -> Document {
+    Document::from_blocks(parse_parser_to_blocks(parser))
+}
+
+/// Parse the events of `parser` into blocks, followed by a
+/// `Block::LinkDefinition` for every link definition of the source, in
+/// source order. Written back, the definitions appear once each, from
+/// these blocks (see `write_blocks`).
+pub fn parse_parser_to_blocks(parser: Parser<'_>) -> Vec<Block> {
+    // the definitions are known before the first event is produced
+    let defs = link_definition_blocks(parser.reference_definitions());
+    let events: Vec<Event> = parser.collect();
+    let mut blocks = parse_events_to_blocks(&events);
+    blocks.extend(defs);
+    blocks
+}
+
+/// Write a document as markdown. Definitions referenced from the body are
+/// written next to the first paragraph that uses them, as
+/// `blocks_to_markdown` does, but only once each; the remaining ones are
+/// written at the end of the document. Definitions are gathered and
+/// deduplicated first (see `Document::dedupe_definitions`), so
+/// `link_definitions` take precedence over the targets stored in the links,
+/// and links disagreeing on the target of an id all get the first one.
+pub fn document_to_markdown(doc: &Document) -> String {
+    document_to_markdown_with(doc, &WriterOptions::default())
+}
+
+/// Write a document like `document_to_markdown`, with `options`.
+pub fn document_to_markdown_with(doc: &Document, options: &WriterOptions) -> String {
+    let mut doc = doc.clone();
+    doc.dedupe_definitions();
+    let mut blocks = Vec::new();
+    if let Some((kind, content)) = &doc.metadata {
+        blocks.push(Block::Metadata {
+            kind: *kind,
+            content: content.clone(),
+        });
+    }
+    blocks.extend(doc.blocks.iter().cloned());
+    blocks.extend(
+        doc.footnotes
+            .iter()
+            .map(|(label, children)| Block::FootnoteDefinition(label.clone(), children.clone())),
+    );
+    let mut out = blocks_to_markdown_with_unique_definitions(&blocks, options);
+
+    let mut used = Vec::new();
+    collect_link_definitions(&blocks, &mut used);
+    let unused: Vec<&LinkDefinition> = doc
+        .link_definitions
+        .iter()
+        .filter(|d| !used.iter().any(|u| labels_match(&u.id, &d.id)))
+        .collect();
+    if !unused.is_empty() && !out.is_empty() {
+        out.push_str(&"\n".repeat(options.blank_lines));
+    }
+    for def in unused {
+        out.push_str(&render_link_definition(&def.id, &def.dest, &def.title).apply());
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,409 @@
+//! Crate-owned event model.
+//!
+//! The AST layer speaks pulldown-cmark's `Event` type, whose text borrows
+//! from the source. `MdEvent` is an owned mirror of that model, holding
+//! `String`s: producers of events that have no source to borrow from (HTML
+//! readers, mdast importers, hand-written generators) can emit `MdEvent`s,
+//! keep them around or serialize them, and pulldown events from any source
+//! can be converted into them. The small `Copy` enums inside tags, such as
+//! `HeadingLevel` and `LinkType`, are pulldown-cmark's own.
+//!
+//! Every event converts both ways: `MdEvent::to_pulldown` and
+//! `MdEvent::from` are inverses. The enums are `#[non_exhaustive]` so the
+//! model can follow pulldown-cmark as it grows; `EVENT_MODEL_VERSION` is
+//! bumped whenever variants are added or their meaning changes.
+
+use crate::ast::block::{Block, block_to_events};
+use crate::ast::parse::parse_events_to_blocks;
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, LinkType,
+    MetadataBlockKind, Tag, TagEnd,
+};
+
+/// Version of the event model described by `MdEvent`, `MdTag` and
+/// `MdTagEnd`.
+pub const EVENT_MODEL_VERSION: u32 = 1;
+
+/// An owned markdown event. Mirrors `pulldown_cmark::Event`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MdEvent {
+    Start(MdTag),
+    End(MdTagEnd),
+    Text(String),
+    Code(String),
+    InlineMath(String),
+    DisplayMath(String),
+    Html(String),
+    InlineHtml(String),
+    FootnoteReference(String),
+    SoftBreak,
+    HardBreak,
+    Rule,
+    TaskListMarker(bool),
+}
+
+/// An owned start tag. Mirrors `pulldown_cmark::Tag`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MdTag {
+    Paragraph,
+    Heading {
+        level: HeadingLevel,
+        id: Option<String>,
+        classes: Vec<String>,
+        attrs: Vec<(String, Option<String>)>,
+    },
+    BlockQuote(Option<BlockQuoteKind>),
+    /// A code block; `Some(info)` for fenced blocks, `None` for indented.
+    CodeBlock(Option<String>),
+    HtmlBlock,
+    List(Option<u64>),
+    Item,
+    FootnoteDefinition(String),
+    DefinitionList,
+    DefinitionListTitle,
+    DefinitionListDefinition,
+    Table(Vec<Alignment>),
+    TableHead,
+    TableRow,
+    TableCell,
+    Emphasis,
+    Strong,
+    Strikethrough,
+    Superscript,
+    Subscript,
+    Link {
+        link_type: LinkType,
+        dest_url: String,
+        title: String,
+        id: String,
+    },
+    Image {
+        link_type: LinkType,
+        dest_url: String,
+        title: String,
+        id: String,
+    },
+    MetadataBlock(MetadataBlockKind),
+}
+
+/// An end tag. Mirrors `pulldown_cmark::TagEnd`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum MdTagEnd {
+    Paragraph,
+    Heading(HeadingLevel),
+    BlockQuote(Option<BlockQuoteKind>),
+    CodeBlock,
+    HtmlBlock,
+    /// `true` for ordered lists.
+    List(bool),
+    Item,
+    FootnoteDefinition,
+    DefinitionList,
+    DefinitionListTitle,
+    DefinitionListDefinition,
+    Table,
+    TableHead,
+    TableRow,
+    TableCell,
+    Emphasis,
+    Strong,
+    Strikethrough,
+    Superscript,
+    Subscript,
+    Link,
+    Image,
+    MetadataBlock(MetadataBlockKind),
+}
+
+impl MdTag {
+    /// The end tag closing this tag.
+    pub fn to_end(&self) -> MdTagEnd {
+        match self {
+            MdTag::Paragraph => MdTagEnd::Paragraph,
+            MdTag::Heading { level, .. } => MdTagEnd::Heading(*level),
+            MdTag::BlockQuote(kind) => MdTagEnd::BlockQuote(*kind),
+            MdTag::CodeBlock(_) => MdTagEnd::CodeBlock,
+            MdTag::HtmlBlock => MdTagEnd::HtmlBlock,
+            MdTag::List(start) => MdTagEnd::List(start.is_some()),
+            MdTag::Item => MdTagEnd::Item,
+            MdTag::FootnoteDefinition(_) => MdTagEnd::FootnoteDefinition,
+            MdTag::DefinitionList => MdTagEnd::DefinitionList,
+            MdTag::DefinitionListTitle => MdTagEnd::DefinitionListTitle,
+            MdTag::DefinitionListDefinition => MdTagEnd::DefinitionListDefinition,
+            MdTag::Table(_) => MdTagEnd::Table,
+            MdTag::TableHead => MdTagEnd::TableHead,
+            MdTag::TableRow => MdTagEnd::TableRow,
+            MdTag::TableCell => MdTagEnd::TableCell,
+            MdTag::Emphasis => MdTagEnd::Emphasis,
+            MdTag::Strong => MdTagEnd::Strong,
+            MdTag::Strikethrough => MdTagEnd::Strikethrough,
+            MdTag::Superscript => MdTagEnd::Superscript,
+            MdTag::Subscript => MdTagEnd::Subscript,
+            MdTag::Link { .. } => MdTagEnd::Link,
+            MdTag::Image { .. } => MdTagEnd::Image,
+            MdTag::MetadataBlock(kind) => MdTagEnd::MetadataBlock(*kind),
+        }
+    }
+
+    /// Convert to a pulldown-cmark tag.
+    pub fn to_pulldown(&self) -> Tag<'static> {
+        fn cow(s: &str) -> CowStr<'static> {
+            CowStr::from(s.to_string())
+        }
+        match self {
+            MdTag::Paragraph => Tag::Paragraph,
+            MdTag::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+            } => Tag::Heading {
+                level: *level,
+                id: id.as_deref().map(cow),
+                classes: classes.iter().map(|c| cow(c)).collect(),
+                attrs: attrs
+                    .iter()
+                    .map(|(k, v)| (cow(k), v.as_deref().map(cow)))
+                    .collect(),
+            },
+            MdTag::BlockQuote(kind) => Tag::BlockQuote(*kind),
+            MdTag::CodeBlock(Some(info)) => Tag::CodeBlock(CodeBlockKind::Fenced(cow(info))),
+            MdTag::CodeBlock(None) => Tag::CodeBlock(CodeBlockKind::Indented),
+            MdTag::HtmlBlock => Tag::HtmlBlock,
+            MdTag::List(start) => Tag::List(*start),
+            MdTag::Item => Tag::Item,
+            MdTag::FootnoteDefinition(label) => Tag::FootnoteDefinition(cow(label)),
+            MdTag::DefinitionList => Tag::DefinitionList,
+            MdTag::DefinitionListTitle => Tag::DefinitionListTitle,
+            MdTag::DefinitionListDefinition => Tag::DefinitionListDefinition,
+            MdTag::Table(aligns) => Tag::Table(aligns.clone()),
+            MdTag::TableHead => Tag::TableHead,
+            MdTag::TableRow => Tag::TableRow,
+            MdTag::TableCell => Tag::TableCell,
+            MdTag::Emphasis => Tag::Emphasis,
+            MdTag::Strong => Tag::Strong,
+            MdTag::Strikethrough => Tag::Strikethrough,
+            MdTag::Superscript => Tag::Superscript,
+            MdTag::Subscript => Tag::Subscript,
+            MdTag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            } => Tag::Link {
+                link_type: *link_type,
+                dest_url: cow(dest_url),
+                title: cow(title),
+                id: cow(id),
+            },
+            MdTag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            } => Tag::Image {
+                link_type: *link_type,
+                dest_url: cow(dest_url),
+                title: cow(title),
+                id: cow(id),
+            },
+            MdTag::MetadataBlock(kind) => Tag::MetadataBlock(*kind),
+        }
+    }
+}
+
+impl From<&Tag<'_>> for MdTag {
+    fn from(t: &Tag<'_>) -> Self {
+        match t {
+            Tag::Paragraph => MdTag::Paragraph,
+            Tag::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+            } => MdTag::Heading {
+                level: *level,
+                id: id.as_ref().map(|s| s.to_string()),
+                classes: classes.iter().map(|c| c.to_string()).collect(),
+                attrs: attrs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.as_ref().map(|s| s.to_string())))
+                    .collect(),
+            },
+            Tag::BlockQuote(kind) => MdTag::BlockQuote(*kind),
+            Tag::CodeBlock(CodeBlockKind::Fenced(info)) => MdTag::CodeBlock(Some(info.to_string())),
+            Tag::CodeBlock(CodeBlockKind::Indented) => MdTag::CodeBlock(None),
+            Tag::HtmlBlock => MdTag::HtmlBlock,
+            Tag::List(start) => MdTag::List(*start),
+            Tag::Item => MdTag::Item,
+            Tag::FootnoteDefinition(label) => MdTag::FootnoteDefinition(label.to_string()),
+            Tag::DefinitionList => MdTag::DefinitionList,
+            Tag::DefinitionListTitle => MdTag::DefinitionListTitle,
+            Tag::DefinitionListDefinition => MdTag::DefinitionListDefinition,
+            Tag::Table(aligns) => MdTag::Table(aligns.clone()),
+            Tag::TableHead => MdTag::TableHead,
+            Tag::TableRow => MdTag::TableRow,
+            Tag::TableCell => MdTag::TableCell,
+            Tag::Emphasis => MdTag::Emphasis,
+            Tag::Strong => MdTag::Strong,
+            Tag::Strikethrough => MdTag::Strikethrough,
+            Tag::Superscript => MdTag::Superscript,
+            Tag::Subscript => MdTag::Subscript,
+            Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            } => MdTag::Link {
+                link_type: *link_type,
+                dest_url: dest_url.to_string(),
+                title: title.to_string(),
+                id: id.to_string(),
+            },
+            Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            } => MdTag::Image {
+                link_type: *link_type,
+                dest_url: dest_url.to_string(),
+                title: title.to_string(),
+                id: id.to_string(),
+            },
+            Tag::MetadataBlock(kind) => MdTag::MetadataBlock(*kind),
+        }
+    }
+}
+
+impl MdTagEnd {
+    /// Convert to a pulldown-cmark end tag.
+    pub fn to_pulldown(&self) -> TagEnd {
+        match *self {
+            MdTagEnd::Paragraph => TagEnd::Paragraph,
+            MdTagEnd::Heading(level) => TagEnd::Heading(level),
+            MdTagEnd::BlockQuote(kind) => TagEnd::BlockQuote(kind),
+            MdTagEnd::CodeBlock => TagEnd::CodeBlock,
+            MdTagEnd::HtmlBlock => TagEnd::HtmlBlock,
+            MdTagEnd::List(ordered) => TagEnd::List(ordered),
+            MdTagEnd::Item => TagEnd::Item,
+            MdTagEnd::FootnoteDefinition => TagEnd::FootnoteDefinition,
+            MdTagEnd::DefinitionList => TagEnd::DefinitionList,
+            MdTagEnd::DefinitionListTitle => TagEnd::DefinitionListTitle,
+            MdTagEnd::DefinitionListDefinition => TagEnd::DefinitionListDefinition,
+            MdTagEnd::Table => TagEnd::Table,
+            MdTagEnd::TableHead => TagEnd::TableHead,
+            MdTagEnd::TableRow => TagEnd::TableRow,
+            MdTagEnd::TableCell => TagEnd::TableCell,
+            MdTagEnd::Emphasis => TagEnd::Emphasis,
+            MdTagEnd::Strong => TagEnd::Strong,
+            MdTagEnd::Strikethrough => TagEnd::Strikethrough,
+            MdTagEnd::Superscript => TagEnd::Superscript,
+            MdTagEnd::Subscript => TagEnd::Subscript,
+            MdTagEnd::Link => TagEnd::Link,
+            MdTagEnd::Image => TagEnd::Image,
+            MdTagEnd::MetadataBlock(kind) => TagEnd::MetadataBlock(kind),
+        }
+    }
+}
+
+impl From<TagEnd> for MdTagEnd {
+    fn from(t: TagEnd) -> Self {
+        match t {
+            TagEnd::Paragraph => MdTagEnd::Paragraph,
+            TagEnd::Heading(level) => MdTagEnd::Heading(level),
+            TagEnd::BlockQuote(kind) => MdTagEnd::BlockQuote(kind),
+            TagEnd::CodeBlock => MdTagEnd::CodeBlock,
+            TagEnd::HtmlBlock => MdTagEnd::HtmlBlock,
+            TagEnd::List(ordered) => MdTagEnd::List(ordered),
+            TagEnd::Item => MdTagEnd::Item,
+            TagEnd::FootnoteDefinition => MdTagEnd::FootnoteDefinition,
+            TagEnd::DefinitionList => MdTagEnd::DefinitionList,
+            TagEnd::DefinitionListTitle => MdTagEnd::DefinitionListTitle,
+            TagEnd::DefinitionListDefinition => MdTagEnd::DefinitionListDefinition,
+            TagEnd::Table => MdTagEnd::Table,
+            TagEnd::TableHead => MdTagEnd::TableHead,
+            TagEnd::TableRow => MdTagEnd::TableRow,
+            TagEnd::TableCell => MdTagEnd::TableCell,
+            TagEnd::Emphasis => MdTagEnd::Emphasis,
+            TagEnd::Strong => MdTagEnd::Strong,
+            TagEnd::Strikethrough => MdTagEnd::Strikethrough,
+            TagEnd::Superscript => MdTagEnd::Superscript,
+            TagEnd::Subscript => MdTagEnd::Subscript,
+            TagEnd::Link => MdTagEnd::Link,
+            TagEnd::Image => MdTagEnd::Image,
+            TagEnd::MetadataBlock(kind) => MdTagEnd::MetadataBlock(kind),
+        }
+    }
+}
+
+impl MdEvent {
+    /// Convert to a pulldown-cmark event.
+    pub fn to_pulldown(&self) -> Event<'static> {
+        fn cow(s: &str) -> CowStr<'static> {
+            CowStr::from(s.to_string())
+        }
+        match self {
+            MdEvent::Start(tag) => Event::Start(tag.to_pulldown()),
+            MdEvent::End(end) => Event::End(end.to_pulldown()),
+            MdEvent::Text(s) => Event::Text(cow(s)),
+            MdEvent::Code(s) => Event::Code(cow(s)),
+            MdEvent::InlineMath(s) => Event::InlineMath(cow(s)),
+            MdEvent::DisplayMath(s) => Event::DisplayMath(cow(s)),
+            MdEvent::Html(s) => Event::Html(cow(s)),
+            MdEvent::InlineHtml(s) => Event::InlineHtml(cow(s)),
+            MdEvent::FootnoteReference(s) => Event::FootnoteReference(cow(s)),
+            MdEvent::SoftBreak => Event::SoftBreak,
+            MdEvent::HardBreak => Event::HardBreak,
+            MdEvent::Rule => Event::Rule,
+            MdEvent::TaskListMarker(checked) => Event::TaskListMarker(*checked),
+        }
+    }
+}
+
+impl From<&Event<'_>> for MdEvent {
+    fn from(ev: &Event<'_>) -> Self {
+        match ev {
+            Event::Start(tag) => MdEvent::Start(MdTag::from(tag)),
+            Event::End(end) => MdEvent::End(MdTagEnd::from(*end)),
+            Event::Text(s) => MdEvent::Text(s.to_string()),
+            Event::Code(s) => MdEvent::Code(s.to_string()),
+            Event::InlineMath(s) => MdEvent::InlineMath(s.to_string()),
+            Event::DisplayMath(s) => MdEvent::DisplayMath(s.to_string()),
+            Event::Html(s) => MdEvent::Html(s.to_string()),
+            Event::InlineHtml(s) => MdEvent::InlineHtml(s.to_string()),
+            Event::FootnoteReference(s) => MdEvent::FootnoteReference(s.to_string()),
+            Event::SoftBreak => MdEvent::SoftBreak,
+            Event::HardBreak => MdEvent::HardBreak,
+            Event::Rule => MdEvent::Rule,
+            Event::TaskListMarker(checked) => MdEvent::TaskListMarker(*checked),
+        }
+    }
+}
+
+impl From<Event<'_>> for MdEvent {
+    fn from(ev: Event<'_>) -> Self {
+        MdEvent::from(&ev)
+    }
+}
+
+/// Parse crate-owned events into blocks.
+pub fn parse_md_events_to_blocks(events: &[MdEvent]) -> Vec<Block> {
+    let evs: Vec<Event<'static>> = events.iter().map(MdEvent::to_pulldown).collect();
+    parse_events_to_blocks(&evs)
+}
+
+/// Convert blocks into crate-owned events.
+pub fn blocks_to_md_events(blocks: &[Block]) -> Vec<MdEvent> {
+    blocks
+        .iter()
+        .flat_map(block_to_events)
+        .map(MdEvent::from)
+        .collect()
+}
@@ -0,0 +1,292 @@
+//! Lazy, borrowing counterpart of `block_to_events`.
+//!
+//! `block_events_iter` walks a block with an explicit stack and yields its
+//! events one at a time. Strings held by the AST (link destinations,
+//! labels, single-fragment text) are borrowed rather than cloned, so large
+//! documents can be fed to `pulldown_cmark::html::push_html` or any other
+//! event consumer without building their event vector first. The events
+//! are the same as those of `block_to_events`.
+
+use crate::ast::block::block_to_events;
+use crate::ast::custom::{BlockNode, InlineNode};
+use crate::ast::inline::inline_to_events;
+use crate::ast::{Block, Inline};
+use crate::text::Region;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag, TagEnd};
+use std::slice;
+
+/// Iterate over the events of `b` lazily. See the module documentation.
+pub fn block_events_iter(b: &Block) -> impl Iterator<Item = Event<'_>> {
+    BlockEvents {
+        stack: vec![Frame::Blocks(slice::from_ref(b).iter())],
+        custom: None,
+    }
+}
+
+/// A custom node met by `block_events_iter_with`.
+pub(crate) enum CustomNode<'a> {
+    Block(&'a dyn BlockNode),
+    Inline(&'a dyn InlineNode),
+}
+
+/// Replaces the events of a custom node with a single event, or leaves
+/// them with `None`.
+pub(crate) type CustomEvent<'f> = dyn Fn(CustomNode<'_>) -> Option<Event<'static>> + 'f;
+
+/// `block_events_iter`, with the events of custom nodes, at any depth,
+/// replaced by the event `custom` returns for them.
+pub(crate) fn block_events_iter_with<'a>(
+    b: &'a Block,
+    custom: &'a CustomEvent<'a>,
+) -> impl Iterator<Item = Event<'a>> {
+    BlockEvents {
+        stack: vec![Frame::Blocks(slice::from_ref(b).iter())],
+        custom: Some(custom),
+    }
+}
+
+/// Work left at one level of the walk.
+enum Frame<'a> {
+    Blocks(slice::Iter<'a, Block>),
+    Inlines(slice::Iter<'a, Inline>),
+    /// The items of a list, each wrapped in `Item` tags.
+    Items(slice::Iter<'a, Vec<Block>>),
+    /// Table body rows, each wrapped in `TableRow` tags.
+    Rows(slice::Iter<'a, Vec<Vec<Inline>>>),
+    /// Table cells, each wrapped in `TableCell` tags.
+    Cells(slice::Iter<'a, Vec<Inline>>),
+    /// Events built ahead, e.g. the closing tag of a container.
+    Ready(std::vec::IntoIter<Event<'a>>),
+}
+
+struct BlockEvents<'a> {
+    stack: Vec<Frame<'a>>,
+    custom: Option<&'a CustomEvent<'a>>,
+}
+
+/// The text of a region, borrowed when it is a single fragment.
+fn region_text(r: &Region) -> CowStr<'_> {
+    match r.as_single_str() {
+        Some(s) => CowStr::Borrowed(s),
+        None => CowStr::from(r.apply()),
+    }
+}
+
+impl<'a> BlockEvents<'a> {
+    /// Queue the events following the first one of a node, which is
+    /// returned.
+    fn then(&mut self, first: Event<'a>, rest: Vec<Event<'a>>) -> Option<Event<'a>> {
+        self.stack.push(Frame::Ready(rest.into_iter()));
+        Some(first)
+    }
+
+    /// Open a container: queue its closing tag, then its children.
+    fn open(&mut self, start: Tag<'a>, children: Frame<'a>) -> Option<Event<'a>> {
+        let end = start.to_end();
+        self.stack
+            .push(Frame::Ready(vec![Event::End(end)].into_iter()));
+        self.stack.push(children);
+        Some(Event::Start(start))
+    }
+
+    /// Queue events computed eagerly, for nodes without a lazy form.
+    fn eager(&mut self, events: Vec<Event<'static>>) -> Option<Event<'a>> {
+        self.stack.push(Frame::Ready(events.into_iter()));
+        None
+    }
+
+    fn open_block(&mut self, b: &'a Block) -> Option<Event<'a>> {
+        match b {
+            Block::Paragraph(children) => {
+                self.open(Tag::Paragraph, Frame::Inlines(children.iter()))
+            }
+            Block::Heading {
+                level,
+                id,
+                children,
+                ..
+            } => self.open(
+                Tag::Heading {
+                    level: *level,
+                    id: id.as_deref().map(CowStr::Borrowed),
+                    classes: vec![],
+                    attrs: vec![],
+                },
+                Frame::Inlines(children.iter()),
+            ),
+            Block::BlockQuote(children) => {
+                self.open(Tag::BlockQuote(None), Frame::Blocks(children.iter()))
+            }
+            Block::Alert { kind, children } => {
+                self.open(Tag::BlockQuote(Some(*kind)), Frame::Blocks(children.iter()))
+            }
+            Block::CodeBlock { kind, content } => {
+                let kind = match kind {
+                    CodeBlockKind::Fenced(info) => CodeBlockKind::Fenced(CowStr::Borrowed(info)),
+                    CodeBlockKind::Indented => CodeBlockKind::Indented,
+                };
+                self.then(
+                    Event::Start(Tag::CodeBlock(kind)),
+                    vec![
+                        Event::Text(region_text(content)),
+                        Event::End(TagEnd::CodeBlock),
+                    ],
+                )
+            }
+            Block::HtmlBlock(r) => Some(Event::Html(region_text(r))),
+            Block::List { start, items } => {
+                self.open(Tag::List(*start), Frame::Items(items.iter()))
+            }
+            Block::Item(children) => self.open(Tag::Item, Frame::Blocks(children.iter())),
+            Block::Rule => Some(Event::Rule),
+            Block::FootnoteDefinition(label, children) => self.open(
+                Tag::FootnoteDefinition(CowStr::Borrowed(label)),
+                Frame::Blocks(children.iter()),
+            ),
+            Block::TablePlaceholder(aligns) => self.then(
+                Event::Start(Tag::Table(aligns.clone())),
+                vec![Event::End(TagEnd::Table)],
+            ),
+            Block::TableRow(cells) => self.open(Tag::TableRow, Frame::Cells(cells.iter())),
+            Block::TableHead(cells) => self.open(Tag::TableHead, Frame::Cells(cells.iter())),
+            Block::Table { aligns, head, rows } => {
+                let first = self.open(Tag::Table(aligns.clone()), Frame::Rows(rows.iter()));
+                // the header row comes first, ahead of the body rows
+                if let Some(start) = self.open(Tag::TableHead, Frame::Cells(head.iter())) {
+                    self.stack.push(Frame::Ready(vec![start].into_iter()));
+                }
+                first
+            }
+            Block::Custom(c) => match self.custom.and_then(|f| f(CustomNode::Block(&**c))) {
+                Some(ev) => Some(ev),
+                None => self.eager(block_to_events(b)),
+            },
+            Block::Metadata { .. }
+            | Block::Comment(_)
+            | Block::Raw(_)
+            | Block::LinkDefinition { .. } => self.eager(block_to_events(b)),
+        }
+    }
+
+    fn open_inline(&mut self, inl: &'a Inline) -> Option<Event<'a>> {
+        match inl {
+            Inline::Text(r) => match r.as_single_str() {
+                // text with line breaks splits into soft breaks
+                Some(s) if !s.contains('\n') => Some(Event::Text(CowStr::Borrowed(s))),
+                _ => self.eager(inline_to_events(inl)),
+            },
+            Inline::Code(r) => Some(Event::Code(region_text(r))),
+            Inline::InlineHtml(r) => Some(Event::InlineHtml(region_text(r))),
+            Inline::Html(r) => Some(Event::Html(region_text(r))),
+            Inline::SoftBreak => Some(Event::SoftBreak),
+            Inline::HardBreak => Some(Event::HardBreak),
+            Inline::Emphasis(children) => self.open(Tag::Emphasis, Frame::Inlines(children.iter())),
+            Inline::Strong(children) => self.open(Tag::Strong, Frame::Inlines(children.iter())),
+            Inline::Strikethrough(children) => {
+                self.open(Tag::Strikethrough, Frame::Inlines(children.iter()))
+            }
+            Inline::Subscript(children) => {
+                self.open(Tag::Subscript, Frame::Inlines(children.iter()))
+            }
+            Inline::Superscript(children) => {
+                self.open(Tag::Superscript, Frame::Inlines(children.iter()))
+            }
+            Inline::Link {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => self.open(
+                Tag::Link {
+                    link_type: *link_type,
+                    dest_url: CowStr::Borrowed(dest),
+                    title: CowStr::Borrowed(title),
+                    id: CowStr::Borrowed(id),
+                },
+                Frame::Inlines(children.iter()),
+            ),
+            Inline::Image {
+                link_type,
+                dest,
+                title,
+                id,
+                children,
+            } => self.open(
+                Tag::Image {
+                    link_type: *link_type,
+                    dest_url: CowStr::Borrowed(dest),
+                    title: CowStr::Borrowed(title),
+                    id: CowStr::Borrowed(id),
+                },
+                Frame::Inlines(children.iter()),
+            ),
+            Inline::FootnoteReference(label) => {
+                Some(Event::FootnoteReference(CowStr::Borrowed(label)))
+            }
+            Inline::InlineMath(r) => Some(Event::InlineMath(region_text(r))),
+            Inline::DisplayMath(r) => Some(Event::DisplayMath(region_text(r))),
+            Inline::Custom(c) => match self.custom.and_then(|f| f(CustomNode::Inline(&**c))) {
+                Some(ev) => Some(ev),
+                None => self.eager(inline_to_events(inl)),
+            },
+            Inline::Comment(_) | Inline::Raw(_) => self.eager(inline_to_events(inl)),
+        }
+    }
+}
+
+impl<'a> Iterator for BlockEvents<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        loop {
+            let event = match self.stack.last_mut()? {
+                Frame::Blocks(it) => match it.next() {
+                    Some(b) => self.open_block(b),
+                    None => {
+                        self.stack.pop();
+                        None
+                    }
+                },
+                Frame::Inlines(it) => match it.next() {
+                    Some(inl) => self.open_inline(inl),
+                    None => {
+                        self.stack.pop();
+                        None
+                    }
+                },
+                Frame::Items(it) => match it.next() {
+                    Some(item) => self.open(Tag::Item, Frame::Blocks(item.iter())),
+                    None => {
+                        self.stack.pop();
+                        None
+                    }
+                },
+                Frame::Rows(it) => match it.next() {
+                    Some(row) => self.open(Tag::TableRow, Frame::Cells(row.iter())),
+                    None => {
+                        self.stack.pop();
+                        None
+                    }
+                },
+                Frame::Cells(it) => match it.next() {
+                    Some(cell) => self.open(Tag::TableCell, Frame::Inlines(cell.iter())),
+                    None => {
+                        self.stack.pop();
+                        None
+                    }
+                },
+                Frame::Ready(it) => match it.next() {
+                    Some(ev) => Some(ev),
+                    None => {
+                        self.stack.pop();
+                        None
+                    }
+                },
+            };
+            if event.is_some() {
+                return event;
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Extension syntaxes implemented in one place.
+//!
+//! An `Extension` bundles what a custom block syntax needs: recognising it
+//! in an event stream, and writing the resulting node back as markdown
+//! (and, optionally, HTML). Register extensions with an
+//! `ExtensionRegistry`, then parse and write through it:
+//!
+//! ```ignore
+//! let mut extensions = ExtensionRegistry::new();
+//! extensions.register(Admonitions, ParserOptions::new());
+//! let blocks = extensions.parse(&events);
+//! let md = extensions.to_markdown(&blocks);
+//! let html = extensions.to_html(&blocks);
+//! ```
+//!
+//! Parsed nodes are stored as `Block::Custom`. When a registry is given to
+//! the writer (see `WriterOptions::with_extensions`), it renders nodes of an
+//! extension with `Extension::write_markdown` instead of
+//! `BlockNode::to_region`, at any depth.
+
+use crate::Region;
+use crate::ast::ParseContext;
+use crate::ast::block::Block;
+use crate::ast::custom::{BlockNode, BlockParser};
+use crate::ast::parser_registry::{ParserOptions, ParserRegistry};
+use crate::ast::writer::html::{HtmlOptions, push_html_with};
+use crate::ast::writer::{WriteContext, WriterOptions, blocks_to_markdown_with};
+use pulldown_cmark::Event;
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::sync::Arc;
+
+/// A custom block syntax: its parser and its writers.
+pub trait Extension: Send + Sync + 'static {
+    /// The node the extension parses into and writes.
+    type Node: BlockNode;
+
+    /// Try to parse the node at `events[idx]`, returning the number of
+    /// consumed events along with the node, as `BlockParser::try_parse`.
+    fn try_parse(
+        &self,
+        events: &[Event],
+        idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, Self::Node)>;
+
+    /// Render the node as markdown, following the same rules as
+    /// `BlockNode::to_region`.
+    fn write_markdown(&self, node: &Self::Node) -> Region;
+
+    /// Render the node as markdown knowing where it is written, as
+    /// `BlockNode::to_region_in`. The writer calls this method; the default
+    /// ignores the context and returns `write_markdown`.
+    fn write_markdown_in(&self, node: &Self::Node, _ctx: &WriteContext) -> Region {
+        self.write_markdown(node)
+    }
+
+    /// Render the node as HTML. The default leaves HTML output to the
+    /// events of the node.
+    fn write_html(&self, _node: &Self::Node) -> Option<String> {
+        None
+    }
+}
+
+/// Object-safe form of `Extension`, working on `dyn BlockNode`.
+trait AnyExtension: Send + Sync {
+    fn node_type(&self) -> TypeId;
+    fn try_parse(&self, events: &[Event], idx: usize, ctx: &ParseContext)
+    -> Option<(usize, Block)>;
+    fn write_markdown(&self, node: &dyn BlockNode, ctx: &WriteContext) -> Option<Region>;
+    fn write_html(&self, node: &dyn BlockNode) -> Option<String>;
+}
+
+impl<E: Extension> AnyExtension for E {
+    fn node_type(&self) -> TypeId {
+        TypeId::of::<E::Node>()
+    }
+
+    fn try_parse(
+        &self,
+        events: &[Event],
+        idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, Block)> {
+        Extension::try_parse(self, events, idx, ctx)
+            .map(|(n, node)| (n, Block::Custom(Arc::new(node))))
+    }
+
+    fn write_markdown(&self, node: &dyn BlockNode, ctx: &WriteContext) -> Option<Region> {
+        let any: &dyn Any = node;
+        any.downcast_ref::<E::Node>()
+            .map(|n| Extension::write_markdown_in(self, n, ctx))
+    }
+
+    fn write_html(&self, node: &dyn BlockNode) -> Option<String> {
+        let any: &dyn Any = node;
+        any.downcast_ref::<E::Node>()
+            .and_then(|n| Extension::write_html(self, n))
+    }
+}
+
+/// Adapter registering an extension's parser with a `ParserRegistry`.
+struct ExtensionParser(Arc<dyn AnyExtension>);
+
+impl BlockParser for ExtensionParser {
+    fn try_parse(
+        &self,
+        events: &[Event],
+        idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, Block)> {
+        self.0.try_parse(events, idx, ctx)
+    }
+}
+
+/// Registered extensions, used for parsing and writing. Cloning is cheap:
+/// the extensions themselves are shared.
+#[derive(Clone, Default)]
+pub struct ExtensionRegistry {
+    extensions: Vec<Arc<dyn AnyExtension>>,
+    parsers: ParserRegistry,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an extension. Its parser takes part in parsing with
+    /// `options`; for writing, an extension registered earlier wins over a
+    /// later one for the same node type.
+    pub fn register(&mut self, extension: impl Extension, options: ParserOptions) -> &mut Self {
+        let extension: Arc<dyn AnyExtension> = Arc::new(extension);
+        self.parsers
+            .register_block(ExtensionParser(extension.clone()), options);
+        self.extensions.push(extension);
+        self
+    }
+
+    /// Number of registered extensions.
+    pub fn len(&self) -> usize {
+        self.extensions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    /// The parsers of the registered extensions, e.g. to add further
+    /// parsers to a copy.
+    pub fn parsers(&self) -> &ParserRegistry {
+        &self.parsers
+    }
+
+    /// Parse `events`, recognising the syntax of every extension.
+    pub fn parse(&self, events: &[Event]) -> Vec<Block> {
+        self.parsers.parse(events)
+    }
+
+    /// Write `blocks` as markdown, rendering extension nodes with their
+    /// extension.
+    pub fn to_markdown(&self, blocks: &[Block]) -> String {
+        let options = WriterOptions::new().with_extensions(self.clone());
+        let mut out = String::new();
+        blocks_to_markdown_with(blocks, &options, &mut out);
+        out
+    }
+
+    /// Write `blocks` as HTML with `blocks_to_html`. Extension nodes are
+    /// rendered with `Extension::write_html` when it returns some HTML.
+    pub fn to_html(&self, blocks: &[Block]) -> String {
+        let mut out = String::new();
+        push_html_with(&mut out, blocks, &HtmlOptions::default(), Some(self));
+        out
+    }
+
+    fn extension_for(&self, node: &dyn BlockNode) -> Option<&Arc<dyn AnyExtension>> {
+        let any: &dyn Any = node;
+        let id = any.type_id();
+        self.extensions.iter().find(|e| e.node_type() == id)
+    }
+
+    /// Markdown for `node` from the registered extension that handles it.
+    pub(crate) fn write_markdown(
+        &self,
+        node: &dyn BlockNode,
+        ctx: &WriteContext,
+    ) -> Option<Region> {
+        self.extension_for(node)
+            .and_then(|e| e.write_markdown(node, ctx))
+    }
+
+    /// HTML for `node` from the registered extension that handles it.
+    pub(crate) fn write_html(&self, node: &dyn BlockNode) -> Option<String> {
+        self.extension_for(node).and_then(|e| e.write_html(node))
+    }
+}
+
+impl fmt::Debug for ExtensionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtensionRegistry")
+            .field("extensions", &self.extensions.len())
+            .finish_non_exhaustive()
+    }
+}
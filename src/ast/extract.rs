@@ -0,0 +1,262 @@
+//! Collecting links and images, e.g. for a link checker or an asset
+//! pipeline.
+//!
+//! `links` and `images` return every link or image of a document in
+//! document order, with its destination, title and text, and the path of
+//! the block holding it. Paths are those of `Cursor`, so a reported link can
+//! be edited in place with `Cursor::at`.
+//!
+//! `extract_section` copies out the section under one heading, with the
+//! footnotes and reference definitions it needs, e.g. for an excerpt.
+
+use crate::ast::outline::outline;
+use crate::ast::visit::{Visitor, walk_block, walk_blocks, walk_inline};
+use crate::ast::writer::plaintext::inlines_text;
+use crate::ast::{Block, Inline, label_key};
+use pulldown_cmark::{HeadingLevel, LinkType};
+
+/// A link or image found in a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkRef {
+    pub link_type: LinkType,
+    pub dest: String,
+    pub title: String,
+    /// Reference id, empty for inline links.
+    pub id: String,
+    /// The link text, or the alt text of an image, without markup.
+    pub text: String,
+    /// Path of the innermost block holding the link, as `Cursor::path`
+    /// returns it.
+    pub path: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Link,
+    Image,
+}
+
+struct Collect {
+    kind: Kind,
+    path: Vec<usize>,
+    found: Vec<LinkRef>,
+}
+
+impl Collect {
+    fn blocks(&mut self, blocks: &[Block]) {
+        for (i, b) in blocks.iter().enumerate() {
+            self.path.push(i);
+            match b {
+                Block::BlockQuote(children)
+                | Block::Alert { children, .. }
+                | Block::Item(children)
+                | Block::FootnoteDefinition(_, children) => self.blocks(children),
+                Block::List { items, .. } => {
+                    for (j, item) in items.iter().enumerate() {
+                        self.path.push(j);
+                        self.blocks(item);
+                        self.path.pop();
+                    }
+                }
+                // leaf blocks, and custom blocks through their events
+                _ => walk_block(self, b),
+            }
+            self.path.pop();
+        }
+    }
+}
+
+impl Visitor for Collect {
+    fn visit_inline(&mut self, inl: &Inline) {
+        match (inl, self.kind) {
+            (
+                Inline::Link {
+                    link_type,
+                    dest,
+                    title,
+                    id,
+                    children,
+                },
+                Kind::Link,
+            )
+            | (
+                Inline::Image {
+                    link_type,
+                    dest,
+                    title,
+                    id,
+                    children,
+                },
+                Kind::Image,
+            ) => self.found.push(LinkRef {
+                link_type: *link_type,
+                dest: dest.clone(),
+                title: title.clone(),
+                id: id.clone(),
+                text: inlines_text(children),
+                path: self.path.clone(),
+            }),
+            _ => {}
+        }
+        walk_inline(self, inl);
+    }
+}
+
+fn collect(blocks: &[Block], kind: Kind) -> Vec<LinkRef> {
+    let mut c = Collect {
+        kind,
+        path: Vec::new(),
+        found: Vec::new(),
+    };
+    c.blocks(blocks);
+    c.found
+}
+
+/// Every link of `blocks`, autolinks included, in document order.
+pub fn links(blocks: &[Block]) -> Vec<LinkRef> {
+    collect(blocks, Kind::Link)
+}
+
+/// Every image of `blocks`, in document order.
+pub fn images(blocks: &[Block]) -> Vec<LinkRef> {
+    collect(blocks, Kind::Image)
+}
+
+/// Which heading `extract_section` looks for. Every criterion set must
+/// hold; the default matches the first heading.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionSelector {
+    /// The heading text, without markup, compared case-insensitively.
+    pub text: Option<String>,
+    /// The heading anchor: its explicit id, or else its GitHub slug as a
+    /// `Slugger` numbers it.
+    pub slug: Option<String>,
+    pub level: Option<HeadingLevel>,
+}
+
+impl SectionSelector {
+    /// The heading reading `text`.
+    pub fn text(text: impl Into<String>) -> Self {
+        SectionSelector {
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
+
+    /// The heading anchored at `slug`, e.g. `"getting-started"`.
+    pub fn slug(slug: impl Into<String>) -> Self {
+        SectionSelector {
+            slug: Some(slug.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Only match headings of `level`.
+    pub fn level(mut self, level: HeadingLevel) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    fn matches(&self, level: HeadingLevel, text: &str, anchor: &str) -> bool {
+        self.level.is_none_or(|l| l == level)
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|t| t.trim().to_lowercase() == text.trim().to_lowercase())
+            && self.slug.as_ref().is_none_or(|s| s == anchor)
+    }
+}
+
+/// Footnote labels and reference ids a section relies on.
+#[derive(Default)]
+struct Needs {
+    footnotes: Vec<String>,
+    references: Vec<String>,
+}
+
+impl Visitor for Needs {
+    fn visit_inline(&mut self, inl: &Inline) {
+        match inl {
+            Inline::FootnoteReference(label) => {
+                let key = label_key(label);
+                if !self.footnotes.contains(&key) {
+                    self.footnotes.push(key);
+                }
+            }
+            Inline::Link { link_type, id, .. } | Inline::Image { link_type, id, .. }
+                if matches!(
+                    link_type,
+                    LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut
+                ) && !id.is_empty() =>
+            {
+                let key = label_key(id);
+                if !self.references.contains(&key) {
+                    self.references.push(key);
+                }
+            }
+            _ => {}
+        }
+        walk_inline(self, inl);
+    }
+}
+
+/// The section under the first top-level heading `selector` matches: the
+/// heading and the blocks after it, up to the next heading of the same or
+/// a higher level. Footnote definitions and reference definitions the
+/// section uses but does not hold are appended, in document order, with
+/// the footnotes those footnotes use in turn. Empty when no heading
+/// matches.
+pub fn extract_section(blocks: &[Block], selector: &SectionSelector) -> Vec<Block> {
+    let Some((start, level)) = outline(blocks).into_iter().find_map(|e| match e.path[..] {
+        [i] if selector.matches(e.level, &e.text, &e.slug) => Some((i, e.level)),
+        _ => None,
+    }) else {
+        return Vec::new();
+    };
+    let end = blocks[start + 1..]
+        .iter()
+        .position(|b| matches!(b, Block::Heading { level: l, .. } if *l <= level))
+        .map_or(blocks.len(), |n| start + 1 + n);
+    let mut out = blocks[start..end].to_vec();
+
+    let mut needs = Needs::default();
+    walk_blocks(&mut needs, &out);
+    let mut held = Vec::new();
+    for b in &out {
+        if let Block::FootnoteDefinition(label, _) = b {
+            held.push(label_key(label));
+        }
+    }
+    // footnotes may refer to other footnotes: collect until nothing new
+    let mut footnotes: Vec<&Block> = Vec::new();
+    let mut checked = 0;
+    while checked < needs.footnotes.len() {
+        let key = needs.footnotes[checked].clone();
+        checked += 1;
+        if held.contains(&key) {
+            continue;
+        }
+        if let Some(def) = blocks
+            .iter()
+            .find(|b| matches!(b, Block::FootnoteDefinition(label, _) if label_key(label) == key))
+        {
+            held.push(key);
+            walk_block(&mut needs, def);
+            footnotes.push(def);
+        }
+    }
+    let mut extra: Vec<Block> = blocks
+        .iter()
+        .enumerate()
+        .filter(|&(i, b)| match b {
+            Block::FootnoteDefinition(..) => footnotes.iter().any(|f| std::ptr::eq(*f, b)),
+            Block::LinkDefinition { id, .. } => {
+                !(start..end).contains(&i) && needs.references.contains(&label_key(id))
+            }
+            _ => false,
+        })
+        .map(|(_, b)| b.clone())
+        .collect();
+    out.append(&mut extra);
+    out
+}
@@ -0,0 +1,174 @@
+//! Scanning `:::` fenced containers in an event stream, for fenced divs and
+//! container directives.
+//!
+//! A container opens with a paragraph line the caller recognizes and closes
+//! with a line of three or more colons, which container directives require
+//! to be at least as long as the opening fence. Fences are found on the
+//! first and last lines of top-level paragraphs only; the lines between
+//! stay content.
+
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+/// Index just past the element starting at `events[i]`.
+pub(crate) fn element_end(events: &[Event], i: usize) -> usize {
+    if !matches!(events[i], Event::Start(_)) {
+        return i + 1;
+    }
+    let mut depth = 0usize;
+    for (j, e) in events.iter().enumerate().skip(i) {
+        match e {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    return j + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    events.len()
+}
+
+/// The text of a paragraph line made of text events only.
+pub(crate) fn line_text(line: &[Event]) -> Option<String> {
+    let mut text = String::new();
+    for e in line {
+        match e {
+            Event::Text(t) => text.push_str(t),
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+/// The number of colons opening a fence line.
+fn fence_len(line: &[Event]) -> usize {
+    line_text(line).map_or(0, |t| {
+        let t = t.trim_start();
+        t.len() - t.trim_start_matches(':').len()
+    })
+}
+
+fn is_close(line: &[Event]) -> bool {
+    line_text(line).is_some_and(|t| {
+        let t = t.trim();
+        t.len() >= 3 && t.chars().all(|c| c == ':')
+    })
+}
+
+enum Piece<'e, T> {
+    Open(T, &'e [Event<'e>]),
+    Close(&'e [Event<'e>]),
+    Content(Vec<Event<'e>>),
+}
+
+fn paragraph<'e>(line: &[Event<'e>]) -> Vec<Event<'e>> {
+    let mut out = vec![Event::Start(Tag::Paragraph)];
+    out.extend(line.iter().cloned());
+    out.push(Event::End(TagEnd::Paragraph));
+    out
+}
+
+/// Split the top-level element at `events[i..end]` into fences and the
+/// content around them.
+fn pieces<'e, T>(
+    events: &'e [Event<'e>],
+    i: usize,
+    end: usize,
+    open: &impl Fn(&[Event]) -> Option<T>,
+) -> Vec<Piece<'e, T>> {
+    if !matches!(events[i], Event::Start(Tag::Paragraph)) {
+        return vec![Piece::Content(events[i..end].to_vec())];
+    }
+    let mut lines: Vec<&'e [Event<'e>]> = events[i + 1..end - 1]
+        .split(|e| matches!(e, Event::SoftBreak))
+        .collect();
+    let mut out = Vec::new();
+    while let Some(&line) = lines.first() {
+        if is_close(line) {
+            out.push(Piece::Close(line));
+        } else if let Some(head) = open(line) {
+            out.push(Piece::Open(head, line));
+        } else {
+            break;
+        }
+        lines.remove(0);
+    }
+    let mut closing = Vec::new();
+    while let Some(&line) = lines.last().filter(|l| is_close(l)) {
+        closing.insert(0, Piece::Close(line));
+        lines.pop();
+    }
+    if !lines.is_empty() {
+        let mut content = Vec::new();
+        for (n, line) in lines.iter().enumerate() {
+            if n > 0 {
+                content.push(Event::SoftBreak);
+            }
+            content.extend(line.iter().cloned());
+        }
+        out.push(Piece::Content(paragraph(&content)));
+    }
+    out.extend(closing);
+    out
+}
+
+/// Scan a container opening at `events[0]`, which must be a paragraph
+/// whose first line `open` recognizes. Returns the number of events it
+/// spans, what `open` returned for it, and the events between its fences,
+/// nested fences included as paragraphs of their own. With
+/// `match_lengths`, a closing fence shorter than the fence it would close
+/// is content instead.
+pub(crate) fn scan_container<T>(
+    events: &[Event],
+    open: impl Fn(&[Event]) -> Option<T>,
+    match_lengths: bool,
+) -> Option<(usize, T, Vec<Event<'static>>)> {
+    if !matches!(events.first(), Some(Event::Start(Tag::Paragraph))) {
+        return None;
+    }
+    let mut head = None;
+    let mut inner: Vec<Event<'static>> = Vec::new();
+    // the lengths of the open fences, the outermost first
+    let mut fences: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while i < events.len() && !matches!(events[i], Event::End(_)) {
+        let end = element_end(events, i);
+        let pieces = pieces(events, i, end, &open);
+        let count = pieces.len();
+        for (n, piece) in pieces.into_iter().enumerate() {
+            match piece {
+                Piece::Open(h, line) if head.is_none() => {
+                    head = Some(h);
+                    fences.push(fence_len(line));
+                }
+                // the container must open with the fence
+                _ if head.is_none() => return None,
+                Piece::Open(_, line) => {
+                    fences.push(fence_len(line));
+                    inner.extend(paragraph(line).into_iter().map(Event::into_static));
+                }
+                Piece::Close(line)
+                    if match_lengths && fences.last().is_some_and(|&f| fence_len(line) < f) =>
+                {
+                    inner.extend(paragraph(line).into_iter().map(Event::into_static));
+                }
+                Piece::Close(_) if fences.len() == 1 => {
+                    // the closing fence must end its element
+                    if n + 1 != count {
+                        return None;
+                    }
+                    return Some((end, head?, inner));
+                }
+                Piece::Close(line) => {
+                    fences.pop();
+                    inner.extend(paragraph(line).into_iter().map(Event::into_static));
+                }
+                Piece::Content(evs) => inner.extend(evs.into_iter().map(Event::into_static)),
+            }
+        }
+        i = end;
+    }
+    None
+}
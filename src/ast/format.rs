@@ -0,0 +1,117 @@
+//! A markdown formatter: parse, normalize, write.
+//!
+//! `format_markdown` brings a source into the one layout this crate writes,
+//! the way `rustfmt` or `prettier` do for code, and `check` tells whether a
+//! source already is in it. Formatting is idempotent: formatting the output
+//! again returns it unchanged. It never changes what a source means: when
+//! the output would parse into another document than the source, or would
+//! not settle, formatting fails instead.
+
+use crate::ast::document::{Document, document_to_markdown_with, parse_markdown_to_document};
+use crate::ast::transform::{NormalizeOptions, normalize};
+use crate::ast::writer::WriterOptions;
+use pulldown_cmark::Options;
+use std::fmt;
+
+/// How `format_markdown` reads, tidies and writes a source.
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    /// Extensions the source is parsed with. Defaults to GitHub-flavored
+    /// markdown with footnotes, heading attributes and front matter.
+    pub parse: Options,
+    /// The normalization applied between parsing and writing.
+    pub normalize: NormalizeOptions,
+    /// How the document is written. Defaults to one blank line between
+    /// blocks, as markdownlint's MD012 expects.
+    pub writer: WriterOptions,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            parse: Options::ENABLE_TABLES
+                | Options::ENABLE_FOOTNOTES
+                | Options::ENABLE_STRIKETHROUGH
+                | Options::ENABLE_TASKLISTS
+                | Options::ENABLE_HEADING_ATTRIBUTES
+                | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+                | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS
+                | Options::ENABLE_GFM,
+            normalize: NormalizeOptions::default(),
+            writer: WriterOptions {
+                blank_lines: 1,
+                ..WriterOptions::default()
+            },
+        }
+    }
+}
+
+/// Why a source could not be formatted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The output parses into another document than the source: the
+    /// writer cannot yet express some of its content.
+    Changed,
+    /// Formatting the output again keeps changing it.
+    Unstable,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Changed => write!(f, "formatting would change the document"),
+            FormatError::Unstable => write!(f, "formatting does not reach a fixed point"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Writing a parsed document can change how the next parse sees it (a
+/// merged text run, a definition moved next to its first use), so the
+/// single pass is repeated until its output no longer changes. In practice
+/// a second pass is rarely needed; the bound only guards against a
+/// writer bug making passes alternate forever.
+const MAX_PASSES: usize = 8;
+
+/// The document `src` formats from: parsed, normalized, and with its
+/// definitions gathered as writing gathers them.
+fn parse(src: &str, options: &FormatOptions) -> Document {
+    let mut doc = parse_markdown_to_document(src, options.parse);
+    normalize(&mut doc.blocks, options.normalize);
+    for (_, children) in &mut doc.footnotes {
+        normalize(children, options.normalize);
+    }
+    doc.dedupe_definitions();
+    doc
+}
+
+/// Format `src`: parse it, normalize the blocks and write them back. The
+/// result is a fixed point, so formatting it again returns it unchanged,
+/// and it parses into the same document as `src`.
+pub fn format_markdown(src: &str, options: &FormatOptions) -> Result<String, FormatError> {
+    let source = parse(src, options);
+    let mut out = document_to_markdown_with(&source, &options.writer);
+    let mut settled = false;
+    for _ in 1..MAX_PASSES {
+        let next = document_to_markdown_with(&parse(&out, options), &options.writer);
+        if next == out {
+            settled = true;
+            break;
+        }
+        out = next;
+    }
+    if !settled {
+        return Err(FormatError::Unstable);
+    }
+    if parse(&out, options) != source {
+        return Err(FormatError::Changed);
+    }
+    Ok(out)
+}
+
+/// Whether `src` is already formatted, i.e. `format_markdown` would return
+/// it unchanged.
+pub fn check(src: &str, options: &FormatOptions) -> Result<bool, FormatError> {
+    Ok(format_markdown(src, options)? == src)
+}
@@ -0,0 +1,116 @@
+//! Typed front matter.
+//!
+//! `parse` deserializes the metadata block of a `Document` into any
+//! `Deserialize` type, and `set` serializes a value back into it, so a
+//! static-site pipeline can read and update fields like `title` or `date`
+//! without touching the rest of the document. YAML (`---`) front matter
+//! needs the `yaml` feature, TOML (`+++`) front matter the `toml` feature.
+//!
+//! ```ignore
+//! #[derive(Deserialize, Serialize)]
+//! struct Page { title: String, draft: bool }
+//!
+//! let mut page: Page = frontmatter::parse(&doc)?;
+//! page.draft = false;
+//! frontmatter::set(&mut doc, &page)?;
+//! ```
+
+use crate::ast::document::Document;
+use crate::text::Region;
+use pulldown_cmark::MetadataBlockKind;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Why front matter could not be read or written.
+#[derive(Debug)]
+pub enum FrontMatterError {
+    /// The document has no front matter.
+    Missing,
+    /// The front matter is in a format whose feature is not enabled.
+    Unsupported(MetadataBlockKind),
+    #[cfg(feature = "yaml")]
+    Yaml(serde_norway::Error),
+    #[cfg(feature = "toml")]
+    TomlDe(Box<toml::de::Error>),
+    #[cfg(feature = "toml")]
+    TomlSer(toml::ser::Error),
+}
+
+impl fmt::Display for FrontMatterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrontMatterError::Missing => write!(f, "the document has no front matter"),
+            FrontMatterError::Unsupported(MetadataBlockKind::YamlStyle) => {
+                write!(f, "YAML front matter needs the `yaml` feature")
+            }
+            FrontMatterError::Unsupported(MetadataBlockKind::PlusesStyle) => {
+                write!(f, "TOML front matter needs the `toml` feature")
+            }
+            #[cfg(feature = "yaml")]
+            FrontMatterError::Yaml(e) => write!(f, "invalid YAML front matter: {}", e),
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlDe(e) => write!(f, "invalid TOML front matter: {}", e),
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlSer(e) => write!(f, "cannot write TOML front matter: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FrontMatterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "yaml")]
+            FrontMatterError::Yaml(e) => Some(e),
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlDe(e) => Some(e),
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlSer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Deserialize the front matter of `doc`.
+pub fn parse<T: DeserializeOwned>(doc: &Document) -> Result<T, FrontMatterError> {
+    let (kind, content) = doc.metadata.as_ref().ok_or(FrontMatterError::Missing)?;
+    let content = content.apply();
+    match kind {
+        #[cfg(feature = "yaml")]
+        MetadataBlockKind::YamlStyle => {
+            serde_norway::from_str(&content).map_err(FrontMatterError::Yaml)
+        }
+        #[cfg(feature = "toml")]
+        MetadataBlockKind::PlusesStyle => {
+            toml::from_str(&content).map_err(|e| FrontMatterError::TomlDe(Box::new(e)))
+        }
+        #[allow(unreachable_patterns)]
+        kind => Err(FrontMatterError::Unsupported(*kind)),
+    }
+}
+
+/// Replace the front matter of `doc` with `value`, serialized in the format
+/// the document already uses. A document without front matter gets YAML,
+/// or TOML when only the `toml` feature is enabled.
+pub fn set<T: Serialize>(doc: &mut Document, value: &T) -> Result<(), FrontMatterError> {
+    let kind = match &doc.metadata {
+        Some((kind, _)) => *kind,
+        None if cfg!(feature = "yaml") => MetadataBlockKind::YamlStyle,
+        None => MetadataBlockKind::PlusesStyle,
+    };
+    let content = match kind {
+        #[cfg(feature = "yaml")]
+        MetadataBlockKind::YamlStyle => {
+            serde_norway::to_string(value).map_err(FrontMatterError::Yaml)?
+        }
+        #[cfg(feature = "toml")]
+        MetadataBlockKind::PlusesStyle => {
+            toml::to_string(value).map_err(FrontMatterError::TomlSer)?
+        }
+        #[allow(unreachable_patterns)]
+        kind => return Err(FrontMatterError::Unsupported(kind)),
+    };
+    // the writer puts the closing fence on a line of its own
+    doc.metadata = Some((kind, Region::from_str(content.trim_end_matches('\n'))));
+    Ok(())
+}
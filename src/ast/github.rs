@@ -0,0 +1,208 @@
+//! GitHub references in text: `@user` mentions and `#123` or
+//! `owner/repo#123` issues.
+//!
+//! `GitHubReferences` is an `InlineParser` turning such references into
+//! links, as GitHub renders them in issues and release notes:
+//!
+//! ```ignore
+//! let mut registry = ParserRegistry::new();
+//! registry.register_inline(GitHubReferences::new("rust-lang/rust"), ParserOptions::new());
+//! let blocks = registry.parse(&events);
+//! ```
+//!
+//! The link targets come from URL templates, GitHub's own by default, so
+//! references can point to another host. References inside links and code
+//! are left alone, as are `@` in e-mail addresses and `#` in the middle of
+//! words.
+
+use crate::ast::ParseContext;
+use crate::ast::custom::InlineParser;
+use crate::ast::inline::Inline;
+use pulldown_cmark::{Event, Tag};
+
+/// Links `@user`, `#123` and `owner/repo#123` references.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitHubReferences {
+    repository: Option<String>,
+    user_url: String,
+    issue_url: String,
+}
+
+impl Default for GitHubReferences {
+    fn default() -> Self {
+        GitHubReferences {
+            repository: None,
+            user_url: "https://github.com/{user}".to_string(),
+            issue_url: "https://github.com/{owner}/{repo}/issues/{number}".to_string(),
+        }
+    }
+}
+
+impl GitHubReferences {
+    /// References for `repository`, as `owner/repo`: the repository bare
+    /// `#123` references point to.
+    pub fn new(repository: impl Into<String>) -> Self {
+        GitHubReferences {
+            repository: Some(repository.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Link mentions to `template`, with `{user}` for the user name.
+    pub fn user_url(mut self, template: impl Into<String>) -> Self {
+        self.user_url = template.into();
+        self
+    }
+
+    /// Link issues to `template`, with `{owner}`, `{repo}` and `{number}`
+    /// for the parts of the reference.
+    pub fn issue_url(mut self, template: impl Into<String>) -> Self {
+        self.issue_url = template.into();
+        self
+    }
+
+    /// Split `text` around its references, or `None` if it has none.
+    pub fn link_text(&self, text: &str) -> Option<Vec<Inline>> {
+        let mut out = Vec::new();
+        let mut done = 0;
+        let mut i = 0;
+        while i < text.len() {
+            // a reference cannot begin inside the previous one
+            let Some((start, end, dest)) = self.reference_at(text, i).filter(|r| r.0 >= done)
+            else {
+                i += text[i..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            if done < start {
+                out.push(Inline::text(&text[done..start]));
+            }
+            out.push(Inline::link(&dest, [Inline::text(&text[start..end])]));
+            done = end;
+            i = end;
+        }
+        if out.is_empty() {
+            return None;
+        }
+        if done < text.len() {
+            out.push(Inline::text(&text[done..]));
+        }
+        Some(out)
+    }
+
+    /// The reference whose `@` or `#` is at byte `i` of `text`: where it
+    /// starts and ends, and its URL.
+    fn reference_at(&self, text: &str, i: usize) -> Option<(usize, usize, String)> {
+        let rest = &text[i..];
+        if let Some(name) = rest.strip_prefix('@') {
+            if !boundary_before(&text[..i]) {
+                return None;
+            }
+            let n = user_len(name);
+            if n == 0 || !boundary_after(&name[n..]) {
+                return None;
+            }
+            let url = self.user_url.replace("{user}", &name[..n]);
+            return Some((i, i + 1 + n, url));
+        }
+        let digits = rest.strip_prefix('#')?;
+        let n = digits
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(digits.len());
+        // issues are numbered from 1, written without leading zeros
+        if n == 0 || digits.starts_with('0') || !boundary_after(&digits[n..]) {
+            return None;
+        }
+        let number = &digits[..n];
+        // an `owner/repo` directly before the `#`
+        let before = &text[..i];
+        let start = before
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || "-_./".contains(c)))
+            .map_or(0, |p| {
+                p + before[p..].chars().next().map_or(1, char::len_utf8)
+            });
+        let (start, repository) = if start == i {
+            (i, self.repository.clone()?)
+        } else {
+            let repo = &before[start..];
+            let (owner, name) = repo.split_once('/')?;
+            if user_len(owner) != owner.len() || name.is_empty() || name.contains('/') {
+                return None;
+            }
+            (start, repo.to_string())
+        };
+        if !boundary_before(&text[..start]) {
+            return None;
+        }
+        let (owner, repo) = repository.split_once('/')?;
+        let url = self
+            .issue_url
+            .replace("{owner}", owner)
+            .replace("{repo}", repo)
+            .replace("{number}", number);
+        Some((start, i + 1 + n, url))
+    }
+}
+
+/// The length of the GitHub user name at the start of `s`: letters, digits
+/// and single hyphens, not at either end, at most 39 characters.
+fn user_len(s: &str) -> usize {
+    let n = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(s.len());
+    let name = s[..n].trim_end_matches('-');
+    if name.starts_with('-') || name.contains("--") || name.len() > 39 {
+        return 0;
+    }
+    name.len()
+}
+
+/// Whether a reference may start after `before`: not inside a word or an
+/// e-mail address.
+fn boundary_before(before: &str) -> bool {
+    before
+        .chars()
+        .next_back()
+        .is_none_or(|c| !(c.is_alphanumeric() || "_@#/.-`".contains(c)))
+}
+
+/// Whether a reference may end before `after`.
+fn boundary_after(after: &str) -> bool {
+    after
+        .chars()
+        .next()
+        .is_none_or(|c| !(c.is_alphanumeric() || c == '_' || c == '@'))
+}
+
+impl InlineParser for GitHubReferences {
+    /// A text event that is a single reference.
+    fn try_parse(
+        &self,
+        events: &[Event],
+        idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, Inline)> {
+        match self.try_parse_inlines(events, idx, ctx)? {
+            (n, inlines) if inlines.len() == 1 => inlines.into_iter().next().map(|i| (n, i)),
+            _ => None,
+        }
+    }
+
+    fn try_parse_inlines(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, Vec<Inline>)> {
+        if ctx
+            .ancestors
+            .iter()
+            .any(|t| matches!(t, Tag::Link { .. } | Tag::Image { .. }))
+        {
+            return None;
+        }
+        let Some(Event::Text(text)) = events.first() else {
+            return None;
+        };
+        self.link_text(text).map(|inlines| (1, inlines))
+    }
+}
@@ -1,11 +1,13 @@
+use crate::ast::block::raw_parse_options;
 use crate::ast::custom::InlineNode;
 use crate::text::Region;
-use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use pulldown_cmark::{CowStr, Event, Parser, Tag, TagEnd};
 use std::sync::Arc;
 
 /// Inline level AST nodes. They own their text via `Region` which composes
 /// `Line`/`Fragment` from `src/text`.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Inline {
     Text(Region),
     Code(Region),
@@ -35,11 +37,102 @@ pub enum Inline {
     FootnoteReference(String),
     InlineMath(Region),
     DisplayMath(Region),
+    /// An inline HTML comment. Holds the text between `<!--` and `-->`.
+    Comment(Region),
+    /// Markdown emitted verbatim by the writer, without any escaping.
+    Raw(Region),
     /// A user-provided custom inline node. Boxed trait object so the AST
     /// can carry arbitrary user types that implement `InlineNode`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::ast::serde_impl::custom_inline")
+    )]
     Custom(Arc<dyn InlineNode + 'static>),
 }
 
+// Every field compares totally (pulldown's `CodeBlockKind`, `LinkType` and
+// `Alignment` only lack the `Eq` marker), so equality is an equivalence.
+impl Eq for Inline {}
+
+impl Inline {
+    /// Plain text.
+    pub fn text(s: &str) -> Self {
+        Inline::Text(Region::from_str(s))
+    }
+
+    /// An inline code span.
+    pub fn code(s: &str) -> Self {
+        Inline::Code(Region::from_str(s))
+    }
+
+    /// Emphasis around `children`.
+    pub fn emphasis<I>(children: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Inline>,
+    {
+        Inline::Emphasis(children.into_iter().map(Into::into).collect())
+    }
+
+    /// Strong emphasis around `children`.
+    pub fn strong<I>(children: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Inline>,
+    {
+        Inline::Strong(children.into_iter().map(Into::into).collect())
+    }
+
+    /// An inline link (`[children](dest)`) without a title.
+    pub fn link<I>(dest: &str, children: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Inline>,
+    {
+        Inline::Link {
+            link_type: pulldown_cmark::LinkType::Inline,
+            dest: dest.to_string(),
+            title: String::new(),
+            id: String::new(),
+            children: children.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Clone the inline, copying custom nodes instead of sharing their
+    /// `Arc` with the original. See `Block::deep_clone`.
+    pub fn deep_clone(&self) -> Inline {
+        let mut inl = self.clone();
+        inl.unshare_custom();
+        inl
+    }
+
+    pub(crate) fn unshare_custom(&mut self) {
+        match self {
+            Inline::Emphasis(c)
+            | Inline::Strong(c)
+            | Inline::Strikethrough(c)
+            | Inline::Subscript(c)
+            | Inline::Superscript(c)
+            | Inline::Link { children: c, .. }
+            | Inline::Image { children: c, .. } => c.iter_mut().for_each(Inline::unshare_custom),
+            Inline::Custom(node) => *node = Arc::from(dyn_clone::clone_box(&**node)),
+            _ => {}
+        }
+    }
+}
+
+impl From<&str> for Inline {
+    fn from(s: &str) -> Self {
+        Inline::text(s)
+    }
+}
+
+impl From<String> for Inline {
+    fn from(s: String) -> Self {
+        Inline::text(&s)
+    }
+}
+
 /// Convert `Inline` to a sequence of pulldown-cmark Events (owned, 'static).
 pub fn inline_to_events(inl: &Inline) -> Vec<Event<'static>> {
     match inl {
@@ -142,6 +235,23 @@ pub fn inline_to_events(inl: &Inline) -> Vec<Event<'static>> {
         Inline::FootnoteReference(s) => vec![Event::FootnoteReference(CowStr::from(s.clone()))],
         Inline::InlineMath(r) => vec![Event::InlineMath(CowStr::from(r.apply()))],
         Inline::DisplayMath(r) => vec![Event::DisplayMath(CowStr::from(r.apply()))],
+        Inline::Comment(body) => vec![Event::InlineHtml(CowStr::from(format!(
+            "<!--{}-->",
+            body.apply()
+        )))],
+        Inline::Raw(r) => {
+            // parse the raw markdown and keep only the paragraph's content
+            let src = r.apply();
+            Parser::new_ext(&src, raw_parse_options())
+                .filter(|e| {
+                    !matches!(
+                        e,
+                        Event::Start(Tag::Paragraph) | Event::End(TagEnd::Paragraph)
+                    )
+                })
+                .map(|e| e.into_static())
+                .collect()
+        }
         Inline::Custom(c) => c.to_events(),
     }
 }
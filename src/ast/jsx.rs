@@ -0,0 +1,432 @@
+//! MDX components, the JSX elements of HTML blocks.
+//!
+//! ```markdown
+//! <Callout type="warning" dismissible>
+//!
+//! Back up your *data* first.
+//!
+//! </Callout>
+//!
+//! <Chart data={[1, 2, 3]} />
+//! ```
+//!
+//! Markdown reads such elements as HTML blocks, opaque strings, or as plain
+//! text when it does not take them for HTML. With `JsxElements` registered,
+//! an HTML block or a paragraph of plain text opening with a component (a
+//! tag whose name starts with an uppercase letter, or holds a `.`, as in
+//! `<Tabs.Item>`) parses into a `JsxElement` with its name, attributes and
+//! the blocks between its opening and closing tags, whether they sit in the
+//! same block or around markdown separated by blank lines. Elements
+//! are written back in the blank-line form, so their children stay
+//! markdown.
+//!
+//! ```ignore
+//! let mut extensions = ExtensionRegistry::new();
+//! extensions.register(JsxElements::default(), ParserOptions::new());
+//! let blocks = extensions.parse(&events);
+//! ```
+//!
+//! Components inside paragraphs stay inline HTML.
+
+use crate::Region;
+use crate::ast::ParseContext;
+use crate::ast::block::{Block, block_to_events};
+use crate::ast::custom::BlockNode;
+use crate::ast::extension::Extension;
+use crate::ast::fenced::element_end;
+use crate::ast::parse::{HookResult, parse_events_to_blocks_with_hook};
+use crate::ast::writer::html::{HtmlOptions, blocks_to_html};
+use crate::ast::writer::{WriteContext, WriterOptions, write_blocks};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
+use std::any::Any;
+use std::sync::Arc;
+
+/// The value of a JSX attribute.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsxValue {
+    /// A quoted string, without its quotes.
+    String(String),
+    /// A `{...}` expression, without its braces.
+    Expression(String),
+}
+
+/// A JSX element: its name, attributes and children.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsxElement {
+    /// The tag name, e.g. `Callout` or `Tabs.Item`.
+    pub name: String,
+    /// The attributes in order, `None` for one without a value.
+    pub attrs: Vec<(String, Option<JsxValue>)>,
+    pub children: Vec<Block>,
+}
+
+impl JsxElement {
+    pub fn new(name: impl Into<String>) -> Self {
+        JsxElement {
+            name: name.into(),
+            attrs: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// The value of attribute `name`, if it is set.
+    pub fn attr(&self, name: &str) -> Option<&JsxValue> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_ref())
+    }
+
+    /// The opening tag, self-closing when there are no children.
+    fn open_tag(&self) -> String {
+        let mut out = format!("<{}", self.name);
+        for (name, value) in &self.attrs {
+            out.push(' ');
+            out.push_str(name);
+            match value {
+                Some(JsxValue::String(s)) if s.contains('"') => out.push_str(&format!("='{s}'")),
+                Some(JsxValue::String(s)) => out.push_str(&format!("=\"{s}\"")),
+                Some(JsxValue::Expression(e)) => out.push_str(&format!("={{{e}}}")),
+                None => {}
+            }
+        }
+        out.push_str(if self.children.is_empty() { " />" } else { ">" });
+        out
+    }
+
+    fn render(&self, options: &WriterOptions) -> Region {
+        let mut md = self.open_tag();
+        if !self.children.is_empty() {
+            let mut body = String::new();
+            write_blocks(&mut body, &self.children, options)
+                .expect("writing to a String cannot fail");
+            let blank = "\n".repeat(options.blank_lines.max(1));
+            md.push('\n');
+            md.push_str(&blank);
+            md.push_str(body.trim_end_matches('\n'));
+            md.push('\n');
+            md.push_str(&blank);
+            md.push_str(&format!("</{}>", self.name));
+        }
+        Region::from_str(&md)
+    }
+}
+
+fn html_block(html: String) -> [Event<'static>; 3] {
+    [
+        Event::Start(Tag::HtmlBlock),
+        Event::Html(CowStr::from(html + "\n")),
+        Event::End(TagEnd::HtmlBlock),
+    ]
+}
+
+impl BlockNode for JsxElement {
+    /// The tags as HTML blocks around the events of the children.
+    fn to_events(&self) -> Vec<Event<'static>> {
+        let mut events = html_block(self.open_tag()).to_vec();
+        if !self.children.is_empty() {
+            events.extend(self.children.iter().flat_map(block_to_events));
+            events.extend(html_block(format!("</{}>", self.name)));
+        }
+        events
+    }
+
+    fn to_region(&self) -> Region {
+        self.render(&WriterOptions::default())
+    }
+
+    fn to_region_in(&self, ctx: &WriteContext) -> Region {
+        self.render(ctx.options)
+    }
+
+    /// The tags around the HTML of the children.
+    fn to_html(&self) -> Option<String> {
+        let mut out = self.open_tag();
+        out.push('\n');
+        if !self.children.is_empty() {
+            out.push_str(&blocks_to_html(&self.children, &HtmlOptions::default()));
+            out.push_str(&format!("</{}>\n", self.name));
+        }
+        Some(out)
+    }
+
+    fn node_eq(&self, other: &dyn BlockNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<JsxElement>() == Some(self)
+    }
+}
+
+/// A parsed opening tag.
+struct OpenTag {
+    name: String,
+    attrs: Vec<(String, Option<JsxValue>)>,
+    self_closing: bool,
+    len: usize,
+}
+
+fn is_component(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) || name.contains('.')
+}
+
+fn name_len(s: &str) -> usize {
+    if !s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return 0;
+    }
+    s.find(|c: char| !(c.is_ascii_alphanumeric() || "._-:".contains(c)))
+        .unwrap_or(s.len())
+}
+
+/// The length of the `{...}` expression at the start of `s`, braces
+/// included, skipping braces in string literals.
+fn expression_len(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse the opening tag of a component at the start of `s`.
+fn open_tag(s: &str) -> Option<OpenTag> {
+    let rest = s.strip_prefix('<')?;
+    let n = name_len(rest);
+    if n == 0 || !is_component(&rest[..n]) {
+        return None;
+    }
+    let name = rest[..n].to_string();
+    let mut rest = &rest[n..];
+    let mut attrs = Vec::new();
+    loop {
+        let trimmed = rest.trim_start();
+        if let Some(after) = trimmed.strip_prefix("/>") {
+            let len = s.len() - after.len();
+            return Some(OpenTag {
+                name,
+                attrs,
+                self_closing: true,
+                len,
+            });
+        }
+        if let Some(after) = trimmed.strip_prefix('>') {
+            let len = s.len() - after.len();
+            return Some(OpenTag {
+                name,
+                attrs,
+                self_closing: false,
+                len,
+            });
+        }
+        // attributes are separated by whitespace
+        if trimmed.len() == rest.len() {
+            return None;
+        }
+        let n = name_len(trimmed);
+        if n == 0 {
+            return None;
+        }
+        let key = trimmed[..n].to_string();
+        rest = &trimmed[n..];
+        let value = match rest.trim_start().strip_prefix('=') {
+            None => None,
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, len) = match after.chars().next()? {
+                    q @ ('"' | '\'') => {
+                        let end = after[1..].find(q)? + 1;
+                        (JsxValue::String(after[1..end].to_string()), end + 1)
+                    }
+                    '{' => {
+                        let end = expression_len(after)?;
+                        (JsxValue::Expression(after[1..end - 1].to_string()), end)
+                    }
+                    _ => return None,
+                };
+                rest = &after[len..];
+                Some(value)
+            }
+        };
+        attrs.push((key, value));
+    }
+}
+
+/// The length of the closing tag for `name` at the start of `s`.
+fn close_tag(s: &str, name: &str) -> Option<usize> {
+    let rest = s.strip_prefix("</")?.strip_prefix(name)?;
+    let after = rest.trim_start().strip_prefix('>')?;
+    Some(s.len() - after.len())
+}
+
+/// Follow the tags of `name` through `s`, starting `depth` elements deep.
+/// Returns where the element at depth one closes, or the depth at the end.
+fn find_close(s: &str, name: &str, mut depth: usize) -> Result<(usize, usize), usize> {
+    let mut i = 0;
+    while let Some(at) = s[i..].find('<').map(|n| n + i) {
+        if let Some(len) = close_tag(&s[at..], name) {
+            depth -= 1;
+            if depth == 0 {
+                return Ok((at, at + len));
+            }
+            i = at + len;
+        } else if let Some(tag) = open_tag(&s[at..]).filter(|t| t.name == name) {
+            depth += usize::from(!tag.self_closing);
+            i = at + tag.len;
+        } else {
+            i = at + 1;
+        }
+    }
+    Err(depth)
+}
+
+/// The source of an HTML block, or of a paragraph of plain text: tags
+/// markdown does not take for HTML, like `<Tabs.Item>` or attributes with
+/// expressions, come as text.
+fn block_text(events: &[Event]) -> Option<String> {
+    let inner = match events {
+        [
+            Event::Start(Tag::HtmlBlock),
+            inner @ ..,
+            Event::End(TagEnd::HtmlBlock),
+        ]
+        | [
+            Event::Start(Tag::Paragraph),
+            inner @ ..,
+            Event::End(TagEnd::Paragraph),
+        ] => inner,
+        _ => return None,
+    };
+    let mut text = String::new();
+    for e in inner {
+        match e {
+            Event::Html(t) | Event::InlineHtml(t) | Event::Text(t) => text.push_str(t),
+            Event::SoftBreak => text.push('\n'),
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+/// The `<Name ...>` syntax, parsed into `JsxElement`s.
+#[derive(Clone, Copy, Debug)]
+pub struct JsxElements {
+    /// Options for parsing the markdown inside an HTML block holding both
+    /// tags of an element. None by default.
+    pub options: Options,
+}
+
+impl Default for JsxElements {
+    fn default() -> Self {
+        JsxElements {
+            options: Options::empty(),
+        }
+    }
+}
+
+impl JsxElements {
+    fn parse_at(&self, events: &[Event], ctx: &ParseContext) -> Option<(usize, JsxElement)> {
+        if ctx.parent_collects_inlines || !matches!(events.first(), Some(Event::Start(_))) {
+            return None;
+        }
+        let first = element_end(events, 0);
+        let text = block_text(&events[..first])?;
+        let source = text.trim_start();
+        let tag = open_tag(source)?;
+        let mut element = JsxElement {
+            name: tag.name,
+            attrs: tag.attrs,
+            children: Vec::new(),
+        };
+        let rest = &source[tag.len..];
+        if tag.self_closing {
+            return rest.trim().is_empty().then_some((first, element));
+        }
+        // both tags in this block
+        let depth = match find_close(rest, &element.name, 1) {
+            Ok((at, end)) => {
+                if !rest[end..].trim().is_empty() {
+                    return None;
+                }
+                element.children = self.parse_source(&rest[..at]);
+                return Some((first, element));
+            }
+            Err(depth) => depth,
+        };
+        // the closing tag in a later HTML block, markdown in between
+        let mut inner: Vec<Event<'static>> = Vec::new();
+        if !rest.trim().is_empty() {
+            inner.extend(html_block(rest.trim_end().to_string()));
+        }
+        let (mut depth, mut i) = (depth, first);
+        while i < events.len() && !matches!(events[i], Event::End(_)) {
+            let end = element_end(events, i);
+            if let Some(text) = block_text(&events[i..end]) {
+                match find_close(&text, &element.name, depth) {
+                    Ok((at, close)) if text[close..].trim().is_empty() => {
+                        if !text[..at].trim().is_empty() {
+                            inner.extend(html_block(text[..at].trim_end().to_string()));
+                        }
+                        element.children = self.parse_inner(&inner);
+                        return Some((end, element));
+                    }
+                    Ok(_) => return None,
+                    Err(d) => depth = d,
+                }
+            }
+            inner.extend(events[i..end].iter().cloned().map(Event::into_static));
+            i = end;
+        }
+        None
+    }
+
+    /// Parse markdown taken from an HTML block.
+    fn parse_source(&self, src: &str) -> Vec<Block> {
+        let events: Vec<Event> = Parser::new_ext(src, self.options).collect();
+        self.parse_inner(&events)
+    }
+
+    /// Parse the blocks of an element, nested elements included.
+    fn parse_inner(&self, events: &[Event]) -> Vec<Block> {
+        let mut hook = |evs: &[Event], _i: usize, ctx: &ParseContext| {
+            self.parse_at(evs, ctx)
+                .map(|(n, node)| (n, HookResult::Block(Block::Custom(Arc::new(node)))))
+        };
+        parse_events_to_blocks_with_hook(events, Some(&mut hook))
+    }
+}
+
+impl Extension for JsxElements {
+    type Node = JsxElement;
+
+    fn try_parse(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, JsxElement)> {
+        self.parse_at(events, ctx)
+    }
+
+    fn write_markdown(&self, node: &JsxElement) -> Region {
+        node.to_region()
+    }
+
+    fn write_markdown_in(&self, node: &JsxElement, ctx: &WriteContext) -> Region {
+        node.to_region_in(ctx)
+    }
+
+    fn write_html(&self, node: &JsxElement) -> Option<String> {
+        node.to_html()
+    }
+}
@@ -0,0 +1,354 @@
+//! Style checks over a block tree, the building blocks of a markdown linter.
+//!
+//! A `Rule` looks at every block and inline of a document and reports what
+//! it objects to through its `LintContext`. A `Linter` runs a set of rules
+//! and collects their `Diagnostic`s, each carrying the path of the block it
+//! was found in and, when linting source text, the byte span of that block.
+//!
+//! ```ignore
+//! let mut linter = Linter::with_default_rules();
+//! linter.add(MyRule);
+//! for d in linter.lint_markdown(src, Options::all()) {
+//!     eprintln!("{d}");
+//! }
+//! ```
+
+use crate::ast::parse::{parse_offset_events_to_blocks, span_at};
+use crate::ast::visit::{Visitor, walk_block, walk_inline};
+use crate::ast::writer::plaintext::inlines_text;
+use crate::ast::writer::{WriterOptions, block_to_markdown};
+use crate::ast::{Block, Inline};
+use pulldown_cmark::{Options, Parser};
+use std::fmt;
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
+
+/// A problem reported by a rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Name of the rule that reported it.
+    pub rule: &'static str,
+    pub message: String,
+    /// Path of the block it was found in, as `Cursor::path` returns it.
+    pub path: Vec<usize>,
+    /// Byte range of that block in the source, when the linter was given
+    /// source text.
+    pub span: Option<Range<usize>>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{}..{}", span.start, span.end)?,
+            None => write!(f, "{:?}", self.path)?,
+        }
+        write!(f, ": {}: {}", self.rule, self.message)
+    }
+}
+
+/// What a rule sees of its surroundings, and where it reports.
+pub struct LintContext<'a> {
+    rule: &'static str,
+    path: &'a [usize],
+    in_link: bool,
+    out: &'a mut Vec<Diagnostic>,
+}
+
+impl LintContext<'_> {
+    /// Path of the block being checked, or holding the inline being
+    /// checked.
+    pub fn path(&self) -> &[usize] {
+        self.path
+    }
+
+    /// Whether the inline being checked is inside a link or an image.
+    pub fn in_link(&self) -> bool {
+        self.in_link
+    }
+
+    /// Report a problem at the current block.
+    pub fn report(&mut self, message: impl Into<String>) {
+        self.out.push(Diagnostic {
+            rule: self.rule,
+            message: message.into(),
+            path: self.path.to_vec(),
+            span: None,
+        });
+    }
+}
+
+/// A lint rule. Rules are called for every block, containers included,
+/// and every inline, in document order; both checks default to doing
+/// nothing.
+pub trait Rule: Send + Sync {
+    /// The name diagnostics are reported under, e.g. `"line-length"`.
+    fn name(&self) -> &'static str;
+
+    /// Called before each document, to clear state kept across nodes.
+    fn reset(&mut self) {}
+
+    fn check_block(&mut self, _block: &Block, _cx: &mut LintContext<'_>) {}
+
+    fn check_inline(&mut self, _inline: &Inline, _cx: &mut LintContext<'_>) {}
+}
+
+/// Runs rules over documents.
+#[derive(Default)]
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Linter {
+    /// A linter without rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A linter with the built-in rules, with their default settings.
+    pub fn with_default_rules() -> Self {
+        let mut linter = Linter::new();
+        linter
+            .add(HeadingIncrement::default())
+            .add(HeadingPunctuation::default())
+            .add(BareUrls)
+            .add(LineLength::default());
+        linter
+    }
+
+    /// Append a rule.
+    pub fn add(&mut self, rule: impl Rule + 'static) -> &mut Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Names of the registered rules, in order.
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|r| r.name()).collect()
+    }
+
+    /// Run every rule over `blocks`. Diagnostics come in document order.
+    pub fn lint(&mut self, blocks: &[Block]) -> Vec<Diagnostic> {
+        for rule in &mut self.rules {
+            rule.reset();
+        }
+        let mut run = Run {
+            rules: &mut self.rules,
+            path: Vec::new(),
+            links: 0,
+            out: Vec::new(),
+        };
+        run.blocks(blocks);
+        run.out
+    }
+
+    /// Parse `src` and lint it, giving every diagnostic the source span of
+    /// its block.
+    pub fn lint_markdown(&mut self, src: &str, options: Options) -> Vec<Diagnostic> {
+        let events: Vec<_> = Parser::new_ext(src, options).into_offset_iter().collect();
+        let spanned = parse_offset_events_to_blocks(&events);
+        let blocks: Vec<Block> = spanned.iter().map(|s| s.block.clone()).collect();
+        let mut diagnostics = self.lint(&blocks);
+        for d in &mut diagnostics {
+            d.span = span_at(&spanned, &d.path);
+        }
+        diagnostics
+    }
+}
+
+struct Run<'a> {
+    rules: &'a mut [Box<dyn Rule>],
+    path: Vec<usize>,
+    /// Depth of links and images around the current inline.
+    links: usize,
+    out: Vec<Diagnostic>,
+}
+
+impl Run<'_> {
+    fn blocks(&mut self, blocks: &[Block]) {
+        for (i, b) in blocks.iter().enumerate() {
+            self.path.push(i);
+            match b {
+                Block::BlockQuote(children)
+                | Block::Alert { children, .. }
+                | Block::Item(children)
+                | Block::FootnoteDefinition(_, children) => {
+                    self.check_block(b);
+                    self.blocks(children);
+                }
+                Block::List { items, .. } => {
+                    self.check_block(b);
+                    for (j, item) in items.iter().enumerate() {
+                        self.path.push(j);
+                        self.blocks(item);
+                        self.path.pop();
+                    }
+                }
+                _ => self.visit_block(b),
+            }
+            self.path.pop();
+        }
+    }
+
+    fn check_block(&mut self, b: &Block) {
+        for rule in self.rules.iter_mut() {
+            let mut cx = LintContext {
+                rule: rule.name(),
+                path: &self.path,
+                in_link: false,
+                out: &mut self.out,
+            };
+            rule.check_block(b, &mut cx);
+        }
+    }
+}
+
+impl Visitor for Run<'_> {
+    fn visit_block(&mut self, b: &Block) {
+        self.check_block(b);
+        walk_block(self, b);
+    }
+
+    fn visit_inline(&mut self, inl: &Inline) {
+        for rule in self.rules.iter_mut() {
+            let mut cx = LintContext {
+                rule: rule.name(),
+                path: &self.path,
+                in_link: self.links > 0,
+                out: &mut self.out,
+            };
+            rule.check_inline(inl, &mut cx);
+        }
+        let link = matches!(inl, Inline::Link { .. } | Inline::Image { .. });
+        self.links += usize::from(link);
+        walk_inline(self, inl);
+        self.links -= usize::from(link);
+    }
+}
+
+/// Headings should go down one level at a time: an `h2` may follow an
+/// `h1`, an `h4` may not follow an `h2`.
+#[derive(Clone, Debug, Default)]
+pub struct HeadingIncrement {
+    last: Option<usize>,
+}
+
+impl Rule for HeadingIncrement {
+    fn name(&self) -> &'static str {
+        "heading-increment"
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+    }
+
+    fn check_block(&mut self, block: &Block, cx: &mut LintContext<'_>) {
+        let Block::Heading { level, .. } = block else {
+            return;
+        };
+        let level = *level as usize;
+        if let Some(last) = self.last
+            && level > last + 1
+        {
+            cx.report(format!("h{level} follows h{last}, skipping a level"));
+        }
+        self.last = Some(level);
+    }
+}
+
+/// Headings should not end in punctuation.
+#[derive(Clone, Debug)]
+pub struct HeadingPunctuation {
+    /// The characters a heading may not end with.
+    pub punctuation: String,
+}
+
+impl Default for HeadingPunctuation {
+    fn default() -> Self {
+        HeadingPunctuation {
+            punctuation: ".,;:!。，；：！".to_string(),
+        }
+    }
+}
+
+impl Rule for HeadingPunctuation {
+    fn name(&self) -> &'static str {
+        "heading-punctuation"
+    }
+
+    fn check_block(&mut self, block: &Block, cx: &mut LintContext<'_>) {
+        let Block::Heading { children, .. } = block else {
+            return;
+        };
+        let text = inlines_text(children);
+        if let Some(c) = text.trim_end().chars().last()
+            && self.punctuation.contains(c)
+        {
+            cx.report(format!("heading ends with {c:?}"));
+        }
+    }
+}
+
+/// URLs should be links, not bare text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BareUrls;
+
+impl Rule for BareUrls {
+    fn name(&self) -> &'static str {
+        "bare-urls"
+    }
+
+    fn check_inline(&mut self, inline: &Inline, cx: &mut LintContext<'_>) {
+        let Inline::Text(text) = inline else {
+            return;
+        };
+        if cx.in_link() {
+            return;
+        }
+        let text = text.apply();
+        for word in text.split_whitespace() {
+            if word.starts_with("http://") || word.starts_with("https://") {
+                cx.report(format!("bare URL {word}"));
+            }
+        }
+    }
+}
+
+/// Lines of paragraphs, headings and code blocks should fit in `max`
+/// columns, measured on the block as the writer renders it.
+#[derive(Clone, Copy, Debug)]
+pub struct LineLength {
+    pub max: usize,
+}
+
+impl Default for LineLength {
+    fn default() -> Self {
+        LineLength { max: 80 }
+    }
+}
+
+impl Rule for LineLength {
+    fn name(&self) -> &'static str {
+        "line-length"
+    }
+
+    fn check_block(&mut self, block: &Block, cx: &mut LintContext<'_>) {
+        if !matches!(
+            block,
+            Block::Paragraph(_) | Block::Heading { .. } | Block::CodeBlock { .. }
+        ) {
+            return;
+        }
+        let md = block_to_markdown(block, &WriterOptions::default());
+        for (n, line) in md.lines().enumerate() {
+            let width = line.width();
+            if width > self.max {
+                cx.report(format!(
+                    "line {} is {} columns, over {}",
+                    n + 1,
+                    width,
+                    self.max
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Formatting-preserving editing.
+//!
+//! `parse_markdown_lossless` keeps, next to every top-level block, the exact
+//! source text it was parsed from and the text between it and the previous
+//! block (blank lines, link reference definitions, ...). The writer
+//! re-emits untouched blocks byte-for-byte and only re-serializes the ones
+//! whose `block` no longer equals what was parsed, so an edit to one
+//! paragraph leaves the formatting of the rest of the file alone:
+//!
+//! ```ignore
+//! let mut doc = parse_markdown_lossless(src, Options::empty());
+//! doc.blocks[2].block = Block::paragraph(["rewritten"]);
+//! let md = lossless_to_markdown(&doc);
+//! ```
+//!
+//! The unit of preservation is the top-level block: a change anywhere inside
+//! a list or blockquote re-serializes the whole container.
+
+use crate::ast::block::Block;
+use crate::ast::label_key;
+use crate::ast::parse::parse_offset_events_to_blocks;
+use crate::ast::writer::block_to_region_defining;
+use pulldown_cmark::{Options, Parser};
+
+/// The source a block was parsed from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Origin {
+    /// The block as parsed, to detect modifications.
+    block: Block,
+    /// Source text between the previous block and this one.
+    leading: String,
+    /// Source text of the block itself.
+    text: String,
+}
+
+/// A top-level block, together with its source text when it was parsed
+/// rather than constructed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceBlock {
+    pub block: Block,
+    origin: Option<Origin>,
+}
+
+impl SourceBlock {
+    /// A block without source text; the writer always serializes it.
+    pub fn new(block: Block) -> Self {
+        SourceBlock {
+            block,
+            origin: None,
+        }
+    }
+
+    /// The exact source text the block was parsed from, if any.
+    pub fn source(&self) -> Option<&str> {
+        self.origin.as_ref().map(|o| o.text.as_str())
+    }
+
+    /// Whether the writer has to serialize the block: it was constructed,
+    /// or it no longer equals the block that was parsed.
+    pub fn is_modified(&self) -> bool {
+        self.origin.as_ref().is_none_or(|o| o.block != self.block)
+    }
+}
+
+impl From<Block> for SourceBlock {
+    fn from(block: Block) -> Self {
+        SourceBlock::new(block)
+    }
+}
+
+/// A document that remembers its source. Blocks may be edited, inserted,
+/// removed or reordered freely; see the module documentation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LosslessDocument {
+    pub blocks: Vec<SourceBlock>,
+    /// Source text after the last block.
+    pub trailing: String,
+    /// The label keys of the link reference definitions of the source.
+    /// They stay in its text, so serialized blocks do not write them again.
+    defined: Vec<String>,
+}
+
+impl LosslessDocument {
+    /// The blocks, without their source text.
+    pub fn into_blocks(self) -> Vec<Block> {
+        self.blocks.into_iter().map(|b| b.block).collect()
+    }
+
+    /// The markdown of a block written in place of source text.
+    fn serialize(&self, block: &Block) -> String {
+        block_to_region_defining(block, &self.defined).apply()
+    }
+}
+
+/// Parse markdown source, keeping the source text of every top-level block.
+pub fn parse_markdown_lossless(src: &str, options: Options) -> LosslessDocument {
+    let parser = Parser::new_ext(src, options).into_offset_iter();
+    let mut doc = LosslessDocument {
+        defined: parser
+            .reference_definitions()
+            .iter()
+            .map(|(id, _)| label_key(id))
+            .collect(),
+        ..LosslessDocument::default()
+    };
+    let events: Vec<_> = parser.collect();
+    let mut end = 0;
+    for spanned in parse_offset_events_to_blocks(&events) {
+        // an element that parses into several blocks keeps its source with
+        // the first of them
+        let start = spanned.span.start.max(end);
+        let span = start..start + trim_blank_lines(&src[start..spanned.span.end.max(start)]);
+        doc.blocks.push(SourceBlock {
+            origin: Some(Origin {
+                block: spanned.block.clone(),
+                leading: src[end..span.start].to_string(),
+                text: src[span.clone()].to_string(),
+            }),
+            block: spanned.block,
+        });
+        end = span.end;
+    }
+    doc.trailing = src[end..].to_string();
+    doc
+}
+
+/// Length of `text` without its trailing blank lines, which some blocks
+/// (lists, indented code) include in their span. They are kept as part of
+/// the text before the next block instead.
+fn trim_blank_lines(text: &str) -> usize {
+    let mut end = text.len();
+    while let Some(body) = text[..end].strip_suffix('\n') {
+        let line_start = body.rfind('\n').map_or(0, |i| i + 1);
+        if line_start == 0 || !body[line_start..].trim().is_empty() {
+            break;
+        }
+        end = line_start;
+    }
+    end
+}
+
+/// Write a lossless document. Unmodified blocks are copied from the source,
+/// modified ones are serialized in place of their source text, and blocks
+/// without a source are separated from the preceding text by a blank line.
+pub fn lossless_to_markdown(doc: &LosslessDocument) -> String {
+    let mut out = String::new();
+    let mut after_new = false;
+    for node in &doc.blocks {
+        match &node.origin {
+            Some(origin) => {
+                out.push_str(&origin.leading);
+                if after_new {
+                    separate(&mut out);
+                }
+                if origin.block == node.block {
+                    out.push_str(&origin.text);
+                } else {
+                    out.push_str(&doc.serialize(&node.block));
+                    if origin.text.ends_with('\n') {
+                        out.push('\n');
+                    }
+                }
+            }
+            None => {
+                separate(&mut out);
+                out.push_str(&doc.serialize(&node.block));
+                out.push('\n');
+            }
+        }
+        after_new = node.origin.is_none();
+    }
+    out.push_str(&doc.trailing);
+    out
+}
+
+/// End `out` with a blank line, unless it is empty.
+fn separate(out: &mut String) {
+    if !out.is_empty() {
+        while !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+    }
+}
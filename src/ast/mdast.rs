@@ -0,0 +1,377 @@
+//! mdast JSON reader.
+//!
+//! Builds blocks from the JSON form of an mdast tree, the syntax tree
+//! remark and unified work on, so a document run through remark plugins
+//! can come back into this crate to be written out as markdown:
+//!
+//! ```ignore
+//! let blocks = mdast::from_mdast_json(&std::fs::read_to_string("doc.json")?)?;
+//! let md = blocks_to_markdown(&blocks);
+//! ```
+//!
+//! The standard node set is read, along with GFM's (`delete`, `table`,
+//! `footnoteDefinition`, `footnoteReference` and `checked` list items),
+//! front matter's (`yaml`, `toml`) and remark-math's (`math`,
+//! `inlineMath`). The blocks are shaped as parsing the markdown would shape
+//! them. References take their destination from the tree's definitions.
+//! Positions and `data` are ignored. Other node types are an error.
+
+use crate::ast::parse::comment_body;
+use crate::ast::{Block, Inline};
+use crate::text::Region;
+use pulldown_cmark::{Alignment, CodeBlockKind, CowStr, HeadingLevel, LinkType, MetadataBlockKind};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Why an mdast tree could not be read.
+#[derive(Debug)]
+pub enum MdastError {
+    /// The input is not JSON.
+    Json(serde_json::Error),
+    /// A node of a type this reader does not know, or in a place it
+    /// cannot be, like a paragraph among inlines.
+    Unsupported(String),
+    /// A node lacking a field its type needs, or with a field of the
+    /// wrong shape.
+    Malformed { node: String, field: &'static str },
+}
+
+impl fmt::Display for MdastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MdastError::Json(e) => write!(f, "invalid mdast JSON: {}", e),
+            MdastError::Unsupported(t) => write!(f, "unsupported mdast node `{}`", t),
+            MdastError::Malformed { node, field } => {
+                write!(f, "mdast node `{}` has no valid `{}`", node, field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MdastError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MdastError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for MdastError {
+    fn from(e: serde_json::Error) -> Self {
+        MdastError::Json(e)
+    }
+}
+
+/// Read blocks from mdast JSON: a `root` node, or any single flow node.
+pub fn from_mdast_json(json: &str) -> Result<Vec<Block>, MdastError> {
+    from_mdast(&serde_json::from_str(json)?)
+}
+
+/// Read blocks from an mdast tree already parsed into a `Value`.
+pub fn from_mdast(tree: &Value) -> Result<Vec<Block>, MdastError> {
+    let mut definitions = HashMap::new();
+    collect_definitions(tree, &mut definitions);
+    let reader = Reader { definitions };
+    match node_type(tree)? {
+        "root" => reader.blocks(children(tree)?),
+        _ => reader.blocks(std::slice::from_ref(tree)),
+    }
+}
+
+/// Definitions by identifier: where references to them point.
+fn collect_definitions<'a>(node: &'a Value, out: &mut HashMap<&'a str, (&'a str, &'a str)>) {
+    if node["type"] == "definition"
+        && let (Some(id), Some(url)) = (node["identifier"].as_str(), node["url"].as_str())
+    {
+        // the first definition of a label wins, as in markdown
+        out.entry(id)
+            .or_insert((url, node["title"].as_str().unwrap_or_default()));
+    }
+    if let Some(children) = node["children"].as_array() {
+        for c in children {
+            collect_definitions(c, out);
+        }
+    }
+}
+
+fn node_type(node: &Value) -> Result<&str, MdastError> {
+    node["type"].as_str().ok_or_else(|| MdastError::Malformed {
+        node: node.to_string(),
+        field: "type",
+    })
+}
+
+fn string<'a>(node: &'a Value, name: &'static str) -> Result<&'a str, MdastError> {
+    node[name].as_str().ok_or_else(|| MdastError::Malformed {
+        node: node_type(node).unwrap_or_default().to_string(),
+        field: name,
+    })
+}
+
+fn children(node: &Value) -> Result<&[Value], MdastError> {
+    node["children"]
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| MdastError::Malformed {
+            node: node_type(node).unwrap_or_default().to_string(),
+            field: "children",
+        })
+}
+
+/// The label a reference or definition is written with: its `label`, or
+/// the normalized `identifier` when the tree has none.
+fn label(node: &Value) -> Result<&str, MdastError> {
+    match node["label"].as_str() {
+        Some(label) => Ok(label),
+        None => string(node, "identifier"),
+    }
+}
+
+fn heading_level(node: &Value) -> Result<HeadingLevel, MdastError> {
+    Ok(match node["depth"].as_u64() {
+        Some(1) => HeadingLevel::H1,
+        Some(2) => HeadingLevel::H2,
+        Some(3) => HeadingLevel::H3,
+        Some(4) => HeadingLevel::H4,
+        Some(5) => HeadingLevel::H5,
+        Some(6) => HeadingLevel::H6,
+        _ => {
+            return Err(MdastError::Malformed {
+                node: "heading".to_string(),
+                field: "depth",
+            });
+        }
+    })
+}
+
+fn alignment(a: &Value) -> Alignment {
+    match a.as_str() {
+        Some("left") => Alignment::Left,
+        Some("right") => Alignment::Right,
+        Some("center") => Alignment::Center,
+        _ => Alignment::None,
+    }
+}
+
+/// Text whose line endings are soft breaks, as mdast keeps them.
+fn text(s: &str, out: &mut Vec<Inline>) {
+    for (i, line) in s.split('\n').enumerate() {
+        if i > 0 {
+            out.push(Inline::SoftBreak);
+        }
+        if !line.is_empty() {
+            out.push(Inline::text(line));
+        }
+    }
+}
+
+struct Reader<'a> {
+    definitions: HashMap<&'a str, (&'a str, &'a str)>,
+}
+
+impl Reader<'_> {
+    fn blocks(&self, nodes: &[Value]) -> Result<Vec<Block>, MdastError> {
+        nodes.iter().map(|n| self.block(n)).collect()
+    }
+
+    fn block(&self, node: &Value) -> Result<Block, MdastError> {
+        Ok(match node_type(node)? {
+            "paragraph" => Block::Paragraph(self.inlines(children(node)?)?),
+            "heading" => Block::Heading {
+                level: heading_level(node)?,
+                id: None,
+                classes: Vec::new(),
+                attrs: Vec::new(),
+                children: self.inlines(children(node)?)?,
+            },
+            "thematicBreak" => Block::Rule,
+            "blockquote" => Block::BlockQuote(self.blocks(children(node)?)?),
+            "list" => {
+                let start = match node["ordered"].as_bool() {
+                    Some(true) => Some(node["start"].as_u64().unwrap_or(1)),
+                    _ => None,
+                };
+                let items = children(node)?
+                    .iter()
+                    .map(|item| self.item(item))
+                    .collect::<Result<_, _>>()?;
+                Block::List { start, items }
+            }
+            "code" => {
+                // the meta only follows a language
+                let info = match (node["lang"].as_str(), node["meta"].as_str()) {
+                    (Some(lang), Some(meta)) => format!("{} {}", lang, meta),
+                    (Some(lang), None) => lang.to_string(),
+                    (None, _) => String::new(),
+                };
+                let value = string(node, "value")?;
+                let content = match value.is_empty() {
+                    true => String::new(),
+                    false => format!("{}\n", value),
+                };
+                Block::CodeBlock {
+                    kind: CodeBlockKind::Fenced(CowStr::from(info)),
+                    content: Region::from_str(&content),
+                }
+            }
+            "html" => {
+                let value = string(node, "value")?;
+                match comment_body(value) {
+                    Some(body) => Block::Comment(Region::from_str(body)),
+                    None => Block::HtmlBlock(Region::from_str(value)),
+                }
+            }
+            "definition" => Block::LinkDefinition {
+                id: label(node)?.to_string(),
+                dest: string(node, "url")?.to_string(),
+                title: node["title"].as_str().unwrap_or_default().to_string(),
+            },
+            "footnoteDefinition" => {
+                Block::FootnoteDefinition(label(node)?.to_string(), self.blocks(children(node)?)?)
+            }
+            "table" => self.table(node)?,
+            "yaml" | "toml" => Block::Metadata {
+                kind: match node["type"] == "yaml" {
+                    true => MetadataBlockKind::YamlStyle,
+                    false => MetadataBlockKind::PlusesStyle,
+                },
+                content: Region::from_str(string(node, "value")?),
+            },
+            "math" => Block::Paragraph(vec![Inline::DisplayMath(Region::from_str(string(
+                node, "value",
+            )?))]),
+            t => return Err(MdastError::Unsupported(t.to_string())),
+        })
+    }
+
+    /// A list item's blocks, its `checked` state written as the task
+    /// marker text the parser leaves in the first paragraph.
+    fn item(&self, node: &Value) -> Result<Vec<Block>, MdastError> {
+        if node_type(node)? != "listItem" {
+            return Err(MdastError::Unsupported(node_type(node)?.to_string()));
+        }
+        let mut blocks = self.blocks(children(node)?)?;
+        if let Some(checked) = node["checked"].as_bool() {
+            let marker = Inline::text(if checked { "[x] " } else { "[ ] " });
+            match blocks.first_mut() {
+                Some(Block::Paragraph(inls)) => inls.insert(0, marker),
+                _ => blocks.insert(0, Block::Paragraph(vec![marker])),
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn table(&self, node: &Value) -> Result<Block, MdastError> {
+        let aligns = node["align"]
+            .as_array()
+            .map(|a| a.iter().map(alignment).collect())
+            .unwrap_or_default();
+        let mut rows = children(node)?
+            .iter()
+            .map(|row| {
+                if node_type(row)? != "tableRow" {
+                    return Err(MdastError::Unsupported(node_type(row)?.to_string()));
+                }
+                children(row)?
+                    .iter()
+                    .map(|cell| match node_type(cell)? {
+                        "tableCell" => self.inlines(children(cell)?),
+                        t => Err(MdastError::Unsupported(t.to_string())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // the first row is the head
+        let head = match rows.is_empty() {
+            true => Vec::new(),
+            false => rows.remove(0),
+        };
+        Ok(Block::Table { aligns, head, rows })
+    }
+
+    fn inlines(&self, nodes: &[Value]) -> Result<Vec<Inline>, MdastError> {
+        let mut out = Vec::new();
+        for n in nodes {
+            self.inline(n, &mut out)?;
+        }
+        Ok(out)
+    }
+
+    fn inline(&self, node: &Value, out: &mut Vec<Inline>) -> Result<(), MdastError> {
+        let inl = match node_type(node)? {
+            "text" => {
+                text(string(node, "value")?, out);
+                return Ok(());
+            }
+            "emphasis" => Inline::Emphasis(self.inlines(children(node)?)?),
+            "strong" => Inline::Strong(self.inlines(children(node)?)?),
+            "delete" => Inline::Strikethrough(self.inlines(children(node)?)?),
+            "inlineCode" => Inline::code(string(node, "value")?),
+            "break" => Inline::HardBreak,
+            "html" => {
+                let value = string(node, "value")?;
+                match comment_body(value) {
+                    Some(body) => Inline::Comment(Region::from_str(body)),
+                    None => Inline::InlineHtml(Region::from_str(value)),
+                }
+            }
+            "link" => Inline::Link {
+                link_type: LinkType::Inline,
+                dest: string(node, "url")?.to_string(),
+                title: node["title"].as_str().unwrap_or_default().to_string(),
+                id: String::new(),
+                children: self.inlines(children(node)?)?,
+            },
+            "image" => Inline::Image {
+                link_type: LinkType::Inline,
+                dest: string(node, "url")?.to_string(),
+                title: node["title"].as_str().unwrap_or_default().to_string(),
+                id: String::new(),
+                children: alt(node),
+            },
+            "linkReference" | "imageReference" => {
+                let link_type = match node["referenceType"].as_str() {
+                    Some("collapsed") => LinkType::Collapsed,
+                    Some("shortcut") => LinkType::Shortcut,
+                    _ => LinkType::Reference,
+                };
+                let (dest, title) = self
+                    .definitions
+                    .get(string(node, "identifier")?)
+                    .copied()
+                    .unwrap_or_default();
+                let (dest, title, id) = (dest.to_string(), title.to_string(), label(node)?);
+                match node["type"] == "linkReference" {
+                    true => Inline::Link {
+                        link_type,
+                        dest,
+                        title,
+                        id: id.to_string(),
+                        children: self.inlines(children(node)?)?,
+                    },
+                    false => Inline::Image {
+                        link_type,
+                        dest,
+                        title,
+                        id: id.to_string(),
+                        children: alt(node),
+                    },
+                }
+            }
+            "footnoteReference" => Inline::FootnoteReference(label(node)?.to_string()),
+            "inlineMath" => Inline::InlineMath(Region::from_str(string(node, "value")?)),
+            t => return Err(MdastError::Unsupported(t.to_string())),
+        };
+        out.push(inl);
+        Ok(())
+    }
+}
+
+/// An image's `alt` as its children.
+fn alt(node: &Value) -> Vec<Inline> {
+    let mut out = Vec::new();
+    text(node["alt"].as_str().unwrap_or_default(), &mut out);
+    out
+}
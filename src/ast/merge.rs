@@ -0,0 +1,280 @@
+//! Concatenating documents into one.
+//!
+//! Pasting block lists together is not enough: two documents may both use
+//! a footnote `[^1]` or a reference `[docs]`, and each may open with front
+//! matter. `merge_documents` renames labels that collide with those of
+//! earlier documents, merges front matter into a single block per kind at
+//! the top, and can put a separator between the documents.
+
+use crate::ast::visit::{Edit, VisitMut, Visitor, walk_block, walk_block_mut, walk_blocks};
+use crate::ast::visit::{walk_blocks_mut, walk_inline, walk_inline_mut};
+use crate::ast::{Block, Inline, label_key};
+use crate::text::Region;
+use pulldown_cmark::{LinkType, MetadataBlockKind};
+use std::collections::{HashMap, HashSet};
+
+/// How `merge_documents_with` joins documents.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeOptions {
+    /// A block put between two documents, e.g. `Block::Rule`.
+    pub separator: Option<Block>,
+}
+
+/// The footnote labels and reference definitions of one document.
+#[derive(Default)]
+struct Labels {
+    footnotes: Vec<String>,
+    /// Reference ids with the destination and title they stand for.
+    references: Vec<(String, String, String)>,
+}
+
+impl Visitor for Labels {
+    fn visit_block(&mut self, b: &Block) {
+        match b {
+            Block::FootnoteDefinition(label, _) => self.footnotes.push(label.clone()),
+            Block::LinkDefinition { id, dest, title } => {
+                self.references
+                    .push((id.clone(), dest.clone(), title.clone()))
+            }
+            _ => {}
+        }
+        walk_block(self, b);
+    }
+
+    fn visit_inline(&mut self, inl: &Inline) {
+        match inl {
+            Inline::FootnoteReference(label) => self.footnotes.push(label.clone()),
+            Inline::Link {
+                link_type: LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut,
+                dest,
+                title,
+                id,
+                ..
+            }
+            | Inline::Image {
+                link_type: LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut,
+                dest,
+                title,
+                id,
+                ..
+            } if !id.is_empty() => self
+                .references
+                .push((id.clone(), dest.clone(), title.clone())),
+            _ => {}
+        }
+        walk_inline(self, inl);
+    }
+}
+
+/// Labels to rename in one document, by normalized label, and reference
+/// definitions already written by an earlier document.
+struct Renames {
+    footnotes: HashMap<String, String>,
+    references: HashMap<String, String>,
+    duplicate_definitions: HashSet<(String, String, String)>,
+}
+
+impl Renames {
+    fn reference(&self, id: &str) -> Option<&String> {
+        self.references.get(&label_key(id))
+    }
+}
+
+impl VisitMut for Renames {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        match b {
+            Block::FootnoteDefinition(label, _) => {
+                if let Some(new) = self.footnotes.get(&label_key(label)) {
+                    *label = new.clone();
+                }
+            }
+            Block::LinkDefinition { id, dest, title } => {
+                let key = (label_key(id), dest.clone(), title.clone());
+                if self.duplicate_definitions.contains(&key) {
+                    return Edit::Remove;
+                }
+                if let Some(new) = self.reference(id) {
+                    *id = new.clone();
+                }
+            }
+            _ => {}
+        }
+        walk_block_mut(self, b);
+        Edit::Keep
+    }
+
+    fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+        match inl {
+            Inline::FootnoteReference(label) => {
+                if let Some(new) = self.footnotes.get(&label_key(label)) {
+                    *label = new.clone();
+                }
+            }
+            Inline::Link { link_type, id, .. } | Inline::Image { link_type, id, .. } => {
+                if let Some(new) = self.reference(id) {
+                    *id = new.clone();
+                    // the text of a shortcut or collapsed reference is
+                    // its label, which no longer matches
+                    if matches!(link_type, LinkType::Collapsed | LinkType::Shortcut) {
+                        *link_type = LinkType::Reference;
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_inline_mut(self, inl);
+        Edit::Keep
+    }
+}
+
+/// `label` with the first numeric suffix not in `taken`.
+fn fresh_label(label: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| format!("{label}-{n}"))
+        .find(|l| !taken.contains(&label_key(l)))
+        .expect("some suffix is free")
+}
+
+/// Front matter of the merged document, one block per kind.
+#[derive(Default)]
+struct Metadata {
+    blocks: Vec<(MetadataBlockKind, Vec<String>)>,
+}
+
+impl Metadata {
+    /// The key of a top-level front matter entry starting at `line`.
+    fn key(line: &str) -> Option<&str> {
+        if line.starts_with(char::is_whitespace) || line.starts_with(['-', '#']) {
+            return None;
+        }
+        let end = line.find([':', '='])?;
+        Some(line[..end].trim())
+    }
+
+    /// Add the entries of `content` whose key is not set yet. An entry is
+    /// a top-level line with its indented continuation lines.
+    fn add(&mut self, kind: MetadataBlockKind, content: &Region) {
+        let text = content.apply();
+        let Some((_, lines)) = self.blocks.iter_mut().find(|(k, _)| *k == kind) else {
+            self.blocks
+                .push((kind, text.lines().map(str::to_string).collect()));
+            return;
+        };
+        let keys: HashSet<String> = lines
+            .iter()
+            .filter_map(|l| Metadata::key(l))
+            .map(str::to_string)
+            .collect();
+        let mut keep = false;
+        for line in text.lines() {
+            if let Some(key) = Metadata::key(line) {
+                keep = !keys.contains(key);
+            } else if !line.starts_with(char::is_whitespace) && !line.starts_with('-') {
+                // a line outside any entry, such as a comment
+                keep = false;
+            }
+            if keep {
+                lines.push(line.to_string());
+            }
+        }
+    }
+}
+
+/// Concatenate documents, as `merge_documents_with` does with default
+/// options.
+pub fn merge_documents(docs: Vec<Vec<Block>>) -> Vec<Block> {
+    merge_documents_with(docs, &MergeOptions::default())
+}
+
+/// Concatenate documents into one:
+///
+/// - footnote labels and reference ids used by an earlier document are
+///   renamed (`note` becomes `note-2`) in the later one, along with their
+///   references; a reference definition repeating an earlier one exactly
+///   is kept shared, and its copy dropped;
+/// - front matter blocks move to the top, one per kind: later documents
+///   only add the top-level keys the earlier ones did not set;
+/// - `options.separator` goes between consecutive documents.
+pub fn merge_documents_with(docs: Vec<Vec<Block>>, options: &MergeOptions) -> Vec<Block> {
+    let mut footnotes_taken: HashSet<String> = HashSet::new();
+    // normalized id -> (dest, title) of every reference so far
+    let mut references_taken: HashMap<String, (String, String)> = HashMap::new();
+    let mut metadata = Metadata::default();
+    let mut body = Vec::new();
+
+    for (i, mut doc) in docs.into_iter().enumerate() {
+        let mut labels = Labels::default();
+        walk_blocks(&mut labels, &doc);
+
+        let own: HashSet<String> = labels.footnotes.iter().map(|l| label_key(l)).collect();
+        let mut renames = Renames {
+            footnotes: HashMap::new(),
+            references: HashMap::new(),
+            duplicate_definitions: HashSet::new(),
+        };
+        for label in &labels.footnotes {
+            let key = label_key(label);
+            if footnotes_taken.contains(&key) && !renames.footnotes.contains_key(&key) {
+                let taken = footnotes_taken.union(&own).cloned().collect();
+                renames.footnotes.insert(key, fresh_label(label, &taken));
+            }
+        }
+        footnotes_taken.extend(
+            own.into_iter()
+                .filter(|k| !renames.footnotes.contains_key(k)),
+        );
+        footnotes_taken.extend(renames.footnotes.values().map(|l| label_key(l)));
+
+        let own: HashSet<String> = labels.references.iter().map(|r| label_key(&r.0)).collect();
+        let mut added = HashMap::new();
+        for (id, dest, title) in &labels.references {
+            let key = label_key(id);
+            if renames.references.contains_key(&key) || added.contains_key(&key) {
+                continue;
+            }
+            match references_taken.get(&key) {
+                Some((d, t)) if d == dest && t == title => {
+                    renames
+                        .duplicate_definitions
+                        .insert((key, dest.clone(), title.clone()));
+                }
+                Some(_) => {
+                    let taken: HashSet<String> =
+                        references_taken.keys().chain(&own).cloned().collect();
+                    let new = fresh_label(id, &taken);
+                    added.insert(label_key(&new), (dest.clone(), title.clone()));
+                    renames.references.insert(key, new);
+                }
+                None => {
+                    added.insert(key, (dest.clone(), title.clone()));
+                }
+            }
+        }
+        references_taken.extend(added);
+
+        walk_blocks_mut(&mut renames, &mut doc);
+
+        if i > 0
+            && let Some(sep) = &options.separator
+        {
+            body.push(sep.clone());
+        }
+        for b in doc {
+            match b {
+                Block::Metadata { kind, content } => metadata.add(kind, &content),
+                b => body.push(b),
+            }
+        }
+    }
+
+    let mut out: Vec<Block> = metadata
+        .blocks
+        .into_iter()
+        .map(|(kind, lines)| Block::Metadata {
+            kind,
+            content: Region::from_str(&lines.join("\n")),
+        })
+        .collect();
+    out.extend(body);
+    out
+}
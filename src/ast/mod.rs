@@ -1,18 +1,84 @@
+pub mod admonition;
 pub mod block;
+pub mod borrowed;
+pub mod cursor;
 pub mod custom;
+pub mod diagnostics;
+pub mod diff;
+pub mod directive;
+pub mod document;
+pub mod event;
+pub mod event_iter;
+pub mod extension;
+pub mod extract;
+mod fenced;
+pub mod format;
+#[cfg(any(feature = "yaml", feature = "toml"))]
+pub mod frontmatter;
+pub mod github;
 pub mod inline;
+pub mod jsx;
+pub mod lint;
+pub mod lossless;
+#[cfg(feature = "mdast")]
+pub mod mdast;
+pub mod merge;
+pub mod obsidian;
+pub mod outline;
 pub mod parse;
+pub mod parser_registry;
+pub mod pipeline;
+#[cfg(feature = "serde")]
+pub mod registry;
+pub mod semantic;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod shortcode;
+pub mod slug;
+pub mod transform;
+pub mod validate;
+pub mod visit;
 pub mod writer;
 
 pub use block::Block;
 pub use block::block_to_events;
+pub use block::{CellKey, SortOrder};
+pub use cursor::Cursor;
+pub use diagnostics::ParseDiagnostic;
+pub use document::{
+    DefinitionConflict, Document, LinkDefinition, document_to_markdown, document_to_markdown_with,
+    parse_events_to_document, parse_markdown_to_document, parse_parser_to_blocks,
+    parse_parser_to_document,
+};
+pub use event::{MdEvent, MdTag, MdTagEnd, blocks_to_md_events, parse_md_events_to_blocks};
+pub use event_iter::block_events_iter;
 pub use inline::Inline;
 pub use inline::inline_to_events;
+pub use lossless::{LosslessDocument, SourceBlock, lossless_to_markdown, parse_markdown_lossless};
+pub use merge::{MergeOptions, merge_documents, merge_documents_with};
+pub use parse::link_definition_blocks;
 pub use parse::parse_events_to_blocks;
 pub use parse::parse_events_to_blocks_with_parsers;
-pub use writer::blocks_to_markdown;
+pub use parse::{HookResult, ParseHook, parse_events_to_blocks_with_hook};
+pub use parse::{ParseOptions, parse_events_to_blocks_with_options};
+pub use parse::{SpannedBlock, parse_offset_events_to_blocks};
+pub use parse::{parse_events_to_blocks_with_diagnostics, try_parse_events_to_blocks};
+pub use writer::{Backend, render_with};
+pub use writer::{HtmlOptions, blocks_to_html, blocks_to_plaintext};
+pub use writer::{ListLevel, MarkdownWriter, RenderCache, WriteContext, WriterOptions};
+pub use writer::{block_to_markdown, blocks_to_markdown, events_to_markdown, inline_to_markdown};
+pub use writer::{blocks_to_markdown_into, write_blocks, write_blocks_io};
 
 pub use custom::{BlockNode, InlineNode};
+pub use extension::{Extension, ExtensionRegistry};
+pub use parser_registry::{ParserOptions, ParserRegistry};
+pub use pipeline::{Pipeline, Transform};
+pub use semantic::{SemanticOptions, semantic_eq, semantic_eq_with};
+pub use slug::{SlugOptions, Slugger, slugify};
+pub use validate::{Issue, validate};
+pub use visit::{Edit, VisitMut, walk_block_mut, walk_blocks_mut, walk_inline_mut};
+pub use visit::{Visitor, walk_block, walk_blocks, walk_inline};
+pub use visit::{transform_blocks, transform_inlines};
 
 /// Context passed to a parse hook. This struct gives limited visibility into
 /// the parser's current state so a hook can make context-aware decisions.
@@ -20,15 +86,36 @@ pub use custom::{BlockNode, InlineNode};
 /// Fields:
 /// - `depth`: current stack depth (0 == top-level)
 /// - `parent_tag`: the parent's `Tag<'static>` (if any)
+/// - `ancestors`: tags of all enclosing frames, outermost first
 /// - `parent_collects_inlines`: whether the parent frame is collecting inlines
 /// - `event_index`: current event index in the original slice
-pub struct ParseContext {
+/// - `preceding_blocks` / `preceding_inlines`: siblings already built at the
+///   current level
+pub struct ParseContext<'a> {
     /// current stack depth (0 == top-level)
     pub depth: usize,
     /// parent's tag (if any), converted to a 'static Tag for convenience
     pub parent_tag: Option<pulldown_cmark::Tag<'static>>,
+    /// tags of all enclosing frames, outermost first (the last one is
+    /// `parent_tag`)
+    pub ancestors: Vec<pulldown_cmark::Tag<'static>>,
     /// whether parent frame (if any) is collecting inlines
     pub parent_collects_inlines: bool,
     /// current event index in the original slice
     pub event_index: usize,
+    /// blocks already built at the current level, in document order (the
+    /// top-level output at depth 0)
+    pub preceding_blocks: &'a [Block],
+    /// inlines already built in the current frame when it collects inlines
+    pub preceding_inlines: &'a [Inline],
+}
+
+/// The key footnote labels and link reference ids compare by: matching is
+/// case-insensitive, with runs of whitespace collapsed.
+pub(crate) fn label_key(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
 }
@@ -0,0 +1,357 @@
+//! Obsidian notes: wikilinks, embeds, callouts and tags.
+//!
+//! ```markdown
+//! See [[Other note#Heading|the heading]] and ![[diagram.png]].
+//!
+//! > [!tip]- Folded by default
+//! > Tagged #project/alpha.
+//! ```
+//!
+//! `parse_note` parses a note with the syntax Obsidian adds to markdown.
+//! Wikilinks and embeds come from pulldown-cmark as links and images with
+//! `LinkType::WikiLink`, which the writer writes back as `[[...]]` and
+//! `![[...]]`. Callouts, blockquotes opening with `[!kind]`, become
+//! `Callout` nodes and `#tags` become `Tag` inlines (see `callouts` and
+//! `tags`, which also work on trees parsed otherwise). All of it writes
+//! back in Obsidian's syntax with `blocks_to_markdown`.
+
+use crate::ast::block::{Block, block_to_events};
+use crate::ast::custom::{BlockNode, InlineNode};
+use crate::ast::inline::Inline;
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::transform::merge_text;
+use crate::ast::visit::{Edit, VisitMut, walk_block_mut, walk_blocks_mut, walk_inline_mut};
+use crate::ast::writer::html::{HtmlOptions, blocks_to_html};
+use crate::ast::writer::{WriteContext, WriterOptions, block_to_markdown, write_blocks};
+use crate::{Line, Region};
+use pulldown_cmark::{BlockQuoteKind, CowStr, Event, Options, Parser, Tag as PTag, TagEnd};
+use std::any::Any;
+use std::sync::Arc;
+
+/// The parser options matching Obsidian's markdown: tables, footnotes,
+/// strikethrough, task lists, math, YAML front matter and wikilinks.
+/// GitHub alerts are left out, since `callouts` handles every kind.
+pub fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_MATH
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_WIKILINKS
+}
+
+/// Parse an Obsidian note: `options`, then `tags` and `callouts`.
+pub fn parse_note(src: &str) -> Vec<Block> {
+    let events: Vec<Event> = Parser::new_ext(src, options()).collect();
+    let mut blocks = parse_events_to_blocks(&events);
+    tags(&mut blocks);
+    callouts(&mut blocks);
+    blocks
+}
+
+/// Whether a callout starts folded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fold {
+    /// `+`: foldable, open.
+    Open,
+    /// `-`: foldable, closed.
+    Closed,
+}
+
+/// A callout: `> [!kind]` with an optional fold marker and title.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Callout {
+    /// The kind, e.g. `note` or `tip`, as written.
+    pub kind: String,
+    pub fold: Option<Fold>,
+    /// The rest of the first line, empty when the kind is the title.
+    pub title: Vec<Inline>,
+    pub children: Vec<Block>,
+}
+
+impl Callout {
+    pub fn new(kind: impl Into<String>, children: impl IntoIterator<Item = Block>) -> Self {
+        Callout {
+            kind: kind.into(),
+            fold: None,
+            title: Vec::new(),
+            children: children.into_iter().collect(),
+        }
+    }
+
+    /// The first line, without its `> `.
+    fn head(&self) -> String {
+        let fold = match self.fold {
+            Some(Fold::Open) => "+",
+            Some(Fold::Closed) => "-",
+            None => "",
+        };
+        let mut head = format!("[!{}]{fold}", self.kind);
+        if !self.title.is_empty() {
+            let title = block_to_markdown(
+                &Block::Paragraph(self.title.clone()),
+                &WriterOptions::default(),
+            );
+            head.push(' ');
+            head.push_str(title.trim_end_matches('\n'));
+        }
+        head
+    }
+
+    fn render(&self, options: &WriterOptions) -> Region {
+        let mut md = format!("> {}", self.head());
+        if !self.children.is_empty() {
+            let mut body = String::new();
+            let quote = Block::BlockQuote(self.children.clone());
+            write_blocks(&mut body, std::slice::from_ref(&quote), options)
+                .expect("writing to a String cannot fail");
+            md.push('\n');
+            md.push_str(body.trim_end_matches('\n'));
+        }
+        Region::from_str(&md)
+    }
+}
+
+impl BlockNode for Callout {
+    /// A blockquote opening with the first line, as the source reads.
+    fn to_events(&self) -> Vec<Event<'static>> {
+        let mut events = vec![
+            Event::Start(PTag::BlockQuote(None)),
+            Event::Start(PTag::Paragraph),
+            Event::Text(CowStr::from(self.head())),
+            Event::End(TagEnd::Paragraph),
+        ];
+        events.extend(self.children.iter().flat_map(block_to_events));
+        events.push(Event::End(TagEnd::BlockQuote(None)));
+        events
+    }
+
+    fn to_region(&self) -> Region {
+        self.render(&WriterOptions::default())
+    }
+
+    fn to_region_in(&self, ctx: &WriteContext) -> Region {
+        self.render(ctx.options)
+    }
+
+    /// A `div` in the shape Obsidian renders, with the kind in
+    /// `data-callout`.
+    fn to_html(&self) -> Option<String> {
+        let options = HtmlOptions::default();
+        let title = match self.title.is_empty() {
+            true => self.kind.clone(),
+            false => {
+                let html = blocks_to_html(&[Block::Paragraph(self.title.clone())], &options);
+                let html = html.trim_end_matches('\n');
+                let html = html.strip_prefix("<p>").unwrap_or(html);
+                html.strip_suffix("</p>").unwrap_or(html).to_string()
+            }
+        };
+        Some(format!(
+            "<div class=\"callout\" data-callout=\"{}\">\n<div class=\"callout-title\">{}</div>\n<div class=\"callout-content\">\n{}</div>\n</div>\n",
+            self.kind.replace('&', "&amp;").replace('"', "&quot;"),
+            title,
+            blocks_to_html(&self.children, &options)
+        ))
+    }
+
+    fn node_eq(&self, other: &dyn BlockNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<Callout>() == Some(self)
+    }
+}
+
+/// The callout a blockquote or GitHub alert holding `children` makes, if
+/// its first paragraph opens with `[!kind]`.
+fn callout(children: &[Block]) -> Option<Callout> {
+    let (Block::Paragraph(first), rest) = children.split_first()? else {
+        return None;
+    };
+    let mut first = first.clone();
+    merge_text(&mut first);
+    let Some(Inline::Text(t)) = first.first() else {
+        return None;
+    };
+    let text = t.apply();
+    let (kind, after) = text.strip_prefix("[!")?.split_once(']')?;
+    if kind.is_empty() || kind.contains(char::is_whitespace) {
+        return None;
+    }
+    let (fold, after) = match after.chars().next() {
+        Some('+') => (Some(Fold::Open), &after[1..]),
+        Some('-') => (Some(Fold::Closed), &after[1..]),
+        _ => (None, after),
+    };
+    if !after.is_empty() && !after.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut line = first.split_off(1);
+    let after = after.trim_start();
+    if !after.is_empty() {
+        line.insert(0, Inline::text(after));
+    }
+    // the title ends with the first line
+    let body = match line
+        .iter()
+        .position(|i| matches!(i, Inline::SoftBreak | Inline::HardBreak))
+    {
+        Some(n) => line.split_off(n + 1).into_iter().collect(),
+        None => Vec::new(),
+    };
+    if matches!(line.last(), Some(Inline::SoftBreak | Inline::HardBreak)) {
+        line.pop();
+    }
+    let mut children: Vec<Block> = Vec::new();
+    if !body.is_empty() {
+        children.push(Block::Paragraph(body));
+    }
+    children.extend(rest.iter().cloned());
+    Some(Callout {
+        kind: kind.to_string(),
+        fold,
+        title: line,
+        children,
+    })
+}
+
+fn alert_kind(kind: &BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "note",
+        BlockQuoteKind::Tip => "tip",
+        BlockQuoteKind::Important => "important",
+        BlockQuoteKind::Warning => "warning",
+        BlockQuoteKind::Caution => "caution",
+    }
+}
+
+struct Callouts;
+
+impl VisitMut for Callouts {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        walk_block_mut(self, b);
+        let found = match b {
+            Block::BlockQuote(children) => callout(children),
+            Block::Alert { kind, children } => Some(
+                callout(children)
+                    .unwrap_or_else(|| Callout::new(alert_kind(kind), children.clone())),
+            ),
+            _ => None,
+        };
+        match found {
+            Some(c) => Edit::Replace(vec![Block::Custom(Arc::new(c))]),
+            None => Edit::Keep,
+        }
+    }
+}
+
+/// Turn blockquotes opening with `[!kind]` into `Callout`s, at any depth,
+/// along with GitHub alerts, whose kind they keep.
+pub fn callouts(blocks: &mut Vec<Block>) {
+    walk_blocks_mut(&mut Callouts, blocks);
+}
+
+/// An Obsidian tag, `#name`, where the name may hold `/` for nesting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    /// The name, without the `#`.
+    pub name: String,
+}
+
+impl InlineNode for Tag {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![Event::Text(CowStr::from(format!("#{}", self.name)))]
+    }
+
+    fn to_line(&self) -> Line {
+        Line::from_str(&format!("#{}", self.name))
+    }
+
+    fn to_html(&self) -> Option<String> {
+        Some(format!(
+            "<a class=\"tag\" href=\"#{0}\">#{0}</a>",
+            self.name
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;")
+        ))
+    }
+
+    fn node_eq(&self, other: &dyn InlineNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<Tag>() == Some(self)
+    }
+}
+
+/// Split `text` around its tags, or `None` if it has none. A tag follows
+/// a space or the start of the text, and its name is made of letters,
+/// digits, `_`, `-` and `/`, not digits alone.
+fn split_tags(text: &str) -> Option<Vec<Inline>> {
+    let mut out = Vec::new();
+    let mut done = 0;
+    for (i, _) in text.match_indices('#') {
+        if i < done
+            || text[..i]
+                .chars()
+                .next_back()
+                .is_some_and(|c| !c.is_whitespace())
+        {
+            continue;
+        }
+        let name = &text[i + 1..];
+        let n = name
+            .find(|c: char| !(c.is_alphanumeric() || "_-/".contains(c)))
+            .unwrap_or(name.len());
+        let name = &name[..n];
+        if name.is_empty() || name.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        if done < i {
+            out.push(Inline::text(&text[done..i]));
+        }
+        out.push(Inline::Custom(Arc::new(Tag {
+            name: name.to_string(),
+        })));
+        done = i + 1 + n;
+    }
+    if out.is_empty() {
+        return None;
+    }
+    if done < text.len() {
+        out.push(Inline::text(&text[done..]));
+    }
+    Some(out)
+}
+
+struct Tags;
+
+impl VisitMut for Tags {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        if let Block::Paragraph(children) | Block::Heading { children, .. } = b {
+            merge_text(children);
+        }
+        walk_block_mut(self, b);
+        Edit::Keep
+    }
+
+    fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+        match inl {
+            // link text is not searched
+            Inline::Link { .. } | Inline::Image { .. } => Edit::Keep,
+            Inline::Text(t) => match split_tags(&t.apply()) {
+                Some(with) => Edit::Replace(with),
+                None => Edit::Keep,
+            },
+            _ => {
+                walk_inline_mut(self, inl);
+                Edit::Keep
+            }
+        }
+    }
+}
+
+/// Turn `#tags` in the text of `blocks` into `Tag` inlines, at any depth
+/// but outside links.
+pub fn tags(blocks: &mut Vec<Block>) {
+    walk_blocks_mut(&mut Tags, blocks);
+}
@@ -0,0 +1,202 @@
+//! Section tree built from the headings of a flat block list.
+//!
+//! `build_sections` groups every heading with the blocks that follow it up
+//! to the next heading of the same or a higher level; deeper headings
+//! become subsections. `flatten_sections` turns the tree back into blocks,
+//! so a document can be restructured (chapters moved, levels shifted) and
+//! written out again.
+//!
+//! `outline` lists the headings alone, flat and in document order, with
+//! their text and anchor, e.g. for a sidebar or a table of contents.
+
+use crate::ast::block::Block;
+use crate::ast::parse::{parse_offset_events_to_blocks, span_at};
+use crate::ast::slug::Slugger;
+use crate::ast::writer::plaintext::inlines_text;
+use pulldown_cmark::{HeadingLevel, Options, Parser};
+use std::ops::Range;
+
+/// A heading together with the content it introduces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// The `Block::Heading` opening the section. Only the preamble (blocks
+    /// before the first heading) is a section without one.
+    pub heading: Option<Block>,
+    /// Blocks between the heading and the first subsection.
+    pub children_blocks: Vec<Block>,
+    /// Sections introduced by deeper headings.
+    pub subsections: Vec<Section>,
+}
+
+impl Section {
+    /// The level of the section's heading, `None` for the preamble.
+    pub fn level(&self) -> Option<HeadingLevel> {
+        match &self.heading {
+            Some(Block::Heading { level, .. }) => Some(*level),
+            _ => None,
+        }
+    }
+
+    /// Move this section and all its subsections `by` levels deeper (or
+    /// shallower when negative), clamping to `h1`..`h6`.
+    pub fn shift_level(&mut self, by: i8) {
+        if let Some(Block::Heading { level, .. }) = &mut self.heading {
+            let n = (*level as i8 + by).clamp(1, 6);
+            *level = HeadingLevel::try_from(n as usize).expect("clamped to a valid level");
+        }
+        for s in &mut self.subsections {
+            s.shift_level(by);
+        }
+    }
+
+    fn flatten_into(&self, out: &mut Vec<Block>) {
+        out.extend(self.heading.iter().cloned());
+        out.extend(self.children_blocks.iter().cloned());
+        for s in &self.subsections {
+            s.flatten_into(out);
+        }
+    }
+}
+
+/// Group blocks into a section tree. Content before the first heading ends
+/// up in a leading section without a heading. Only top-level headings
+/// start sections; headings nested in containers stay part of their block.
+pub fn build_sections(blocks: &[Block]) -> Vec<Section> {
+    let mut out: Vec<Section> = Vec::new();
+    // open sections, outermost first
+    let mut stack: Vec<Section> = Vec::new();
+
+    fn close(stack: &mut Vec<Section>, out: &mut Vec<Section>) {
+        if let Some(done) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.subsections.push(done),
+                None => out.push(done),
+            }
+        }
+    }
+
+    for b in blocks {
+        match b {
+            Block::Heading { level, .. } => {
+                let depth = *level as usize;
+                // a heading closes the preamble and any section at its level
+                // or deeper
+                while let Some(open) = stack.last()
+                    && open.level().is_none_or(|l| l as usize >= depth)
+                {
+                    close(&mut stack, &mut out);
+                }
+                stack.push(Section {
+                    heading: Some(b.clone()),
+                    children_blocks: Vec::new(),
+                    subsections: Vec::new(),
+                });
+            }
+            other => match stack.last_mut() {
+                Some(s) => s.children_blocks.push(other.clone()),
+                None => stack.push(Section {
+                    heading: None,
+                    children_blocks: vec![other.clone()],
+                    subsections: Vec::new(),
+                }),
+            },
+        }
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut out);
+    }
+    out
+}
+
+/// Turn a section tree back into a flat block list.
+pub fn flatten_sections(sections: &[Section]) -> Vec<Block> {
+    let mut out = Vec::new();
+    for s in sections {
+        s.flatten_into(&mut out);
+    }
+    out
+}
+
+/// A heading, as `outline` lists it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutlineEntry {
+    pub level: HeadingLevel,
+    /// The heading text, without markup.
+    pub text: String,
+    /// The anchor of the heading: its explicit id, or else its GitHub slug,
+    /// numbered as a `Slugger` numbers repeated ones.
+    pub slug: String,
+    /// Path of the heading, as `Cursor::path` returns it.
+    pub path: Vec<usize>,
+    /// Byte range of the heading in the source, when the outline was taken
+    /// from source text.
+    pub span: Option<Range<usize>>,
+}
+
+struct Outline {
+    slugger: Slugger,
+    path: Vec<usize>,
+    out: Vec<OutlineEntry>,
+}
+
+impl Outline {
+    fn blocks(&mut self, blocks: &[Block]) {
+        for (i, b) in blocks.iter().enumerate() {
+            self.path.push(i);
+            match b {
+                Block::Heading {
+                    level,
+                    id,
+                    children,
+                    ..
+                } => {
+                    let slug = self.slugger.slug(children);
+                    self.out.push(OutlineEntry {
+                        level: *level,
+                        text: inlines_text(children),
+                        slug: id.clone().unwrap_or(slug),
+                        path: self.path.clone(),
+                        span: None,
+                    });
+                }
+                Block::BlockQuote(children)
+                | Block::Alert { children, .. }
+                | Block::Item(children)
+                | Block::FootnoteDefinition(_, children) => self.blocks(children),
+                Block::List { items, .. } => {
+                    for (j, item) in items.iter().enumerate() {
+                        self.path.push(j);
+                        self.blocks(item);
+                        self.path.pop();
+                    }
+                }
+                _ => {}
+            }
+            self.path.pop();
+        }
+    }
+}
+
+/// Every heading of `blocks`, nested ones included, in document order.
+pub fn outline(blocks: &[Block]) -> Vec<OutlineEntry> {
+    let mut o = Outline {
+        slugger: Slugger::default(),
+        path: Vec::new(),
+        out: Vec::new(),
+    };
+    o.blocks(blocks);
+    o.out
+}
+
+/// Parse `src` and outline it, giving every entry the source span of its
+/// heading.
+pub fn outline_markdown(src: &str, options: Options) -> Vec<OutlineEntry> {
+    let events: Vec<_> = Parser::new_ext(src, options).into_offset_iter().collect();
+    let spanned = parse_offset_events_to_blocks(&events);
+    let blocks: Vec<Block> = spanned.iter().map(|s| s.block.clone()).collect();
+    let mut entries = outline(&blocks);
+    for e in &mut entries {
+        e.span = span_at(&spanned, &e.path);
+    }
+    entries
+}
@@ -1,7 +1,69 @@
+use crate::ast::ParseContext;
 use crate::ast::block::Block;
+use crate::ast::diagnostics::ParseDiagnostic;
 use crate::ast::inline::Inline;
-use crate::text::{Line, Region};
-use pulldown_cmark::{Event, Tag};
+use crate::text::Region;
+use pulldown_cmark::{Event, RefDefs, Tag};
+use std::ops::Range;
+
+// ParseContext is defined and re-exported from `crate::ast::ParseContext`.
+
+/// What a parse hook produced in place of the events it consumed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookResult {
+    /// A single block.
+    Block(Block),
+    /// Several sibling blocks, e.g. a figure followed by its caption.
+    Blocks(Vec<Block>),
+    /// An inline, added to the surrounding inline content. At block level
+    /// it is wrapped in a paragraph of its own.
+    Inline(Inline),
+    /// Several inlines, e.g. text split around the links found in it. At
+    /// block level they are wrapped in a paragraph of their own.
+    Inlines(Vec<Inline>),
+    /// A block that takes the place of the last of
+    /// `ParseContext::preceding_blocks`, e.g. an image paragraph merged with
+    /// the caption that follows it.
+    ReplacePrevious(Block),
+}
+
+impl From<Block> for HookResult {
+    fn from(b: Block) -> Self {
+        HookResult::Block(b)
+    }
+}
+
+impl From<Inline> for HookResult {
+    fn from(inl: Inline) -> Self {
+        HookResult::Inline(inl)
+    }
+}
+
+/// Options for `parse_events_to_blocks_with_options`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Coalesce consecutive `Text` events into one `Inline::Text`, so text
+    /// pulldown-cmark splits around escapes and entities ends up in a single
+    /// region. On by default.
+    pub merge_text: bool,
+    /// Also fold soft breaks that follow text into the merged region as
+    /// line breaks. Off by default, since the block then no longer turns
+    /// back into the same events.
+    pub merge_soft_breaks: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            merge_text: true,
+            merge_soft_breaks: false,
+        }
+    }
+}
+
+/// Signature of a parse hook. See `parse_events_to_blocks_with_hook`.
+pub type ParseHook<'h> =
+    dyn for<'b> FnMut(&'b [Event<'b>], usize, &ParseContext) -> Option<(usize, HookResult)> + 'h;
 
 /// Convert a pulldown-cmark `Event` slice into a vector of `Block` AST nodes.
 /// This is a best-effort parser that understands common tags and will
@@ -10,29 +72,116 @@ use pulldown_cmark::{Event, Tag};
 ///
 /// The hook, if provided, is invoked with the remaining slice of events at
 /// the current parse position. If the hook recognizes a custom node it may
-/// return `Some((consumed, Block))` to indicate it consumed `consumed`
-/// events and produced the provided `Block`. The parser will then skip the
-/// consumed events and continue. The hook is called before processing the
-/// next event and applies at the current nesting level.
-// ParseContext is defined and re-exported from `crate::ast::ParseContext`.
-
+/// return `Some((consumed, result))` to indicate it consumed `consumed`
+/// events and produced the nodes in `result` (see `HookResult`). The parser
+/// will then skip the consumed events and continue. The hook is called
+/// before processing the next event and applies at the current nesting
+/// level: produced blocks become children of the enclosing container
+/// (blockquote, list item, ...) rather than top-level blocks.
 pub fn parse_events_to_blocks_with_hook<'a>(
     events: &[Event<'a>],
-    mut hook: Option<
-        &mut dyn for<'b> FnMut(
-            &'b [Event<'b>],
-            usize,
-            &crate::ast::ParseContext,
-        ) -> Option<(usize, Block)>,
-    >,
+    hook: Option<&mut ParseHook<'_>>,
+) -> Vec<Block> {
+    parse_core(events, hook, &ParseOptions::default()).0
+}
+
+/// Parse events into blocks with explicit `ParseOptions`.
+pub fn parse_events_to_blocks_with_options<'a>(
+    events: &[Event<'a>],
+    options: &ParseOptions,
 ) -> Vec<Block> {
+    parse_core(events, None, options).0
+}
+
+/// Parse events into blocks, failing with everything the lenient parser
+/// would have had to guess about or drop (see `ParseDiagnostic`).
+pub fn try_parse_events_to_blocks<'a>(
+    events: &[Event<'a>],
+) -> Result<Vec<Block>, Vec<ParseDiagnostic>> {
+    let (blocks, diags) = parse_core(events, None, &ParseOptions::default());
+    if diags.is_empty() {
+        Ok(blocks)
+    } else {
+        Err(diags)
+    }
+}
+
+/// Parse events into blocks like `parse_events_to_blocks`, also returning
+/// the diagnostics `try_parse_events_to_blocks` would fail with.
+pub fn parse_events_to_blocks_with_diagnostics<'a>(
+    events: &[Event<'a>],
+) -> (Vec<Block>, Vec<ParseDiagnostic>) {
+    parse_core(events, None, &ParseOptions::default())
+}
+
+/// The parser behind all `parse_events_to_blocks*` functions.
+fn parse_core<'a>(
+    events: &[Event<'a>],
+    mut hook: Option<&mut ParseHook<'_>>,
+    options: &ParseOptions,
+) -> (Vec<Block>, Vec<ParseDiagnostic>) {
     // A simple stack frame used while parsing Start/End pairs.
     struct Frame<'a> {
         tag: Tag<'a>,
+        // index of the Start event
+        start: usize,
         // collecting inlines vs blocks
         inlines: Vec<Inline>,
         blocks: Vec<Block>,
         collect_inlines: bool,
+        // index in `blocks` of the paragraph collecting the inline content
+        // of a tight list item, while it is the last block
+        tight: Option<usize>,
+    }
+
+    // The inlines the next inline of `frame` goes to: its own, the
+    // paragraph a tight list item gathers its run of inline content in, or
+    // in other block frames a paragraph of its own.
+    fn inline_target<'f>(frame: &'f mut Frame<'_>) -> &'f mut Vec<Inline> {
+        if frame.collect_inlines {
+            return &mut frame.inlines;
+        }
+        let n = match frame.tight {
+            Some(n) if n + 1 == frame.blocks.len() && matches!(frame.tag, Tag::Item) => n,
+            _ => {
+                frame.blocks.push(Block::Paragraph(Vec::new()));
+                frame.blocks.len() - 1
+            }
+        };
+        frame.tight = Some(n);
+        match &mut frame.blocks[n] {
+            Block::Paragraph(inls) => inls,
+            _ => unreachable!("the tight index points at a paragraph"),
+        }
+    }
+
+    // Add `inl` to `inlines`, merging text as `options` ask.
+    fn push_inline(inlines: &mut Vec<Inline>, inl: Inline, options: &ParseOptions) {
+        match (inlines.last_mut(), inl) {
+            (Some(Inline::Text(prev)), Inline::Text(next)) if options.merge_text => {
+                prev.push_str(&next.apply());
+            }
+            (Some(Inline::Text(prev)), Inline::SoftBreak)
+                if options.merge_text && options.merge_soft_breaks =>
+            {
+                prev.push_str("\n");
+            }
+            (_, inl) => inlines.push(inl),
+        }
+    }
+
+    // Add an inline event's node at the current nesting level: to the
+    // innermost frame, or in a paragraph of its own at the top level.
+    fn add_inline(
+        stack: &mut [Frame<'_>],
+        out: &mut Vec<Block>,
+        inl: Inline,
+        options: &ParseOptions,
+    ) {
+        match stack.last_mut() {
+            Some(top) => push_inline(inline_target(top), inl, options),
+            None => out.push(Block::Paragraph(vec![inl])),
+        }
     }
 
     fn region_from_cow(s: &str) -> Region {
@@ -41,298 +190,362 @@ pub fn parse_events_to_blocks_with_hook<'a>(
 
     let mut stack: Vec<Frame> = Vec::new();
     let mut out: Vec<Block> = Vec::new();
+    let mut diags: Vec<ParseDiagnostic> = Vec::new();
 
     // helper to convert Tag<'a> -> Tag<'static>
     fn tag_to_static(t: &Tag) -> Tag<'static> {
         t.clone().into_static()
     }
 
+    // Turn a finished frame into a node and attach it to its parent (or the
+    // output). `i` is the index of the event that closed it.
+    fn close_frame<'a>(
+        frame: Frame<'a>,
+        stack: &mut [Frame<'a>],
+        out: &mut Vec<Block>,
+        diags: &mut Vec<ParseDiagnostic>,
+        i: usize,
+    ) {
+        use pulldown_cmark::Tag::*;
+        let mut maybe_inline: Option<Inline> = None;
+        let node = match frame.tag {
+            Paragraph => Block::Paragraph(frame.inlines),
+            Heading {
+                level,
+                id,
+                classes,
+                attrs,
+            } => Block::Heading {
+                level,
+                id: id.map(|c| c.to_string()),
+                classes: classes.into_iter().map(|c| c.to_string()).collect(),
+                attrs: attrs
+                    .into_iter()
+                    .map(|(a, v)| (a.to_string(), v.map(|s| s.to_string())))
+                    .collect(),
+                children: frame.inlines,
+            },
+            BlockQuote(Some(kind)) => Block::Alert {
+                kind,
+                children: frame.blocks,
+            },
+            BlockQuote(None) => Block::BlockQuote(frame.blocks),
+            CodeBlock(kind) => {
+                // code block content: concatenate paragraph texts as emitted
+                let mut combined = String::new();
+                for b in frame.blocks.into_iter() {
+                    if let Block::Paragraph(inls) = b {
+                        for inl in inls {
+                            if let Inline::Text(r) = inl {
+                                r.apply_into(&mut combined);
+                            }
+                        }
+                    }
+                }
+                let content = Region::from_str(&combined);
+                let kind_owned = kind.into_static();
+                Block::CodeBlock {
+                    kind: kind_owned,
+                    content,
+                }
+            }
+            HtmlBlock => match html_frame_comment(&frame.blocks) {
+                Some(body) => Block::Comment(Region::from_str(&body)),
+                None => {
+                    // build content from any Html blocks collected in frame.blocks
+                    // and any inline Html/Text collected in frame.inlines.
+                    // The html is joined before splitting it into lines:
+                    // pulldown-cmark may end a line in an event of its own,
+                    // as it does for "\r\n".
+                    let mut html = String::new();
+                    for b in frame.blocks.into_iter() {
+                        match b {
+                            Block::HtmlBlock(rgn) => rgn.apply_into(&mut html),
+                            Block::Paragraph(inls) => {
+                                for inl in inls {
+                                    if let Inline::Text(r) = inl {
+                                        r.apply_into(&mut html);
+                                        html.push('\n');
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    for inl in frame.inlines.into_iter() {
+                        match inl {
+                            Inline::Text(r) => {
+                                r.apply_into(&mut html);
+                                html.push('\n');
+                            }
+                            Inline::Html(r) => r.apply_into(&mut html),
+                            _ => {}
+                        }
+                    }
+                    let content = Region::from_str(html.strip_suffix('\n').unwrap_or(&html));
+                    Block::HtmlBlock(content)
+                }
+            },
+            List(start) => {
+                let mut items: Vec<Vec<Block>> = Vec::new();
+                for b in frame.blocks.into_iter() {
+                    match b {
+                        Block::Item(children) => items.push(children),
+                        other => items.push(vec![other]),
+                    }
+                }
+                Block::List { start, items }
+            }
+            Item => Block::Item(frame.blocks),
+            FootnoteDefinition(label) => Block::FootnoteDefinition(label.to_string(), frame.blocks),
+            Table(aligns) => {
+                let mut head: Option<Vec<Vec<Inline>>> = None;
+                let mut rows: Vec<Vec<Vec<Inline>>> = Vec::new();
+                for b in frame.blocks.into_iter() {
+                    match b {
+                        Block::TableHead(cells) if head.is_none() => head = Some(cells),
+                        Block::TableRow(cells) | Block::TableHead(cells) => rows.push(cells),
+                        Block::Paragraph(inls) => rows.push(vec![inls]),
+                        Block::Item(children) => {
+                            let mut inls_acc: Vec<Inline> = Vec::new();
+                            for ch in children {
+                                if let Block::Paragraph(mut p_inls) = ch {
+                                    inls_acc.append(&mut p_inls);
+                                }
+                            }
+                            rows.push(vec![inls_acc]);
+                        }
+                        _ => {}
+                    }
+                }
+                Block::Table {
+                    aligns,
+                    head: head.unwrap_or_default(),
+                    rows,
+                }
+            }
+            TableHead | TableRow => {
+                let mut row_cells: Vec<Vec<Inline>> = Vec::new();
+                for b in frame.blocks.into_iter() {
+                    if let Block::Paragraph(inls) = b {
+                        row_cells.push(inls);
+                    }
+                }
+                if frame.tag == TableHead {
+                    Block::TableHead(row_cells)
+                } else {
+                    Block::TableRow(row_cells)
+                }
+            }
+            TableCell => Block::Paragraph(frame.inlines),
+            Emphasis => {
+                maybe_inline = Some(Inline::Emphasis(frame.inlines));
+                Block::Paragraph(Vec::new())
+            }
+            Strong => {
+                maybe_inline = Some(Inline::Strong(frame.inlines));
+                Block::Paragraph(Vec::new())
+            }
+            Strikethrough => {
+                maybe_inline = Some(Inline::Strikethrough(frame.inlines));
+                Block::Paragraph(Vec::new())
+            }
+            Subscript => {
+                maybe_inline = Some(Inline::Subscript(frame.inlines));
+                Block::Paragraph(Vec::new())
+            }
+            Superscript => {
+                maybe_inline = Some(Inline::Superscript(frame.inlines));
+                Block::Paragraph(Vec::new())
+            }
+            Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            } => {
+                maybe_inline = Some(Inline::Link {
+                    link_type,
+                    dest: dest_url.to_string(),
+                    title: title.to_string(),
+                    id: id.to_string(),
+                    children: frame.inlines,
+                });
+                Block::Paragraph(Vec::new())
+            }
+            Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            } => {
+                maybe_inline = Some(Inline::Image {
+                    link_type,
+                    dest: dest_url.to_string(),
+                    title: title.to_string(),
+                    id: id.to_string(),
+                    children: frame.inlines,
+                });
+                Block::Paragraph(Vec::new())
+            }
+            Tag::MetadataBlock(kind) => {
+                let mut combined = String::new();
+                for inl in frame.inlines {
+                    if let Inline::Text(r) = inl {
+                        r.apply_into(&mut combined);
+                    }
+                }
+                // the parser reports the body with its trailing
+                // newline; the closing fence is re-added on write
+                if combined.ends_with('\n') {
+                    combined.pop();
+                }
+                Block::Metadata {
+                    kind,
+                    content: Region::from_str(&combined),
+                }
+            }
+            _ => Block::Paragraph(frame.inlines),
+        };
+
+        if let Some(parent) = stack.last_mut() {
+            if let Some(inl) = maybe_inline.take() {
+                inline_target(parent).push(inl);
+            } else if parent.collect_inlines {
+                match node {
+                    Block::Paragraph(inls) => parent.inlines.extend(inls),
+                    other => {
+                        // inline frames are built from their inlines only
+                        diags.push(ParseDiagnostic::DroppedEvent { index: i });
+                        parent.blocks.push(other);
+                    }
+                }
+            } else {
+                parent.blocks.push(node);
+            }
+        } else {
+            if let Some(inl) = maybe_inline.take() {
+                out.push(Block::Paragraph(vec![inl]));
+            } else {
+                out.push(node);
+            }
+        }
+    }
+
     let mut i: usize = 0;
     while i < events.len() {
         // build minimal context for the hook and try it first
-        let ctx = crate::ast::ParseContext {
-            depth: stack.len(),
-            parent_tag: stack.last().map(|f| tag_to_static(&f.tag)),
-            parent_collects_inlines: stack.last().map(|f| f.collect_inlines).unwrap_or(false),
-            event_index: i,
-        };
-        if let Some(h) = hook.as_mut() {
-            if let Some((consumed, blk)) = h(&events[i..], i, &ctx) {
-                out.push(blk);
-                i = i.saturating_add(consumed);
-                continue;
+        if let Some(h) = hook.as_mut()
+            && let Some((consumed, result)) = h(
+                &events[i..],
+                i,
+                &crate::ast::ParseContext {
+                    depth: stack.len(),
+                    parent_tag: stack.last().map(|f| tag_to_static(&f.tag)),
+                    ancestors: stack.iter().map(|f| tag_to_static(&f.tag)).collect(),
+                    parent_collects_inlines: stack
+                        .last()
+                        .map(|f| f.collect_inlines)
+                        .unwrap_or(false),
+                    event_index: i,
+                    preceding_blocks: stack.last().map_or(&out, |f| &f.blocks),
+                    preceding_inlines: stack.last().map_or(&[], |f| &f.inlines),
+                },
+            )
+        {
+            // the hook applies at the current nesting level
+            let blocks = match stack.last_mut() {
+                Some(top) => &mut top.blocks,
+                None => &mut out,
+            };
+            match result {
+                HookResult::Block(blk) => blocks.push(blk),
+                HookResult::Blocks(blks) => blocks.extend(blks),
+                HookResult::Inline(inl) => match stack.last_mut() {
+                    Some(top) => inline_target(top).push(inl),
+                    None => out.push(Block::Paragraph(vec![inl])),
+                },
+                HookResult::Inlines(inls) => match stack.last_mut() {
+                    Some(top) => inline_target(top).extend(inls),
+                    None => out.push(Block::Paragraph(inls)),
+                },
+                HookResult::ReplacePrevious(blk) => {
+                    blocks.pop();
+                    blocks.push(blk);
+                }
             }
+            i = i.saturating_add(consumed);
+            continue;
         }
 
         let ev = &events[i];
+        check_placement(
+            ev,
+            i,
+            stack.last().map(|f| (&f.tag, f.collect_inlines)),
+            &mut diags,
+        );
         match ev {
             Event::Start(tag) => {
                 // decide whether this tag should collect inlines or blocks
-                let collect_inlines = matches!(
-                    tag,
-                    Tag::Paragraph
-                        | Tag::Heading { .. }
-                        | Tag::Emphasis
-                        | Tag::Strong
-                        | Tag::Strikethrough
-                        | Tag::Subscript
-                        | Tag::Superscript
-                        | Tag::Link { .. }
-                        | Tag::Image { .. }
-                        | Tag::TableCell
-                );
+                let collect_inlines = collects_inlines(tag);
+                if !is_supported_tag(tag) {
+                    diags.push(ParseDiagnostic::UnsupportedTag {
+                        index: i,
+                        tag: tag_to_static(tag),
+                    });
+                }
                 stack.push(Frame {
                     tag: tag.clone(),
+                    start: i,
                     inlines: Vec::new(),
                     blocks: Vec::new(),
                     collect_inlines,
+                    tight: None,
                 });
                 i += 1;
             }
-            Event::End(_tagend) => {
-                if let Some(frame) = stack.pop() {
-                    // convert frame into either Block or Inline and append to parent or root
-                    use pulldown_cmark::Tag::*;
-                    let mut maybe_inline: Option<Inline> = None;
-                    let node = match frame.tag {
-                        Paragraph => Block::Paragraph(frame.inlines),
-                        Heading {
-                            level,
-                            id,
-                            classes,
-                            attrs,
-                        } => Block::Heading {
-                            level,
-                            id: id.map(|c| c.to_string()),
-                            classes: classes.into_iter().map(|c| c.to_string()).collect(),
-                            attrs: attrs
-                                .into_iter()
-                                .map(|(a, v)| (a.to_string(), v.map(|s| s.to_string())))
-                                .collect(),
-                            children: frame.inlines,
-                        },
-                        BlockQuote(_kind) => Block::BlockQuote(frame.blocks),
-                        CodeBlock(kind) => {
-                            // code block content: concatenate paragraph texts as emitted
-                            let mut combined = String::new();
-                            for b in frame.blocks.into_iter() {
-                                if let Block::Paragraph(inls) = b {
-                                    for inl in inls {
-                                        if let Inline::Text(r) = inl {
-                                            combined.push_str(&r.apply());
-                                        }
-                                    }
-                                }
-                            }
-                            let content = Region::from_str(&combined);
-                            let kind_owned = kind.into_static();
-                            Block::CodeBlock {
-                                kind: kind_owned,
-                                content,
-                            }
-                        }
-                        HtmlBlock => {
-                            // build content from any Html blocks collected in frame.blocks
-                            // and any inline Html/Text collected in frame.inlines.
-                            let mut content = Region::new();
-                            for b in frame.blocks.into_iter() {
-                                match b {
-                                    Block::HtmlBlock(rgn) => {
-                                        for l in rgn.apply().split('\n') {
-                                            content.push_back_line(Line::from_str(l));
-                                        }
-                                    }
-                                    Block::Paragraph(inls) => {
-                                        for inl in inls {
-                                            if let Inline::Text(r) = inl {
-                                                content.push_back_line(Line::from_str(&r.apply()));
-                                            }
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            for inl in frame.inlines.into_iter() {
-                                match inl {
-                                    Inline::Text(r) => {
-                                        content.push_back_line(Line::from_str(&r.apply()));
-                                    }
-                                    Inline::Html(r) => {
-                                        for l in r.apply().split('\n') {
-                                            content.push_back_line(Line::from_str(l));
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            Block::HtmlBlock(content)
-                        }
-                        List(start) => {
-                            let mut items: Vec<Vec<Block>> = Vec::new();
-                            for b in frame.blocks.into_iter() {
-                                match b {
-                                    Block::Item(children) => items.push(children),
-                                    other => items.push(vec![other]),
-                                }
-                            }
-                            Block::List { start, items }
-                        }
-                        Item => Block::Item(frame.blocks),
-                        FootnoteDefinition(label) => {
-                            Block::FootnoteDefinition(label.to_string(), frame.blocks)
-                        }
-                        Table(aligns) => {
-                            let mut rows: Vec<Vec<Vec<Inline>>> = Vec::new();
-                            for b in frame.blocks.into_iter() {
-                                match b {
-                                    Block::TableRow(cells) => rows.push(cells),
-                                    Block::Paragraph(inls) => rows.push(vec![inls]),
-                                    other => match other {
-                                        Block::Item(children) => {
-                                            let mut inls_acc: Vec<Inline> = Vec::new();
-                                            for ch in children {
-                                                if let Block::Paragraph(mut p_inls) = ch {
-                                                    inls_acc.append(&mut p_inls);
-                                                }
-                                            }
-                                            rows.push(vec![inls_acc]);
-                                        }
-                                        _ => {}
-                                    },
-                                }
-                            }
-                            Block::Table(aligns, rows)
-                        }
-                        TableHead | TableRow => {
-                            let mut row_cells: Vec<Vec<Inline>> = Vec::new();
-                            for b in frame.blocks.into_iter() {
-                                match b {
-                                    Block::Paragraph(inls) => row_cells.push(inls),
-                                    _ => {}
-                                }
-                            }
-                            Block::TableRow(row_cells)
-                        }
-                        TableCell => Block::Paragraph(frame.inlines),
-                        Emphasis => {
-                            maybe_inline = Some(Inline::Emphasis(frame.inlines));
-                            Block::Paragraph(Vec::new())
-                        }
-                        Strong => {
-                            maybe_inline = Some(Inline::Strong(frame.inlines));
-                            Block::Paragraph(Vec::new())
-                        }
-                        Strikethrough => {
-                            maybe_inline = Some(Inline::Strikethrough(frame.inlines));
-                            Block::Paragraph(Vec::new())
-                        }
-                        Subscript => {
-                            maybe_inline = Some(Inline::Subscript(frame.inlines));
-                            Block::Paragraph(Vec::new())
-                        }
-                        Superscript => {
-                            maybe_inline = Some(Inline::Superscript(frame.inlines));
-                            Block::Paragraph(Vec::new())
-                        }
-                        Tag::Link {
-                            link_type,
-                            dest_url,
-                            title,
-                            id,
-                        } => {
-                            maybe_inline = Some(Inline::Link {
-                                link_type,
-                                dest: dest_url.to_string(),
-                                title: title.to_string(),
-                                id: id.to_string(),
-                                children: frame.inlines,
-                            });
-                            Block::Paragraph(Vec::new())
-                        }
-                        Tag::Image {
-                            link_type,
-                            dest_url,
-                            title,
-                            id,
-                        } => {
-                            maybe_inline = Some(Inline::Image {
-                                link_type,
-                                dest: dest_url.to_string(),
-                                title: title.to_string(),
-                                id: id.to_string(),
-                                children: frame.inlines,
-                            });
-                            Block::Paragraph(Vec::new())
-                        }
-                        Tag::MetadataBlock(_kind) => Block::Paragraph(frame.inlines),
-                        _ => Block::Paragraph(frame.inlines),
-                    };
-
-                    if let Some(parent) = stack.last_mut() {
-                        if let Some(inl) = maybe_inline.take() {
-                            if parent.collect_inlines {
-                                parent.inlines.push(inl);
-                            } else {
-                                if let Some(last) = parent.blocks.last_mut() {
-                                    match last {
-                                        Block::Paragraph(inls) => inls.push(inl),
-                                        _ => parent.blocks.push(Block::Paragraph(vec![inl])),
-                                    }
-                                } else {
-                                    parent.blocks.push(Block::Paragraph(vec![inl]));
-                                }
+            Event::End(tagend) => {
+                match stack.iter().rposition(|f| f.tag.to_end() == *tagend) {
+                    Some(pos) => {
+                        // frames left open inside the one this ends are
+                        // closed with it
+                        for depth in (pos..stack.len()).rev() {
+                            let frame = stack.pop().expect("stack is deeper than pos");
+                            if depth != pos {
+                                diags.push(ParseDiagnostic::MismatchedEnd {
+                                    index: i,
+                                    expected: frame.tag.to_end(),
+                                    found: *tagend,
+                                });
                             }
-                        } else if parent.collect_inlines {
-                            match node {
-                                Block::Paragraph(inls) => parent.inlines.extend(inls),
-                                other => parent.blocks.push(other),
-                            }
-                        } else {
-                            parent.blocks.push(node);
-                        }
-                    } else {
-                        if let Some(inl) = maybe_inline.take() {
-                            out.push(Block::Paragraph(vec![inl]));
-                        } else {
-                            out.push(node);
+                            close_frame(frame, &mut stack, &mut out, &mut diags, i);
                         }
                     }
+                    // a stray End closes nothing and is ignored
+                    None => diags.push(ParseDiagnostic::UnmatchedEnd {
+                        index: i,
+                        end: *tagend,
+                    }),
                 }
                 i += 1;
             }
             Event::Text(t) => {
-                let r = region_from_cow(t);
-                if let Some(top) = stack.last_mut() {
-                    if top.collect_inlines {
-                        top.inlines.push(Inline::Text(r));
-                    } else {
-                        top.blocks.push(Block::Paragraph(vec![Inline::Text(r)]));
-                    }
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::Text(r)]));
-                }
+                let inl = Inline::Text(region_from_cow(t));
+                add_inline(&mut stack, &mut out, inl, options);
                 i += 1;
             }
             Event::Code(t) => {
-                let r = region_from_cow(t);
-                if let Some(top) = stack.last_mut() {
-                    if top.collect_inlines {
-                        top.inlines.push(Inline::Code(r));
-                    } else {
-                        top.blocks.push(Block::Paragraph(vec![Inline::Code(r)]));
-                    }
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::Code(r)]));
-                }
+                let inl = Inline::Code(region_from_cow(t));
+                add_inline(&mut stack, &mut out, inl, options);
                 i += 1;
             }
             Event::InlineHtml(t) => {
-                let r = region_from_cow(t);
-                if let Some(top) = stack.last_mut() {
-                    top.inlines.push(Inline::InlineHtml(r));
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::InlineHtml(r)]));
-                }
+                let inl = match comment_body(t) {
+                    Some(body) => Inline::Comment(region_from_cow(body)),
+                    None => Inline::InlineHtml(region_from_cow(t)),
+                };
+                add_inline(&mut stack, &mut out, inl, options);
                 i += 1;
             }
             Event::Html(t) => {
@@ -349,27 +562,11 @@ pub fn parse_events_to_blocks_with_hook<'a>(
                 i += 1;
             }
             Event::SoftBreak => {
-                if let Some(top) = stack.last_mut() {
-                    if top.collect_inlines {
-                        top.inlines.push(Inline::SoftBreak);
-                    } else {
-                        top.blocks.push(Block::Paragraph(vec![Inline::SoftBreak]));
-                    }
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::SoftBreak]));
-                }
+                add_inline(&mut stack, &mut out, Inline::SoftBreak, options);
                 i += 1;
             }
             Event::HardBreak => {
-                if let Some(top) = stack.last_mut() {
-                    if top.collect_inlines {
-                        top.inlines.push(Inline::HardBreak);
-                    } else {
-                        top.blocks.push(Block::Paragraph(vec![Inline::HardBreak]));
-                    }
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::HardBreak]));
-                }
+                add_inline(&mut stack, &mut out, Inline::HardBreak, options);
                 i += 1;
             }
             Event::Rule => {
@@ -381,52 +578,283 @@ pub fn parse_events_to_blocks_with_hook<'a>(
                 i += 1;
             }
             Event::TaskListMarker(b) => {
-                if let Some(top) = stack.last_mut() {
-                    top.inlines.push(Inline::Text(Region::from_str(if *b {
-                        "[x]"
-                    } else {
-                        "[ ]"
-                    })));
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::Text(Region::from_str(
-                        if *b { "[x]" } else { "[ ]" },
-                    ))]));
-                }
+                // kept as the text it is written as, space included
+                let marker = Inline::Text(Region::from_str(if *b { "[x] " } else { "[ ] " }));
+                add_inline(&mut stack, &mut out, marker, options);
                 i += 1;
             }
             Event::FootnoteReference(t) => {
-                let s = t.to_string();
-                if let Some(top) = stack.last_mut() {
-                    top.inlines.push(Inline::FootnoteReference(s));
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::FootnoteReference(s)]));
-                }
+                let inl = Inline::FootnoteReference(t.to_string());
+                add_inline(&mut stack, &mut out, inl, options);
                 i += 1;
             }
             Event::InlineMath(t) => {
-                let r = region_from_cow(t);
-                if let Some(top) = stack.last_mut() {
-                    top.inlines.push(Inline::InlineMath(r));
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::InlineMath(r)]));
-                }
+                let inl = Inline::InlineMath(region_from_cow(t));
+                add_inline(&mut stack, &mut out, inl, options);
                 i += 1;
             }
             Event::DisplayMath(t) => {
-                let r = region_from_cow(t);
-                if let Some(top) = stack.last_mut() {
-                    top.inlines.push(Inline::DisplayMath(r));
-                } else {
-                    out.push(Block::Paragraph(vec![Inline::DisplayMath(r)]));
-                }
+                let inl = Inline::DisplayMath(region_from_cow(t));
+                add_inline(&mut stack, &mut out, inl, options);
                 i += 1;
             }
         }
     }
 
+    // close whatever is still open so its content isn't lost
+    while let Some(frame) = stack.pop() {
+        diags.push(ParseDiagnostic::UnclosedTag {
+            index: frame.start,
+            tag: tag_to_static(&frame.tag),
+        });
+        close_frame(frame, &mut stack, &mut out, &mut diags, events.len());
+    }
+    (out, diags)
+}
+
+/// Whether a frame for `tag` collects inlines (as opposed to blocks).
+fn collects_inlines(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Paragraph
+            | Tag::Heading { .. }
+            | Tag::Emphasis
+            | Tag::Strong
+            | Tag::Strikethrough
+            | Tag::Subscript
+            | Tag::Superscript
+            | Tag::Link { .. }
+            | Tag::Image { .. }
+            | Tag::TableCell
+            | Tag::MetadataBlock(_)
+    )
+}
+
+/// Tags the parser has a node for; anything else falls back to a paragraph.
+fn is_supported_tag(tag: &Tag) -> bool {
+    !matches!(
+        tag,
+        Tag::DefinitionList | Tag::DefinitionListTitle | Tag::DefinitionListDefinition
+    )
+}
+
+/// Report an event the parser can only place by guessing, or not at all,
+/// given the innermost open frame (its tag and whether it collects inlines).
+fn check_placement(
+    ev: &Event,
+    index: usize,
+    top: Option<(&Tag, bool)>,
+    diags: &mut Vec<ParseDiagnostic>,
+) {
+    let diag = match (ev, top) {
+        // wrapped in a paragraph; expected directly in tight list items and
+        // as the content of code and HTML blocks
+        (Event::Text(_) | Event::Code(_) | Event::SoftBreak | Event::HardBreak, None) => {
+            ParseDiagnostic::WrappedInline { index }
+        }
+        (
+            Event::Text(_) | Event::Code(_) | Event::SoftBreak | Event::HardBreak,
+            Some((tag, false)),
+        ) if !matches!(tag, Tag::Item | Tag::CodeBlock(_) | Tag::HtmlBlock) => {
+            ParseDiagnostic::WrappedInline { index }
+        }
+        // pushed onto the frame's inlines, which only some frames use
+        (
+            Event::InlineHtml(_)
+            | Event::TaskListMarker(_)
+            | Event::FootnoteReference(_)
+            | Event::InlineMath(_)
+            | Event::DisplayMath(_),
+            None,
+        ) => ParseDiagnostic::WrappedInline { index },
+        (
+            Event::InlineHtml(_)
+            | Event::TaskListMarker(_)
+            | Event::FootnoteReference(_)
+            | Event::InlineMath(_)
+            | Event::DisplayMath(_),
+            Some((tag, false)),
+        ) if is_supported_tag(tag) && !matches!(tag, Tag::HtmlBlock) => {
+            ParseDiagnostic::DroppedEvent { index }
+        }
+        (Event::Rule, Some((_, true))) => ParseDiagnostic::DroppedEvent { index },
+        _ => return,
+    };
+    diags.push(diag);
+}
+
+/// If `s` is exactly one HTML comment (`<!-- ... -->`, ignoring surrounding
+/// whitespace) return the text between the delimiters.
+pub(crate) fn comment_body(s: &str) -> Option<&str> {
+    let body = s.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    if body.contains("-->") {
+        return None;
+    }
+    Some(body)
+}
+
+/// The comment body of an HTML block frame whose only content is a single
+/// HTML comment. The block's `Html` events each carry their own line ending,
+/// so concatenating them reproduces the source text.
+fn html_frame_comment(blocks: &[Block]) -> Option<String> {
+    let mut combined = String::new();
+    for b in blocks {
+        match b {
+            Block::HtmlBlock(r) => r.apply_into(&mut combined),
+            _ => return None,
+        }
+    }
+    comment_body(&combined).map(|b| b.to_string())
+}
+
+/// A parsed block together with the byte range of the source it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpannedBlock {
+    pub block: Block,
+    pub span: Range<usize>,
+    /// Spans of the block's children, for container blocks: the blocks of
+    /// a blockquote or footnote definition, the items of a list (as
+    /// `Block::Item`), and the blocks of a list item. Inline content that
+    /// sits directly in a container (tight list items) forms one paragraph
+    /// child.
+    pub children: Vec<SpannedBlock>,
+}
+
+/// The span of the block at `path`, as `Cursor::path` returns it, or of
+/// the innermost block on the way that has no spanned children. The
+/// children of a spanned list are its items, so list paths descend the same
+/// way as those of other containers.
+pub(crate) fn span_at(spanned: &[SpannedBlock], path: &[usize]) -> Option<Range<usize>> {
+    let (first, rest) = path.split_first()?;
+    let block = spanned.get(*first)?;
+    if rest.is_empty() {
+        return Some(block.span.clone());
+    }
+    span_at(&block.children, rest).or_else(|| Some(block.span.clone()))
+}
+
+/// Parse the `(Event, Range)` pairs of `Parser::into_offset_iter` into
+/// blocks, recording the source span of every block.
+pub fn parse_offset_events_to_blocks<'a>(
+    events: &[(Event<'a>, Range<usize>)],
+) -> Vec<SpannedBlock> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        if is_inline_event(&events[i].0) {
+            // a run of inline content outside a paragraph
+            let start = i;
+            while i < events.len() && is_inline_event(&events[i].0) {
+                i += element_len(events, i);
+            }
+            let run = &events[start..i];
+            let mut inlines = Vec::new();
+            for b in parse_events_to_blocks(&strip_offsets(run)) {
+                if let Block::Paragraph(inls) = b {
+                    inlines.extend(inls);
+                }
+            }
+            out.push(SpannedBlock {
+                block: Block::Paragraph(inlines),
+                span: run[0].1.start..run[run.len() - 1].1.end,
+                children: Vec::new(),
+            });
+            continue;
+        }
+        let len = element_len(events, i);
+        let element = &events[i..i + len];
+        let children = match &element[0].0 {
+            Event::Start(tag) if is_container_tag(tag) && len >= 2 => {
+                parse_offset_events_to_blocks(&element[1..len - 1])
+            }
+            _ => Vec::new(),
+        };
+        for block in parse_events_to_blocks(&strip_offsets(element)) {
+            out.push(SpannedBlock {
+                block,
+                span: element[0].1.clone(),
+                children: children.clone(),
+            });
+        }
+        i += len;
+    }
     out
 }
 
+fn strip_offsets<'a>(events: &[(Event<'a>, Range<usize>)]) -> Vec<Event<'a>> {
+    events.iter().map(|(e, _)| e.clone()).collect()
+}
+
+/// Number of events making up the element starting at `i`: a `Start` up to
+/// its matching `End`, or a single event.
+fn element_len<T>(events: &[(Event, T)], i: usize) -> usize {
+    if !matches!(events[i].0, Event::Start(_)) {
+        return 1;
+    }
+    let mut depth = 0usize;
+    for (n, (ev, _)) in events[i..].iter().enumerate() {
+        match ev {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    return n + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    events.len() - i
+}
+
+fn is_inline_event(ev: &Event) -> bool {
+    match ev {
+        Event::Start(tag) => matches!(
+            tag,
+            Tag::Emphasis
+                | Tag::Strong
+                | Tag::Strikethrough
+                | Tag::Subscript
+                | Tag::Superscript
+                | Tag::Link { .. }
+                | Tag::Image { .. }
+        ),
+        Event::End(_) | Event::Rule | Event::Html(_) => false,
+        _ => true,
+    }
+}
+
+fn is_container_tag(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::BlockQuote(_)
+            | Tag::List(_)
+            | Tag::Item
+            | Tag::FootnoteDefinition(_)
+            | Tag::DefinitionList
+            | Tag::DefinitionListDefinition
+    )
+}
+
+/// Recover a document's link reference definitions, which the event stream
+/// does not carry, as `Block::LinkDefinition`s in source order. Pass the
+/// map from `Parser::reference_definitions()`.
+pub fn link_definition_blocks(defs: &RefDefs) -> Vec<Block> {
+    let mut found: Vec<(usize, Block)> = defs
+        .iter()
+        .map(|(id, def)| {
+            let block = Block::LinkDefinition {
+                id: id.to_string(),
+                dest: def.dest.to_string(),
+                title: def.title.as_deref().unwrap_or_default().to_string(),
+            };
+            (def.span.start, block)
+        })
+        .collect();
+    found.sort_by_key(|(start, _)| *start);
+    found.into_iter().map(|(_, b)| b).collect()
+}
+
 // Backwards compatible wrapper without hook
 pub fn parse_events_to_blocks<'a>(events: &[Event<'a>]) -> Vec<Block> {
     parse_events_to_blocks_with_hook(events, None)
@@ -439,10 +867,10 @@ pub fn parse_events_to_blocks_with_parsers<'a>(
     parsers: &[&dyn crate::ast::custom::BlockParser],
 ) -> Vec<Block> {
     let mut hook =
-        |evs: &[Event], i: usize, ctx: &crate::ast::ParseContext| -> Option<(usize, Block)> {
+        |evs: &[Event], i: usize, ctx: &crate::ast::ParseContext| -> Option<(usize, HookResult)> {
             for p in parsers.iter() {
                 if let Some((consumed, blk)) = p.try_parse(evs, i, ctx) {
-                    return Some((consumed, blk));
+                    return Some((consumed, HookResult::Block(blk)));
                 }
             }
             None
@@ -0,0 +1,178 @@
+//! Ordered collection of custom block and inline parsers.
+//!
+//! `parse_events_to_blocks_with_parsers` tries a slice of block parsers in
+//! order. A `ParserRegistry` is the reusable form of that: parsers are
+//! registered once with `ParserOptions` (priority, the containers they may
+//! run in, whether they shadow lower-priority parsers) and the registry can
+//! then parse any number of event streams.
+//!
+//! ```ignore
+//! let mut registry = ParserRegistry::new();
+//! registry
+//!     .register_block(Admonitions, ParserOptions::new().priority(10))
+//!     .register_inline(
+//!         Kbd,
+//!         ParserOptions::new().within(|t| matches!(t, Tag::TableCell)),
+//!     );
+//! let blocks = registry.parse(&events);
+//! ```
+
+use crate::ast::ParseContext;
+use crate::ast::block::Block;
+use crate::ast::custom::{BlockParser, InlineParser};
+use crate::ast::parse::{HookResult, parse_events_to_blocks_with_hook};
+use pulldown_cmark::{Event, Tag};
+use std::sync::Arc;
+
+/// How a parser takes part in a `ParserRegistry`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParserOptions {
+    priority: i32,
+    within: Option<fn(&Tag) -> bool>,
+    exclusive: bool,
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parsers with a higher priority are tried first. Parsers with the same
+    /// priority are tried in registration order. Defaults to 0.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Only run the parser when one of the enclosing containers matches
+    /// `filter`, e.g. `|t| matches!(t, Tag::BlockQuote(_))`.
+    pub fn within(mut self, filter: fn(&Tag) -> bool) -> Self {
+        self.within = Some(filter);
+        self
+    }
+
+    /// Stop at this parser: wherever it is allowed to run, parsers with a
+    /// lower priority are not tried, even when this one declines.
+    pub fn exclusive(mut self) -> Self {
+        self.exclusive = true;
+        self
+    }
+}
+
+#[derive(Clone)]
+enum AnyParser {
+    Block(Arc<dyn BlockParser>),
+    Inline(Arc<dyn InlineParser>),
+}
+
+#[derive(Clone)]
+struct Entry {
+    parser: AnyParser,
+    options: ParserOptions,
+}
+
+impl Entry {
+    fn applies(&self, ctx: &ParseContext) -> bool {
+        // block parsers run between blocks, inline parsers inside inline
+        // content, which tight list items hold directly
+        let level = match self.parser {
+            AnyParser::Block(_) => !ctx.parent_collects_inlines,
+            AnyParser::Inline(_) => {
+                ctx.parent_collects_inlines || matches!(ctx.parent_tag, Some(Tag::Item))
+            }
+        };
+        level
+            && self
+                .options
+                .within
+                .is_none_or(|f| ctx.ancestors.iter().any(f))
+    }
+}
+
+/// Block and inline parsers ordered by priority. Cloning is cheap: the
+/// parsers themselves are shared.
+#[derive(Clone, Default)]
+pub struct ParserRegistry {
+    // sorted by descending priority, registration order within a priority
+    entries: Vec<Entry>,
+}
+
+impl ParserRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a parser for custom blocks. It is only tried between
+    /// blocks, never inside inline content.
+    pub fn register_block(
+        &mut self,
+        parser: impl BlockParser + 'static,
+        options: ParserOptions,
+    ) -> &mut Self {
+        self.insert(AnyParser::Block(Arc::new(parser)), options)
+    }
+
+    /// Register a parser for custom inlines. It is only tried inside inline
+    /// content (paragraphs, headings, table cells, tight list items, ...).
+    pub fn register_inline(
+        &mut self,
+        parser: impl InlineParser + 'static,
+        options: ParserOptions,
+    ) -> &mut Self {
+        self.insert(AnyParser::Inline(Arc::new(parser)), options)
+    }
+
+    fn insert(&mut self, parser: AnyParser, options: ParserOptions) -> &mut Self {
+        let at = self
+            .entries
+            .partition_point(|e| e.options.priority >= options.priority);
+        self.entries.insert(at, Entry { parser, options });
+        self
+    }
+
+    /// Number of registered parsers.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Try the registered parsers at `events[0]`, in priority order. This is
+    /// the hook `parse` installs; it can also be called from a custom hook.
+    pub fn try_parse(
+        &self,
+        events: &[Event],
+        idx: usize,
+        ctx: &ParseContext,
+    ) -> Option<(usize, HookResult)> {
+        for entry in self.entries.iter().filter(|e| e.applies(ctx)) {
+            let found = match &entry.parser {
+                AnyParser::Block(p) => p
+                    .try_parse(events, idx, ctx)
+                    .map(|(n, b)| (n, HookResult::Block(b))),
+                AnyParser::Inline(p) => {
+                    p.try_parse_inlines(events, idx, ctx)
+                        .map(|(n, mut inls)| match inls.len() {
+                            1 => (n, HookResult::Inline(inls.remove(0))),
+                            _ => (n, HookResult::Inlines(inls)),
+                        })
+                }
+            };
+            if found.is_some() || entry.options.exclusive {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// Parse `events` into blocks with the registered parsers.
+    pub fn parse(&self, events: &[Event]) -> Vec<Block> {
+        if self.entries.is_empty() {
+            return parse_events_to_blocks_with_hook(events, None);
+        }
+        let mut hook = |evs: &[Event], i: usize, ctx: &ParseContext| self.try_parse(evs, i, ctx);
+        parse_events_to_blocks_with_hook(events, Some(&mut hook))
+    }
+}
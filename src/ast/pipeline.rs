@@ -0,0 +1,79 @@
+//! Ordered transforms applied to blocks between parsing and writing.
+//!
+//! A `Pipeline` holds a list of `Transform`s (any `Fn(&mut Vec<Block>)`
+//! qualifies) and runs them in registration order, so independent passes
+//! such as link rewriting, footnote renumbering or expanding custom nodes
+//! can be written separately and composed:
+//!
+//! ```ignore
+//! let mut pipeline = Pipeline::new();
+//! pipeline.add(rewrite_links).add(expand_admonitions);
+//! let md = pipeline.markdown(&events);
+//! ```
+
+use crate::ast::block::Block;
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::writer::blocks_to_markdown;
+use pulldown_cmark::Event;
+use std::sync::Arc;
+
+/// A pass over a parsed block list.
+pub trait Transform: Send + Sync {
+    fn apply(&self, blocks: &mut Vec<Block>);
+}
+
+impl<F> Transform for F
+where
+    F: Fn(&mut Vec<Block>) + Send + Sync,
+{
+    fn apply(&self, blocks: &mut Vec<Block>) {
+        self(blocks)
+    }
+}
+
+/// Transforms run in the order they were added. Cloning is cheap: the
+/// transforms themselves are shared.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    transforms: Vec<Arc<dyn Transform>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a transform to the end of the pipeline.
+    pub fn add(&mut self, transform: impl Transform + 'static) -> &mut Self {
+        self.transforms.push(Arc::new(transform));
+        self
+    }
+
+    /// Number of registered transforms.
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Apply every transform to `blocks`, in order.
+    pub fn run(&self, blocks: &mut Vec<Block>) {
+        for t in &self.transforms {
+            t.apply(blocks);
+        }
+    }
+
+    /// Parse `events` and run the pipeline over the result.
+    pub fn parse(&self, events: &[Event]) -> Vec<Block> {
+        let mut blocks = parse_events_to_blocks(events);
+        self.run(&mut blocks);
+        blocks
+    }
+
+    /// Parse `events`, run the pipeline and write the result as markdown.
+    pub fn markdown(&self, events: &[Event]) -> String {
+        blocks_to_markdown(&self.parse(events))
+    }
+}
@@ -0,0 +1,175 @@
+//! Runtime registry for serializing custom nodes with their payload.
+//!
+//! Without a registry a `Block::Custom` is serialized as the events and
+//! markdown it produces, and comes back as an `OpaqueBlock` that replays
+//! them. Registering a node type under a name lets it be serialized with
+//! its own data (`"type"` and `"payload"` fields) and deserialized into the
+//! original type:
+//!
+//! ```ignore
+//! let mut registry = NodeRegistry::new();
+//! registry.register_block::<Admonition>("admonition");
+//! let json = registry.with(|| serde_json::to_string(&blocks))?;
+//! let back: Vec<Block> = registry.with(|| serde_json::from_str(&json))?;
+//! ```
+//!
+//! The registry is only consulted inside `NodeRegistry::with`, on the
+//! current thread. Payloads of unregistered types are skipped in favour of
+//! the opaque form, so documents stay readable without the registry.
+
+use crate::ast::custom::{BlockNode, InlineNode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(crate) type SerializeFn<N> = fn(&N) -> &dyn erased_serde::Serialize;
+pub(crate) type DeserializeFn<N> =
+    fn(&mut dyn erased_serde::Deserializer<'_>) -> Result<Arc<N>, erased_serde::Error>;
+
+struct Entries<N: ?Sized> {
+    by_type: HashMap<TypeId, (&'static str, SerializeFn<N>)>,
+    by_name: HashMap<&'static str, DeserializeFn<N>>,
+}
+
+impl<N: ?Sized> Default for Entries<N> {
+    fn default() -> Self {
+        Entries {
+            by_type: HashMap::new(),
+            by_name: HashMap::new(),
+        }
+    }
+}
+
+impl<N: ?Sized> Clone for Entries<N> {
+    fn clone(&self) -> Self {
+        Entries {
+            by_type: self.by_type.clone(),
+            by_name: self.by_name.clone(),
+        }
+    }
+}
+
+/// Maps custom node types to the names they are serialized under.
+#[derive(Clone, Default)]
+pub struct NodeRegistry {
+    blocks: Entries<dyn BlockNode>,
+    inlines: Entries<dyn InlineNode>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a custom block type under `name`. Registering another type
+    /// under the same name replaces the earlier one for deserialization.
+    pub fn register_block<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: BlockNode + Serialize + DeserializeOwned,
+    {
+        fn ser<T: BlockNode + Serialize>(n: &dyn BlockNode) -> &dyn erased_serde::Serialize {
+            let any: &dyn Any = n;
+            any.downcast_ref::<T>()
+                .expect("registered under this type id")
+        }
+        fn de<T: BlockNode + DeserializeOwned>(
+            d: &mut dyn erased_serde::Deserializer<'_>,
+        ) -> Result<Arc<dyn BlockNode>, erased_serde::Error> {
+            Ok(Arc::new(erased_serde::deserialize::<T>(d)?))
+        }
+        self.blocks
+            .by_type
+            .insert(TypeId::of::<T>(), (name, ser::<T>));
+        self.blocks.by_name.insert(name, de::<T>);
+        self
+    }
+
+    /// Register a custom inline type under `name`.
+    pub fn register_inline<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: InlineNode + Serialize + DeserializeOwned,
+    {
+        fn ser<T: InlineNode + Serialize>(n: &dyn InlineNode) -> &dyn erased_serde::Serialize {
+            let any: &dyn Any = n;
+            any.downcast_ref::<T>()
+                .expect("registered under this type id")
+        }
+        fn de<T: InlineNode + DeserializeOwned>(
+            d: &mut dyn erased_serde::Deserializer<'_>,
+        ) -> Result<Arc<dyn InlineNode>, erased_serde::Error> {
+            Ok(Arc::new(erased_serde::deserialize::<T>(d)?))
+        }
+        self.inlines
+            .by_type
+            .insert(TypeId::of::<T>(), (name, ser::<T>));
+        self.inlines.by_name.insert(name, de::<T>);
+        self
+    }
+
+    /// Run `f` with this registry active for (de)serialization on the
+    /// current thread. Calls can be nested; the innermost registry wins.
+    pub fn with<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<NodeRegistry>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                ACTIVE.with(|a| *a.borrow_mut() = self.0.take());
+            }
+        }
+        let prev = ACTIVE.with(|a| a.borrow_mut().replace(self.clone()));
+        let _restore = Restore(prev);
+        f()
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<NodeRegistry>> = const { RefCell::new(None) };
+}
+
+/// The registry active on the current thread, to carry it over to others.
+#[cfg(feature = "rayon")]
+pub(crate) fn active() -> Option<NodeRegistry> {
+    ACTIVE.with(|a| a.borrow().clone())
+}
+
+pub(crate) fn block_serializer(
+    node: &dyn BlockNode,
+) -> Option<(&'static str, SerializeFn<dyn BlockNode>)> {
+    let any: &dyn Any = node;
+    let id = any.type_id();
+    ACTIVE.with(|a| {
+        a.borrow()
+            .as_ref()
+            .and_then(|r| r.blocks.by_type.get(&id).copied())
+    })
+}
+
+pub(crate) fn block_deserializer(name: &str) -> Option<DeserializeFn<dyn BlockNode>> {
+    ACTIVE.with(|a| {
+        a.borrow()
+            .as_ref()
+            .and_then(|r| r.blocks.by_name.get(name).copied())
+    })
+}
+
+pub(crate) fn inline_serializer(
+    node: &dyn InlineNode,
+) -> Option<(&'static str, SerializeFn<dyn InlineNode>)> {
+    let any: &dyn Any = node;
+    let id = any.type_id();
+    ACTIVE.with(|a| {
+        a.borrow()
+            .as_ref()
+            .and_then(|r| r.inlines.by_type.get(&id).copied())
+    })
+}
+
+pub(crate) fn inline_deserializer(name: &str) -> Option<DeserializeFn<dyn InlineNode>> {
+    ACTIVE.with(|a| {
+        a.borrow()
+            .as_ref()
+            .and_then(|r| r.inlines.by_name.get(name).copied())
+    })
+}
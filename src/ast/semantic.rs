@@ -0,0 +1,83 @@
+//! Comparing documents by what they say rather than how they are stored.
+//!
+//! Two block trees can render the same document and still differ: text
+//! split into several `Text` inlines, a soft break where the other has a
+//! space, a tight list item against a loose one. `semantic_eq` compares
+//! the event streams of both documents with those differences smoothed
+//! out, the way the round-trip tests compare a document with its
+//! re-parsed output.
+
+use crate::ast::Block;
+use crate::ast::block::block_to_events;
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+/// Which differences `semantic_eq_with` ignores. All are ignored by
+/// default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SemanticOptions {
+    /// Treat a soft break as a space, so that text wrapped differently
+    /// compares equal.
+    pub soft_breaks_as_spaces: bool,
+    /// Ignore paragraph boundaries inside list items, so that a tight
+    /// list equals the same list written loose.
+    pub ignore_tightness: bool,
+}
+
+impl Default for SemanticOptions {
+    fn default() -> Self {
+        SemanticOptions {
+            soft_breaks_as_spaces: true,
+            ignore_tightness: true,
+        }
+    }
+}
+
+/// The events of `blocks` with text runs merged and the differences
+/// `options` ignores taken out.
+fn canonical_events(blocks: &[Block], options: &SemanticOptions) -> Vec<Event<'static>> {
+    let mut out = Vec::new();
+    let mut text: Option<String> = None;
+    let mut items = 0usize;
+    let flush = |out: &mut Vec<Event<'static>>, text: &mut Option<String>| {
+        if let Some(t) = text.take() {
+            out.push(Event::Text(CowStr::from(t)));
+        }
+    };
+    for ev in blocks.iter().flat_map(block_to_events) {
+        match ev {
+            Event::Text(t) => text.get_or_insert_with(String::new).push_str(&t),
+            Event::SoftBreak if options.soft_breaks_as_spaces => {
+                text.get_or_insert_with(String::new).push(' ')
+            }
+            Event::Start(Tag::Paragraph) | Event::End(TagEnd::Paragraph)
+                if options.ignore_tightness && items > 0 =>
+            {
+                // a boundary all the same: text does not run across it
+                flush(&mut out, &mut text);
+            }
+            ev => {
+                flush(&mut out, &mut text);
+                match ev {
+                    Event::Start(Tag::Item) => items += 1,
+                    Event::End(TagEnd::Item) => items -= 1,
+                    _ => {}
+                }
+                out.push(ev);
+            }
+        }
+    }
+    flush(&mut out, &mut text);
+    out
+}
+
+/// Whether `a` and `b` are the same document, ignoring how text is split
+/// into inlines, where soft breaks fall, and list tightness.
+pub fn semantic_eq(a: &[Block], b: &[Block]) -> bool {
+    semantic_eq_with(a, b, &SemanticOptions::default())
+}
+
+/// Like `semantic_eq`, ignoring only the differences `options` selects.
+/// Text split differently always compares equal.
+pub fn semantic_eq_with(a: &[Block], b: &[Block], options: &SemanticOptions) -> bool {
+    canonical_events(a, options) == canonical_events(b, options)
+}
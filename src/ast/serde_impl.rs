@@ -0,0 +1,193 @@
+//! Serde helpers for AST fields whose types can't derive an owned
+//! representation directly.
+//!
+//! `CodeBlockKind<'static>` borrows from the deserializer in pulldown-cmark's
+//! own impl, and custom nodes are trait objects. Both are mapped onto owned
+//! stand-ins here.
+
+use crate::ast::custom::{BlockNode, InlineNode, OpaqueBlock, OpaqueInline};
+use crate::ast::event::MdEvent;
+use crate::ast::registry::{self, DeserializeFn};
+use crate::text::{Line, Region};
+use pulldown_cmark::Event;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+/// Code block kinds serialize like `MdTag::CodeBlock`: the fence info string
+/// for fenced blocks and `null` for indented ones.
+pub(crate) mod code_block_kind {
+    use super::*;
+    use pulldown_cmark::{CodeBlockKind, CowStr};
+
+    pub(crate) fn serialize<S: Serializer>(
+        kind: &CodeBlockKind<'static>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let info = match kind {
+            CodeBlockKind::Fenced(info) => Some(info.as_ref()),
+            CodeBlockKind::Indented => None,
+        };
+        info.serialize(s)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<CodeBlockKind<'static>, D::Error> {
+        Ok(match Option::<String>::deserialize(d)? {
+            Some(info) => CodeBlockKind::Fenced(CowStr::from(info)),
+            None => CodeBlockKind::Indented,
+        })
+    }
+}
+
+/// Serialized form of a custom node: the events it produces and its
+/// markdown rendering, preceded by the registered type name and payload
+/// when its type is known to the active `NodeRegistry`.
+const CUSTOM_FIELDS: &[&str] = &["type", "payload", "events", "markdown"];
+
+fn serialize_custom<S: Serializer>(
+    entry: Option<(&'static str, &dyn erased_serde::Serialize)>,
+    events: Vec<Event<'static>>,
+    markdown: String,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    let mut st = s.serialize_struct("Custom", if entry.is_some() { 4 } else { 2 })?;
+    match entry {
+        Some((name, payload)) => {
+            st.serialize_field("type", name)?;
+            st.serialize_field("payload", payload)?;
+        }
+        None => {
+            st.skip_field("type")?;
+            st.skip_field("payload")?;
+        }
+    }
+    st.serialize_field("events", &to_md_events(events))?;
+    st.serialize_field("markdown", &markdown)?;
+    st.end()
+}
+
+fn to_md_events(events: Vec<Event<'static>>) -> Vec<MdEvent> {
+    events.iter().map(MdEvent::from).collect()
+}
+
+fn from_md_events(events: &[MdEvent]) -> Vec<Event<'static>> {
+    events.iter().map(MdEvent::to_pulldown).collect()
+}
+
+/// Reads a custom node back: the registered type when the payload can be
+/// decoded, otherwise an opaque node built from the events and markdown.
+struct CustomVisitor<N: ?Sized> {
+    lookup: fn(&str) -> Option<DeserializeFn<N>>,
+    opaque: fn(Vec<Event<'static>>, String) -> Arc<N>,
+}
+
+struct PayloadSeed<N: ?Sized>(DeserializeFn<N>);
+
+impl<'de, N: ?Sized> DeserializeSeed<'de> for PayloadSeed<N> {
+    type Value = Arc<N>;
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<Arc<N>, D::Error> {
+        let mut erased = <dyn erased_serde::Deserializer>::erase(d);
+        (self.0)(&mut erased).map_err(de::Error::custom)
+    }
+}
+
+impl<'de, N: ?Sized> Visitor<'de> for CustomVisitor<N> {
+    type Value = Arc<N>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a custom node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Arc<N>, A::Error> {
+        let mut name: Option<String> = None;
+        let mut node = None;
+        let mut events: Option<Vec<MdEvent>> = None;
+        let mut markdown: Option<String> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "type" => name = Some(map.next_value()?),
+                "payload" => match name.as_deref().and_then(self.lookup) {
+                    Some(de) => node = Some(map.next_value_seed(PayloadSeed(de))?),
+                    None => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                },
+                "events" => events = Some(map.next_value()?),
+                "markdown" => markdown = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if let Some(node) = node {
+            return Ok(node);
+        }
+        let events = events.ok_or_else(|| de::Error::missing_field("events"))?;
+        let markdown = markdown.ok_or_else(|| de::Error::missing_field("markdown"))?;
+        Ok((self.opaque)(from_md_events(&events), markdown))
+    }
+}
+
+pub(crate) mod custom_block {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        node: &Arc<dyn BlockNode>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let entry = registry::block_serializer(&**node).map(|(name, ser)| (name, ser(&**node)));
+        serialize_custom(entry, node.to_events(), node.to_region().apply(), s)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Arc<dyn BlockNode>, D::Error> {
+        d.deserialize_struct(
+            "Custom",
+            CUSTOM_FIELDS,
+            CustomVisitor {
+                lookup: registry::block_deserializer,
+                opaque: |events, markdown| {
+                    Arc::new(OpaqueBlock {
+                        events,
+                        region: Region::from_str(&markdown),
+                    })
+                },
+            },
+        )
+    }
+}
+
+pub(crate) mod custom_inline {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        node: &Arc<dyn InlineNode>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let entry = registry::inline_serializer(&**node).map(|(name, ser)| (name, ser(&**node)));
+        serialize_custom(entry, node.to_events(), node.to_line().apply(), s)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Arc<dyn InlineNode>, D::Error> {
+        d.deserialize_struct(
+            "Custom",
+            CUSTOM_FIELDS,
+            CustomVisitor {
+                lookup: registry::inline_deserializer,
+                opaque: |events, markdown| {
+                    Arc::new(OpaqueInline {
+                        events,
+                        line: Line::from_str(&markdown),
+                    })
+                },
+            },
+        )
+    }
+}
@@ -0,0 +1,411 @@
+//! Static-site shortcodes, kept verbatim.
+//!
+//! Hugo and Zola content embeds template calls in markdown:
+//!
+//! ```markdown
+//! {{< figure src="a_b.png" alt="*not* emphasis" >}}
+//!
+//! {{% notice tip %}}Markdown *inside*.{{% /notice %}}
+//!
+//! {{ youtube(id="w7Ft2ymGmfc") }}
+//!
+//! {% quote(author="Ann") %}
+//! Quoted.
+//! {% end %}
+//! ```
+//!
+//! Plain markdown parsing reads their arguments as text, emphasis or HTML,
+//! so transforming a document can change them. `parse_shortcodes` parses
+//! the source with each shortcode as a `Shortcode` node holding its exact
+//! text: an inline where it sits in inline content, a block where it is a
+//! paragraph of its own. Both write back as that text.
+//!
+//! The opening and closing shortcodes of a pair are separate nodes, and the
+//! markdown between them parses as usual. Shortcodes in code, in HTML
+//! blocks or spanning several blocks stay what markdown makes of them.
+
+use crate::ast::ParseContext;
+use crate::ast::block::Block;
+use crate::ast::custom::{BlockNode, InlineNode};
+use crate::ast::inline::Inline;
+use crate::ast::parse::{HookResult, parse_events_to_blocks_with_hook};
+use crate::{Fragment, Line, Region};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
+use std::any::Any;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// The delimiters of a shortcode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShortcodeDelimiter {
+    /// Hugo's `{{< name args >}}`, whose inner content is not markdown.
+    HugoAngle,
+    /// Hugo's `{{% name args %}}`, whose inner content is markdown.
+    HugoPercent,
+    /// Zola's `{{ name(args) }}`.
+    ZolaInline,
+    /// Zola's `{% name(args) %}` and `{% end %}`, around a body.
+    ZolaBody,
+}
+
+impl ShortcodeDelimiter {
+    fn tokens(self) -> (&'static str, &'static str) {
+        match self {
+            ShortcodeDelimiter::HugoAngle => ("{{<", ">}}"),
+            ShortcodeDelimiter::HugoPercent => ("{{%", "%}}"),
+            ShortcodeDelimiter::ZolaInline => ("{{", "}}"),
+            ShortcodeDelimiter::ZolaBody => ("{%", "%}"),
+        }
+    }
+}
+
+/// A shortcode, as written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shortcode {
+    raw: String,
+    delimiter: ShortcodeDelimiter,
+}
+
+impl Shortcode {
+    /// The shortcode `raw` is made of, or `None` if it is not exactly one
+    /// shortcode.
+    pub fn parse(raw: &str) -> Option<Shortcode> {
+        let delimiter = [
+            ShortcodeDelimiter::HugoAngle,
+            ShortcodeDelimiter::HugoPercent,
+            ShortcodeDelimiter::ZolaInline,
+            ShortcodeDelimiter::ZolaBody,
+        ]
+        .into_iter()
+        .find(|d| {
+            let (open, close) = d.tokens();
+            // the first closing token ends the shortcode
+            raw.starts_with(open)
+                && raw[open.len()..].find(close) == raw.len().checked_sub(open.len() + close.len())
+        })?;
+        let shortcode = Shortcode {
+            raw: raw.to_string(),
+            delimiter,
+        };
+        let valid = match delimiter {
+            ShortcodeDelimiter::HugoAngle | ShortcodeDelimiter::HugoPercent => {
+                !shortcode.name().is_empty()
+            }
+            // Zola calls a function, or closes a body with `end`
+            ShortcodeDelimiter::ZolaInline | ShortcodeDelimiter::ZolaBody => {
+                let inner = shortcode.inner();
+                let call = inner.ends_with(')') && is_name(shortcode.name());
+                call || (delimiter == ShortcodeDelimiter::ZolaBody && inner == "end")
+            }
+        };
+        valid.then_some(shortcode)
+    }
+
+    /// The text of the shortcode, delimiters included.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn delimiter(&self) -> ShortcodeDelimiter {
+        self.delimiter
+    }
+
+    /// What is between the delimiters, trimmed.
+    fn inner(&self) -> &str {
+        let (open, close) = self.delimiter.tokens();
+        self.raw[open.len()..self.raw.len() - close.len()].trim()
+    }
+
+    /// The name of the shortcode, without the `/` of a closing Hugo
+    /// shortcode. A Zola `{% end %}` is named `end`.
+    pub fn name(&self) -> &str {
+        let inner = self.inner();
+        match self.delimiter {
+            ShortcodeDelimiter::HugoAngle | ShortcodeDelimiter::HugoPercent => {
+                let inner = inner.strip_prefix('/').unwrap_or(inner).trim_start();
+                inner.split_whitespace().next().unwrap_or("")
+            }
+            _ => inner.split('(').next().unwrap_or("").trim(),
+        }
+    }
+
+    /// The arguments as written: the rest of a Hugo shortcode after its
+    /// name, what is between the parentheses of a Zola one.
+    pub fn args(&self) -> &str {
+        let inner = self.inner();
+        match self.delimiter {
+            ShortcodeDelimiter::HugoAngle | ShortcodeDelimiter::HugoPercent => {
+                let inner = inner.strip_prefix('/').unwrap_or(inner).trim_start();
+                inner[self.name().len()..].trim()
+            }
+            _ => inner
+                .find('(')
+                .map_or("", |i| inner[i + 1..inner.len() - 1].trim()),
+        }
+    }
+
+    /// Whether this closes a pair: `{{< /name >}}` or `{% end %}`.
+    pub fn is_closing(&self) -> bool {
+        match self.delimiter {
+            ShortcodeDelimiter::HugoAngle | ShortcodeDelimiter::HugoPercent => {
+                self.inner().starts_with('/')
+            }
+            ShortcodeDelimiter::ZolaBody => self.inner() == "end",
+            ShortcodeDelimiter::ZolaInline => false,
+        }
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+impl BlockNode for Shortcode {
+    /// The shortcode as the text of a paragraph.
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Paragraph),
+            Event::Text(CowStr::from(self.raw.clone())),
+            Event::End(TagEnd::Paragraph),
+        ]
+    }
+
+    fn to_region(&self) -> Region {
+        Region::from_str(&self.raw)
+    }
+
+    fn node_eq(&self, other: &dyn BlockNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<Shortcode>() == Some(self)
+    }
+}
+
+impl InlineNode for Shortcode {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![Event::Text(CowStr::from(self.raw.clone()))]
+    }
+
+    /// The shortcode as one fragment, which wrapping never breaks.
+    fn to_line(&self) -> Line {
+        Line::from_fragments([Fragment::atomic(self.raw.clone())])
+    }
+
+    fn node_eq(&self, other: &dyn InlineNode) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<Shortcode>() == Some(self)
+    }
+}
+
+/// Byte ranges of the shortcodes in `src`, in order.
+fn find_shortcodes(src: &str) -> Vec<Range<usize>> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while let Some(at) = src[i..].find('{').map(|n| n + i) {
+        let found = [">}}", "%}}", "}}", "%}"].into_iter().find_map(|close| {
+            let end = src[at..].find(close)? + at + close.len();
+            Shortcode::parse(&src[at..end]).map(|_| at..end)
+        });
+        match found {
+            Some(range) => {
+                i = range.end;
+                out.push(range);
+            }
+            None => i = at + 1,
+        }
+    }
+    out
+}
+
+fn is_inline_tag(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis
+            | Tag::Strong
+            | Tag::Strikethrough
+            | Tag::Superscript
+            | Tag::Subscript
+            | Tag::Link { .. }
+            | Tag::Image { .. }
+    )
+}
+
+fn is_inline_end(tag: &TagEnd) -> bool {
+    matches!(
+        tag,
+        TagEnd::Emphasis
+            | TagEnd::Strong
+            | TagEnd::Strikethrough
+            | TagEnd::Superscript
+            | TagEnd::Subscript
+            | TagEnd::Link
+            | TagEnd::Image
+    )
+}
+
+/// Where an event of `range` sits: inline tags by their opening or closing
+/// marker, everything else by its whole range.
+fn position(event: &Event, range: &Range<usize>) -> Range<usize> {
+    match event {
+        Event::Start(tag) if is_inline_tag(tag) => range.start..range.start + 1,
+        Event::End(tag) if is_inline_end(tag) => range.end - 1..range.end,
+        _ => range.clone(),
+    }
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Whether the shortcode at `span` can become a node: it sits in the inline
+/// content of one block, and the events it cuts through are text that can
+/// be split.
+fn replaceable(src: &str, events: &[(Event, Range<usize>, usize)], span: &Range<usize>) -> bool {
+    let mut parent = None;
+    for (event, range, block) in events {
+        if !overlaps(&position(event, range), span) {
+            continue;
+        }
+        let inside = span.start <= range.start && range.end <= span.end;
+        let ok = match event {
+            // the block boundaries around the shortcode
+            Event::Start(_) | Event::End(_) if !is_inline_event(event) => continue,
+            // text cut by the shortcode must read as its source
+            Event::Text(t) => inside || **t == src[range.clone()],
+            // inline tags must open and close inside the shortcode
+            Event::Start(_)
+            | Event::End(_)
+            | Event::InlineHtml(_)
+            | Event::Code(_)
+            | Event::SoftBreak
+            | Event::HardBreak => inside,
+            _ => false,
+        };
+        if !ok || *parent.get_or_insert(*block) != *block {
+            return false;
+        }
+    }
+    // only blocks holding inline content
+    parent.is_some_and(|p| {
+        matches!(
+            events.get(p).map(|e| &e.0),
+            Some(Event::Start(
+                Tag::Paragraph | Tag::Heading { .. } | Tag::TableCell | Tag::Item
+            ))
+        )
+    })
+}
+
+/// A placeholder for shortcode `n`, turned into its node by the parse hook.
+fn placeholder(n: usize) -> Event<'static> {
+    Event::Text(CowStr::from(format!("\u{E000}{n}\u{E001}")))
+}
+
+fn placeholder_index(event: &Event) -> Option<usize> {
+    match event {
+        Event::Text(t) => t
+            .strip_prefix('\u{E000}')?
+            .strip_suffix('\u{E001}')?
+            .parse()
+            .ok(),
+        _ => None,
+    }
+}
+
+/// Parse `src` with `options`, keeping its shortcodes verbatim as
+/// `Shortcode` nodes: `Inline::Custom` in inline content, `Block::Custom`
+/// for a paragraph holding nothing but one shortcode.
+pub fn parse_shortcodes(src: &str, options: Options) -> Vec<Block> {
+    // every event with the index of the block it sits in
+    let mut events: Vec<(Event, Range<usize>, usize)> = Vec::new();
+    let mut blocks: Vec<usize> = Vec::new();
+    for (event, range) in Parser::new_ext(src, options).into_offset_iter() {
+        let parent = blocks.last().copied().unwrap_or(usize::MAX);
+        match &event {
+            Event::Start(tag) if !is_inline_tag(tag) => blocks.push(events.len()),
+            Event::End(tag) if !is_inline_end(tag) => {
+                blocks.pop();
+            }
+            _ => {}
+        }
+        events.push((event, range, parent));
+    }
+
+    let spans: Vec<Range<usize>> = find_shortcodes(src)
+        .into_iter()
+        .filter(|span| replaceable(src, &events, span))
+        .collect();
+    let shortcodes: Vec<Shortcode> = spans
+        .iter()
+        .map(|span| Shortcode::parse(&src[span.clone()]).expect("found as a shortcode"))
+        .collect();
+
+    // the events with each shortcode replaced by its placeholder
+    let mut out: Vec<Event> = Vec::with_capacity(events.len());
+    let mut emitted = vec![false; spans.len()];
+    for (event, range, _) in &events {
+        let pos = position(event, range);
+        let cut: Vec<usize> = (0..spans.len())
+            .filter(|&n| overlaps(&spans[n], &pos))
+            .collect();
+        let inside = |n: usize| spans[n].start <= range.start && range.end <= spans[n].end;
+        match (event, cut.as_slice()) {
+            (_, []) => out.push(event.clone()),
+            (Event::Start(_) | Event::End(_), _) if !is_inline_event(event) => {
+                out.push(event.clone())
+            }
+            (_, [n]) if inside(*n) => {
+                if !std::mem::replace(&mut emitted[*n], true) {
+                    out.push(placeholder(*n));
+                }
+            }
+            // text around or between shortcodes: split it
+            (Event::Text(_), cut) => {
+                let mut at = range.start;
+                for &n in cut {
+                    if at < spans[n].start {
+                        out.push(Event::Text(CowStr::from(&src[at..spans[n].start])));
+                    }
+                    if !std::mem::replace(&mut emitted[n], true) {
+                        out.push(placeholder(n));
+                    }
+                    at = spans[n].end;
+                }
+                if at < range.end {
+                    out.push(Event::Text(CowStr::from(&src[at..range.end])));
+                }
+            }
+            _ => out.push(event.clone()),
+        }
+    }
+
+    let mut hook = |evs: &[Event], _i: usize, ctx: &ParseContext| {
+        if let Some(n) = evs.first().and_then(placeholder_index) {
+            let node = Inline::Custom(Arc::new(shortcodes.get(n)?.clone()));
+            return Some((1, HookResult::Inline(node)));
+        }
+        match evs {
+            [
+                Event::Start(Tag::Paragraph),
+                p,
+                Event::End(TagEnd::Paragraph),
+                ..,
+            ] if !ctx.parent_collects_inlines => {
+                let n = placeholder_index(p)?;
+                let node = Block::Custom(Arc::new(shortcodes.get(n)?.clone()));
+                Some((3, HookResult::Block(node)))
+            }
+            _ => None,
+        }
+    };
+    parse_events_to_blocks_with_hook(&out, Some(&mut hook))
+}
+
+fn is_inline_event(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) => is_inline_tag(tag),
+        Event::End(tag) => is_inline_end(tag),
+        _ => true,
+    }
+}
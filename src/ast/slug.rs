@@ -0,0 +1,119 @@
+//! Heading slugs, the anchors headings are linked by.
+//!
+//! `slugify` computes the slug GitHub gives a heading: its text lowercased,
+//! punctuation dropped and spaces turned into hyphens. A `Slugger` slugs
+//! the headings of one document in order and numbers repeated slugs
+//! (`intro`, `intro-1`, ...) so that every anchor is unique; its
+//! `SlugOptions` change how text becomes a slug. Every feature that needs
+//! anchors goes through here, so they all agree.
+
+use crate::ast::Inline;
+use crate::ast::writer::plaintext::inlines_text;
+use std::collections::HashMap;
+
+/// How heading text becomes a slug. The default is GitHub's algorithm.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlugOptions {
+    /// Keep letters and digits outside ASCII, as GitHub does. When false
+    /// they are dropped like punctuation.
+    pub keep_unicode: bool,
+    /// Put in place of each space; GitHub uses '-'.
+    pub separator: char,
+    /// Merge runs of separators into one and trim them from both ends.
+    /// GitHub keeps them, so "a - b" becomes "a---b".
+    pub collapse_separators: bool,
+    /// Number repeated slugs in a `Slugger`, as GitHub does. When false a
+    /// repeated heading gets the same slug again.
+    pub dedupe: bool,
+}
+
+impl Default for SlugOptions {
+    fn default() -> Self {
+        SlugOptions {
+            keep_unicode: true,
+            separator: '-',
+            collapse_separators: false,
+            dedupe: true,
+        }
+    }
+}
+
+impl SlugOptions {
+    /// GitHub's algorithm, the default.
+    pub fn github() -> Self {
+        Self::default()
+    }
+
+    /// The slug of `text`, without deduplication.
+    pub fn slug_str(&self, text: &str) -> String {
+        let mut out = String::new();
+        for c in text.chars().flat_map(char::to_lowercase) {
+            if c.is_whitespace() {
+                if !(self.collapse_separators && (out.is_empty() || out.ends_with(self.separator)))
+                {
+                    out.push(self.separator);
+                }
+            } else if (c.is_alphanumeric() && (self.keep_unicode || c.is_ascii()))
+                || matches!(c, '-' | '_')
+            {
+                out.push(c);
+            }
+        }
+        if self.collapse_separators {
+            while out.ends_with(self.separator) {
+                out.pop();
+            }
+        }
+        out
+    }
+}
+
+/// The GitHub slug of a heading's content, without deduplication.
+pub fn slugify(inlines: &[Inline]) -> String {
+    SlugOptions::github().slug_str(&inlines_text(inlines))
+}
+
+/// Slugs the headings of a document in order, keeping slugs unique.
+#[derive(Clone, Debug, Default)]
+pub struct Slugger {
+    options: SlugOptions,
+    /// Slugs handed out, with the last counter used for each base slug.
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn new(options: SlugOptions) -> Self {
+        Slugger {
+            options,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// The slug of the next heading, given its content.
+    pub fn slug(&mut self, inlines: &[Inline]) -> String {
+        self.slug_str(&inlines_text(inlines))
+    }
+
+    /// The slug of the next heading, given its text.
+    pub fn slug_str(&mut self, text: &str) -> String {
+        let base = self.options.slug_str(text);
+        if !self.options.dedupe {
+            return base;
+        }
+        let mut slug = base.clone();
+        while self.seen.contains_key(&slug) {
+            let n = self.seen.get_mut(&base).map_or(1, |n| {
+                *n += 1;
+                *n
+            });
+            slug = format!("{base}-{n}");
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+
+    /// Forget the slugs handed out, e.g. to start another document.
+    pub fn reset(&mut self) {
+        self.seen.clear();
+    }
+}
@@ -0,0 +1,942 @@
+//! Ready-made passes over a block list.
+//!
+//! Each pass takes `&mut Vec<Block>`, so it can be used on its own or
+//! registered with a `Pipeline` inside a closure.
+
+use crate::ast::visit::{
+    Edit, VisitMut, Visitor, walk_block, walk_block_mut, walk_blocks, walk_blocks_mut, walk_inline,
+    walk_inline_mut,
+};
+use crate::ast::{Block, Inline, label_key};
+use crate::text::Region;
+use pulldown_cmark::{Alignment, HeadingLevel, LinkType};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// What `shift_headings` does with a heading pushed past `h6`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeadingOverflow {
+    /// Make it an `h6`.
+    #[default]
+    Clamp,
+    /// Make it a paragraph holding its content in bold.
+    Bold,
+    /// Fail without changing anything.
+    Error,
+}
+
+/// A heading `shift_headings` could not shift under `HeadingOverflow::Error`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadingOverflowError {
+    /// The heading, as it was.
+    pub heading: Block,
+    /// The level it would have had, above 6.
+    pub level: i32,
+}
+
+impl fmt::Display for HeadingOverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "heading shifted to level {}, past h6", self.level)
+    }
+}
+
+impl std::error::Error for HeadingOverflowError {}
+
+fn shifted(level: HeadingLevel, delta: i8) -> i32 {
+    (level as i32 + delta as i32).max(1)
+}
+
+/// Move every heading, at any depth, `delta` levels deeper (or shallower
+/// when negative), e.g. `1` to embed a document under an `h1` of another.
+/// Headings shifted above `h1` become `h1`; those shifted past `h6` are
+/// handled as `overflow` says.
+pub fn shift_headings(
+    blocks: &mut Vec<Block>,
+    delta: i8,
+    overflow: HeadingOverflow,
+) -> Result<(), HeadingOverflowError> {
+    if overflow == HeadingOverflow::Error {
+        struct FirstOverflow(i8, Option<HeadingOverflowError>);
+        impl Visitor for FirstOverflow {
+            fn visit_block(&mut self, b: &Block) {
+                match b {
+                    Block::Heading { level, .. } if self.1.is_none() => {
+                        let level = shifted(*level, self.0);
+                        if level > 6 {
+                            self.1 = Some(HeadingOverflowError {
+                                heading: b.clone(),
+                                level,
+                            });
+                        }
+                    }
+                    _ => walk_block(self, b),
+                }
+            }
+        }
+        let mut check = FirstOverflow(delta, None);
+        walk_blocks(&mut check, blocks);
+        if let Some(err) = check.1 {
+            return Err(err);
+        }
+    }
+    struct Shift(i8, HeadingOverflow);
+    impl VisitMut for Shift {
+        fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+            let Block::Heading {
+                level, children, ..
+            } = b
+            else {
+                walk_block_mut(self, b);
+                return Edit::Keep;
+            };
+            match HeadingLevel::try_from(shifted(*level, self.0) as usize) {
+                Ok(new) => *level = new,
+                Err(_) if self.1 == HeadingOverflow::Bold => {
+                    let content = std::mem::take(children);
+                    return Edit::Replace(vec![Block::Paragraph(vec![Inline::Strong(content)])]);
+                }
+                Err(_) => *level = HeadingLevel::H6,
+            }
+            Edit::Keep
+        }
+    }
+    walk_blocks_mut(&mut Shift(delta, overflow), blocks);
+    Ok(())
+}
+
+/// What a destination passed to `rewrite_links` belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    /// A link, autolinks included.
+    Link,
+    Image,
+    /// A reference definition, `[id]: dest`.
+    Definition,
+}
+
+/// Replace link and image destinations, at any depth, with what `rewrite`
+/// returns for them; `None` keeps a destination as it is. Reference
+/// definitions are rewritten too, so that a reference link and its
+/// definition stay in agreement.
+pub fn rewrite_links<F>(blocks: &mut Vec<Block>, rewrite: F)
+where
+    F: FnMut(&str, LinkKind) -> Option<String>,
+{
+    struct Rewrite<F>(F);
+    impl<F: FnMut(&str, LinkKind) -> Option<String>> Rewrite<F> {
+        fn apply(&mut self, dest: &mut String, kind: LinkKind) {
+            if let Some(new) = (self.0)(dest, kind) {
+                *dest = new;
+            }
+        }
+    }
+    impl<F: FnMut(&str, LinkKind) -> Option<String>> VisitMut for Rewrite<F> {
+        fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+            if let Block::LinkDefinition { dest, .. } = b {
+                self.apply(dest, LinkKind::Definition);
+            }
+            walk_block_mut(self, b);
+            Edit::Keep
+        }
+
+        fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+            match inl {
+                Inline::Link { dest, .. } => self.apply(dest, LinkKind::Link),
+                Inline::Image { dest, .. } => self.apply(dest, LinkKind::Image),
+                _ => {}
+            }
+            walk_inline_mut(self, inl);
+            Edit::Keep
+        }
+    }
+    walk_blocks_mut(&mut Rewrite(rewrite), blocks);
+}
+
+/// Merge adjacent text inlines into one.
+pub(crate) fn merge_text(inlines: &mut Vec<Inline>) {
+    if !inlines
+        .windows(2)
+        .any(|w| matches!(w, [Inline::Text(_), Inline::Text(_)]))
+    {
+        return;
+    }
+    let mut out: Vec<Inline> = Vec::with_capacity(inlines.len());
+    for inl in inlines.drain(..) {
+        match (out.last_mut(), inl) {
+            (Some(Inline::Text(prev)), Inline::Text(next)) => {
+                *prev = Region::from_str(&(prev.apply() + &next.apply()));
+            }
+            (_, inl) => out.push(inl),
+        }
+    }
+    *inlines = out;
+}
+
+/// What `normalize` does. Every step is on by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Merge adjacent text inlines into one.
+    pub merge_text: bool,
+    /// Remove empty text, empty emphasis-like inlines, and the paragraphs
+    /// left empty.
+    pub remove_empty: bool,
+    /// Turn emphasis holding nothing but the same emphasis (`Strong` in
+    /// `Strong`, ...) into one.
+    pub collapse_emphasis: bool,
+    /// Move top-level reference definitions to the end of the document,
+    /// sorted by id.
+    pub sort_definitions: bool,
+    /// Gather the table pieces `TablePlaceholder`, `TableHead` and
+    /// `TableRow` into `Table`s, as the parser does.
+    pub unify_tables: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            merge_text: true,
+            remove_empty: true,
+            collapse_emphasis: true,
+            sort_definitions: true,
+            unify_tables: true,
+        }
+    }
+}
+
+struct Normalize(NormalizeOptions);
+
+impl Normalize {
+    fn inlines(&self, inlines: &mut Vec<Inline>) {
+        if self.0.merge_text {
+            merge_text(inlines);
+        }
+    }
+
+    /// Gather each run of table pieces (a `TablePlaceholder`, then a
+    /// `TableHead`, then `TableRow`s, each optional) into a `Table`.
+    fn blocks(&self, blocks: &mut Vec<Block>) {
+        let is_piece = |b: &Block| {
+            matches!(
+                b,
+                Block::TablePlaceholder(_) | Block::TableHead(_) | Block::TableRow(_)
+            )
+        };
+        if !self.0.unify_tables || !blocks.iter().any(is_piece) {
+            return;
+        }
+        let mut out = Vec::with_capacity(blocks.len());
+        let mut pieces = std::mem::take(blocks).into_iter().peekable();
+        while let Some(b) = pieces.peek() {
+            if !is_piece(b) {
+                out.extend(pieces.next());
+                continue;
+            }
+            let mut aligns = None;
+            let mut head = Vec::new();
+            let mut rows: Vec<Vec<Vec<Inline>>> = Vec::new();
+            // a piece that cannot come where it is starts the next table
+            while let Some(piece) = pieces.next_if(|p| match p {
+                Block::TablePlaceholder(_) => {
+                    aligns.is_none() && head.is_empty() && rows.is_empty()
+                }
+                Block::TableHead(_) => head.is_empty() && rows.is_empty(),
+                Block::TableRow(_) => true,
+                _ => false,
+            }) {
+                match piece {
+                    Block::TablePlaceholder(a) => aligns = Some(a),
+                    Block::TableHead(cells) => head = cells,
+                    Block::TableRow(cells) => rows.push(cells),
+                    _ => {}
+                }
+            }
+            let columns = std::iter::once(&head)
+                .chain(&rows)
+                .map(Vec::len)
+                .max()
+                .unwrap_or(0);
+            out.push(Block::Table {
+                aligns: aligns.unwrap_or_else(|| vec![Alignment::None; columns]),
+                head,
+                rows,
+            });
+        }
+        *blocks = out;
+    }
+}
+
+impl VisitMut for Normalize {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        walk_block_mut(self, b);
+        match b {
+            Block::Paragraph(children) | Block::Heading { children, .. } => self.inlines(children),
+            Block::TableRow(cells) | Block::TableHead(cells) => {
+                cells.iter_mut().for_each(|c| self.inlines(c))
+            }
+            Block::Table { head, rows, .. } => head
+                .iter_mut()
+                .chain(rows.iter_mut().flatten())
+                .for_each(|c| self.inlines(c)),
+            Block::BlockQuote(children)
+            | Block::Alert { children, .. }
+            | Block::Item(children)
+            | Block::FootnoteDefinition(_, children) => self.blocks(children),
+            Block::List { items, .. } => items.iter_mut().for_each(|item| self.blocks(item)),
+            _ => {}
+        }
+        match b {
+            Block::Paragraph(children) if self.0.remove_empty && children.is_empty() => {
+                Edit::Remove
+            }
+            _ => Edit::Keep,
+        }
+    }
+
+    fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+        walk_inline_mut(self, inl);
+        match inl {
+            Inline::Emphasis(children)
+            | Inline::Strong(children)
+            | Inline::Strikethrough(children)
+            | Inline::Subscript(children)
+            | Inline::Superscript(children) => {
+                self.inlines(children);
+                if self.0.remove_empty && children.is_empty() {
+                    return Edit::Remove;
+                }
+            }
+            Inline::Link { children, .. } | Inline::Image { children, .. } => {
+                self.inlines(children)
+            }
+            Inline::Text(text) if self.0.remove_empty && text.apply().is_empty() => {
+                return Edit::Remove;
+            }
+            _ => {}
+        }
+        if self.0.collapse_emphasis {
+            let collapsed = match inl {
+                Inline::Emphasis(c) => match c.as_mut_slice() {
+                    [Inline::Emphasis(inner)] => Some(Inline::Emphasis(std::mem::take(inner))),
+                    _ => None,
+                },
+                Inline::Strong(c) => match c.as_mut_slice() {
+                    [Inline::Strong(inner)] => Some(Inline::Strong(std::mem::take(inner))),
+                    _ => None,
+                },
+                Inline::Strikethrough(c) => match c.as_mut_slice() {
+                    [Inline::Strikethrough(inner)] => {
+                        Some(Inline::Strikethrough(std::mem::take(inner)))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(collapsed) = collapsed {
+                *inl = collapsed;
+            }
+        }
+        Edit::Keep
+    }
+}
+
+/// Bring `blocks` into a canonical form, so that documents meaning the
+/// same thing compare equal and diff only where they differ:
+/// adjacent text is merged, empty inlines and paragraphs are removed,
+/// nested emphasis of one kind is collapsed, reference definitions are
+/// sorted at the end, and table pieces are gathered into tables. `options` turns
+/// steps off.
+pub fn normalize(blocks: &mut Vec<Block>, options: NormalizeOptions) {
+    let mut pass = Normalize(options);
+    walk_blocks_mut(&mut pass, blocks);
+    pass.blocks(blocks);
+    if options.sort_definitions {
+        let (mut definitions, rest): (Vec<Block>, Vec<Block>) = std::mem::take(blocks)
+            .into_iter()
+            .partition(|b| matches!(b, Block::LinkDefinition { .. }));
+        definitions.sort_by_cached_key(|b| match b {
+            Block::LinkDefinition { id, .. } => label_key(id),
+            _ => String::new(),
+        });
+        *blocks = rest;
+        blocks.extend(definitions);
+    }
+}
+
+/// Rename footnotes to `1`, `2`, ... in the order they are first
+/// referenced, and reorder the top-level footnote definitions to match:
+/// they keep the places definitions had, filled in the new order.
+/// Definitions nothing refers to are numbered last, in document order.
+pub fn renumber_footnotes(blocks: &mut Vec<Block>) {
+    #[derive(Default)]
+    struct Order {
+        referenced: Vec<String>,
+        defined: Vec<String>,
+    }
+    impl Visitor for Order {
+        fn visit_block(&mut self, b: &Block) {
+            if let Block::FootnoteDefinition(label, _) = b {
+                self.defined.push(label_key(label));
+            }
+            walk_block(self, b);
+        }
+        fn visit_inline(&mut self, inl: &Inline) {
+            if let Inline::FootnoteReference(label) = inl {
+                self.referenced.push(label_key(label));
+            }
+            walk_inline(self, inl);
+        }
+    }
+    let mut order = Order::default();
+    walk_blocks(&mut order, blocks);
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    for key in order.referenced.into_iter().chain(order.defined) {
+        let next = numbers.len() + 1;
+        numbers.entry(key).or_insert(next);
+    }
+
+    struct Rename<'a>(&'a HashMap<String, usize>);
+    impl VisitMut for Rename<'_> {
+        fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+            if let Block::FootnoteDefinition(label, _) = b {
+                *label = self.0[&label_key(label)].to_string();
+            }
+            walk_block_mut(self, b);
+            Edit::Keep
+        }
+        fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+            if let Inline::FootnoteReference(label) = inl {
+                *label = self.0[&label_key(label)].to_string();
+            }
+            walk_inline_mut(self, inl);
+            Edit::Keep
+        }
+    }
+    walk_blocks_mut(&mut Rename(&numbers), blocks);
+
+    let slots: Vec<usize> = (0..blocks.len())
+        .filter(|&i| matches!(blocks[i], Block::FootnoteDefinition(..)))
+        .collect();
+    let mut definitions: Vec<Block> = slots
+        .iter()
+        .map(|&i| std::mem::replace(&mut blocks[i], Block::Rule))
+        .collect();
+    definitions.sort_by_key(|b| match b {
+        Block::FootnoteDefinition(label, _) => label.parse::<usize>().unwrap_or(usize::MAX),
+        _ => usize::MAX,
+    });
+    for (i, def) in slots.into_iter().zip(definitions) {
+        blocks[i] = def;
+    }
+}
+
+/// How `substitute` escapes the values it puts into text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TemplateEscape {
+    /// Insert values as they are, so they may carry markdown.
+    #[default]
+    None,
+    /// Backslash-escape markdown punctuation, so values read as plain text.
+    Markdown,
+    /// Replace `&`, `<`, `>`, `"` and `'` by HTML entities.
+    Html,
+}
+
+impl TemplateEscape {
+    fn apply(self, value: &str) -> String {
+        match self {
+            TemplateEscape::None => value.to_string(),
+            TemplateEscape::Markdown => {
+                let mut out = String::with_capacity(value.len());
+                for c in value.chars() {
+                    if "\\`*_[]<>#|~!".contains(c) {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+                out
+            }
+            TemplateEscape::Html => {
+                let mut out = String::with_capacity(value.len());
+                for c in value.chars() {
+                    match c {
+                        '&' => out.push_str("&amp;"),
+                        '<' => out.push_str("&lt;"),
+                        '>' => out.push_str("&gt;"),
+                        '"' => out.push_str("&quot;"),
+                        '\'' => out.push_str("&#39;"),
+                        c => out.push(c),
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Replace `{{name}}` placeholders with the value of `name` in `vars`, in
+/// text (headings, table cells and link text included), inline code, code
+/// blocks, heading ids, and link, image and reference destinations.
+/// Whitespace around the name is ignored: `{{ name }}` works too. Values
+/// going into text are escaped as `escape` says; code and destinations get
+/// them as they are.
+///
+/// Placeholders without a value are left in place. Their names are
+/// returned, once each, in the order they first appear.
+pub fn substitute<K, V>(
+    blocks: &mut Vec<Block>,
+    vars: &HashMap<K, V>,
+    escape: TemplateEscape,
+) -> Vec<String>
+where
+    K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+    V: AsRef<str>,
+{
+    struct Substitute<'a, K, V> {
+        vars: &'a HashMap<K, V>,
+        escape: TemplateEscape,
+        unresolved: Vec<String>,
+    }
+    impl<K, V> Substitute<'_, K, V>
+    where
+        K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+        V: AsRef<str>,
+    {
+        fn fill(&mut self, s: &mut String, escape: TemplateEscape) {
+            if !s.contains("{{") {
+                return;
+            }
+            let mut out = String::with_capacity(s.len());
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find("{{") {
+                let after = &rest[start + 2..];
+                let Some(end) = after.find("}}") else {
+                    break;
+                };
+                out.push_str(&rest[..start]);
+                let name = after[..end].trim();
+                if name.is_empty() || name.contains(['{', '}', '\n']) {
+                    // not a placeholder; one of its braces may open one
+                    out.push('{');
+                    rest = &rest[start + 1..];
+                    continue;
+                }
+                match self.vars.get(name) {
+                    Some(value) => out.push_str(&escape.apply(value.as_ref())),
+                    None => {
+                        if !self.unresolved.iter().any(|n| n == name) {
+                            self.unresolved.push(name.to_string());
+                        }
+                        out.push_str(&rest[start..start + 2 + end + 2]);
+                    }
+                }
+                rest = &after[end + 2..];
+            }
+            out.push_str(rest);
+            *s = out;
+        }
+
+        fn fill_region(&mut self, r: &mut Region, escape: TemplateEscape) {
+            let mut s = r.apply();
+            if s.contains("{{") {
+                self.fill(&mut s, escape);
+                *r = Region::from_str(&s);
+            }
+        }
+    }
+    impl<K, V> VisitMut for Substitute<'_, K, V>
+    where
+        K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+        V: AsRef<str>,
+    {
+        fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+            // the parser may split a placeholder over several text inlines
+            match b {
+                Block::Paragraph(children) => merge_text(children),
+                Block::Heading { id, children, .. } => {
+                    if let Some(id) = id {
+                        self.fill(id, TemplateEscape::None);
+                    }
+                    merge_text(children);
+                }
+                Block::TableRow(cells) | Block::TableHead(cells) => {
+                    cells.iter_mut().for_each(merge_text)
+                }
+                Block::Table { head, rows, .. } => head
+                    .iter_mut()
+                    .chain(rows.iter_mut().flatten())
+                    .for_each(merge_text),
+                Block::CodeBlock { content, .. } => self.fill_region(content, TemplateEscape::None),
+                Block::LinkDefinition { dest, .. } => self.fill(dest, TemplateEscape::None),
+                _ => {}
+            }
+            walk_block_mut(self, b);
+            Edit::Keep
+        }
+
+        fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+            match inl {
+                Inline::Text(text) => self.fill_region(text, self.escape),
+                Inline::Code(code) => self.fill_region(code, TemplateEscape::None),
+                Inline::Emphasis(children)
+                | Inline::Strong(children)
+                | Inline::Strikethrough(children)
+                | Inline::Subscript(children)
+                | Inline::Superscript(children) => merge_text(children),
+                Inline::Link { dest, children, .. } | Inline::Image { dest, children, .. } => {
+                    self.fill(dest, TemplateEscape::None);
+                    merge_text(children);
+                }
+                _ => {}
+            }
+            walk_inline_mut(self, inl);
+            Edit::Keep
+        }
+    }
+    let mut pass = Substitute {
+        vars,
+        escape,
+        unresolved: Vec::new(),
+    };
+    walk_blocks_mut(&mut pass, blocks);
+    pass.unresolved
+}
+
+/// Recognizes `<!-- include: path -->` comment blocks, returning the path.
+pub fn include_comment(b: &Block) -> Option<String> {
+    let Block::Comment(body) = b else {
+        return None;
+    };
+    let body = body.apply();
+    let target = body.trim().strip_prefix("include:")?.trim();
+    (!target.is_empty()).then(|| target.to_string())
+}
+
+/// What `include` looks for, and how deep it goes.
+#[derive(Clone, Copy, Debug)]
+pub struct IncludeOptions {
+    /// Returns the target of a block marking an include, e.g. a path.
+    /// Defaults to `include_comment`.
+    pub marker: fn(&Block) -> Option<String>,
+    /// How many includes may be nested inside one another.
+    pub max_depth: usize,
+}
+
+impl Default for IncludeOptions {
+    fn default() -> Self {
+        IncludeOptions {
+            marker: include_comment,
+            max_depth: 16,
+        }
+    }
+}
+
+/// Why `include` stopped.
+#[derive(Debug)]
+pub enum IncludeError<E> {
+    /// A target includes itself. `chain` holds the targets being included,
+    /// outermost first, ending with the repeated one.
+    Cycle { chain: Vec<String> },
+    /// Including `target` would nest includes deeper than `max_depth`.
+    TooDeep { target: String, max_depth: usize },
+    /// The loader failed on `target`.
+    Load { target: String, error: E },
+}
+
+impl<E: fmt::Display> fmt::Display for IncludeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeError::Cycle { chain } => write!(f, "include cycle: {}", chain.join(" -> ")),
+            IncludeError::TooDeep { target, max_depth } => write!(
+                f,
+                "including {} nests more than {} includes",
+                target, max_depth
+            ),
+            IncludeError::Load { target, error } => {
+                write!(f, "cannot include {}: {}", target, error)
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for IncludeError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IncludeError::Load { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Replace include markers, at any depth, with the blocks `load` returns
+/// for their target, e.g. a file read and parsed. Included blocks are
+/// searched for markers too; a target including itself, directly or not,
+/// is an error, as is nesting past `options.max_depth`. On error `blocks`
+/// is left partly resolved.
+pub fn include<F, E>(
+    blocks: &mut Vec<Block>,
+    options: &IncludeOptions,
+    load: F,
+) -> Result<(), IncludeError<E>>
+where
+    F: FnMut(&str) -> Result<Vec<Block>, E>,
+{
+    struct Include<'a, F, E> {
+        options: &'a IncludeOptions,
+        load: F,
+        chain: Vec<String>,
+        error: Option<IncludeError<E>>,
+    }
+    impl<F, E> Include<'_, F, E>
+    where
+        F: FnMut(&str) -> Result<Vec<Block>, E>,
+    {
+        fn resolve(&mut self, target: String) -> Result<Vec<Block>, IncludeError<E>> {
+            if self.chain.contains(&target) {
+                let mut chain = self.chain.clone();
+                chain.push(target);
+                return Err(IncludeError::Cycle { chain });
+            }
+            if self.chain.len() >= self.options.max_depth {
+                return Err(IncludeError::TooDeep {
+                    target,
+                    max_depth: self.options.max_depth,
+                });
+            }
+            let mut blocks = (self.load)(&target).map_err(|error| IncludeError::Load {
+                target: target.clone(),
+                error,
+            })?;
+            self.chain.push(target);
+            walk_blocks_mut(self, &mut blocks);
+            self.chain.pop();
+            Ok(blocks)
+        }
+    }
+    impl<F, E> VisitMut for Include<'_, F, E>
+    where
+        F: FnMut(&str) -> Result<Vec<Block>, E>,
+    {
+        fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+            if self.error.is_some() {
+                return Edit::Keep;
+            }
+            let Some(target) = (self.options.marker)(b) else {
+                walk_block_mut(self, b);
+                return Edit::Keep;
+            };
+            match self.resolve(target) {
+                Ok(blocks) => Edit::Replace(blocks),
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                    Edit::Keep
+                }
+            }
+        }
+
+        fn visit_inline_mut(&mut self, _inl: &mut Inline) -> Edit<Inline> {
+            Edit::Keep
+        }
+    }
+    let mut pass = Include {
+        options,
+        load,
+        chain: Vec::new(),
+        error: None,
+    };
+    walk_blocks_mut(&mut pass, blocks);
+    match pass.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// What `embed_images` does with the images it loads.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EmbedImages {
+    /// Replace the destination by a `data:` URI holding the image.
+    DataUri,
+    /// Write the image into `dir`, under its file name, and point the
+    /// destination at `prefix` followed by that name, e.g. `assets/`.
+    CopyTo { dir: PathBuf, prefix: String },
+}
+
+/// Make images self-contained. `load` is called once for every distinct
+/// image destination and returns the bytes of the image, or `None` to
+/// leave it alone (a remote URL, say); the images it returns are embedded
+/// or copied as `mode` says. Returns the distinct destinations found, in
+/// document order, before rewriting. A reference image takes its
+/// definition along: the `LinkDefinition` for its id, and the links using
+/// that id, get the new destination too.
+///
+/// With `EmbedImages::CopyTo`, images with the same file name get `-1`,
+/// `-2`, ... appended to it. On error `blocks` is left partly rewritten.
+pub fn embed_images<F>(
+    blocks: &mut Vec<Block>,
+    mode: &EmbedImages,
+    mut load: F,
+) -> io::Result<Vec<String>>
+where
+    F: FnMut(&str) -> io::Result<Option<Vec<u8>>>,
+{
+    let mut found: Vec<String> = Vec::new();
+    let mut rewritten: HashMap<String, Option<String>> = HashMap::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut error = None;
+    let mut embed = |dest: &str| -> io::Result<Option<String>> {
+        let Some(bytes) = load(dest)? else {
+            return Ok(None);
+        };
+        match mode {
+            EmbedImages::DataUri => Ok(Some(format!(
+                "data:{};base64,{}",
+                image_mime(dest),
+                base64(&bytes)
+            ))),
+            EmbedImages::CopyTo { dir, prefix } => {
+                let path = dest.split(['?', '#']).next().unwrap_or(dest);
+                let file = path.rsplit(['/', '\\']).next().unwrap_or(path);
+                let (stem, ext) = match file.rsplit_once('.') {
+                    Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+                    _ => (file, None),
+                };
+                let mut name = file.to_string();
+                let mut n = 0;
+                while names.contains(&name) {
+                    n += 1;
+                    name = match ext {
+                        Some(ext) => format!("{stem}-{n}.{ext}"),
+                        None => format!("{stem}-{n}"),
+                    };
+                }
+                fs::create_dir_all(dir)?;
+                fs::write(dir.join(&name), bytes)?;
+                let new = format!("{prefix}{name}");
+                names.push(name);
+                Ok(Some(new))
+            }
+        }
+    };
+    // reference images share their destination with a definition
+    struct ReferenceImages(HashMap<String, String>);
+    impl Visitor for ReferenceImages {
+        fn visit_inline(&mut self, inl: &Inline) {
+            if let Inline::Image { dest, id, .. } = inl
+                && is_reference(inl)
+            {
+                self.0.entry(label_key(id)).or_insert_with(|| dest.clone());
+            }
+            walk_inline(self, inl);
+        }
+    }
+    let mut images = ReferenceImages(HashMap::new());
+    walk_blocks(&mut images, blocks);
+
+    rewrite_links(blocks, |dest, kind| {
+        if kind != LinkKind::Image || error.is_some() {
+            return None;
+        }
+        if let Some(new) = rewritten.get(dest) {
+            return new.clone();
+        }
+        found.push(dest.to_string());
+        match embed(dest) {
+            Ok(new) => {
+                rewritten.insert(dest.to_string(), new.clone());
+                new
+            }
+            Err(e) => {
+                error = Some(e);
+                None
+            }
+        }
+    });
+    if let Some(e) = error {
+        return Err(e);
+    }
+
+    // so the definitions, and the links sharing them, move along
+    struct Retarget(HashMap<String, String>);
+    impl VisitMut for Retarget {
+        fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+            if let Block::LinkDefinition { id, dest, .. } = b
+                && let Some(new) = self.0.get(&label_key(id))
+            {
+                dest.clone_from(new);
+            }
+            walk_block_mut(self, b);
+            Edit::Keep
+        }
+        fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+            if is_reference(inl)
+                && let Inline::Link { id, dest, .. } | Inline::Image { id, dest, .. } = inl
+                && let Some(new) = self.0.get(&label_key(id))
+            {
+                dest.clone_from(new);
+            }
+            walk_inline_mut(self, inl);
+            Edit::Keep
+        }
+    }
+    let moved: HashMap<String, String> = images
+        .0
+        .into_iter()
+        .filter_map(|(id, dest)| Some((id, rewritten.get(&dest)?.clone()?)))
+        .collect();
+    if !moved.is_empty() {
+        walk_blocks_mut(&mut Retarget(moved), blocks);
+    }
+    Ok(found)
+}
+
+/// Whether a link or image refers to a definition by its id.
+fn is_reference(inl: &Inline) -> bool {
+    match inl {
+        Inline::Link { link_type, id, .. } | Inline::Image { link_type, id, .. } => {
+            matches!(
+                link_type,
+                LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut
+            ) && !id.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// The MIME type of an image, guessed from the extension of its path.
+fn image_mime(dest: &str) -> &'static str {
+    let path = dest.split(['?', '#']).next().unwrap_or(dest);
+    let ext = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    match ext.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,277 @@
+//! Checking a block tree for broken internal structure.
+//!
+//! Hand-built or transformed trees can end up with pieces that do not fit
+//! together: a footnote reference without its definition, two headings
+//! with the same anchor, a table row with a cell too many. The writer renders
+//! them all the same, so `validate` reports them as `Issue`s, each with the
+//! path of the block it was found in (as `Cursor::path` returns it).
+
+use crate::ast::slug::Slugger;
+use crate::ast::visit::{Visitor, walk_block, walk_inline};
+use crate::ast::{Block, Inline, label_key};
+use pulldown_cmark::LinkType;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// A footnote reference whose label has no definition.
+    MissingFootnote { label: String, path: Vec<usize> },
+    /// A footnote definition nothing refers to.
+    UnusedFootnote { label: String, path: Vec<usize> },
+    /// A heading anchor already used by an earlier heading. The anchor is
+    /// the heading's id, or else its slug as `outline` gives it, so an
+    /// explicit id can collide with a slug generated for another heading.
+    DuplicateHeadingId { id: String, path: Vec<usize> },
+    /// A reference link or image whose id has no `Block::LinkDefinition`
+    /// and which carries no destination of its own to write one from.
+    UnknownReference { id: String, path: Vec<usize> },
+    /// A link, image or reference definition with an empty destination.
+    EmptyDestination { path: Vec<usize> },
+    /// A table row whose cell count differs from the table's column count.
+    /// Row 0 is the header row.
+    TableColumns {
+        path: Vec<usize>,
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Issue {
+    /// Path of the block the issue was found in.
+    pub fn path(&self) -> &[usize] {
+        match self {
+            Issue::MissingFootnote { path, .. }
+            | Issue::UnusedFootnote { path, .. }
+            | Issue::DuplicateHeadingId { path, .. }
+            | Issue::UnknownReference { path, .. }
+            | Issue::EmptyDestination { path }
+            | Issue::TableColumns { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::MissingFootnote { label, path } => {
+                write!(f, "{:?}: footnote [^{}] is not defined", path, label)
+            }
+            Issue::UnusedFootnote { label, path } => {
+                write!(f, "{:?}: footnote [^{}] is never referenced", path, label)
+            }
+            Issue::DuplicateHeadingId { id, path } => {
+                write!(f, "{:?}: heading id {:?} is already used", path, id)
+            }
+            Issue::UnknownReference { id, path } => {
+                write!(f, "{:?}: reference [{}] is not defined", path, id)
+            }
+            Issue::EmptyDestination { path } => write!(f, "{:?}: empty link destination", path),
+            Issue::TableColumns {
+                path,
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{:?}: table row {} has {} cells, expected {}",
+                path, row, found, expected
+            ),
+        }
+    }
+}
+
+/// Something the checks look at, found at a path.
+enum Fact {
+    FootnoteReference(String),
+    FootnoteDefinition(String),
+    HeadingAnchor(String),
+    Link {
+        reference: bool,
+        id: String,
+        empty: bool,
+    },
+    LinkDefinition {
+        id: String,
+        empty: bool,
+    },
+    TableColumns {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+#[derive(Default)]
+struct Collect {
+    slugger: Slugger,
+    path: Vec<usize>,
+    facts: Vec<(Vec<usize>, Fact)>,
+}
+
+impl Collect {
+    fn push(&mut self, fact: Fact) {
+        self.facts.push((self.path.clone(), fact));
+    }
+
+    fn blocks(&mut self, blocks: &[Block]) {
+        for (i, b) in blocks.iter().enumerate() {
+            self.path.push(i);
+            match b {
+                Block::FootnoteDefinition(label, children) => {
+                    self.push(Fact::FootnoteDefinition(label.clone()));
+                    self.blocks(children);
+                }
+                Block::BlockQuote(children)
+                | Block::Alert { children, .. }
+                | Block::Item(children) => self.blocks(children),
+                Block::List { items, .. } => {
+                    for (j, item) in items.iter().enumerate() {
+                        self.path.push(j);
+                        self.blocks(item);
+                        self.path.pop();
+                    }
+                }
+                _ => self.visit_block(b),
+            }
+            self.path.pop();
+        }
+    }
+}
+
+impl Visitor for Collect {
+    fn visit_block(&mut self, b: &Block) {
+        match b {
+            Block::Heading { id, children, .. } => {
+                // every heading takes a slug, as in `outline`
+                let slug = self.slugger.slug(children);
+                self.push(Fact::HeadingAnchor(id.clone().unwrap_or(slug)));
+            }
+            Block::LinkDefinition { id, dest, .. } => self.push(Fact::LinkDefinition {
+                id: id.clone(),
+                empty: dest.is_empty(),
+            }),
+            Block::Table {
+                aligns, head, rows, ..
+            } => {
+                let expected = aligns.len();
+                // an empty header row is allowed: the writer fills it in
+                let head = (!head.is_empty()).then_some(head);
+                for (row, cells) in head.into_iter().chain(rows).enumerate() {
+                    if cells.len() != expected {
+                        self.push(Fact::TableColumns {
+                            row: row + usize::from(head.is_none()),
+                            expected,
+                            found: cells.len(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_block(self, b);
+    }
+
+    fn visit_inline(&mut self, inl: &Inline) {
+        match inl {
+            Inline::FootnoteReference(label) => self.push(Fact::FootnoteReference(label.clone())),
+            Inline::Link {
+                link_type,
+                dest,
+                id,
+                ..
+            }
+            | Inline::Image {
+                link_type,
+                dest,
+                id,
+                ..
+            } => self.push(Fact::Link {
+                reference: matches!(
+                    link_type,
+                    LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut
+                ) && !id.is_empty(),
+                id: id.clone(),
+                empty: dest.is_empty(),
+            }),
+            _ => {}
+        }
+        walk_inline(self, inl);
+    }
+}
+
+/// Check `blocks` for broken internal structure, returning the issues in
+/// document order:
+///
+/// - footnote references without a definition, and definitions without a
+///   reference;
+/// - headings whose id, or generated slug, an earlier heading already has
+///   as its anchor;
+/// - reference links and images with neither a matching
+///   `Block::LinkDefinition` nor a destination;
+/// - links, images and reference definitions with an empty destination;
+/// - table rows with more or fewer cells than the table has columns.
+pub fn validate(blocks: &[Block]) -> Vec<Issue> {
+    let mut collect = Collect::default();
+    collect.blocks(blocks);
+    let facts = collect.facts;
+
+    let mut referenced = HashSet::new();
+    let mut defined = HashSet::new();
+    let mut definitions = HashSet::new();
+    for (_, fact) in &facts {
+        match fact {
+            Fact::FootnoteReference(label) => {
+                referenced.insert(label_key(label));
+            }
+            Fact::FootnoteDefinition(label) => {
+                defined.insert(label_key(label));
+            }
+            Fact::LinkDefinition { id, .. } => {
+                definitions.insert(label_key(id));
+            }
+            _ => {}
+        }
+    }
+
+    let mut anchors = HashSet::new();
+    let mut issues = Vec::new();
+    for (path, fact) in facts {
+        let issue = match fact {
+            Fact::FootnoteReference(label) if !defined.contains(&label_key(&label)) => {
+                Issue::MissingFootnote { label, path }
+            }
+            Fact::FootnoteDefinition(label) if !referenced.contains(&label_key(&label)) => {
+                Issue::UnusedFootnote { label, path }
+            }
+            Fact::HeadingAnchor(id) if !anchors.insert(id.clone()) => {
+                Issue::DuplicateHeadingId { id, path }
+            }
+            Fact::Link {
+                reference: true,
+                id,
+                empty: true,
+            } if !definitions.contains(&label_key(&id)) => Issue::UnknownReference { id, path },
+            Fact::Link {
+                reference: false,
+                empty: true,
+                ..
+            }
+            | Fact::LinkDefinition { empty: true, .. } => Issue::EmptyDestination { path },
+            Fact::TableColumns {
+                row,
+                expected,
+                found,
+            } => Issue::TableColumns {
+                path,
+                row,
+                expected,
+                found,
+            },
+            _ => continue,
+        };
+        issues.push(issue);
+    }
+    issues
+}
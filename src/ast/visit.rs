@@ -0,0 +1,299 @@
+//! Traversal of the owned AST.
+//!
+//! A `Visitor` overrides `visit_block` and/or `visit_inline` for the nodes
+//! it cares about; `walk_blocks` calls them for every node in document
+//! order, at any depth. An overriding method decides whether to descend by
+//! calling `walk_block` / `walk_inline` itself:
+//!
+//! ```ignore
+//! struct Links(Vec<String>);
+//! impl Visitor for Links {
+//!     fn visit_inline(&mut self, inl: &Inline) {
+//!         if let Inline::Link { dest, .. } = inl {
+//!             self.0.push(dest.clone());
+//!         }
+//!         walk_inline(self, inl);
+//!     }
+//! }
+//! let mut links = Links(Vec::new());
+//! walk_blocks(&mut links, &blocks);
+//! ```
+//!
+//! Custom nodes have no children of their own; the walk visits the blocks
+//! and inlines their events parse to instead.
+//!
+//! `VisitMut` is the in-place counterpart: `walk_blocks_mut` hands out
+//! `&mut Block` / `&mut Inline`, and each visit returns an `Edit` to keep,
+//! remove or replace the node among its siblings.
+
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::{Block, Inline};
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+/// A read-only pass over blocks and inlines. Both methods default to
+/// walking the children of the node.
+pub trait Visitor {
+    fn visit_block(&mut self, b: &Block) {
+        walk_block(self, b);
+    }
+
+    fn visit_inline(&mut self, inl: &Inline) {
+        walk_inline(self, inl);
+    }
+}
+
+/// Visit every block of `blocks`, in order.
+pub fn walk_blocks<V: Visitor + ?Sized>(v: &mut V, blocks: &[Block]) {
+    for b in blocks {
+        v.visit_block(b);
+    }
+}
+
+fn walk_inlines<V: Visitor + ?Sized>(v: &mut V, inlines: &[Inline]) {
+    for inl in inlines {
+        v.visit_inline(inl);
+    }
+}
+
+fn walk_cells<V: Visitor + ?Sized>(v: &mut V, cells: &[Vec<Inline>]) {
+    for cell in cells {
+        walk_inlines(v, cell);
+    }
+}
+
+/// Visit the children of `b`: the blocks of containers and list items,
+/// the inlines of paragraphs, headings and table cells.
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, b: &Block) {
+    match b {
+        Block::Paragraph(children) | Block::Heading { children, .. } => walk_inlines(v, children),
+        Block::BlockQuote(children)
+        | Block::Alert { children, .. }
+        | Block::Item(children)
+        | Block::FootnoteDefinition(_, children) => walk_blocks(v, children),
+        Block::List { items, .. } => {
+            for item in items {
+                walk_blocks(v, item);
+            }
+        }
+        Block::TableRow(cells) | Block::TableHead(cells) => walk_cells(v, cells),
+        Block::Table { head, rows, .. } => {
+            walk_cells(v, head);
+            for row in rows {
+                walk_cells(v, row);
+            }
+        }
+        Block::Custom(node) => walk_blocks(v, &parse_events_to_blocks(&node.to_events())),
+        Block::CodeBlock { .. }
+        | Block::HtmlBlock(_)
+        | Block::Rule
+        | Block::TablePlaceholder(_)
+        | Block::Metadata { .. }
+        | Block::Comment(_)
+        | Block::Raw(_)
+        | Block::LinkDefinition { .. } => {}
+    }
+}
+
+/// Visit the children of `inl`, for the inlines that have any.
+pub fn walk_inline<V: Visitor + ?Sized>(v: &mut V, inl: &Inline) {
+    match inl {
+        Inline::Emphasis(children)
+        | Inline::Strong(children)
+        | Inline::Strikethrough(children)
+        | Inline::Subscript(children)
+        | Inline::Superscript(children)
+        | Inline::Link { children, .. }
+        | Inline::Image { children, .. } => walk_inlines(v, children),
+        Inline::Custom(node) => {
+            // the inline events only form blocks inside a paragraph
+            let mut events = vec![Event::Start(Tag::Paragraph)];
+            events.extend(node.to_events());
+            events.push(Event::End(TagEnd::Paragraph));
+            if let [Block::Paragraph(children)] = parse_events_to_blocks(&events).as_slice() {
+                walk_inlines(v, children);
+            }
+        }
+        Inline::Text(_)
+        | Inline::Code(_)
+        | Inline::InlineHtml(_)
+        | Inline::Html(_)
+        | Inline::SoftBreak
+        | Inline::HardBreak
+        | Inline::FootnoteReference(_)
+        | Inline::InlineMath(_)
+        | Inline::DisplayMath(_)
+        | Inline::Comment(_)
+        | Inline::Raw(_) => {}
+    }
+}
+
+/// What to do with a node after `VisitMut` visited it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit<T> {
+    /// Keep the node, with any change made through the `&mut` reference.
+    Keep,
+    /// Remove the node.
+    Remove,
+    /// Put other nodes in its place: none removes it, several insert
+    /// siblings. Keep the node by including it, e.g. `vec![new, old]`.
+    Replace(Vec<T>),
+}
+
+/// An in-place pass over blocks and inlines. Each node can be changed
+/// through its reference, or replaced by any number of siblings through the
+/// returned `Edit`. Both methods default to walking the children of the
+/// node and keeping it.
+///
+/// Nodes are visited in document order. Nodes put in place by `Replace`
+/// are not visited. Custom nodes cannot be changed from the outside, so
+/// the walk does not enter them.
+pub trait VisitMut {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        walk_block_mut(self, b);
+        Edit::Keep
+    }
+
+    fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+        walk_inline_mut(self, inl);
+        Edit::Keep
+    }
+}
+
+/// Visit every node of `nodes` and apply the edits, in order.
+fn edit_each<T>(nodes: &mut Vec<T>, mut visit: impl FnMut(&mut T) -> Edit<T>) {
+    let mut i = 0;
+    while i < nodes.len() {
+        match visit(&mut nodes[i]) {
+            Edit::Keep => i += 1,
+            Edit::Remove => {
+                nodes.remove(i);
+            }
+            Edit::Replace(with) => {
+                let n = with.len();
+                nodes.splice(i..=i, with);
+                i += n;
+            }
+        }
+    }
+}
+
+/// Visit every block of `blocks` and apply the returned edits.
+pub fn walk_blocks_mut<V: VisitMut + ?Sized>(v: &mut V, blocks: &mut Vec<Block>) {
+    edit_each(blocks, |b| v.visit_block_mut(b));
+}
+
+fn walk_inlines_mut<V: VisitMut + ?Sized>(v: &mut V, inlines: &mut Vec<Inline>) {
+    edit_each(inlines, |inl| v.visit_inline_mut(inl));
+}
+
+fn walk_cells_mut<V: VisitMut + ?Sized>(v: &mut V, cells: &mut [Vec<Inline>]) {
+    for cell in cells {
+        walk_inlines_mut(v, cell);
+    }
+}
+
+/// Visit the children of `b`, like `walk_block`, applying their edits.
+pub fn walk_block_mut<V: VisitMut + ?Sized>(v: &mut V, b: &mut Block) {
+    match b {
+        Block::Paragraph(children) | Block::Heading { children, .. } => {
+            walk_inlines_mut(v, children)
+        }
+        Block::BlockQuote(children)
+        | Block::Alert { children, .. }
+        | Block::Item(children)
+        | Block::FootnoteDefinition(_, children) => walk_blocks_mut(v, children),
+        Block::List { items, .. } => {
+            for item in items {
+                walk_blocks_mut(v, item);
+            }
+        }
+        Block::TableRow(cells) | Block::TableHead(cells) => walk_cells_mut(v, cells),
+        Block::Table { head, rows, .. } => {
+            walk_cells_mut(v, head);
+            for row in rows {
+                walk_cells_mut(v, row);
+            }
+        }
+        Block::Custom(_)
+        | Block::CodeBlock { .. }
+        | Block::HtmlBlock(_)
+        | Block::Rule
+        | Block::TablePlaceholder(_)
+        | Block::Metadata { .. }
+        | Block::Comment(_)
+        | Block::Raw(_)
+        | Block::LinkDefinition { .. } => {}
+    }
+}
+
+/// Visit the children of `inl`, like `walk_inline`, applying their edits.
+pub fn walk_inline_mut<V: VisitMut + ?Sized>(v: &mut V, inl: &mut Inline) {
+    match inl {
+        Inline::Emphasis(children)
+        | Inline::Strong(children)
+        | Inline::Strikethrough(children)
+        | Inline::Subscript(children)
+        | Inline::Superscript(children)
+        | Inline::Link { children, .. }
+        | Inline::Image { children, .. } => walk_inlines_mut(v, children),
+        Inline::Custom(_)
+        | Inline::Text(_)
+        | Inline::Code(_)
+        | Inline::InlineHtml(_)
+        | Inline::Html(_)
+        | Inline::SoftBreak
+        | Inline::HardBreak
+        | Inline::FootnoteReference(_)
+        | Inline::InlineMath(_)
+        | Inline::DisplayMath(_)
+        | Inline::Comment(_)
+        | Inline::Raw(_) => {}
+    }
+}
+
+struct BlockFn<F>(F);
+
+impl<F: FnMut(&Block) -> Option<Vec<Block>>> VisitMut for BlockFn<F> {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        match (self.0)(b) {
+            Some(with) => Edit::Replace(with),
+            None => {
+                walk_block_mut(self, b);
+                Edit::Keep
+            }
+        }
+    }
+}
+
+struct InlineFn<F>(F);
+
+impl<F: FnMut(&Inline) -> Option<Vec<Inline>>> VisitMut for InlineFn<F> {
+    fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+        match (self.0)(inl) {
+            Some(with) => Edit::Replace(with),
+            None => {
+                walk_inline_mut(self, inl);
+                Edit::Keep
+            }
+        }
+    }
+}
+
+/// Replace blocks at any depth: `f` returns the blocks to put in place of
+/// a block (none to drop it), or `None` to keep it and transform its
+/// children. Replacements are not transformed again.
+///
+/// ```ignore
+/// transform_blocks(&mut blocks, |b| match b {
+///     Block::HtmlBlock(_) => Some(vec![]),
+///     _ => None,
+/// });
+/// ```
+pub fn transform_blocks(blocks: &mut Vec<Block>, f: impl FnMut(&Block) -> Option<Vec<Block>>) {
+    walk_blocks_mut(&mut BlockFn(f), blocks);
+}
+
+/// Replace the inlines of `blocks` at any depth, like `transform_blocks`.
+pub fn transform_inlines(blocks: &mut Vec<Block>, f: impl FnMut(&Inline) -> Option<Vec<Inline>>) {
+    walk_blocks_mut(&mut InlineFn(f), blocks);
+}
@@ -0,0 +1,270 @@
+//! Pluggable output formats.
+//!
+//! A `Backend` renders each kind of node from the already rendered output
+//! of its children; `render_with` walks the AST and feeds it bottom-up. A new
+//! format (Typst, org, a custom HTML flavour, ...) only has to say how one
+//! node of each kind looks:
+//!
+//! ```ignore
+//! struct Org;
+//! impl Backend for Org {
+//!     type Output = String;
+//!     fn heading(&mut self, level: HeadingLevel, _id: Option<&str>, content: String) -> String {
+//!         format!("{} {content}", "*".repeat(level as usize))
+//!     }
+//!     // ...
+//! }
+//! let org = render_with(&mut Org, &blocks);
+//! ```
+//!
+//! Methods for constructs that most formats render alike, or not at all,
+//! have defaults: alerts render as block quotes, front matter, comments and
+//! link definitions render as nothing, and custom nodes are rendered from
+//! their events.
+
+use crate::ast::custom::{BlockNode, InlineNode};
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::{Block, Inline};
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, Event, HeadingLevel, MetadataBlockKind, Tag, TagEnd,
+};
+
+/// An output format. Every method returns the output for one node, given
+/// the output of its children.
+pub trait Backend {
+    /// What nodes render to, e.g. `String`.
+    type Output;
+
+    /// Combine the outputs of consecutive blocks.
+    fn blocks(&mut self, blocks: Vec<Self::Output>) -> Self::Output;
+    /// Combine the outputs of consecutive inlines.
+    fn inlines(&mut self, inlines: Vec<Self::Output>) -> Self::Output;
+
+    fn paragraph(&mut self, content: Self::Output) -> Self::Output;
+    fn heading(
+        &mut self,
+        level: HeadingLevel,
+        id: Option<&str>,
+        content: Self::Output,
+    ) -> Self::Output;
+    fn blockquote(&mut self, content: Self::Output) -> Self::Output;
+    fn alert(&mut self, _kind: BlockQuoteKind, content: Self::Output) -> Self::Output {
+        self.blockquote(content)
+    }
+    /// A code block; `info` is the info string of a fenced block.
+    fn code_block(&mut self, info: Option<&str>, content: &str) -> Self::Output;
+    fn html_block(&mut self, html: &str) -> Self::Output;
+    /// A list; each item is the combined output of its blocks.
+    fn list(&mut self, start: Option<u64>, items: Vec<Self::Output>) -> Self::Output;
+    fn rule(&mut self) -> Self::Output;
+    fn footnote_definition(&mut self, label: &str, content: Self::Output) -> Self::Output;
+    /// A table: one output per header cell and per body cell.
+    fn table(
+        &mut self,
+        aligns: &[Alignment],
+        head: Vec<Self::Output>,
+        rows: Vec<Vec<Self::Output>>,
+    ) -> Self::Output;
+    fn metadata(&mut self, _kind: MetadataBlockKind, _content: &str) -> Self::Output {
+        self.blocks(Vec::new())
+    }
+    fn comment_block(&mut self, _text: &str) -> Self::Output {
+        self.blocks(Vec::new())
+    }
+    /// Markdown kept verbatim (`Block::Raw`). Rendered as a code block
+    /// without info string by default.
+    fn raw_block(&mut self, text: &str) -> Self::Output {
+        self.code_block(None, text)
+    }
+    fn link_definition(&mut self, _id: &str, _dest: &str, _title: &str) -> Self::Output {
+        self.blocks(Vec::new())
+    }
+    /// A custom block. Returning `None`, the default, renders the blocks
+    /// described by its events instead.
+    fn custom_block(&mut self, _node: &dyn BlockNode) -> Option<Self::Output> {
+        None
+    }
+
+    fn text(&mut self, text: &str) -> Self::Output;
+    fn code(&mut self, code: &str) -> Self::Output;
+    fn inline_html(&mut self, html: &str) -> Self::Output;
+    fn soft_break(&mut self) -> Self::Output;
+    fn hard_break(&mut self) -> Self::Output;
+    fn emphasis(&mut self, content: Self::Output) -> Self::Output;
+    fn strong(&mut self, content: Self::Output) -> Self::Output;
+    fn strikethrough(&mut self, content: Self::Output) -> Self::Output;
+    fn subscript(&mut self, content: Self::Output) -> Self::Output {
+        content
+    }
+    fn superscript(&mut self, content: Self::Output) -> Self::Output {
+        content
+    }
+    fn link(&mut self, dest: &str, title: &str, content: Self::Output) -> Self::Output;
+    /// An image; `alt` is the output of its description.
+    fn image(&mut self, dest: &str, title: &str, alt: Self::Output) -> Self::Output;
+    fn footnote_reference(&mut self, label: &str) -> Self::Output;
+    fn math(&mut self, _display: bool, text: &str) -> Self::Output {
+        self.code(text)
+    }
+    fn comment_inline(&mut self, _text: &str) -> Self::Output {
+        self.inlines(Vec::new())
+    }
+    /// Markdown kept verbatim (`Inline::Raw`). Rendered as text by default.
+    fn raw_inline(&mut self, text: &str) -> Self::Output {
+        self.text(text)
+    }
+    /// A custom inline, like `custom_block`.
+    fn custom_inline(&mut self, _node: &dyn InlineNode) -> Option<Self::Output> {
+        None
+    }
+}
+
+/// Render `blocks` with `backend`.
+pub fn render_with<B: Backend + ?Sized>(backend: &mut B, blocks: &[Block]) -> B::Output {
+    let out = blocks.iter().map(|b| render_block(backend, b)).collect();
+    backend.blocks(out)
+}
+
+fn render_inlines<B: Backend + ?Sized>(backend: &mut B, inlines: &[Inline]) -> B::Output {
+    let out = inlines.iter().map(|i| render_inline(backend, i)).collect();
+    backend.inlines(out)
+}
+
+fn render_row<B: Backend + ?Sized>(backend: &mut B, cells: &[Vec<Inline>]) -> Vec<B::Output> {
+    cells.iter().map(|c| render_inlines(backend, c)).collect()
+}
+
+fn render_block<B: Backend + ?Sized>(backend: &mut B, b: &Block) -> B::Output {
+    match b {
+        Block::Paragraph(children) => {
+            let content = render_inlines(backend, children);
+            backend.paragraph(content)
+        }
+        Block::Heading {
+            level,
+            id,
+            children,
+            ..
+        } => {
+            let content = render_inlines(backend, children);
+            backend.heading(*level, id.as_deref(), content)
+        }
+        Block::BlockQuote(children) => {
+            let content = render_with(backend, children);
+            backend.blockquote(content)
+        }
+        Block::Alert { kind, children } => {
+            let content = render_with(backend, children);
+            backend.alert(*kind, content)
+        }
+        Block::CodeBlock { kind, content } => {
+            let info = match kind {
+                CodeBlockKind::Fenced(info) => Some(info.as_ref()),
+                CodeBlockKind::Indented => None,
+            };
+            backend.code_block(info, &content.apply())
+        }
+        Block::HtmlBlock(html) => backend.html_block(&html.apply()),
+        Block::List { start, items } => {
+            let items = items
+                .iter()
+                .map(|item| render_with(backend, item))
+                .collect();
+            backend.list(*start, items)
+        }
+        Block::Item(children) => render_with(backend, children),
+        Block::Rule => backend.rule(),
+        Block::FootnoteDefinition(label, children) => {
+            let content = render_with(backend, children);
+            backend.footnote_definition(label, content)
+        }
+        Block::TablePlaceholder(aligns) => backend.table(aligns, Vec::new(), Vec::new()),
+        Block::TableHead(cells) => {
+            let head = render_row(backend, cells);
+            backend.table(&[], head, Vec::new())
+        }
+        Block::TableRow(cells) => {
+            let row = render_row(backend, cells);
+            backend.table(&[], Vec::new(), vec![row])
+        }
+        Block::Table { aligns, head, rows } => {
+            let head = render_row(backend, head);
+            let rows = rows.iter().map(|r| render_row(backend, r)).collect();
+            backend.table(aligns, head, rows)
+        }
+        Block::Metadata { kind, content } => backend.metadata(*kind, &content.apply()),
+        Block::Comment(text) => backend.comment_block(&text.apply()),
+        Block::Raw(text) => backend.raw_block(&text.apply()),
+        Block::LinkDefinition { id, dest, title } => backend.link_definition(id, dest, title),
+        Block::Custom(node) => match backend.custom_block(node.as_ref()) {
+            Some(out) => out,
+            None => render_with(backend, &parse_events_to_blocks(&node.to_events())),
+        },
+    }
+}
+
+fn render_inline<B: Backend + ?Sized>(backend: &mut B, inl: &Inline) -> B::Output {
+    match inl {
+        Inline::Text(text) => backend.text(&text.apply()),
+        Inline::Code(code) => backend.code(&code.apply()),
+        Inline::InlineHtml(html) | Inline::Html(html) => backend.inline_html(&html.apply()),
+        Inline::SoftBreak => backend.soft_break(),
+        Inline::HardBreak => backend.hard_break(),
+        Inline::Emphasis(children) => {
+            let content = render_inlines(backend, children);
+            backend.emphasis(content)
+        }
+        Inline::Strong(children) => {
+            let content = render_inlines(backend, children);
+            backend.strong(content)
+        }
+        Inline::Strikethrough(children) => {
+            let content = render_inlines(backend, children);
+            backend.strikethrough(content)
+        }
+        Inline::Subscript(children) => {
+            let content = render_inlines(backend, children);
+            backend.subscript(content)
+        }
+        Inline::Superscript(children) => {
+            let content = render_inlines(backend, children);
+            backend.superscript(content)
+        }
+        Inline::Link {
+            dest,
+            title,
+            children,
+            ..
+        } => {
+            let content = render_inlines(backend, children);
+            backend.link(dest, title, content)
+        }
+        Inline::Image {
+            dest,
+            title,
+            children,
+            ..
+        } => {
+            let alt = render_inlines(backend, children);
+            backend.image(dest, title, alt)
+        }
+        Inline::FootnoteReference(label) => backend.footnote_reference(label),
+        Inline::InlineMath(text) => backend.math(false, &text.apply()),
+        Inline::DisplayMath(text) => backend.math(true, &text.apply()),
+        Inline::Comment(text) => backend.comment_inline(&text.apply()),
+        Inline::Raw(text) => backend.raw_inline(&text.apply()),
+        Inline::Custom(node) => match backend.custom_inline(node.as_ref()) {
+            Some(out) => out,
+            None => {
+                // the inline events only form blocks inside a paragraph
+                let mut events = vec![Event::Start(Tag::Paragraph)];
+                events.extend(node.to_events());
+                events.push(Event::End(TagEnd::Paragraph));
+                match parse_events_to_blocks(&events).as_slice() {
+                    [Block::Paragraph(children)] => render_inlines(backend, children),
+                    _ => backend.inlines(Vec::new()),
+                }
+            }
+        },
+    }
+}
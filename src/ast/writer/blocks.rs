@@ -1,13 +1,21 @@
-use crate::ast::{Block, Inline};
-use crate::text::{Line, Region};
-use pulldown_cmark::{Alignment as PAlign, CodeBlockKind, HeadingLevel};
+use crate::ast::custom::BlockNode;
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::{Block, Inline, label_key};
+use crate::text::{Fragment, Line, Region};
+use pulldown_cmark::{
+    Alignment as PAlign, BlockQuoteKind, CodeBlockKind, HeadingLevel, MetadataBlockKind,
+};
 
-use super::inline::inline_to_line;
+use super::inline::{ReferenceDef, render_inline};
 use super::utils::pad_to_width;
+use super::{ListLevel, WriteContext, WriterOptions};
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::{fmt, io};
 
-fn render_paragraph(p: &Vec<Inline>) -> Region {
+fn render_paragraph(p: &[Inline], ctx: &WriteContext) -> Region {
     let mut r = Region::new();
-    let mut defs: Vec<super::inline::ReferenceDef> = Vec::new();
+    let mut defs: Vec<ReferenceDef> = Vec::new();
     let mut curr = Line::new();
     for inl in p {
         match inl {
@@ -18,17 +26,17 @@ fn render_paragraph(p: &Vec<Inline>) -> Region {
             Inline::HardBreak => {
                 // Represent hard break by ending the current line with two
                 // spaces and starting a new line (stay within same paragraph).
-                curr.push("  ");
+                curr.push(Fragment::from_static("  "));
                 r.push_back_line(curr);
                 curr = Line::new();
             }
             _ => {
-                let (ln, def) = inline_to_line(inl);
+                let (ln, def) = render_inline(inl, ctx);
                 let tmp = ln;
-                if let Some(def) = def {
-                    if !defs.iter().any(|d| d.id == def.id) {
-                        defs.push(def);
-                    }
+                if let Some(def) = def
+                    && !defs.iter().any(|d| d.id == def.id)
+                {
+                    defs.push(def);
                 }
                 let s = tmp.apply();
                 let mut parts = s.split('\n').peekable();
@@ -45,23 +53,131 @@ fn render_paragraph(p: &Vec<Inline>) -> Region {
         }
     }
     r.push_back_line(curr);
+    // definitions the document holds as blocks are written there
+    if !ctx.defined.is_empty() {
+        defs.retain(|def| !ctx.defined.contains(&label_key(&def.id)));
+    }
+    if let Some(written) = ctx.written {
+        written.borrow_mut().claim(&mut defs);
+    }
     if !defs.is_empty() && !r.is_empty() {
         r.push_back_line(Line::from_str(""));
     }
     for def in defs {
-        if def.title.is_empty() {
-            r.push_back_suffix_line(Line::from_str(&format!("[{}]: {}", def.id, def.dest)));
-        } else {
-            r.push_back_suffix_line(Line::from_str(&format!(
-                "[{}]: {} \"{}\"",
-                def.id, def.dest, def.title
-            )));
-        }
+        r.push_back_suffix_line(render_link_definition(&def.id, &def.dest, &def.title));
     }
     r
 }
 
-fn render_heading(level: &HeadingLevel, content: &Vec<Inline>) -> Region {
+/// The reference definitions a write has written so far, shared by the
+/// paragraphs it renders so each definition is written once, after the
+/// first paragraph using it.
+#[derive(Debug, Default)]
+pub(crate) struct WrittenDefinitions {
+    /// The label keys of the ids written.
+    ids: Vec<String>,
+    /// Set for a `MarkdownWriter`: the definitions are collected here for
+    /// it to write at the end, rather than after their paragraph.
+    deferred: Option<Vec<ReferenceDef>>,
+}
+
+impl WrittenDefinitions {
+    /// Definitions collected rather than written.
+    pub(super) fn deferred() -> Self {
+        WrittenDefinitions {
+            ids: Vec::new(),
+            deferred: Some(Vec::new()),
+        }
+    }
+
+    /// Drop from `defs` the definitions already written and record the
+    /// others, which move to `deferred` if it is set.
+    fn claim(&mut self, defs: &mut Vec<ReferenceDef>) {
+        defs.retain(|def| {
+            let id = label_key(&def.id);
+            let new = !self.ids.contains(&id);
+            if new {
+                self.ids.push(id);
+            }
+            new
+        });
+        if let Some(deferred) = self.deferred.as_mut() {
+            deferred.append(defs);
+        }
+    }
+
+    /// The definitions collected so far.
+    pub(super) fn pending(&self) -> &[ReferenceDef] {
+        self.deferred.as_deref().unwrap_or_default()
+    }
+
+    /// Take the definitions collected so far.
+    pub(super) fn take_pending(&mut self) -> Vec<ReferenceDef> {
+        self.deferred
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+/// The label keys of the `LinkDefinition` blocks among `blocks`, which
+/// write those definitions in place of the paragraphs referencing them.
+pub(super) fn defined_ids(blocks: &[Block]) -> Vec<String> {
+    blocks
+        .iter()
+        .filter_map(|b| match b {
+            Block::LinkDefinition { id, .. } => Some(label_key(id)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render `blocks` like `blocks_to_markdown`, but write each reference
+/// definition only after the first paragraph using its id.
+pub(crate) fn blocks_to_markdown_with_unique_definitions(
+    blocks: &[Block],
+    options: &WriterOptions,
+) -> String {
+    let defined = defined_ids(blocks);
+    let written = RefCell::new(WrittenDefinitions::default());
+    let ctx = WriteContext {
+        defined: &defined,
+        written: Some(&written),
+        ..WriteContext::new(options)
+    };
+    let mut out = String::new();
+    write_blocks_in(&mut out, blocks, &ctx).expect("writing to a String cannot fail");
+    out
+}
+
+pub(crate) fn render_link_definition(id: &str, dest: &str, title: &str) -> Line {
+    // writing to a line cannot fail
+    let mut line = Line::new();
+    if title.is_empty() {
+        let _ = write!(line, "[{}]: {}", id, dest);
+    } else {
+        let _ = write!(line, "[{}]: {} \"{}\"", id, dest, title);
+    }
+    line
+}
+
+/// The `{#id .class key=value}` block closing a heading with attributes.
+fn heading_attributes(
+    id: &Option<String>,
+    classes: &[String],
+    attrs: &[(String, Option<String>)],
+) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    parts.extend(id.iter().map(|id| format!("#{}", id)));
+    parts.extend(classes.iter().map(|c| format!(".{}", c)));
+    parts.extend(attrs.iter().map(|(k, v)| match v {
+        Some(v) => format!("{}={}", k, v),
+        None => k.clone(),
+    }));
+    (!parts.is_empty()).then(|| format!("{{{}}}", parts.join(" ")))
+}
+
+fn render_heading(level: &HeadingLevel, content: &[Inline], ctx: &WriteContext) -> Region {
     let mut r = Region::new();
     let mut l = Line::new();
     let n = match level {
@@ -72,22 +188,25 @@ fn render_heading(level: &HeadingLevel, content: &Vec<Inline>) -> Region {
         HeadingLevel::H5 => 5usize,
         HeadingLevel::H6 => 6usize,
     };
-    l.push(std::iter::repeat('#').take(n).collect::<String>());
-    l.push(" ");
+    l.push("#".repeat(n));
+    l.push(Fragment::from_static(" "));
     for inl in content {
-        let (ln, _def) = inline_to_line(inl);
+        let (ln, _def) = render_inline(inl, ctx);
         l.extend_from_line(&ln);
     }
     r.push_back_line(l);
     r
 }
 
-fn render_codeblock(kind: &CodeBlockKind<'static>, content: &Region) -> Region {
+fn render_codeblock(kind: &CodeBlockKind<'static>, content: &Region, ctx: &WriteContext) -> Region {
     let mut r = Region::new();
+    let content_str = match ctx.options.code_tab_width {
+        Some(width) => content.clone().expand_tabs(width).apply(),
+        None => content.apply(),
+    };
     match kind {
         CodeBlockKind::Fenced(s) => {
             let lang = s.as_ref();
-            let content_str = content.apply();
             let mut max_ticks = 0usize;
             let mut cur = 0usize;
             for ch in content_str.chars() {
@@ -109,18 +228,16 @@ fn render_codeblock(kind: &CodeBlockKind<'static>, content: &Region) -> Region {
             r.push_back_line(Line::from_str(&"`".repeat(ticks)));
         }
         CodeBlockKind::Indented => {
-            let content_str = content.apply();
             let mut inner = Region::from_str(&content_str);
             inner.indent_each_line(4);
-            for l in inner.into_lines() {
-                r.push_back_line(l);
-            }
+            r.append(inner);
         }
     }
     r
 }
 
-fn render_blockquote(children: &Vec<Block>) -> Region {
+/// Blocks rendered one after the other in `ctx`, separated by a blank line.
+fn render_children(children: &[Block], ctx: &WriteContext) -> Region {
     let mut inner = Region::new();
     let mut first = true;
     for b in children {
@@ -128,74 +245,104 @@ fn render_blockquote(children: &Vec<Block>) -> Region {
             inner.push_back_line(Line::from_str(""));
         }
         first = false;
-        let br = block_to_region(b);
-        for l in br.into_lines() {
-            inner.push_back_line(l);
-        }
+        inner.append(render_block(b, ctx));
     }
+    inner
+}
+
+fn render_blockquote(children: &[Block], ctx: &WriteContext) -> Region {
+    let mut inner = render_children(children, &ctx.quoted());
     if inner.is_empty() {
         return Region::new();
     }
-    inner.prefix_each_line("> ");
+    inner.prefix_each_line_with(Fragment::from_static("> "), Fragment::from_static(">"));
     inner
 }
 
-fn render_list(ordered: bool, start: Option<u64>, items: &Vec<Vec<Block>>) -> Region {
+/// A custom block's own rendering, or the rendering of its events when
+/// `to_region` comes back empty.
+fn render_custom(node: &dyn BlockNode, ctx: &WriteContext) -> Region {
+    if let Some(r) = ctx.options.extensions.write_markdown(node, ctx) {
+        return r;
+    }
+    let r = node.to_region_in(ctx);
+    if !r.is_empty() {
+        return r;
+    }
+    let mut r = Region::new();
+    for b in parse_events_to_blocks(&node.to_events()) {
+        if !r.is_empty() {
+            r.push_back_line(Line::from_str(""));
+        }
+        r.append(render_block(&b, ctx));
+    }
+    r
+}
+
+/// The label of an alert kind, as in `> [!NOTE]`.
+fn alert_label(kind: &BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "NOTE",
+        BlockQuoteKind::Tip => "TIP",
+        BlockQuoteKind::Important => "IMPORTANT",
+        BlockQuoteKind::Warning => "WARNING",
+        BlockQuoteKind::Caution => "CAUTION",
+    }
+}
+
+fn render_alert(kind: &BlockQuoteKind, children: &[Block], ctx: &WriteContext) -> Region {
+    let mut r = Region::new();
+    let _ = write!(r, "> [!{}]", alert_label(kind));
+    r.append(render_blockquote(children, ctx));
+    r
+}
+
+fn render_list(
+    ordered: bool,
+    start: Option<u64>,
+    items: &[Vec<Block>],
+    ctx: &WriteContext,
+) -> Region {
     let mut r = Region::new();
     for (i, item) in items.iter().enumerate() {
+        let ctx = ctx.item(ListLevel {
+            start: if ordered { start } else { None },
+            index: i,
+        });
         let marker = if ordered {
             let n = start.unwrap_or(1) + (i as u64);
-            format!("{}. ", n)
+            Fragment::from_string(format!("{}. ", n))
         } else {
-            "- ".to_string()
+            Fragment::from_static("- ")
         };
 
-        // merge consecutive paragraphs inside the item
-        let mut merged: Vec<Block> = Vec::new();
-        for ch in item {
-            if let Some(Block::Paragraph(prev)) = merged.last_mut() {
-                match ch {
-                    Block::Paragraph(inls) => {
-                        prev.extend(inls.clone());
-                        continue;
-                    }
-                    _ => {}
-                }
-            }
-            merged.push(ch.clone());
-        }
-
         let mut item_region = Region::new();
-        let mut first = true;
-        for ch in &merged {
-            if !first {
+        for (j, ch) in item.iter().enumerate() {
+            if j > 0 {
                 item_region.push_back_line(Line::from_str(""));
             }
-            first = false;
-            let br = block_to_region(ch);
-            for l in br.into_lines() {
-                item_region.push_back_line(l);
-            }
+            item_region.append(render_block(ch, &ctx));
         }
 
         if item_region.is_empty() {
             // if first block is nested list, skip placeholder
-            let mut first_is_list = false;
-            if let Some(first_block) = item.get(0) {
-                if let Block::List { .. } = first_block {
-                    first_is_list = true;
-                }
-            }
+            let first_is_list = matches!(item.first(), Some(Block::List { .. }));
             if !first_is_list {
                 item_region.push_back_line(Line::from_str(""));
             }
         }
 
-        item_region.prefix_first_then_indent_rest(marker.as_str());
-        for l in item_region.into_lines() {
-            r.push_back_line(l);
+        // the marker goes on the first line; continuation lines are
+        // indented to line up with it, except blank ones
+        let mut lines = item_region.into_lines().into_iter();
+        if let Some(mut head) = lines.next() {
+            let indent = Fragment::spaces(marker.len());
+            head.prepend(marker);
+            let mut rest = Region::from(lines.collect::<Vec<_>>());
+            rest.prefix_each_line_with(indent, Fragment::from_static(""));
+            r.push_back_line(head);
+            r.append(rest);
         }
-        // r.push_back_line(Line::from_str(""));
     }
     r
 }
@@ -206,54 +353,72 @@ fn render_rule() -> Region {
     r
 }
 
-fn render_footnote_def(id: &str, children: &Vec<Block>) -> Region {
+fn render_footnote_def(id: &str, children: &[Block], ctx: &WriteContext) -> Region {
     let mut r = Region::new();
-    let mut inner = Region::new();
-    let mut first = true;
-    for b in children {
-        if !first {
-            inner.push_back_line(Line::from_str(""));
-        }
-        first = false;
-        let br = block_to_region(b);
-        for l in br.into_lines() {
-            inner.push_back_line(l);
-        }
+    let inner = render_children(children, &ctx.nested());
+    // the label takes the place of the first line's indentation; every
+    // following line (suffix lines included) is indented by four spaces
+    let mut lines = inner.into_lines().into_iter();
+    let mut head = Line::new();
+    let _ = write!(head, "[^{}]: ", id);
+    if let Some(l0) = lines.next() {
+        head.extend_from_line(&l0);
     }
-    inner.indent_each_line(4);
-    let lines = inner.into_lines();
-    if let Some(l0) = lines.get(0) {
-        let mut head = Line::from_str(&format!("[^{}]: ", id));
-        head.push(l0.apply());
-        r.push_back_line(head);
+    r.push_back_line(head);
+    let indent = Fragment::spaces(4);
+    for mut ln in lines {
+        ln.prepend(indent.clone());
+        r.push_back_line(ln);
     }
-    for ln in lines.iter().skip(1) {
-        r.push_back_line(ln.clone());
+    r
+}
+
+fn render_metadata(kind: &MetadataBlockKind, content: &Region) -> Region {
+    let fence = match kind {
+        MetadataBlockKind::YamlStyle => "---",
+        MetadataBlockKind::PlusesStyle => "+++",
+    };
+    let mut r = Region::new();
+    r.push_back_line(Line::from_str(fence));
+    for l in content.iter_lines() {
+        r.push_back_line(l.clone());
     }
+    r.push_back_line(Line::from_str(fence));
     r
 }
 
-fn cell_to_lines(cell: &Vec<Inline>) -> Vec<String> {
+fn cell_to_lines(cell: &[Inline], ctx: &WriteContext) -> Vec<String> {
+    let ctx = &ctx.cell();
     let mut l = Line::new();
     for inl in cell {
-        let (ln, _def) = inline_to_line(inl);
+        let (ln, _def) = render_inline(inl, ctx);
         l.extend_from_line(&ln);
     }
     l.apply().split('\n').map(|s| s.to_string()).collect()
 }
 
-fn render_table_full(aligns: &Vec<PAlign>, rows: &Vec<Vec<Vec<Inline>>>) -> Region {
+fn render_table_full(
+    aligns: &[PAlign],
+    head: &[Vec<Inline>],
+    rows: &[Vec<Vec<Inline>>],
+    ctx: &WriteContext,
+) -> Region {
     let cols = aligns
         .len()
+        .max(head.len())
         .max(rows.iter().map(|r| r.len()).max().unwrap_or(0));
+    if cols == 0 {
+        return Region::new();
+    }
 
-    // build cells_text[row_idx][col_idx] -> Vec<String>
+    // build cells_text[row_idx][col_idx] -> Vec<String>, header first; a
+    // missing header is written as empty cells
     let mut cells_text: Vec<Vec<Vec<String>>> = Vec::new();
-    for r in rows {
+    for r in std::iter::once(head).chain(rows.iter().map(Vec::as_slice)) {
         let mut row_cells: Vec<Vec<String>> = Vec::new();
         for c in 0..cols {
             if let Some(cell) = r.get(c) {
-                row_cells.push(cell_to_lines(cell));
+                row_cells.push(cell_to_lines(cell, ctx));
             } else {
                 row_cells.push(vec![String::new()]);
             }
@@ -261,7 +426,18 @@ fn render_table_full(aligns: &Vec<PAlign>, rows: &Vec<Vec<Vec<Inline>>>) -> Regi
         cells_text.push(row_cells);
     }
 
-    let mut col_widths = vec![0usize; cols];
+    if ctx.options.grid_tables && cells_text.iter().flatten().any(|c| c.len() > 1) {
+        return render_grid_table(aligns, &cells_text, cols);
+    }
+
+    // at least one dash per column in the separator, next to its colons
+    let mut col_widths: Vec<usize> = (0..cols)
+        .map(|c| match aligns.get(c) {
+            Some(PAlign::Left | PAlign::Right) => 2,
+            Some(PAlign::Center) => 3,
+            _ => 1,
+        })
+        .collect();
     for row in &cells_text {
         for (ci, cell_lines) in row.iter().enumerate() {
             for line in cell_lines {
@@ -271,6 +447,17 @@ fn render_table_full(aligns: &Vec<PAlign>, rows: &Vec<Vec<Vec<Inline>>>) -> Regi
         }
     }
 
+    // without outer pipes, a blank header row or a single column would not
+    // be recognised as a table
+    let framed = cols == 1 || cells_text[0].iter().all(|c| c.concat().is_empty());
+    let frame = |mut line: Line| {
+        if framed {
+            line.prepend(Fragment::from_static("| "));
+            line.push(Fragment::from_static(" |"));
+        }
+        line
+    };
+
     let mut reg = Region::new();
     if !cells_text.is_empty() {
         // header is first row
@@ -278,72 +465,197 @@ fn render_table_full(aligns: &Vec<PAlign>, rows: &Vec<Vec<Vec<Inline>>>) -> Regi
         let mut header_line = Line::new();
         for c in 0..cols {
             if c > 0 {
-                header_line.push(" | ");
+                header_line.push(Fragment::from_static(" | "));
             }
             let h = header[c].join("\n");
             header_line.push(pad_to_width(&h, col_widths[c], aligns.get(c)));
         }
-        reg.push_back_line(header_line);
+        reg.push_back_line(frame(header_line));
 
         // separator
         let mut sep = Line::new();
-        for c in 0..cols {
+        for (c, width) in col_widths.iter().copied().enumerate() {
             if c > 0 {
-                sep.push(" | ");
+                sep.push(Fragment::from_static(" | "));
             }
             match aligns.get(c) {
                 Some(PAlign::Left) => {
                     sep.push(pad_to_width(
-                        &format!(":{}", "-".repeat(col_widths[c].saturating_sub(1))),
-                        col_widths[c],
+                        &format!(":{}", "-".repeat(width.saturating_sub(1))),
+                        width,
                         None,
                     ));
                 }
                 Some(PAlign::Right) => {
                     sep.push(pad_to_width(
-                        &format!("{}:", "-".repeat(col_widths[c].saturating_sub(1))),
-                        col_widths[c],
+                        &format!("{}:", "-".repeat(width.saturating_sub(1))),
+                        width,
                         None,
                     ));
                 }
                 Some(PAlign::Center) => {
                     sep.push(pad_to_width(
-                        &format!(":{}:", "-".repeat(col_widths[c].saturating_sub(2))),
-                        col_widths[c],
+                        &format!(":{}:", "-".repeat(width.saturating_sub(2))),
+                        width,
                         None,
                     ));
                 }
                 _ => {
-                    sep.push("-".repeat(col_widths[c]));
+                    sep.push("-".repeat(width));
                 }
             };
         }
-        reg.push_back_line(sep);
+        reg.push_back_line(frame(sep));
 
         // body rows (skip header at idx 0)
-        for r_idx in 1..cells_text.len() {
+        for row in cells_text.iter().skip(1) {
             let mut line = Line::new();
             for c in 0..cols {
                 if c > 0 {
-                    line.push(" | ");
+                    line.push(Fragment::from_static(" | "));
                 }
-                let cell_text = cells_text[r_idx][c].join("\n");
+                let cell_text = row[c].join("\n");
                 line.push(pad_to_width(&cell_text, col_widths[c], aligns.get(c)));
             }
-            reg.push_back_line(line);
+            reg.push_back_line(frame(line));
+        }
+    }
+
+    reg
+}
+
+/// Write a table as a grid table, each cell spanning as many lines as its
+/// content:
+///
+/// ```text
+/// +-----+-------+
+/// | a   | b     |
+/// +=====+=======+
+/// | 1   | two\ |
+/// |     | lines |
+/// +-----+-------+
+/// ```
+///
+/// `cells_text` holds the lines of every cell, header row first. Pandoc
+/// reads grid tables; pulldown-cmark does not.
+fn render_grid_table(aligns: &[PAlign], cells_text: &[Vec<Vec<String>>], cols: usize) -> Region {
+    // inside a grid cell, trailing spaces are padding: write hard breaks
+    // with a backslash instead
+    let cells_text: Vec<Vec<Vec<String>>> = cells_text
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    let last = cell.len() - 1;
+                    cell.iter()
+                        .enumerate()
+                        .map(|(i, l)| match l.strip_suffix("  ") {
+                            Some(l) if i < last => format!("{}\\", l.trim_end()),
+                            _ => l.clone(),
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut col_widths = vec![1usize; cols];
+    for row in &cells_text {
+        for (ci, cell_lines) in row.iter().enumerate() {
+            for line in cell_lines {
+                col_widths[ci] =
+                    col_widths[ci].max(unicode_width::UnicodeWidthStr::width(line.as_str()));
+            }
         }
     }
 
+    let border = |fill: char, with_aligns: bool| {
+        let mut line = Line::from_str("+");
+        for (c, width) in col_widths.iter().copied().enumerate() {
+            let mut cell: Vec<char> = std::iter::repeat_n(fill, width + 2).collect();
+            match aligns.get(c).filter(|_| with_aligns) {
+                Some(PAlign::Left) => cell[0] = ':',
+                Some(PAlign::Right) => cell[width + 1] = ':',
+                Some(PAlign::Center) => {
+                    cell[0] = ':';
+                    cell[width + 1] = ':';
+                }
+                _ => {}
+            }
+            line.push(cell.into_iter().collect::<String>());
+            line.push(Fragment::from_static("+"));
+        }
+        line
+    };
+
+    // a blank header is left out; the top border then carries alignments
+    let headless = cells_text[0].iter().all(|c| c.concat().is_empty());
+    let mut reg = Region::new();
+    reg.push_back_line(border('-', headless));
+    for (ri, row) in cells_text.iter().enumerate().skip(usize::from(headless)) {
+        let height = row.iter().map(Vec::len).max().unwrap_or(1);
+        for li in 0..height {
+            let mut line = Line::from_str("|");
+            for (c, cell) in row.iter().enumerate() {
+                let text = cell.get(li).map_or("", String::as_str);
+                line.push(format!(
+                    " {} |",
+                    pad_to_width(text, col_widths[c], aligns.get(c))
+                ));
+            }
+            reg.push_back_line(line);
+        }
+        // the header row is closed with `=`, which also carries alignments
+        reg.push_back_line(if ri == 0 {
+            border('=', true)
+        } else {
+            border('-', false)
+        });
+    }
     reg
 }
 
 pub fn block_to_region(b: &Block) -> Region {
+    render_block(b, &WriteContext::new(&WriterOptions::default()))
+}
+
+/// Render `b` like `block_to_region`, leaving the definitions whose
+/// label keys are in `defined` to the document holding them.
+pub(crate) fn block_to_region_defining(b: &Block, defined: &[String]) -> Region {
+    let options = WriterOptions::default();
+    let ctx = WriteContext {
+        defined,
+        ..WriteContext::new(&options)
+    };
+    render_block(b, &ctx)
+}
+
+/// Render `b` in the given context, consulting the override hooks of its
+/// options first.
+pub(super) fn render_block(b: &Block, ctx: &WriteContext) -> Region {
+    for f in &ctx.options.block_overrides {
+        if let Some(r) = f(b, ctx) {
+            return r;
+        }
+    }
     match b {
-        Block::Paragraph(inls) => render_paragraph(inls),
+        Block::Paragraph(inls) => render_paragraph(inls, ctx),
         Block::Heading {
-            level, children, ..
-        } => render_heading(level, children),
-        Block::CodeBlock { kind, content } => render_codeblock(kind, content),
+            level,
+            id,
+            classes,
+            attrs,
+            children,
+        } => {
+            let mut r = render_heading(level, children, ctx);
+            if let Some(attrs) = heading_attributes(id, classes, attrs)
+                && let Some(line) = r.last_line_mut()
+            {
+                line.push(Fragment::from_string(format!(" {}", attrs)));
+            }
+            r
+        }
+        Block::CodeBlock { kind, content } => render_codeblock(kind, content, ctx),
         Block::HtmlBlock(rgn) => {
             let mut r = Region::new();
             for l in rgn.apply().split('\n') {
@@ -351,29 +663,197 @@ pub fn block_to_region(b: &Block) -> Region {
             }
             r
         }
-        Block::BlockQuote(children) => render_blockquote(children),
-        Block::List { start, items } => render_list(start.is_some(), *start, items),
+        Block::BlockQuote(children) => render_blockquote(children, ctx),
+        Block::Alert { kind, children } => render_alert(kind, children, ctx),
+        Block::List { start, items } => render_list(start.is_some(), *start, items, ctx),
         Block::Rule => render_rule(),
-        Block::FootnoteDefinition(id, children) => render_footnote_def(id, children),
-        Block::Table(aligns, rows) => render_table_full(aligns, rows),
-        Block::Custom(c) => c.to_region(),
+        Block::FootnoteDefinition(id, children) => render_footnote_def(id, children, ctx),
+        Block::Table { aligns, head, rows } => render_table_full(aligns, head, rows, ctx),
+        Block::Metadata { kind, content } => render_metadata(kind, content),
+        Block::Comment(body) => Region::from_str(&format!("<!--{}-->", body.apply())),
+        Block::Raw(rgn) => rgn.clone(),
+        Block::LinkDefinition { id, dest, title } => {
+            let mut r = Region::new();
+            r.push_back_line(render_link_definition(id, dest, title));
+            r
+        }
+        Block::Custom(c) => render_custom(&**c, ctx),
         _ => Region::new(),
     }
 }
 
+/// Write `blocks` as markdown. With the `rayon` feature, large documents
+/// have their top-level blocks rendered in parallel.
 pub fn blocks_to_markdown(blocks: &[Block]) -> String {
     let mut out = String::new();
-    let mut first = true;
-    for b in blocks {
-        if !first {
-            out.push_str("\n\n");
+    blocks_to_markdown_into(blocks, &mut out);
+    out
+}
+
+/// Append the markdown of `blocks` to `out`, as `blocks_to_markdown`
+/// returns it. Render loops can clear and reuse one buffer:
+///
+/// ```ignore
+/// let mut buf = String::new();
+/// for blocks in changes {
+///     buf.clear();
+///     blocks_to_markdown_into(&blocks, &mut buf);
+///     preview(&buf);
+/// }
+/// ```
+pub fn blocks_to_markdown_into(blocks: &[Block], out: &mut String) {
+    blocks_to_markdown_with(blocks, &WriterOptions::default(), out);
+}
+
+/// `blocks_to_markdown_into` with `options`.
+pub(crate) fn blocks_to_markdown_with(blocks: &[Block], options: &WriterOptions, out: &mut String) {
+    #[cfg(feature = "rayon")]
+    if blocks.len() >= PARALLEL_THRESHOLD {
+        return par_blocks_to_markdown(blocks, options, out);
+    }
+    write_blocks(out, blocks, options).expect("writing to a String cannot fail");
+}
+
+/// Number of top-level blocks from which `blocks_to_markdown` renders in
+/// parallel; below it, spreading the work costs more than it saves.
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD: usize = 64;
+
+/// `write_blocks` into `out`, rendering the blocks on the rayon thread
+/// pool. Rendering a block depends on the block, the options and the
+/// active `NodeRegistry` of the writing thread, which is carried over to
+/// the worker threads.
+#[cfg(feature = "rayon")]
+fn par_blocks_to_markdown(blocks: &[Block], options: &WriterOptions, out: &mut String) {
+    use rayon::prelude::*;
+    let ordered: Vec<&Block> = writing_order(blocks).collect();
+    #[cfg(feature = "serde")]
+    let registry = crate::ast::registry::active();
+    let defined = defined_ids(blocks);
+    let regions: Vec<Region> = ordered
+        .par_iter()
+        .map(|b| {
+            let render = || {
+                let ctx = WriteContext {
+                    defined: &defined,
+                    ..WriteContext::new(options)
+                };
+                render_block(b, &ctx)
+            };
+            #[cfg(feature = "serde")]
+            let render = || match &registry {
+                Some(registry) => registry.with(render),
+                None => render(),
+            };
+            render()
+        })
+        .collect();
+    for (i, region) in regions.into_iter().enumerate() {
+        if i > 0 {
+            for _ in 0..options.blank_lines {
+                out.push('\n');
+            }
         }
-        first = false;
-        let r = block_to_region(b);
-        for ln in r.into_lines() {
-            out.push_str(&ln.apply());
-            out.push('\n');
+        write_region(out, region).expect("writing to a String cannot fail");
+    }
+}
+
+/// The top-level blocks in the order they are written: front matter is
+/// only recognized at the very start of a document, so metadata blocks are
+/// hoisted above everything else.
+fn writing_order(blocks: &[Block]) -> impl Iterator<Item = &Block> {
+    let (meta, rest): (Vec<&Block>, Vec<&Block>) = blocks
+        .iter()
+        .partition(|b| matches!(b, Block::Metadata { .. }));
+    meta.into_iter().chain(rest)
+}
+
+fn write_region<W: fmt::Write>(w: &mut W, region: Region) -> fmt::Result {
+    for ln in region.into_lines() {
+        for frag in ln.fragments() {
+            w.write_str(frag.as_str())?;
         }
+        w.write_char('\n')?;
     }
+    Ok(())
+}
+
+/// Render a single block as markdown with `options`, e.g. to embed it in a
+/// template. The result is what `write_blocks` writes for a document made
+/// of this block alone: its lines, each ending with a newline, followed by
+/// the reference definitions its paragraphs use.
+pub fn block_to_markdown(b: &Block, options: &WriterOptions) -> String {
+    let mut out = String::new();
+    write_blocks(&mut out, std::slice::from_ref(b), options)
+        .expect("writing to a String cannot fail");
     out
 }
+
+/// Write `blocks` as markdown into `w`. Each block is rendered on its own
+/// and its lines go straight to the sink, so the output never exists in
+/// memory as a whole. A reference whose definition is one of `blocks`
+/// leaves it to that block rather than writing it after its paragraph.
+pub fn write_blocks<W: fmt::Write>(
+    w: &mut W,
+    blocks: &[Block],
+    options: &WriterOptions,
+) -> fmt::Result {
+    let defined = defined_ids(blocks);
+    let ctx = WriteContext {
+        defined: &defined,
+        ..WriteContext::new(options)
+    };
+    write_blocks_in(w, blocks, &ctx)
+}
+
+/// Write `blocks` as the top-level blocks of a document, in `ctx`.
+fn write_blocks_in<W: fmt::Write>(w: &mut W, blocks: &[Block], ctx: &WriteContext) -> fmt::Result {
+    for (i, b) in writing_order(blocks).enumerate() {
+        if i > 0 {
+            for _ in 0..ctx.options.blank_lines {
+                w.write_char('\n')?;
+            }
+        }
+        write_region(w, render_block(b, ctx))?;
+    }
+    Ok(())
+}
+
+/// `write_blocks` for an `io::Write` sink, e.g. a file or stdout. Wrap
+/// unbuffered sinks in a `BufWriter`: the output is written in small
+/// pieces.
+pub fn write_blocks_io<W: io::Write>(
+    w: &mut W,
+    blocks: &[Block],
+    options: &WriterOptions,
+) -> io::Result<()> {
+    struct Adapter<'w, W> {
+        inner: &'w mut W,
+        error: Option<io::Error>,
+    }
+    impl<W: io::Write> fmt::Write for Adapter<'_, W> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.inner.write_all(s.as_bytes()).map_err(|e| {
+                self.error = Some(e);
+                fmt::Error
+            })
+        }
+    }
+    let mut adapter = Adapter {
+        inner: w,
+        error: None,
+    };
+    write_blocks(&mut adapter, blocks, options).map_err(|_| {
+        adapter
+            .error
+            .take()
+            .unwrap_or_else(|| io::Error::other("formatter error"))
+    })
+}
+
+/// Renders the block as markdown, the same way `block_to_region` does.
+impl std::fmt::Display for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&block_to_region(self), f)
+    }
+}
@@ -0,0 +1,175 @@
+use super::blocks::{defined_ids, render_block};
+use super::{WriteContext, WriterOptions};
+use crate::ast::visit::{Visitor, walk_block, walk_inline};
+use crate::ast::{Block, Inline};
+use crate::text::Region;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem::discriminant;
+
+/// Renders a document repeatedly, reusing the regions of top-level blocks
+/// that did not change since the previous render, e.g. for a live preview
+/// of a large document.
+///
+/// A block is reused when it equals a block of the previous render, at the
+/// same position or elsewhere (so inserting or moving blocks keeps the
+/// others cached). The unit is the top-level block: a change anywhere in a
+/// list re-renders the whole list. Only the blocks themselves are compared:
+/// if the rendering of a custom node depends on state outside the blocks
+/// and the options, `clear` the cache when that state changes.
+///
+/// ```ignore
+/// let mut cache = RenderCache::new(WriterOptions::default());
+/// let md = cache.render(&blocks);
+/// blocks[3] = Block::paragraph(["edited"]);
+/// let md = cache.render(&blocks); // renders one block
+/// ```
+#[derive(Debug, Default)]
+pub struct RenderCache {
+    options: WriterOptions,
+    // the ids of the definitions held as blocks, which the paragraphs of
+    // the entries left to those blocks
+    defined: Vec<String>,
+    entries: Vec<Entry>,
+    rendered: usize,
+}
+
+/// A rendered block, with its `block_hash`.
+type Entry = (u64, Block, Region);
+
+impl RenderCache {
+    pub fn new(options: WriterOptions) -> Self {
+        RenderCache {
+            options,
+            defined: Vec::new(),
+            entries: Vec::new(),
+            rendered: 0,
+        }
+    }
+
+    /// Write `blocks` as markdown, like `write_blocks` with the options of
+    /// the cache.
+    pub fn render(&mut self, blocks: &[Block]) -> String {
+        let defined = defined_ids(blocks);
+        // a definition added or removed changes what paragraphs write
+        if defined != self.defined {
+            self.entries.clear();
+            self.defined = defined;
+        }
+        let ctx = WriteContext {
+            defined: &self.defined,
+            ..WriteContext::new(&self.options)
+        };
+        let mut old: Vec<Option<Entry>> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(Some)
+            .collect();
+        // the previous blocks by hash, the first in document order last
+        let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (j, entry) in old.iter().enumerate().rev() {
+            if let Some((hash, _, _)) = entry {
+                by_hash.entry(*hash).or_default().push(j);
+            }
+        }
+        self.rendered = 0;
+        for (i, b) in blocks.iter().enumerate() {
+            let hash = block_hash(b);
+            let same = |j: usize| matches!(&old[j], Some((_, ob, _)) if ob == b);
+            // the block at the same position is the likeliest match
+            let found = if i < old.len() && same(i) {
+                Some(i)
+            } else {
+                by_hash.get_mut(&hash).and_then(|list| {
+                    while list.last().is_some_and(|&j| old[j].is_none()) {
+                        list.pop();
+                    }
+                    let n = list.iter().rposition(|&j| same(j))?;
+                    Some(list.remove(n))
+                })
+            };
+            let entry = match found.and_then(|j| old[j].take()) {
+                Some(entry) => entry,
+                None => {
+                    self.rendered += 1;
+                    (hash, b.clone(), render_block(b, &ctx))
+                }
+            };
+            self.entries.push(entry);
+        }
+        self.write()
+    }
+
+    /// Number of blocks the last `render` had to render rather than reuse.
+    pub fn rendered_last(&self) -> usize {
+        self.rendered
+    }
+
+    /// Forget every cached region.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn write(&self) -> String {
+        let mut out = String::new();
+        // front matter is hoisted, as `write_blocks` does
+        let (meta, rest): (Vec<_>, Vec<_>) = self
+            .entries
+            .iter()
+            .partition(|(_, b, _)| matches!(b, Block::Metadata { .. }));
+        for (i, (_, _, region)) in meta.into_iter().chain(rest).enumerate() {
+            if i > 0 {
+                for _ in 0..self.options.blank_lines {
+                    out.push('\n');
+                }
+            }
+            for line in region.iter_lines() {
+                for frag in line.fragments() {
+                    out.push_str(frag.as_str());
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// A hash equal blocks share: the kinds of the nodes and their text. What
+/// custom nodes hold is left out, since they compare with `node_eq`.
+fn block_hash(b: &Block) -> u64 {
+    struct Hash(DefaultHasher);
+    impl Visitor for Hash {
+        fn visit_block(&mut self, b: &Block) {
+            discriminant(b).hash(&mut self.0);
+            if let Block::CodeBlock { content: r, .. }
+            | Block::HtmlBlock(r)
+            | Block::Metadata { content: r, .. }
+            | Block::Comment(r)
+            | Block::Raw(r) = b
+            {
+                r.hash(&mut self.0);
+            }
+            walk_block(self, b);
+        }
+
+        fn visit_inline(&mut self, inl: &Inline) {
+            discriminant(inl).hash(&mut self.0);
+            match inl {
+                Inline::Text(r)
+                | Inline::Code(r)
+                | Inline::InlineHtml(r)
+                | Inline::Html(r)
+                | Inline::InlineMath(r)
+                | Inline::DisplayMath(r)
+                | Inline::Comment(r)
+                | Inline::Raw(r) => r.hash(&mut self.0),
+                Inline::Link { dest, .. } | Inline::Image { dest, .. } => dest.hash(&mut self.0),
+                _ => {}
+            }
+            walk_inline(self, inl);
+        }
+    }
+    let mut hash = Hash(DefaultHasher::new());
+    hash.visit_block(b);
+    hash.0.finish()
+}
@@ -0,0 +1,138 @@
+//! HTML output for the owned AST.
+//!
+//! `blocks_to_html` turns blocks into events and renders them with
+//! pulldown-cmark's HTML writer, except for custom nodes that render
+//! themselves (`BlockNode::to_html`, `InlineNode::to_html`, or the
+//! `Extension` handling them, see `ExtensionRegistry::to_html`): their
+//! HTML is inserted
+//! as is. Nodes without their own HTML are rendered from their events.
+
+use crate::ast::custom::BlockNode;
+use crate::ast::event_iter::{CustomNode, block_events_iter_with};
+use crate::ast::{Block, ExtensionRegistry};
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+/// Options for `blocks_to_html`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HtmlOptions {
+    /// Render soft line breaks as `<br />`, keeping the line structure of
+    /// paragraphs. Off by default.
+    pub hard_breaks: bool,
+}
+
+/// Render `blocks` as HTML.
+pub fn blocks_to_html(blocks: &[Block], options: &HtmlOptions) -> String {
+    let mut out = String::new();
+    push_html(&mut out, blocks, options);
+    out
+}
+
+/// Append the HTML of `blocks` to `out`.
+pub fn push_html(out: &mut String, blocks: &[Block], options: &HtmlOptions) {
+    push_html_with(out, blocks, options, None);
+}
+
+/// `push_html`, rendering the nodes of `extensions` with their extension.
+pub(crate) fn push_html_with(
+    out: &mut String,
+    blocks: &[Block],
+    options: &HtmlOptions,
+    extensions: Option<&ExtensionRegistry>,
+) {
+    // custom nodes that render themselves turn into their HTML instead of
+    // their events
+    let custom = |node: CustomNode| match node {
+        CustomNode::Block(b) => {
+            custom_block_html(b, extensions).map(|html| Event::Html(CowStr::from(html)))
+        }
+        CustomNode::Inline(i) => i
+            .to_html()
+            .map(|html| Event::InlineHtml(CowStr::from(html))),
+    };
+    let events: Vec<Event> = blocks
+        .iter()
+        .flat_map(|b| block_events_iter_with(b, &custom))
+        .collect();
+    let events = tighten_lists(events).into_iter().map(|e| match e {
+        Event::SoftBreak if options.hard_breaks => Event::HardBreak,
+        e => e,
+    });
+    pulldown_cmark::html::push_html(out, events);
+}
+
+/// HTML of a custom block: from the extension handling it, or its own.
+fn custom_block_html(
+    node: &dyn BlockNode,
+    extensions: Option<&ExtensionRegistry>,
+) -> Option<String> {
+    extensions
+        .and_then(|e| e.write_html(node))
+        .or_else(|| node.to_html())
+}
+
+/// Drop the paragraph tags directly inside the items of tight lists, which
+/// pulldown-cmark leaves out. The AST does not record tightness: a list is
+/// tight when the markdown writer writes it tight, i.e. when none of its
+/// items holds more than one block.
+fn tighten_lists(events: Vec<Event>) -> Vec<Event> {
+    enum Open {
+        List(usize, bool),
+        Item(usize),
+        Other,
+    }
+    // first pass: which lists are tight, by the index of their `Start`
+    let mut tight = vec![false; events.len()];
+    let mut stack: Vec<Open> = Vec::new();
+    for (i, ev) in events.iter().enumerate() {
+        if let Some(Open::Item(children)) = stack.last_mut()
+            && matches!(ev, Event::Start(_) | Event::Html(_) | Event::Rule)
+        {
+            *children += 1;
+        }
+        match ev {
+            Event::Start(Tag::List(_)) => stack.push(Open::List(i, true)),
+            Event::Start(Tag::Item) => stack.push(Open::Item(0)),
+            Event::Start(_) => stack.push(Open::Other),
+            Event::End(_) => match stack.pop() {
+                Some(Open::List(start, is_tight)) => tight[start] = is_tight,
+                Some(Open::Item(children)) if children > 1 => {
+                    if let Some(Open::List(_, is_tight)) = stack.last_mut() {
+                        *is_tight = false;
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    // second pass: drop the paragraph tags of tight items
+    let mut out = Vec::with_capacity(events.len());
+    let mut stack: Vec<(bool, bool)> = Vec::new(); // (tight list or item, dropped)
+    for (i, ev) in events.into_iter().enumerate() {
+        match &ev {
+            Event::Start(tag) => {
+                let parent_tight = stack.last().is_some_and(|(t, _)| *t);
+                let entry = match tag {
+                    Tag::List(_) => (tight[i], false),
+                    Tag::Item => (parent_tight, false),
+                    Tag::Paragraph if parent_tight => (false, true),
+                    _ => (false, false),
+                };
+                stack.push(entry);
+                if entry.1 {
+                    continue;
+                }
+            }
+            Event::End(TagEnd::Paragraph) if stack.last().is_some_and(|(_, d)| *d) => {
+                stack.pop();
+                continue;
+            }
+            Event::End(_) => {
+                stack.pop();
+            }
+            _ => {}
+        }
+        out.push(ev);
+    }
+    out
+}
@@ -0,0 +1,87 @@
+use super::blocks::{WrittenDefinitions, render_block, render_link_definition};
+use super::{WriteContext, WriterOptions};
+use crate::ast::Block;
+use std::cell::RefCell;
+use std::fmt;
+
+/// Writes a document block by block, as the blocks are produced.
+///
+/// Blocks are separated according to the `WriterOptions`. Reference
+/// definitions are collected across calls instead of following their
+/// paragraph, and written once each by `finish`, so a definition shared by
+/// several blocks appears a single time. Unlike `blocks_to_markdown`, the
+/// writer cannot hoist front matter: push metadata blocks first.
+///
+/// ```ignore
+/// let mut writer = MarkdownWriter::new(String::new(), WriterOptions::default());
+/// for block in produce_blocks() {
+///     writer.push_block(&block)?;
+/// }
+/// let markdown = writer.finish()?;
+/// ```
+pub struct MarkdownWriter<W: fmt::Write> {
+    out: W,
+    options: WriterOptions,
+    started: bool,
+    // the first definition of a label wins, as in markdown, where labels
+    // match without regard to case
+    definitions: RefCell<WrittenDefinitions>,
+}
+
+impl<W: fmt::Write> MarkdownWriter<W> {
+    pub fn new(out: W, options: WriterOptions) -> Self {
+        MarkdownWriter {
+            out,
+            options,
+            started: false,
+            definitions: RefCell::new(WrittenDefinitions::deferred()),
+        }
+    }
+
+    /// Write `block`, preceded by the blank lines separating it from the
+    /// previous one.
+    pub fn push_block(&mut self, block: &Block) -> fmt::Result {
+        let ctx = WriteContext {
+            written: Some(&self.definitions),
+            ..WriteContext::new(&self.options)
+        };
+        let region = render_block(block, &ctx);
+        self.separate()?;
+        for line in region.into_lines() {
+            for frag in line.fragments() {
+                self.out.write_str(frag.as_str())?;
+            }
+            self.out.write_char('\n')?;
+        }
+        Ok(())
+    }
+
+    /// Reference definitions collected so far, not yet written.
+    pub fn pending_definitions(&self) -> usize {
+        self.definitions.borrow().pending().len()
+    }
+
+    /// Write the collected reference definitions and return the sink.
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        let pending = self.definitions.get_mut().take_pending();
+        if !pending.is_empty() {
+            self.separate()?;
+            for def in pending {
+                let line = render_link_definition(&def.id, &def.dest, &def.title);
+                self.out.write_str(&line.apply())?;
+                self.out.write_char('\n')?;
+            }
+        }
+        Ok(self.out)
+    }
+
+    fn separate(&mut self) -> fmt::Result {
+        if self.started {
+            for _ in 0..self.options.blank_lines {
+                self.out.write_char('\n')?;
+            }
+        }
+        self.started = true;
+        Ok(())
+    }
+}
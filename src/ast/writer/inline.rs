@@ -1,5 +1,9 @@
-use crate::ast::Inline;
-use crate::text::Line;
+use super::{WriteContext, WriterOptions};
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::{Block, Inline};
+use crate::text::{Fragment, Line};
+use pulldown_cmark::{Event, Tag, TagEnd};
+use std::fmt::Write as _;
 
 /// A small type representing a reference-style link/image definition.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -9,17 +13,38 @@ pub struct ReferenceDef {
     pub title: String,
 }
 
+/// Render a single inline as markdown, as it would be written at the top
+/// level of a document with `options`. Reference-style links and images
+/// are written as `[text][id]`; their definitions are left to the enclosing
+/// block (see `block_to_markdown`).
+pub fn inline_to_markdown(inl: &Inline, options: &WriterOptions) -> String {
+    render_inline(inl, &WriteContext::new(options)).0.apply()
+}
+
+/// Renders the inline as markdown with the default options, like
+/// `inline_to_markdown`.
+impl std::fmt::Display for Inline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&inline_to_markdown(self, &WriterOptions::default()))
+    }
+}
+
 /// Produce a Line for the provided `Inline` and optionally return a
 /// reference-definition tuple when the inline was a reference-style link/image.
-pub fn inline_to_line(inl: &Inline) -> (Line, Option<ReferenceDef>) {
+/// The override hooks of the context's options are consulted first.
+pub(super) fn render_inline(inl: &Inline, ctx: &WriteContext) -> (Line, Option<ReferenceDef>) {
+    for f in &ctx.options.inline_overrides {
+        if let Some(line) = f(inl, ctx) {
+            return (line, None);
+        }
+    }
     let mut line = Line::new();
     let mut def: Option<ReferenceDef> = None;
     match inl {
         Inline::Text(r) => {
-            let lines = r.lines();
-            for (i, ln) in lines.iter().enumerate() {
+            for (i, ln) in r.iter_lines().enumerate() {
                 if i > 0 {
-                    line.push("\n");
+                    line.push(Fragment::from_static("\n"));
                 }
                 line.extend_from_line(ln);
             }
@@ -29,75 +54,74 @@ pub fn inline_to_line(inl: &Inline) -> (Line, Option<ReferenceDef>) {
             let ticks = if s.contains('`') { "``" } else { "`" };
             line.push(format!("{}{}{}", ticks, s, ticks));
         }
-        Inline::InlineHtml(r) | Inline::Html(r) => {
-            let lines = r.lines();
-            for (i, ln) in lines.iter().enumerate() {
+        Inline::InlineHtml(r) | Inline::Html(r) | Inline::Raw(r) => {
+            for (i, ln) in r.iter_lines().enumerate() {
                 if i > 0 {
-                    line.push("\n");
+                    line.push(Fragment::from_static("\n"));
                 }
                 line.extend_from_line(ln);
             }
         }
         Inline::SoftBreak => {
-            line.push(" ");
+            line.push(Fragment::from_static(" "));
         }
         Inline::HardBreak => {
-            line.push("  \n");
+            line.push(Fragment::from_static("  \n"));
         }
         Inline::Emphasis(children) => {
-            line.push("*");
+            line.push(Fragment::from_static("*"));
             for c in children {
-                let (ln, d) = inline_to_line(c);
+                let (ln, d) = render_inline(c, ctx);
                 line.extend_from_line(&ln);
                 if def.is_none() {
                     def = d;
                 }
             }
-            line.push("*");
+            line.push(Fragment::from_static("*"));
         }
         Inline::Strong(children) => {
-            line.push("**");
+            line.push(Fragment::from_static("**"));
             for c in children {
-                let (ln, d) = inline_to_line(c);
+                let (ln, d) = render_inline(c, ctx);
                 line.extend_from_line(&ln);
                 if def.is_none() {
                     def = d;
                 }
             }
-            line.push("**");
+            line.push(Fragment::from_static("**"));
         }
         Inline::Strikethrough(children) => {
-            line.push("~~");
+            line.push(Fragment::from_static("~~"));
             for c in children {
-                let (ln, d) = inline_to_line(c);
+                let (ln, d) = render_inline(c, ctx);
                 line.extend_from_line(&ln);
                 if def.is_none() {
                     def = d;
                 }
             }
-            line.push("~~");
+            line.push(Fragment::from_static("~~"));
         }
         Inline::Subscript(children) => {
-            line.push("~{");
+            line.push(Fragment::from_static("~{"));
             for c in children {
-                let (ln, d) = inline_to_line(c);
+                let (ln, d) = render_inline(c, ctx);
                 line.extend_from_line(&ln);
                 if def.is_none() {
                     def = d;
                 }
             }
-            line.push("}");
+            line.push(Fragment::from_static("}"));
         }
         Inline::Superscript(children) => {
-            line.push("^{");
+            line.push(Fragment::from_static("^{"));
             for c in children {
-                let (ln, d) = inline_to_line(c);
+                let (ln, d) = render_inline(c, ctx);
                 line.extend_from_line(&ln);
                 if def.is_none() {
                     def = d;
                 }
             }
-            line.push("}");
+            line.push(Fragment::from_static("}"));
         }
         Inline::Link {
             link_type,
@@ -108,7 +132,7 @@ pub fn inline_to_line(inl: &Inline) -> (Line, Option<ReferenceDef>) {
         } => {
             let mut inner = Line::new();
             for c in children {
-                let (ln, d) = inline_to_line(c);
+                let (ln, d) = render_inline(c, ctx);
                 inner.extend_from_line(&ln);
                 if def.is_none() {
                     def = d;
@@ -127,6 +151,12 @@ pub fn inline_to_line(inl: &Inline) -> (Line, Option<ReferenceDef>) {
                 LinkType::Autolink | LinkType::Email => {
                     line.push(format!("<{}>", dest));
                 }
+                LinkType::WikiLink { has_pothole: true } => {
+                    line.push(format!("[[{}|{}]]", dest, inner.apply()));
+                }
+                LinkType::WikiLink { has_pothole: false } => {
+                    line.push(format!("[[{}]]", dest));
+                }
                 LinkType::Shortcut | LinkType::Collapsed if !id.is_empty() => {
                     line.push(format!("[{}]", inner.apply()));
                     def = Some(ReferenceDef {
@@ -163,7 +193,7 @@ pub fn inline_to_line(inl: &Inline) -> (Line, Option<ReferenceDef>) {
         } => {
             let mut inner = Line::new();
             for c in children {
-                let (ln, d) = inline_to_line(c);
+                let (ln, d) = render_inline(c, ctx);
                 inner.extend_from_line(&ln);
                 if def.is_none() {
                     def = d;
@@ -179,6 +209,12 @@ pub fn inline_to_line(inl: &Inline) -> (Line, Option<ReferenceDef>) {
                         title: title.clone(),
                     });
                 }
+                LinkType::WikiLink { has_pothole: true } => {
+                    line.push(format!("![[{}|{}]]", dest, inner.apply()));
+                }
+                LinkType::WikiLink { has_pothole: false } => {
+                    line.push(format!("![[{}]]", dest));
+                }
                 LinkType::Shortcut | LinkType::Collapsed if !id.is_empty() => {
                     line.push(format!("![{}]", inner.apply()));
                     def = Some(ReferenceDef {
@@ -196,20 +232,43 @@ pub fn inline_to_line(inl: &Inline) -> (Line, Option<ReferenceDef>) {
                 }
             }
         }
+        Inline::Comment(body) => {
+            let _ = write!(line, "<!--{}-->", body);
+        }
         Inline::FootnoteReference(s) => {
-            line.push(format!("[^{}]", s));
+            let _ = write!(line, "[^{}]", s);
         }
         Inline::InlineMath(r) => {
-            line.push(format!("${}$", r.apply()));
+            let _ = write!(line, "${}$", r);
         }
         Inline::DisplayMath(r) => {
-            line.push("\n$$\n");
-            line.push(r.apply());
-            line.push("\n$$\n");
+            // the math holds the line breaks around it, if it had any
+            let _ = write!(line, "$${}$$", r);
         }
         Inline::Custom(c) => {
-            line.push(c.to_line().apply());
+            let own = c.to_line_in(ctx);
+            if own.apply().is_empty() {
+                line.extend_from_line(&custom_events_line(c.to_events(), ctx));
+            } else {
+                line.extend_from_line(&own);
+            }
         }
     }
     (line, def)
 }
+
+/// Render the events of a custom inline whose `to_line` is empty.
+fn custom_events_line(events: Vec<Event<'static>>, ctx: &WriteContext) -> Line {
+    let mut wrapped = vec![Event::Start(Tag::Paragraph)];
+    wrapped.extend(events);
+    wrapped.push(Event::End(TagEnd::Paragraph));
+    let mut line = Line::new();
+    for b in parse_events_to_blocks(&wrapped) {
+        if let Block::Paragraph(inls) = b {
+            for inl in &inls {
+                line.extend_from_line(&render_inline(inl, ctx).0);
+            }
+        }
+    }
+    line
+}
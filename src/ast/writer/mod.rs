@@ -1,6 +1,202 @@
+mod backend;
 mod blocks;
+mod cache;
+pub mod html;
+mod incremental;
 mod inline;
+#[cfg(feature = "pandoc")]
+pub mod pandoc;
+pub mod plaintext;
 mod utils;
 
+pub use backend::{Backend, render_with};
+use blocks::WrittenDefinitions;
 pub use blocks::block_to_region;
-pub use blocks::blocks_to_markdown;
+pub use blocks::{block_to_markdown, write_blocks, write_blocks_io};
+pub(crate) use blocks::{
+    block_to_region_defining, blocks_to_markdown_with, blocks_to_markdown_with_unique_definitions,
+    render_link_definition,
+};
+pub use blocks::{blocks_to_markdown, blocks_to_markdown_into};
+pub use cache::RenderCache;
+pub use html::{HtmlOptions, blocks_to_html};
+pub use incremental::MarkdownWriter;
+pub use inline::inline_to_markdown;
+pub use plaintext::blocks_to_plaintext;
+
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::{Block, ExtensionRegistry, Inline};
+use crate::text::{Line, Region};
+use pulldown_cmark::Event;
+use std::cell::RefCell;
+
+/// Write any event stream as markdown, e.g. the events of a `Parser` after
+/// filtering, or a hand-assembled stream. The events are parsed into blocks
+/// (leniently, see `parse_events_to_blocks`) and written with
+/// `blocks_to_markdown`.
+pub fn events_to_markdown<'a>(events: impl IntoIterator<Item = Event<'a>>) -> String {
+    let events: Vec<Event<'a>> = events.into_iter().collect();
+    blocks_to_markdown(&parse_events_to_blocks(&events))
+}
+
+/// A hook replacing the rendering of a block. See
+/// `WriterOptions::override_block`.
+pub type BlockOverride = fn(&Block, &WriteContext) -> Option<Region>;
+
+/// A hook replacing the rendering of an inline. See
+/// `WriterOptions::override_inline`.
+pub type InlineOverride = fn(&Inline, &WriteContext) -> Option<Line>;
+
+/// Options for `write_blocks`.
+#[derive(Clone, Debug)]
+pub struct WriterOptions {
+    /// Number of blank lines between two blocks. Defaults to 2, as
+    /// `blocks_to_markdown` writes them.
+    pub blank_lines: usize,
+    /// Write tables whose cells span several lines (hard breaks, multi-line
+    /// raw or custom content) as pandoc-style grid tables, with `+---+`
+    /// borders, instead of pipe tables that cannot hold them. Off by
+    /// default: CommonMark parsers, pulldown-cmark included, do not read
+    /// grid tables.
+    pub grid_tables: bool,
+    /// Expand the tabs of code blocks to spaces, with tab stops every that
+    /// many columns. Tabs are kept by default; expanding them keeps
+    /// tab-indented code aligned once it is indented inside list items or
+    /// blockquotes, where a tab stop no longer falls where it did.
+    pub code_tab_width: Option<usize>,
+    /// Hooks tried, in order, before a block is rendered.
+    pub block_overrides: Vec<BlockOverride>,
+    /// Hooks tried, in order, before an inline is rendered.
+    pub inline_overrides: Vec<InlineOverride>,
+    /// Extensions rendering the nodes they parse, tried after the hooks.
+    pub extensions: ExtensionRegistry,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            blank_lines: 2,
+            grid_tables: false,
+            code_tab_width: None,
+            block_overrides: Vec::new(),
+            inline_overrides: Vec::new(),
+            extensions: ExtensionRegistry::new(),
+        }
+    }
+}
+
+impl WriterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intercept the rendering of blocks, at any depth. The first hook that
+    /// returns a region replaces the built-in rendering of the block; a
+    /// hook returning `None` leaves the block to the next hook, and finally
+    /// to the writer. Regions follow the rules of `BlockNode::to_region`:
+    /// the writer adds the prefixes of enclosing containers.
+    ///
+    /// ```ignore
+    /// let options = WriterOptions::new().override_block(|b, _| match b {
+    ///     Block::Rule => Some(Region::from_str("* * *")),
+    ///     _ => None,
+    /// });
+    /// ```
+    pub fn override_block(mut self, hook: BlockOverride) -> Self {
+        self.block_overrides.push(hook);
+        self
+    }
+
+    /// Intercept the rendering of inlines, like `override_block`. The
+    /// line replaces the inline and everything inside it.
+    pub fn override_inline(mut self, hook: InlineOverride) -> Self {
+        self.inline_overrides.push(hook);
+        self
+    }
+
+    /// Render the nodes of `extensions` with `Extension::write_markdown`
+    /// instead of `BlockNode::to_region`, at any depth.
+    pub fn with_extensions(mut self, extensions: ExtensionRegistry) -> Self {
+        self.extensions = extensions;
+        self
+    }
+}
+
+/// Where a node is being written, as seen by override hooks and custom
+/// nodes (`BlockNode::to_region_in`).
+#[derive(Clone, Debug)]
+pub struct WriteContext<'a> {
+    /// The options of the write.
+    pub options: &'a WriterOptions,
+    /// Number of enclosing containers (blockquotes, list items, footnote
+    /// definitions, ...); 0 at the top level.
+    pub depth: usize,
+    /// Whether a blockquote (or alert) encloses the node, so its lines
+    /// will be prefixed with `> `.
+    pub inside_blockquote: bool,
+    /// The enclosing list items, outermost first.
+    pub list_stack: Vec<ListLevel>,
+    /// Whether the node is the content of a table cell, which has to fit
+    /// on one line.
+    pub table_cell: bool,
+    /// The label keys of the definitions the document holds as
+    /// `Block::LinkDefinition`, which paragraphs leave to those blocks.
+    pub(crate) defined: &'a [String],
+    /// The definitions written so far, when the write puts each one once;
+    /// otherwise every paragraph writes the definitions it uses.
+    pub(crate) written: Option<&'a RefCell<WrittenDefinitions>>,
+}
+
+/// A list item enclosing the node being written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListLevel {
+    /// Number of the first item for ordered lists, `None` for bullet lists.
+    pub start: Option<u64>,
+    /// Position of the item in its list, from 0.
+    pub index: usize,
+}
+
+impl<'a> WriteContext<'a> {
+    pub fn new(options: &'a WriterOptions) -> Self {
+        WriteContext {
+            options,
+            depth: 0,
+            inside_blockquote: false,
+            list_stack: Vec::new(),
+            table_cell: false,
+            defined: &[],
+            written: None,
+        }
+    }
+
+    /// The context of the children of a container.
+    pub(crate) fn nested(&self) -> Self {
+        WriteContext {
+            depth: self.depth + 1,
+            ..self.clone()
+        }
+    }
+
+    /// The context of the children of a blockquote.
+    pub(crate) fn quoted(&self) -> Self {
+        WriteContext {
+            inside_blockquote: true,
+            ..self.nested()
+        }
+    }
+
+    /// The context of the blocks of a list item.
+    pub(crate) fn item(&self, level: ListLevel) -> Self {
+        let mut ctx = self.nested();
+        ctx.list_stack.push(level);
+        ctx
+    }
+
+    /// The context of the content of a table cell.
+    pub(crate) fn cell(&self) -> Self {
+        WriteContext {
+            table_cell: true,
+            ..self.clone()
+        }
+    }
+}
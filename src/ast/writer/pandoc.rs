@@ -0,0 +1,353 @@
+//! Pandoc JSON AST writer.
+//!
+//! Converts blocks into the JSON document `pandoc -f json` reads, so a
+//! pipeline can hand an AST to pandoc for PDF/LaTeX/Docx output without
+//! going through markdown again. The output targets pandoc-types
+//! `PANDOC_API_VERSION`.
+//!
+//! Footnote definitions are folded into `Note` inlines at their references,
+//! as pandoc has no separate definition block. Front matter is not
+//! interpreted (pandoc's `meta` stays empty); raw markdown nodes become
+//! `RawBlock`/`RawInline` in the `markdown` format.
+
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::{Block, Inline};
+use pulldown_cmark::{Alignment, BlockQuoteKind, CodeBlockKind, HeadingLevel};
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+/// pandoc-types API version the output is written against.
+pub const PANDOC_API_VERSION: [u32; 3] = [1, 23, 1];
+
+/// Convert blocks into a pandoc JSON document.
+pub fn blocks_to_pandoc(blocks: &[Block]) -> Value {
+    let mut notes = HashMap::new();
+    collect_footnotes(blocks, &mut notes);
+    let w = PandocWriter {
+        notes,
+        expanding: RefCell::new(HashSet::new()),
+    };
+    json!({
+        "pandoc-api-version": PANDOC_API_VERSION,
+        "meta": {},
+        "blocks": w.blocks(blocks),
+    })
+}
+
+/// Convert blocks into a pandoc JSON document serialized as a string.
+pub fn blocks_to_pandoc_json(blocks: &[Block]) -> String {
+    blocks_to_pandoc(blocks).to_string()
+}
+
+fn collect_footnotes<'a>(blocks: &'a [Block], notes: &mut HashMap<String, &'a [Block]>) {
+    for b in blocks {
+        match b {
+            Block::FootnoteDefinition(label, children) => {
+                notes.insert(label.clone(), children);
+                collect_footnotes(children, notes);
+            }
+            Block::BlockQuote(children) | Block::Alert { children, .. } | Block::Item(children) => {
+                collect_footnotes(children, notes)
+            }
+            Block::List { items, .. } => {
+                for item in items {
+                    collect_footnotes(item, notes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn alert_title(kind: &BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "Note",
+        BlockQuoteKind::Tip => "Tip",
+        BlockQuoteKind::Important => "Important",
+        BlockQuoteKind::Warning => "Warning",
+        BlockQuoteKind::Caution => "Caution",
+    }
+}
+
+fn node(t: &str, c: Value) -> Value {
+    json!({ "t": t, "c": c })
+}
+
+fn leaf(t: &str) -> Value {
+    json!({ "t": t })
+}
+
+fn empty_attr() -> Value {
+    json!(["", [], []])
+}
+
+fn heading_level(level: &HeadingLevel) -> u32 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+fn alignment(a: Option<&Alignment>) -> Value {
+    match a {
+        Some(Alignment::Left) => leaf("AlignLeft"),
+        Some(Alignment::Right) => leaf("AlignRight"),
+        Some(Alignment::Center) => leaf("AlignCenter"),
+        _ => leaf("AlignDefault"),
+    }
+}
+
+/// Split text into pandoc's `Str`/`Space`/`SoftBreak` tokens.
+fn text_tokens(s: &str, out: &mut Vec<Value>) {
+    let mut word = String::new();
+    for ch in s.chars() {
+        if ch == ' ' || ch == '\n' {
+            if !word.is_empty() {
+                out.push(node("Str", json!(std::mem::take(&mut word))));
+            }
+            // collapse runs of whitespace into a single token, letting a
+            // line break win over plain spaces
+            let prev_is_space =
+                matches!(out.last(), Some(v) if v["t"] == "Space" || v["t"] == "SoftBreak");
+            if !prev_is_space {
+                out.push(leaf(if ch == '\n' { "SoftBreak" } else { "Space" }));
+            } else if ch == '\n'
+                && let Some(last) = out.last_mut()
+            {
+                *last = leaf("SoftBreak");
+            }
+        } else {
+            word.push(ch);
+        }
+    }
+    if !word.is_empty() {
+        out.push(node("Str", json!(word)));
+    }
+}
+
+struct PandocWriter<'a> {
+    notes: HashMap<String, &'a [Block]>,
+    /// Labels of the notes being expanded, so a note referring back to
+    /// itself is not expanded forever.
+    expanding: RefCell<HashSet<String>>,
+}
+
+impl PandocWriter<'_> {
+    fn blocks(&self, blocks: &[Block]) -> Vec<Value> {
+        let mut out = Vec::new();
+        for b in blocks {
+            self.block(b, &mut out);
+        }
+        out
+    }
+
+    fn block(&self, b: &Block, out: &mut Vec<Value>) {
+        match b {
+            Block::Paragraph(inls) => out.push(node("Para", json!(self.inlines(inls)))),
+            Block::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+                children,
+            } => {
+                let attr = json!([
+                    id.clone().unwrap_or_default(),
+                    classes,
+                    attrs
+                        .iter()
+                        .map(|(k, v)| json!([k, v.clone().unwrap_or_default()]))
+                        .collect::<Vec<_>>()
+                ]);
+                out.push(node(
+                    "Header",
+                    json!([heading_level(level), attr, self.inlines(children)]),
+                ));
+            }
+            Block::BlockQuote(children) => {
+                out.push(node("BlockQuote", json!(self.blocks(children))))
+            }
+            Block::Alert { kind, children } => {
+                // the shape pandoc's own `alerts` extension produces
+                let title = node(
+                    "Div",
+                    json!([
+                        ["", ["title"], []],
+                        [node("Para", json!([node("Str", json!(alert_title(kind)))]))]
+                    ]),
+                );
+                let mut content = vec![title];
+                content.extend(self.blocks(children));
+                let class = alert_title(kind).to_lowercase();
+                out.push(node("Div", json!([["", [class], []], content])));
+            }
+            Block::CodeBlock { kind, content } => {
+                let classes: Vec<String> = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .map(|l| vec![l.to_string()])
+                        .unwrap_or_default(),
+                    CodeBlockKind::Indented => Vec::new(),
+                };
+                let text = content.apply();
+                let text = text.strip_suffix('\n').unwrap_or(&text);
+                out.push(node("CodeBlock", json!([["", classes, []], text])));
+            }
+            Block::HtmlBlock(r) => out.push(node("RawBlock", json!(["html", r.apply()]))),
+            Block::Comment(body) => out.push(node(
+                "RawBlock",
+                json!(["html", format!("<!--{}-->", body.apply())]),
+            )),
+            Block::Raw(r) => out.push(node("RawBlock", json!(["markdown", r.apply()]))),
+            Block::List { start, items } => {
+                let items: Vec<Value> = items.iter().map(|i| json!(self.blocks(i))).collect();
+                match start {
+                    Some(n) => out.push(node(
+                        "OrderedList",
+                        json!([[n, leaf("Decimal"), leaf("Period")], items]),
+                    )),
+                    None => out.push(node("BulletList", json!(items))),
+                }
+            }
+            Block::Item(children) => {
+                out.push(node("Div", json!([empty_attr(), self.blocks(children)])))
+            }
+            Block::Rule => out.push(leaf("HorizontalRule")),
+            // definitions are emitted as notes at their references
+            Block::FootnoteDefinition(..) => {}
+            Block::Table { aligns, head, rows } => out.push(self.table(aligns, head, rows)),
+            // references are already resolved into the links
+            Block::LinkDefinition { .. } => {}
+            Block::TablePlaceholder(_)
+            | Block::TableRow(_)
+            | Block::TableHead(_)
+            | Block::Metadata { .. } => {}
+            Block::Custom(c) => {
+                for b in parse_events_to_blocks(&c.to_events()) {
+                    self.block(&b, out);
+                }
+            }
+        }
+    }
+
+    fn table(
+        &self,
+        aligns: &[Alignment],
+        head: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+    ) -> Value {
+        let cols = aligns
+            .len()
+            .max(head.len())
+            .max(rows.iter().map(|r| r.len()).max().unwrap_or(0));
+        let row = |cells: &[Vec<Inline>]| {
+            let cells: Vec<Value> = (0..cols)
+                .map(|c| {
+                    let content = match cells.get(c) {
+                        Some(inls) if !inls.is_empty() => {
+                            vec![node("Plain", json!(self.inlines(inls)))]
+                        }
+                        _ => Vec::new(),
+                    };
+                    json!([empty_attr(), alignment(aligns.get(c)), 1, 1, content])
+                })
+                .collect();
+            json!([empty_attr(), cells])
+        };
+        let colspecs: Vec<Value> = (0..cols)
+            .map(|c| json!([alignment(aligns.get(c)), leaf("ColWidthDefault")]))
+            .collect();
+        let head: Vec<Value> = if head.is_empty() {
+            Vec::new()
+        } else {
+            vec![row(head)]
+        };
+        let body: Vec<Value> = rows.iter().map(|r| row(r)).collect();
+        node(
+            "Table",
+            json!([
+                empty_attr(),
+                [null, []],
+                colspecs,
+                [empty_attr(), head],
+                [[empty_attr(), 0, [], body]],
+                [empty_attr(), []]
+            ]),
+        )
+    }
+
+    fn inlines(&self, inls: &[Inline]) -> Vec<Value> {
+        let mut out = Vec::new();
+        for inl in inls {
+            self.inline(inl, &mut out);
+        }
+        out
+    }
+
+    fn inline(&self, inl: &Inline, out: &mut Vec<Value>) {
+        match inl {
+            Inline::Text(r) => text_tokens(&r.apply(), out),
+            Inline::Code(r) => out.push(node("Code", json!([empty_attr(), r.apply()]))),
+            Inline::InlineHtml(r) | Inline::Html(r) => {
+                out.push(node("RawInline", json!(["html", r.apply()])))
+            }
+            Inline::Comment(body) => out.push(node(
+                "RawInline",
+                json!(["html", format!("<!--{}-->", body.apply())]),
+            )),
+            Inline::Raw(r) => out.push(node("RawInline", json!(["markdown", r.apply()]))),
+            Inline::SoftBreak => out.push(leaf("SoftBreak")),
+            Inline::HardBreak => out.push(leaf("LineBreak")),
+            Inline::Emphasis(c) => out.push(node("Emph", json!(self.inlines(c)))),
+            Inline::Strong(c) => out.push(node("Strong", json!(self.inlines(c)))),
+            Inline::Strikethrough(c) => out.push(node("Strikeout", json!(self.inlines(c)))),
+            Inline::Subscript(c) => out.push(node("Subscript", json!(self.inlines(c)))),
+            Inline::Superscript(c) => out.push(node("Superscript", json!(self.inlines(c)))),
+            Inline::Link {
+                dest,
+                title,
+                children,
+                ..
+            } => out.push(node(
+                "Link",
+                json!([empty_attr(), self.inlines(children), [dest, title]]),
+            )),
+            Inline::Image {
+                dest,
+                title,
+                children,
+                ..
+            } => out.push(node(
+                "Image",
+                json!([empty_attr(), self.inlines(children), [dest, title]]),
+            )),
+            Inline::FootnoteReference(label) => match self.notes.get(label) {
+                Some(children) if self.expanding.borrow_mut().insert(label.clone()) => {
+                    let note = node("Note", json!(self.blocks(children)));
+                    self.expanding.borrow_mut().remove(label);
+                    out.push(note);
+                }
+                _ => out.push(node("Str", json!(format!("[^{}]", label)))),
+            },
+            Inline::InlineMath(r) => out.push(node("Math", json!([leaf("InlineMath"), r.apply()]))),
+            Inline::DisplayMath(r) => {
+                out.push(node("Math", json!([leaf("DisplayMath"), r.apply()])))
+            }
+            Inline::Custom(c) => {
+                for b in parse_events_to_blocks(&c.to_events()) {
+                    if let Block::Paragraph(inls) = b {
+                        for i in &inls {
+                            self.inline(i, out);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
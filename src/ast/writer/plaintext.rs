@@ -0,0 +1,111 @@
+//! Plain text output for the owned AST.
+//!
+//! `blocks_to_plaintext` keeps the text a reader sees and drops the markup
+//! around it: emphasis and links keep their text, images their alt text,
+//! code its content, and tables become tab-separated lines. HTML, comments,
+//! rules, front matter and link definitions produce no text. Blocks are
+//! separated by a blank line, list items and table rows by a line break.
+
+use crate::ast::parse::parse_events_to_blocks;
+use crate::ast::{Block, Inline};
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+/// Render `blocks` as plain text, e.g. for search indexing or summaries.
+pub fn blocks_to_plaintext(blocks: &[Block]) -> String {
+    join_blocks(blocks, "\n\n")
+}
+
+fn join_blocks(blocks: &[Block], sep: &str) -> String {
+    let mut out = String::new();
+    for b in blocks {
+        let text = block_text(b);
+        if text.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(sep);
+        }
+        out.push_str(&text);
+    }
+    out
+}
+
+fn block_text(b: &Block) -> String {
+    match b {
+        Block::Paragraph(children) | Block::Heading { children, .. } => inlines_text(children),
+        Block::BlockQuote(children)
+        | Block::Alert { children, .. }
+        | Block::FootnoteDefinition(_, children) => join_blocks(children, "\n\n"),
+        Block::Item(children) => join_blocks(children, "\n"),
+        Block::List { items, .. } => {
+            let items: Vec<Block> = items.iter().cloned().map(Block::Item).collect();
+            join_blocks(&items, "\n")
+        }
+        Block::CodeBlock { content, .. } => content.apply().trim_end_matches('\n').to_string(),
+        Block::Raw(content) => content.apply(),
+        Block::TableRow(cells) | Block::TableHead(cells) => row_text(cells),
+        Block::Table { head, rows, .. } => {
+            let mut lines = Vec::new();
+            if head.iter().any(|c| !c.is_empty()) {
+                lines.push(row_text(head));
+            }
+            lines.extend(rows.iter().map(|r| row_text(r)));
+            lines.join("\n")
+        }
+        Block::Custom(node) => blocks_to_plaintext(&parse_events_to_blocks(&node.to_events())),
+        Block::HtmlBlock(_)
+        | Block::Rule
+        | Block::TablePlaceholder(_)
+        | Block::Metadata { .. }
+        | Block::Comment(_)
+        | Block::LinkDefinition { .. } => String::new(),
+    }
+}
+
+fn row_text(cells: &[Vec<Inline>]) -> String {
+    let cells: Vec<String> = cells.iter().map(|c| inlines_text(c)).collect();
+    cells.join("\t")
+}
+
+/// The text of `inlines`, as `blocks_to_plaintext` writes it.
+pub(crate) fn inlines_text(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inl in inlines {
+        push_inline_text(&mut out, inl);
+    }
+    out
+}
+
+fn push_inline_text(out: &mut String, inl: &Inline) {
+    match inl {
+        Inline::Text(r)
+        | Inline::Code(r)
+        | Inline::InlineMath(r)
+        | Inline::DisplayMath(r)
+        | Inline::Raw(r) => r.apply_into(out),
+        Inline::SoftBreak => out.push(' '),
+        Inline::HardBreak => out.push('\n'),
+        Inline::Emphasis(children)
+        | Inline::Strong(children)
+        | Inline::Strikethrough(children)
+        | Inline::Subscript(children)
+        | Inline::Superscript(children)
+        | Inline::Link { children, .. }
+        | Inline::Image { children, .. } => {
+            for c in children {
+                push_inline_text(out, c);
+            }
+        }
+        Inline::Custom(node) => {
+            // the inline events only form blocks inside a paragraph
+            let mut events = vec![Event::Start(Tag::Paragraph)];
+            events.extend(node.to_events());
+            events.push(Event::End(TagEnd::Paragraph));
+            out.push_str(&blocks_to_plaintext(&parse_events_to_blocks(&events)));
+        }
+        Inline::InlineHtml(_)
+        | Inline::Html(_)
+        | Inline::FootnoteReference(_)
+        | Inline::Comment(_) => {}
+    }
+}
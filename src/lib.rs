@@ -1,4 +1,9 @@
 pub mod ast;
+mod macros;
 pub mod text;
 
-pub use text::{Fragment, Line, Region};
+pub use text::{BigRegion, Fragment, Line, Region};
+
+// used by `md!` so callers don't need their own pulldown-cmark dependency
+#[doc(hidden)]
+pub use pulldown_cmark as __pulldown_cmark;
@@ -0,0 +1,233 @@
+//! The `md!` macro for building block lists in code.
+
+/// Build a `Vec<Block>` from a compact markdown-like syntax.
+///
+/// Blocks are optionally separated by `;`:
+///
+/// - `h1 c` .. `h6 c`, `p c`: heading and paragraph, where `c` is a string
+///   literal or a `{ ... }` group of inlines
+/// - `quote { ... }`: blockquote holding nested blocks
+/// - `alert Tip { ... }`: GitHub alert of a `BlockQuoteKind`
+/// - `code "src"`, `code "lang" "src"`: fenced code block
+/// - `ul [item, ...]`, `ol [item, ...]`, `ol 3 [item, ...]`: lists, where an
+///   item is a string literal (a one-line paragraph) or a `{ ... }` group of
+///   blocks
+/// - `hr`, `html "..."`, `comment "..."`, `raw "..."`
+/// - `(expr)`: any expression evaluating to a `Block`
+///
+/// Inlines inside `{ ... }` are string literals, `em { .. }`,
+/// `strong { .. }`, `del { .. }`, `sup { .. }`, `sub { .. }`, `code "x"`,
+/// `math "x"`, `link "url" c`, `img "url" c`, `br` (hard break),
+/// `softbreak`, or `(expr)` for any `Inline`.
+///
+/// ```
+/// use pulldown_cmark_writer::{ast::blocks_to_markdown, md};
+///
+/// let doc = md! {
+///     h1 "Title";
+///     p { "Hello " strong { "world" } };
+///     ul ["one", { p "two"; code "rust" "fn main() {}" }];
+/// };
+/// assert!(blocks_to_markdown(&doc).starts_with("# Title\n"));
+/// ```
+#[macro_export]
+macro_rules! md {
+    ($($body:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut blocks: ::std::vec::Vec<$crate::ast::Block> = ::std::vec::Vec::new();
+        $crate::__md_blocks!(blocks; $($body)*);
+        blocks
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_blocks {
+    ($out:ident;) => {};
+    ($out:ident; ; $($rest:tt)*) => {
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; h1 $c:tt $($rest:tt)*) => {
+        $crate::__md_blocks!(@heading $out; H1 $c; $($rest)*)
+    };
+    ($out:ident; h2 $c:tt $($rest:tt)*) => {
+        $crate::__md_blocks!(@heading $out; H2 $c; $($rest)*)
+    };
+    ($out:ident; h3 $c:tt $($rest:tt)*) => {
+        $crate::__md_blocks!(@heading $out; H3 $c; $($rest)*)
+    };
+    ($out:ident; h4 $c:tt $($rest:tt)*) => {
+        $crate::__md_blocks!(@heading $out; H4 $c; $($rest)*)
+    };
+    ($out:ident; h5 $c:tt $($rest:tt)*) => {
+        $crate::__md_blocks!(@heading $out; H5 $c; $($rest)*)
+    };
+    ($out:ident; h6 $c:tt $($rest:tt)*) => {
+        $crate::__md_blocks!(@heading $out; H6 $c; $($rest)*)
+    };
+    (@heading $out:ident; $level:ident $c:tt; $($rest:tt)*) => {
+        $out.push($crate::ast::Block::Heading {
+            level: $crate::__pulldown_cmark::HeadingLevel::$level,
+            id: ::std::option::Option::None,
+            classes: ::std::vec::Vec::new(),
+            attrs: ::std::vec::Vec::new(),
+            children: $crate::__md_content!($c),
+        });
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; p $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Block::Paragraph($crate::__md_content!($c)));
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; quote { $($inner:tt)* } $($rest:tt)*) => {
+        $out.push($crate::ast::Block::BlockQuote($crate::md!($($inner)*)));
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; alert $kind:ident { $($inner:tt)* } $($rest:tt)*) => {
+        $out.push($crate::ast::Block::Alert {
+            kind: $crate::__pulldown_cmark::BlockQuoteKind::$kind,
+            children: $crate::md!($($inner)*),
+        });
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; code $lang:literal $src:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Block::code($lang, $src));
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; code $src:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Block::code("", $src));
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; ul [$($item:tt),* $(,)?] $($rest:tt)*) => {
+        $out.push($crate::ast::Block::List {
+            start: ::std::option::Option::None,
+            items: ::std::vec![$($crate::__md_item!($item)),*],
+        });
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; ol $start:literal [$($item:tt),* $(,)?] $($rest:tt)*) => {
+        $out.push($crate::ast::Block::List {
+            start: ::std::option::Option::Some($start),
+            items: ::std::vec![$($crate::__md_item!($item)),*],
+        });
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; ol [$($item:tt),* $(,)?] $($rest:tt)*) => {
+        $crate::__md_blocks!($out; ol 1 [$($item),*] $($rest)*)
+    };
+    ($out:ident; hr $($rest:tt)*) => {
+        $out.push($crate::ast::Block::Rule);
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; html $s:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Block::HtmlBlock($crate::Region::from_str($s)));
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; comment $s:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Block::Comment($crate::Region::from_str($s)));
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; raw $s:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Block::Raw($crate::Region::from_str($s)));
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+    ($out:ident; ($e:expr) $($rest:tt)*) => {
+        $out.push($e);
+        $crate::__md_blocks!($out; $($rest)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_item {
+    ({ $($inner:tt)* }) => {
+        $crate::md!($($inner)*)
+    };
+    ($s:literal) => {
+        ::std::vec![$crate::ast::Block::Paragraph($crate::__md_content!($s))]
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_content {
+    ({ $($inner:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut inlines: ::std::vec::Vec<$crate::ast::Inline> = ::std::vec::Vec::new();
+        $crate::__md_inlines!(inlines; $($inner)*);
+        inlines
+    }};
+    ($s:literal) => {
+        ::std::vec![$crate::ast::Inline::Text($crate::Region::from_str($s))]
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_inlines {
+    ($out:ident;) => {};
+    ($out:ident; $s:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Text($crate::Region::from_str($s)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; em $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Emphasis($crate::__md_content!($c)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; strong $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Strong($crate::__md_content!($c)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; del $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Strikethrough($crate::__md_content!($c)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; sup $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Superscript($crate::__md_content!($c)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; sub $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Subscript($crate::__md_content!($c)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; code $s:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Code($crate::Region::from_str($s)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; math $s:literal $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::InlineMath($crate::Region::from_str($s)));
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; link $url:literal $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Link {
+            link_type: $crate::__pulldown_cmark::LinkType::Inline,
+            dest: ::std::string::String::from($url),
+            title: ::std::string::String::new(),
+            id: ::std::string::String::new(),
+            children: $crate::__md_content!($c),
+        });
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; img $url:literal $c:tt $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::Image {
+            link_type: $crate::__pulldown_cmark::LinkType::Inline,
+            dest: ::std::string::String::from($url),
+            title: ::std::string::String::new(),
+            id: ::std::string::String::new(),
+            children: $crate::__md_content!($c),
+        });
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; br $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::HardBreak);
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; softbreak $($rest:tt)*) => {
+        $out.push($crate::ast::Inline::SoftBreak);
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+    ($out:ident; ($e:expr) $($rest:tt)*) => {
+        $out.push($e);
+        $crate::__md_inlines!($out; $($rest)*)
+    };
+}
@@ -0,0 +1,377 @@
+use super::region::normalize_newlines;
+use super::{Fragment, Line, Region};
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+
+/// A `Region` for very large documents. Its main lines live in a ring
+/// buffer, so `push_front_line` is O(1) and `insert_line`, `remove_line`
+/// and `splice` only shift the lines on the shorter side of the edit,
+/// where `Region` shifts every line after it.
+///
+/// `BigRegion` has the line-level API of `Region`, except that `lines`
+/// returns the ring buffer rather than a slice. Convert with `From` in
+/// either direction (moving the lines) to use the rest, such as `wrap`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BigRegion {
+    lines: VecDeque<Line>,
+    // written after the main lines, as in `Region`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    suffix: Vec<Line>,
+}
+
+impl BigRegion {
+    pub fn new() -> Self {
+        BigRegion {
+            lines: VecDeque::new(),
+            suffix: Vec::new(),
+        }
+    }
+
+    /// Create a region from a multiline &str, like `Region::from_str`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        Region::from_str(s).into()
+    }
+
+    /// Create a region from lines, all of them main lines.
+    pub fn from_lines(lines: impl IntoIterator<Item = Line>) -> Self {
+        BigRegion {
+            lines: lines.into_iter().collect(),
+            suffix: Vec::new(),
+        }
+    }
+
+    fn all_lines_mut(&mut self) -> impl Iterator<Item = &mut Line> {
+        self.lines.iter_mut().chain(&mut self.suffix)
+    }
+
+    /// Push a line to the front, in constant time.
+    pub fn push_front_line(&mut self, line: Line) -> &mut Self {
+        self.lines.push_front(line);
+        self
+    }
+
+    /// Push a line to the back
+    pub fn push_back_line(&mut self, line: Line) -> &mut Self {
+        self.lines.push_back(line);
+        self
+    }
+
+    /// Append text after the last main line, like `Region::push_str`.
+    pub fn push_str(&mut self, s: &str) -> &mut Self {
+        let s = normalize_newlines(s);
+        let mut parts = s.split('\n');
+        let first = parts.next().unwrap_or_default();
+        match self.lines.back_mut() {
+            Some(line) if !first.is_empty() => {
+                line.push(first.to_string());
+            }
+            None if !s.is_empty() => {
+                self.lines.push_back(Line::from_str(first));
+            }
+            _ => {}
+        }
+        for part in parts {
+            self.lines.push_back(Line::from_str(part));
+        }
+        self
+    }
+
+    /// Push a line to the suffix (appended after the main lines)
+    pub fn push_back_suffix_line(&mut self, line: Line) -> &mut Self {
+        self.suffix.push(line);
+        self
+    }
+
+    /// Add a prefix fragment to every line
+    pub fn prefix_each_line<F: Into<Fragment>>(&mut self, prefix: F) -> &mut Self {
+        let p = prefix.into();
+        for line in self.all_lines_mut() {
+            line.prepend(p.clone());
+        }
+        self
+    }
+
+    /// Add `prefix` to every line holding text, and `blank_prefix` to the
+    /// empty ones, like `Region::prefix_each_line_with`.
+    pub fn prefix_each_line_with<F: Into<Fragment>, B: Into<Fragment>>(
+        &mut self,
+        prefix: F,
+        blank_prefix: B,
+    ) -> &mut Self {
+        let p = prefix.into();
+        let b = blank_prefix.into();
+        for line in self.all_lines_mut() {
+            if line.is_empty() {
+                if !b.is_empty() {
+                    line.prepend(b.clone());
+                }
+            } else {
+                line.prepend(p.clone());
+            }
+        }
+        self
+    }
+
+    /// Indent each line by `n` spaces
+    pub fn indent_each_line(&mut self, n: usize) -> &mut Self {
+        if n == 0 {
+            return self;
+        }
+        self.prefix_each_line(Fragment::spaces(n))
+    }
+
+    /// Add a prefix to the first line and pad the others to line up with
+    /// it, like `Region::prefix_first_then_indent_rest`.
+    pub fn prefix_first_then_indent_rest<F: Into<Fragment>>(&mut self, prefix: F) -> &mut Self {
+        let p = prefix.into();
+        let pad = Fragment::spaces(p.width());
+        self.prefix_first_then_rest(p, pad)
+    }
+
+    /// Add `first` to the first line and `rest` to every other line, like
+    /// `Region::prefix_first_then_rest`.
+    pub fn prefix_first_then_rest<F: Into<Fragment>, R: Into<Fragment>>(
+        &mut self,
+        first: F,
+        rest: R,
+    ) -> &mut Self {
+        let first = first.into();
+        let rest = rest.into();
+        let mut lines = self.lines.iter_mut().chain(&mut self.suffix);
+        if let Some(line) = lines.next() {
+            line.prepend(first);
+        }
+        if !rest.is_empty() {
+            for line in lines {
+                line.prepend(rest.clone());
+            }
+        }
+        self
+    }
+
+    /// Convert the region into a String, joining lines with '\n'.
+    pub fn apply(&self) -> String {
+        let mut out = String::new();
+        self.apply_into(&mut out);
+        out
+    }
+
+    /// Append the text of the region to `out`, as `apply` returns it.
+    pub fn apply_into(&self, out: &mut String) {
+        for (i, line) in self.iter_lines().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            line.apply_into(out);
+        }
+    }
+
+    /// Convenience to check whether region is empty
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.suffix.is_empty()
+    }
+
+    /// Consume the region and return its lines as a Vec<Line>.
+    pub fn into_lines(self) -> Vec<Line> {
+        let mut out = Vec::from(self.lines);
+        out.extend(self.suffix);
+        out
+    }
+
+    /// Move the lines of `other` to the end of the region, like
+    /// `Region::append`.
+    pub fn append(&mut self, other: BigRegion) -> &mut Self {
+        self.lines.extend(self.suffix.drain(..));
+        self.lines.extend(other.lines);
+        self.lines.extend(other.suffix);
+        self
+    }
+
+    /// Insert a main line at `idx`. Panics if `idx` is greater than the
+    /// number of main lines.
+    pub fn insert_line(&mut self, idx: usize, line: Line) -> &mut Self {
+        self.lines.insert(idx, line);
+        self
+    }
+
+    /// Remove and return the main line at `idx`. Panics if `idx` is out of
+    /// bounds.
+    pub fn remove_line(&mut self, idx: usize) -> Line {
+        self.lines
+            .remove(idx)
+            .unwrap_or_else(|| panic!("line index {idx} out of bounds"))
+    }
+
+    /// Replace the main lines in `range` with `lines`, returning the
+    /// removed ones. Panics if the range is out of bounds.
+    pub fn splice(
+        &mut self,
+        range: impl std::ops::RangeBounds<usize>,
+        lines: impl IntoIterator<Item = Line>,
+    ) -> Vec<Line> {
+        let start = match range.start_bound() {
+            std::ops::Bound::Included(&i) => i,
+            std::ops::Bound::Excluded(&i) => i + 1,
+            std::ops::Bound::Unbounded => 0,
+        };
+        let removed = self.lines.drain(range).collect();
+        let lines: Vec<Line> = lines.into_iter().collect();
+        if start < self.lines.len() / 2 {
+            // closer to the front: move the head out of the way instead
+            let head: Vec<Line> = self.lines.drain(..start).collect();
+            for line in lines.into_iter().rev().chain(head.into_iter().rev()) {
+                self.lines.push_front(line);
+            }
+        } else {
+            let tail = self.lines.split_off(start);
+            self.lines.extend(lines);
+            self.lines.extend(tail);
+        }
+        removed
+    }
+
+    /// Replace every occurrence of `from` with `to` in every line, suffix
+    /// lines included.
+    pub fn replace_str(&mut self, from: &str, to: &str) -> &mut Self {
+        for line in self.all_lines_mut() {
+            line.replace_str(from, to);
+        }
+        self
+    }
+
+    /// Remove trailing whitespace from every line, suffix lines included.
+    pub fn trim_trailing_whitespace(&mut self) -> &mut Self {
+        for line in self.all_lines_mut() {
+            line.trim_end();
+        }
+        self
+    }
+
+    /// Expand the tabs of every line, suffix lines included.
+    pub fn expand_tabs(&mut self, tab_width: usize) -> &mut Self {
+        for line in self.all_lines_mut() {
+            line.expand_tabs(tab_width);
+        }
+        self
+    }
+
+    /// Remove the blank main lines at the start and at the end.
+    pub fn trim_blank_lines(&mut self) -> &mut Self {
+        while self.lines.back().is_some_and(Line::is_blank) {
+            self.lines.pop_back();
+        }
+        while self.lines.front().is_some_and(Line::is_blank) {
+            self.lines.pop_front();
+        }
+        self
+    }
+
+    /// Shorten every run of consecutive blank main lines to at most `max`
+    /// lines.
+    pub fn collapse_blank_runs(&mut self, max: usize) -> &mut Self {
+        let mut run = 0;
+        self.lines.retain(|l| {
+            run = if l.is_blank() { run + 1 } else { 0 };
+            run <= max
+        });
+        self
+    }
+
+    /// Push each line to the back, as `push_back_line` does.
+    pub fn extend_lines(&mut self, lines: impl IntoIterator<Item = Line>) -> &mut Self {
+        self.lines.extend(lines);
+        self
+    }
+
+    /// The main lines of the region, without the suffix lines.
+    pub fn lines(&self) -> &VecDeque<Line> {
+        &self.lines
+    }
+
+    /// The suffix lines, written after the main lines.
+    pub fn suffix_lines(&self) -> &[Line] {
+        &self.suffix
+    }
+
+    /// Remove the suffix lines and return them.
+    pub fn take_suffix(&mut self) -> Vec<Line> {
+        std::mem::take(&mut self.suffix)
+    }
+
+    /// Turn the suffix lines into main lines, after the existing ones.
+    pub fn merge_suffix_into_lines(&mut self) -> &mut Self {
+        self.lines.extend(self.suffix.drain(..));
+        self
+    }
+
+    /// Iterate over every line: the main lines, then the suffix lines.
+    pub fn iter_lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter().chain(&self.suffix)
+    }
+
+    /// The last main line, to modify it in place.
+    pub fn last_line_mut(&mut self) -> Option<&mut Line> {
+        self.lines.back_mut()
+    }
+
+    /// Width of the widest line, suffix lines included.
+    pub fn max_width(&self) -> usize {
+        self.iter_lines().map(Line::width).max().unwrap_or(0)
+    }
+
+    /// Number of lines, suffix lines included.
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + self.suffix.len()
+    }
+}
+
+impl From<Region> for BigRegion {
+    fn from(mut r: Region) -> Self {
+        let suffix = r.take_suffix();
+        BigRegion {
+            lines: VecDeque::from(std::mem::take(r.lines_mut())),
+            suffix,
+        }
+    }
+}
+
+impl From<BigRegion> for Region {
+    fn from(r: BigRegion) -> Self {
+        let mut out = Region::from(Vec::from(r.lines));
+        for line in r.suffix {
+            out.push_back_suffix_line(line);
+        }
+        out
+    }
+}
+
+impl FromIterator<Line> for BigRegion {
+    fn from_iter<I: IntoIterator<Item = Line>>(iter: I) -> Self {
+        BigRegion::from_lines(iter)
+    }
+}
+
+impl Extend<Line> for BigRegion {
+    fn extend<I: IntoIterator<Item = Line>>(&mut self, iter: I) {
+        self.extend_lines(iter);
+    }
+}
+
+/// Appends text as `push_str` does, like the impl for `Region`.
+impl fmt::Write for BigRegion {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl Display for BigRegion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.apply())
+    }
+}
@@ -1,36 +1,128 @@
 use std::fmt::{self, Display, Formatter};
-use std::sync::Arc;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock};
 
 /// A Fragment is the smallest unit: an owned, cheaply clonable piece of text.
 /// Internally we use Arc<str> so cloning fragments is cheap and we avoid
-/// unnecessary allocations while composing lines/regions.
-#[derive(Clone, Eq, PartialEq, Hash, Debug)]
-pub struct Fragment(Arc<str>);
+/// unnecessary allocations while composing lines/regions. Fragments made
+/// with `Fragment::from_static` borrow their text and allocate nothing.
+///
+/// A fragment can be marked atomic (see `Fragment::atomic`): operations
+/// that re-layout text, such as `Region::wrap`, never break inside it.
+///
+/// Fragments compare and hash by text and atomicity; the cached width and
+/// length take no part, so text types are sound keys for maps and sets.
+#[derive(Clone, Debug)]
+pub struct Fragment {
+    text: Text,
+    atomic: bool,
+    /// Display width, measured on first use.
+    width: OnceLock<usize>,
+    /// Character count, counted on first use.
+    len: OnceLock<usize>,
+}
+
+#[derive(Clone, Debug)]
+enum Text {
+    Static(&'static str),
+    Shared(Arc<str>),
+}
+
+/// Spaces borrowed by `Fragment::spaces` for common indentation widths.
+const SPACES: &str = "                                                                ";
+
+impl PartialEq for Fragment {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str() && self.atomic == other.atomic
+    }
+}
+
+impl Eq for Fragment {}
+
+impl Hash for Fragment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+        self.atomic.hash(state);
+    }
+}
 
 impl Fragment {
     /// Create a fragment from a &str
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
-        Fragment(Arc::from(s.to_owned()))
+        Fragment::from_string(s.to_owned())
     }
 
     /// Create a fragment from a String
     pub fn from_string(s: String) -> Self {
-        Fragment(Arc::from(s))
+        Fragment {
+            text: Text::Shared(Arc::from(s)),
+            atomic: false,
+            width: OnceLock::new(),
+            len: OnceLock::new(),
+        }
+    }
+
+    /// Create a fragment borrowing a string literal, such as a list marker
+    /// or an emphasis delimiter. Neither this nor cloning the fragment
+    /// allocates.
+    pub const fn from_static(s: &'static str) -> Self {
+        Fragment {
+            text: Text::Static(s),
+            atomic: false,
+            width: OnceLock::new(),
+            len: OnceLock::new(),
+        }
+    }
+
+    /// Create a fragment that is never broken across lines, e.g. a link or
+    /// an inline code span.
+    pub fn atomic(s: impl Into<String>) -> Self {
+        Fragment {
+            text: Text::Shared(Arc::from(s.into())),
+            atomic: true,
+            width: OnceLock::new(),
+            len: OnceLock::new(),
+        }
+    }
+
+    /// Whether the fragment must stay on one line.
+    pub fn is_atomic(&self) -> bool {
+        self.atomic
     }
 
     /// Create a fragment which is n spaces (useful for indentation)
     pub fn spaces(n: usize) -> Self {
-        Fragment::from_string(" ".repeat(n))
+        match SPACES.get(..n) {
+            Some(s) => Fragment::from_static(s),
+            None => Fragment::from_string(" ".repeat(n)),
+        }
     }
 
     /// Return the inner &str
     pub fn as_str(&self) -> &str {
-        &self.0
+        match &self.text {
+            Text::Static(s) => s,
+            Text::Shared(s) => s,
+        }
     }
 
-    /// Character length
+    /// Display width in terminal columns, as measured by unicode-width.
+    /// Computed once per fragment and shared by its clones made afterwards.
+    pub fn width(&self) -> usize {
+        *self
+            .width
+            .get_or_init(|| unicode_width::UnicodeWidthStr::width(self.as_str()))
+    }
+
+    /// Character length. Counted once per fragment, like `width`.
     pub fn len(&self) -> usize {
-        self.as_str().chars().count()
+        *self.len.get_or_init(|| self.as_str().chars().count())
+    }
+
+    /// Whether the fragment holds no text
+    pub fn is_empty(&self) -> bool {
+        self.as_str().is_empty()
     }
 }
 
@@ -46,6 +138,20 @@ impl From<String> for Fragment {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Fragment {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Fragment {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d).map(Fragment::from_string)
+    }
+}
+
 impl Display for Fragment {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.write_str(self.as_str())
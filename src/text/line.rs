@@ -1,8 +1,14 @@
 use super::Fragment;
 use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// A Line is a sequence of Fragments. We avoid joining fragments until the
 /// final `apply()` so intermediate operations can cheaply clone fragments.
+///
+/// Two lines are equal when they render the same text, no matter how that
+/// text is split into fragments.
 #[derive(Clone, Debug, Default)]
 pub struct Line {
     fragments: Vec<Fragment>,
@@ -16,12 +22,20 @@ impl Line {
     }
 
     /// Create a line with a single fragment from &str
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         Line {
             fragments: vec![Fragment::from(s)],
         }
     }
 
+    /// Create a line from fragments, in order.
+    pub fn from_fragments(fragments: impl IntoIterator<Item = Fragment>) -> Self {
+        Line {
+            fragments: fragments.into_iter().collect(),
+        }
+    }
+
     /// Push fragment to the end
     pub fn push<F: Into<Fragment>>(&mut self, f: F) -> &mut Self {
         self.fragments.push(f.into());
@@ -34,13 +48,154 @@ impl Line {
         self
     }
 
+    /// The fragments of the line, in order.
+    pub fn fragments(&self) -> &[Fragment] {
+        &self.fragments
+    }
+
+    /// The text of the line without allocating, when it is held by a
+    /// single fragment (or none).
+    pub(crate) fn as_single_str(&self) -> Option<&str> {
+        match self.fragments.as_slice() {
+            [] => Some(""),
+            [frag] => Some(frag.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Replace every occurrence of `from` with `to`, including occurrences
+    /// spanning several fragments. A line without occurrences keeps its
+    /// fragments; an edited line becomes a single fragment.
+    pub fn replace_str(&mut self, from: &str, to: &str) -> &mut Self {
+        if from.is_empty() {
+            return self;
+        }
+        let text = self.apply();
+        if text.contains(from) {
+            self.fragments = vec![Fragment::from_string(text.replace(from, to))];
+        }
+        self
+    }
+
+    /// Replace every tab with spaces up to the next tab stop, every
+    /// `tab_width` columns from the start of the line. Columns are counted
+    /// in display width. A tab width of 0 removes tabs. A line without tabs
+    /// keeps its fragments; an edited line becomes a single fragment.
+    pub fn expand_tabs(&mut self, tab_width: usize) -> &mut Self {
+        if !self.fragments.iter().any(|f| f.as_str().contains('\t')) {
+            return self;
+        }
+        let mut out = String::new();
+        let mut col = 0;
+        for c in self.fragments.iter().flat_map(|f| f.as_str().chars()) {
+            if c == '\t' {
+                let n = if tab_width == 0 {
+                    0
+                } else {
+                    tab_width - col % tab_width
+                };
+                out.extend(std::iter::repeat_n(' ', n));
+                col += n;
+            } else {
+                out.push(c);
+                col += c.width().unwrap_or(0);
+            }
+        }
+        self.fragments = vec![Fragment::from_string(out)];
+        self
+    }
+
+    /// Display width of the line, the sum of the widths of its fragments.
+    pub fn width(&self) -> usize {
+        self.fragments.iter().map(Fragment::width).sum()
+    }
+
+    /// Shorten the line to at most `width` columns, ending it with
+    /// `ellipsis` when text was cut. Lines that fit are left alone. The cut
+    /// falls between grapheme clusters, and an atomic fragment that does
+    /// not fit is dropped whole. When `ellipsis` alone is wider than
+    /// `width`, the line is cut without it.
+    pub fn truncate_to_width(&mut self, width: usize, ellipsis: &str) -> &mut Self {
+        if self.width() <= width {
+            return self;
+        }
+        let ellipsis_width = UnicodeWidthStr::width(ellipsis);
+        let (budget, ellipsis) = match width.checked_sub(ellipsis_width) {
+            Some(budget) => (budget, ellipsis),
+            None => (width, ""),
+        };
+        let mut used = 0;
+        let mut kept = Vec::new();
+        for frag in self.fragments.drain(..) {
+            if used + frag.width() <= budget {
+                used += frag.width();
+                kept.push(frag);
+                continue;
+            }
+            if !frag.is_atomic() {
+                let mut end = 0;
+                for (i, g) in frag.as_str().grapheme_indices(true) {
+                    let w = UnicodeWidthStr::width(g);
+                    if used + w > budget {
+                        break;
+                    }
+                    used += w;
+                    end = i + g.len();
+                }
+                if end > 0 {
+                    kept.push(Fragment::from_str(&frag.as_str()[..end]));
+                }
+            }
+            break;
+        }
+        self.fragments = kept;
+        if !ellipsis.is_empty() {
+            self.fragments.push(Fragment::from_str(ellipsis));
+        }
+        self
+    }
+
+    /// Whether the line holds no text at all.
+    pub fn is_empty(&self) -> bool {
+        self.fragments.iter().all(Fragment::is_empty)
+    }
+
+    /// Whether the line holds nothing but whitespace.
+    pub fn is_blank(&self) -> bool {
+        self.fragments.iter().all(|f| f.as_str().trim().is_empty())
+    }
+
+    /// Remove trailing whitespace, keeping the fragments before it.
+    pub fn trim_end(&mut self) -> &mut Self {
+        while let Some(last) = self.fragments.last_mut() {
+            let trimmed = last.as_str().trim_end();
+            if !trimmed.is_empty() {
+                if trimmed.len() < last.as_str().len() {
+                    *last = if last.is_atomic() {
+                        Fragment::atomic(trimmed)
+                    } else {
+                        Fragment::from_str(trimmed)
+                    };
+                }
+                break;
+            }
+            self.fragments.pop();
+        }
+        self
+    }
+
     /// Join fragments into a single String
     pub fn apply(&self) -> String {
         let mut out = String::new();
+        self.apply_into(&mut out);
+        out
+    }
+
+    /// Append the text of the line to `out`, e.g. to reuse a buffer.
+    pub fn apply_into(&self, out: &mut String) {
         for frag in &self.fragments {
             out.push_str(frag.as_str());
         }
-        out
     }
 
     /// Extend this line by cloning fragments from another line. This is a
@@ -51,6 +206,73 @@ impl Line {
     }
 }
 
+impl Line {
+    fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.fragments.iter().flat_map(|f| f.as_str().bytes())
+    }
+}
+
+impl PartialEq for Line {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes().eq(other.bytes())
+    }
+}
+
+impl Eq for Line {}
+
+/// Hashes the rendered text, consistently with equality.
+impl Hash for Line {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.as_single_str() {
+            Some(s) => s.hash(state),
+            None => self.apply().hash(state),
+        }
+    }
+}
+
+// Lines serialize as their rendered text and deserialize into a single
+// fragment, which compares equal to the original.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Line {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&self.apply())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Line {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        Ok(Line {
+            fragments: vec![Fragment::from_string(s)],
+        })
+    }
+}
+
+impl FromIterator<Fragment> for Line {
+    fn from_iter<I: IntoIterator<Item = Fragment>>(iter: I) -> Self {
+        Line::from_fragments(iter)
+    }
+}
+
+impl Extend<Fragment> for Line {
+    fn extend<I: IntoIterator<Item = Fragment>>(&mut self, iter: I) {
+        self.fragments.extend(iter);
+    }
+}
+
+/// Writing to a line pushes each piece as a fragment, so
+/// `write!(line, "{n} items")` needs no intermediate `String`. It never
+/// fails.
+impl fmt::Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if !s.is_empty() {
+            self.push(s);
+        }
+        Ok(())
+    }
+}
+
 impl Display for Line {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.write_str(&self.apply())
@@ -1,7 +1,10 @@
+pub mod big_region;
 pub mod fragment;
 pub mod line;
 pub mod region;
+mod wrap;
 
+pub use big_region::BigRegion;
 pub use fragment::Fragment;
 pub use line::Line;
 pub use region::Region;
@@ -26,6 +29,26 @@ mod tests {
         r = Region::from_str("a\nb");
         r.prefix_first_then_indent_rest("- ");
         assert_eq!(r.apply(), "- a\n  b");
+
+        // continuations line up with wide markers
+        r = Region::from_str("a\nb");
+        r.prefix_first_then_indent_rest("・ ");
+        assert_eq!(r.apply(), "・ a\n   b");
+
+        r = Region::from_str("a\nb\nc");
+        r.prefix_first_then_rest("1. ", "    ");
+        assert_eq!(r.apply(), "1. a\n    b\n    c");
+    }
+
+    #[test]
+    fn blank_lines_get_their_own_prefix() {
+        let mut r = Region::from_str("a\n\nb");
+        r.prefix_each_line_with("> ", ">");
+        assert_eq!(r.apply(), "> a\n>\n> b");
+
+        let mut r = Region::from_str("a\n\nb");
+        r.prefix_each_line_with("  ", "");
+        assert_eq!(r.apply(), "  a\n\n  b");
     }
 
     #[test]
@@ -35,4 +58,294 @@ mod tests {
         r.push_front_line(Line::from_str("head"));
         assert_eq!(r.apply(), "head\ntail");
     }
+
+    #[test]
+    fn push_str_continues_the_last_line() {
+        for (a, b) in [
+            ("ab", "cd"),
+            ("a", "b\nc"),
+            ("", "\nx"),
+            ("a\n", "b"),
+            ("x", ""),
+        ] {
+            let mut r = Region::from_str(a);
+            r.push_str(b);
+            assert_eq!(
+                r,
+                Region::from_str(&format!("{}{}", a, b)),
+                "{:?} + {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn equality_ignores_fragmentation() {
+        let mut a = Line::new();
+        a.push("hel").push("lo");
+        assert_eq!(a, Line::from_str("hello"));
+        assert_ne!(a, Line::from_str("hell"));
+
+        let mut r = Region::new();
+        r.push_back_line(a);
+        assert_eq!(r, Region::from_str("hello"));
+
+        let mut with_suffix = Region::from_str("hello");
+        with_suffix.push_back_suffix_line(Line::from_str("[a]: b"));
+        assert_ne!(with_suffix, Region::from_str("hello\n[a]: b"));
+    }
+
+    #[test]
+    fn lines_are_accessible_in_place() {
+        let mut r = Region::from_str("one\ntwo");
+        r.push_back_suffix_line(Line::from_str("[a]: b"));
+        assert_eq!(r.lines(), &[Line::from_str("one"), Line::from_str("two")]);
+        assert_eq!(r.suffix_lines(), &[Line::from_str("[a]: b")]);
+        assert_eq!(r.line_count(), 3);
+        let all: Vec<String> = r.iter_lines().map(Line::apply).collect();
+        assert_eq!(all, ["one", "two", "[a]: b"]);
+
+        r.last_line_mut().unwrap().push("!");
+        assert_eq!(r.apply(), "one\ntwo!\n[a]: b");
+    }
+
+    #[test]
+    fn suffix_lines_can_be_taken_or_merged() {
+        let mut r = Region::from_str("text");
+        r.push_back_suffix_line(Line::from_str("[a]: b"));
+        let mut merged = r.clone();
+        merged.merge_suffix_into_lines();
+        assert_eq!(merged.apply(), r.apply());
+        assert_eq!(merged.lines().len(), 2);
+
+        assert_eq!(r.take_suffix(), [Line::from_str("[a]: b")]);
+        assert_eq!(r.apply(), "text");
+        assert!(r.suffix_lines().is_empty());
+    }
+
+    #[test]
+    fn regions_and_lines_collect_from_iterators() {
+        let line: Line = ["a", "b"].into_iter().map(Fragment::from).collect();
+        assert_eq!(line.fragments().len(), 2);
+        assert_eq!(line, Line::from_fragments([Fragment::from("ab")]));
+
+        let mut r: Region = ["x", "y"].into_iter().map(Line::from_str).collect();
+        r.extend([line.clone()]);
+        assert_eq!(r.apply(), "x\ny\nab");
+        assert_eq!(Region::from_lines(r.lines().to_vec()), r);
+
+        let mut line = line;
+        line.extend([Fragment::from("c")]);
+        assert_eq!(line.apply(), "abc");
+    }
+
+    #[test]
+    fn tabs_expand_to_tab_stops() {
+        let mut line = Line::new();
+        line.push("a\tb").push("中\t").push("\tc");
+        line.expand_tabs(4);
+        assert_eq!(line.apply(), "a   b中     c");
+        assert_eq!(Line::from_str("\tx").expand_tabs(0).apply(), "x");
+
+        let mut r = Region::from_str("\tx\n  \ty");
+        r.push_back_suffix_line(Line::from_str("\tz"));
+        r.expand_tabs(2);
+        assert_eq!(r.apply(), "  x\n    y\n  z");
+    }
+
+    #[test]
+    fn lines_truncate_to_width() {
+        let mut line = Line::from_str("short");
+        assert_eq!(line.truncate_to_width(5, "…").apply(), "short");
+        assert_eq!(line.truncate_to_width(4, "…").apply(), "sho…");
+
+        // wide characters and grapheme clusters are never split
+        let mut line = Line::from_str("中文字");
+        assert_eq!(line.truncate_to_width(4, "…").apply(), "中…");
+        let mut line = Line::from_str("e\u{301}e\u{301}e\u{301}");
+        assert_eq!(line.truncate_to_width(2, "…").apply(), "e\u{301}…");
+
+        let mut line = Line::new();
+        line.push("see ").push(Fragment::atomic("<url>"));
+        assert_eq!(line.truncate_to_width(7, "...").apply(), "see ...");
+        assert_eq!(
+            Line::from_str("abc").truncate_to_width(2, "...").apply(),
+            "ab"
+        );
+    }
+
+    #[test]
+    fn static_fragments_match_owned_ones() {
+        const MARKER: &str = "- ";
+        let frag = Fragment::from_static(MARKER);
+        assert_eq!(frag, Fragment::from_str("- "));
+        assert!(std::ptr::eq(frag.clone().as_str(), MARKER));
+        assert_eq!(Fragment::spaces(3).as_str(), "   ");
+        assert_eq!(Fragment::spaces(100).len(), 100);
+    }
+
+    #[test]
+    fn big_regions_match_regions() {
+        let mut big = BigRegion::from_str("b\nc\nd\ne");
+        big.push_front_line(Line::from_str("a"));
+        big.push_back_suffix_line(Line::from_str("[x]: y"));
+        assert_eq!(
+            big.splice(1..2, [Line::from_str("B")]),
+            [Line::from_str("b")]
+        );
+        big.splice(4..4, [Line::from_str("e2"), Line::from_str("f")]);
+        big.prefix_each_line_with("> ", ">");
+        assert_eq!(big.apply(), "> a\n> B\n> c\n> d\n> e2\n> f\n> e\n> [x]: y");
+
+        let mut region = Region::from(big.clone());
+        assert_eq!(region.suffix_lines().len(), 1);
+        assert_eq!(region.apply(), big.apply());
+        region.remove_line(0);
+        big.remove_line(0);
+        assert_eq!(BigRegion::from(region), big);
+    }
+
+    #[test]
+    fn lines_and_regions_are_fmt_writers() {
+        use std::fmt::Write;
+        let mut line = Line::from_str("- ");
+        write!(line, "{} items", 3).unwrap();
+        assert_eq!(line.apply(), "- 3 items");
+
+        let mut r = Region::from_str("a");
+        let c = 'c';
+        write!(r, "b\n{c}\n").unwrap();
+        assert_eq!(r, Region::from_str("ab\nc\n"));
+        let mut big = BigRegion::new();
+        write!(big, "x\ny").unwrap();
+        assert_eq!(big.apply(), "x\ny");
+    }
+
+    #[test]
+    fn carriage_returns_end_lines() {
+        let r = Region::from_str("a\r\nb\rc\n\r\nd");
+        assert_eq!(r, Region::from_str("a\nb\nc\n\nd"));
+        assert_eq!(Region::from_str_verbatim("a\r\nb").apply(), "a\r\nb");
+
+        let mut r = Region::from_str("x");
+        r.push_str("y\r\nz");
+        assert_eq!(r.lines(), [Line::from_str("xy"), Line::from_str("z")]);
+    }
+
+    #[test]
+    fn hashes_follow_rendered_text() {
+        use std::collections::HashSet;
+        let mut split = Line::new();
+        split.push("ab").push("c");
+        let lines: HashSet<Line> = [split, Line::from_str("abc")].into_iter().collect();
+        assert_eq!(lines.len(), 1);
+
+        let mut a = Region::from_str("x");
+        a.push_str("y\nz");
+        let regions: HashSet<Region> = [a, Region::from_str("xy\nz")].into_iter().collect();
+        assert_eq!(regions.len(), 1);
+    }
+
+    #[test]
+    fn regions_compose() {
+        let mut a = Region::from_str("a");
+        a.push_back_suffix_line(Line::from_str("[x]: y"));
+        let mut b = Region::from(vec![Line::from_str("b")]);
+        b.extend_lines([Line::from_str("c")]);
+        let text = format!("{}\n{}", a.apply(), b.apply());
+        a.append(b);
+        assert_eq!(a.apply(), text);
+        assert_eq!(a.line_count(), 4);
+        assert!(a.suffix_lines().is_empty());
+    }
+
+    #[test]
+    fn regions_are_edited_in_place() {
+        let mut r = Region::from_str("```\ncode\n```");
+        r.insert_line(1, Line::from_str("first"));
+        assert_eq!(r.remove_line(2), Line::from_str("code"));
+        let removed = r.splice(0..1, [Line::from_str("~~~rust")]);
+        assert_eq!(removed, [Line::from_str("```")]);
+        r.replace_str("```", "~~~");
+        assert_eq!(r.apply(), "~~~rust\nfirst\n~~~");
+
+        // occurrences may span fragments
+        let mut l = Line::new();
+        l.push("> ").push(">");
+        let mut untouched = Line::new();
+        untouched.push("a").push("b");
+        let mut r = Region::from(vec![l, untouched]);
+        r.replace_str(">>", "|");
+        assert_eq!(r.apply(), "> >\nab");
+        r.replace_str("> >", "|");
+        assert_eq!(r.apply(), "|\nab");
+        assert_eq!(r.lines()[1].fragments().len(), 2);
+    }
+
+    #[test]
+    fn wrap_breaks_at_word_boundaries() {
+        let mut r = Region::from_str("the quick brown\nfox jumps over\n\nthe lazy dog");
+        r.wrap(10);
+        assert_eq!(
+            r.apply(),
+            "the quick\nbrown fox\njumps over\n\nthe lazy\ndog"
+        );
+
+        // hard breaks are kept, long words get a line of their own
+        let mut r = Region::from_str("a b  \nc\\\nabcdefghijkl d");
+        r.wrap(5);
+        assert_eq!(r.apply(), "a b  \nc\\\nabcdefghijkl\nd");
+    }
+
+    #[test]
+    fn wrap_handles_cjk_and_atomic_fragments() {
+        let mut r = Region::from_str("中文文本可以\n在任意字符之间换行");
+        r.wrap(8);
+        assert_eq!(r.apply(), "中文文本\n可以在任\n意字符之\n间换行");
+
+        let mut line = Line::from_str("see ");
+        line.push(Fragment::atomic("[a link](https://x.y)"))
+            .push(" now");
+        let mut r = Region::from(vec![line]);
+        r.wrap(10);
+        assert_eq!(r.apply(), "see\n[a link](https://x.y)\nnow");
+    }
+
+    #[test]
+    fn whitespace_is_normalized() {
+        let mut r = Region::from_str("\n \nkeep  \n\n\n\nmiddle\t\n\n");
+        r.trim_blank_lines();
+        assert_eq!(r.apply(), "keep  \n\n\n\nmiddle\t");
+        r.collapse_blank_runs(1).trim_trailing_whitespace();
+        assert_eq!(r.apply(), "keep\n\nmiddle");
+
+        let mut l = Line::new();
+        l.push("a ").push(Fragment::atomic("b  ")).push(" ");
+        l.trim_end();
+        assert_eq!(l.fragments().len(), 2);
+        assert!(l.fragments()[1].is_atomic());
+        assert_eq!(l.apply(), "a b");
+    }
+
+    #[test]
+    fn widths_use_display_columns() {
+        let mut l = Line::from_str("ab");
+        l.push("中文").push("é");
+        assert_eq!(l.width(), 7);
+        let mut r = Region::from_str("short\nlonger line");
+        r.push_back_line(l);
+        assert_eq!(r.max_width(), 11);
+        assert_eq!(Region::new().max_width(), 0);
+    }
+
+    #[test]
+    fn fragment_lengths_count_chars() {
+        let f = Fragment::from_str("中文é");
+        assert_eq!((f.len(), f.width()), (3, 5));
+        // the counts are kept by clones and ignored by equality
+        let g = f.clone();
+        assert_eq!((g.len(), g.width()), (3, 5));
+        assert_eq!(g, Fragment::from_str("中文é"));
+    }
 }
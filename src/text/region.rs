@@ -1,10 +1,15 @@
 use super::{Fragment, Line};
+use std::borrow::Cow;
 use std::fmt::{self, Display, Formatter};
 
 /// A Region is a 2D collection of lines. We provide chainable operations that
 /// mutate the region in-place and return &mut Self so callers can chain many
 /// operations without repeatedly reallocating strings.
-#[derive(Clone, Debug, Default)]
+///
+/// Regions compare line by line (see `Line`'s equality), keeping main lines
+/// and suffix lines apart.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Region {
     lines: Vec<Line>,
     // optional suffix lines that are logically appended after the main
@@ -13,6 +18,10 @@ pub struct Region {
     // which must participate in prefixing/indentation when the region is
     // transformed (for example, reference defs inside a blockquote need
     // to be quoted as well).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
     suffix: Vec<Line>,
 }
 
@@ -24,12 +33,20 @@ impl Region {
         }
     }
 
-    /// Create a region from a multiline &str (split on "\n")
+    /// Create a region from a multiline &str. Lines end at "\n", "\r\n" or
+    /// a lone "\r", so no carriage return is left at the end of a line.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
+        Region::from_str_verbatim(&normalize_newlines(s))
+    }
+
+    /// Create a region from a multiline &str split on "\n" only, keeping
+    /// carriage returns as text.
+    pub fn from_str_verbatim(s: &str) -> Self {
         let lines = if s.is_empty() {
             Vec::new()
         } else {
-            s.split('\n').map(|l| Line::from_str(l)).collect()
+            s.split('\n').map(Line::from_str).collect()
         };
         Region {
             lines,
@@ -37,6 +54,14 @@ impl Region {
         }
     }
 
+    /// Create a region from lines, all of them main lines.
+    pub fn from_lines(lines: impl IntoIterator<Item = Line>) -> Self {
+        Region {
+            lines: lines.into_iter().collect(),
+            suffix: Vec::new(),
+        }
+    }
+
     /// Push a line to the front
     pub fn push_front_line(&mut self, line: Line) -> &mut Self {
         self.lines.insert(0, line);
@@ -49,6 +74,29 @@ impl Region {
         self
     }
 
+    /// Append text after the last main line. The text up to the first line
+    /// break continues that line and every line break starts a new one, so
+    /// `from_str(a)` followed by `push_str(b)` equals `from_str(a + b)`
+    /// (unless a "\r\n" is split between `a` and `b`).
+    pub fn push_str(&mut self, s: &str) -> &mut Self {
+        let s = normalize_newlines(s);
+        let mut parts = s.split('\n');
+        let first = parts.next().unwrap_or_default();
+        match self.lines.last_mut() {
+            Some(line) if !first.is_empty() => {
+                line.push(first.to_string());
+            }
+            None if !s.is_empty() => {
+                self.lines.push(Line::from_str(first));
+            }
+            _ => {}
+        }
+        for part in parts {
+            self.lines.push(Line::from_str(part));
+        }
+        self
+    }
+
     /// Push a line to the suffix (appended after the main lines)
     pub fn push_back_suffix_line(&mut self, line: Line) -> &mut Self {
         self.suffix.push(line);
@@ -67,6 +115,28 @@ impl Region {
         self
     }
 
+    /// Add `prefix` to every line holding text, and `blank_prefix` to the
+    /// empty ones, e.g. `("> ", ">")` for block quotes without trailing
+    /// whitespace, or `("  ", "")` to indent while leaving blank lines empty.
+    pub fn prefix_each_line_with<F: Into<Fragment>, B: Into<Fragment>>(
+        &mut self,
+        prefix: F,
+        blank_prefix: B,
+    ) -> &mut Self {
+        let p = prefix.into();
+        let b = blank_prefix.into();
+        for line in self.lines.iter_mut().chain(&mut self.suffix) {
+            if line.is_empty() {
+                if !b.is_empty() {
+                    line.prepend(b.clone());
+                }
+            } else {
+                line.prepend(p.clone());
+            }
+        }
+        self
+    }
+
     /// Indent each line by `n` spaces
     pub fn indent_each_line(&mut self, n: usize) -> &mut Self {
         if n == 0 {
@@ -85,24 +155,30 @@ impl Region {
     /// Add a prefix to the first line, and for the remaining lines add equal
     /// amount of spaces so they line up with the remainder of the first line.
     /// For example: prefix_first_then_indent_rest("- ") on ["a","b"] ->
-    /// ["- a","  b"]. The spaces count is based on the prefix's char length.
+    /// ["- a","  b"]. The spaces count is the prefix's display width.
     pub fn prefix_first_then_indent_rest<F: Into<Fragment>>(&mut self, prefix: F) -> &mut Self {
         let p = prefix.into();
-        let pad = p.len();
-        if let Some(first) = self.lines.get_mut(0) {
-            first.prepend(p.clone());
-        } else if let Some(first) = self.suffix.get_mut(0) {
-            // if main lines are empty, apply prefix to first suffix line
-            first.prepend(p.clone());
-        }
-        if pad > 0 {
-            let sp = Fragment::spaces(pad);
-            for line in self.lines.iter_mut().skip(1) {
-                line.prepend(sp.clone());
-            }
-            // indent the suffix lines as well
-            for line in self.suffix.iter_mut() {
-                line.prepend(sp.clone());
+        let pad = Fragment::spaces(p.width());
+        self.prefix_first_then_rest(p, pad)
+    }
+
+    /// Add `first` to the first line and `rest` to every other line, suffix
+    /// lines included, e.g. `("1. ", "    ")` for four-space continuations.
+    /// When there are no main lines, the first suffix line gets `first`.
+    pub fn prefix_first_then_rest<F: Into<Fragment>, R: Into<Fragment>>(
+        &mut self,
+        first: F,
+        rest: R,
+    ) -> &mut Self {
+        let first = first.into();
+        let rest = rest.into();
+        let mut lines = self.lines.iter_mut().chain(&mut self.suffix);
+        if let Some(line) = lines.next() {
+            line.prepend(first);
+        }
+        if !rest.is_empty() {
+            for line in lines {
+                line.prepend(rest.clone());
             }
         }
         self
@@ -112,22 +188,29 @@ impl Region {
     /// only place we eagerly allocate the final result.
     pub fn apply(&self) -> String {
         let mut out = String::new();
-        let mut first = true;
-        for line in &self.lines {
-            if !first {
+        self.apply_into(&mut out);
+        out
+    }
+
+    /// Append the text of the region to `out`, as `apply` returns it. Hot
+    /// loops can reuse one buffer instead of allocating a String per call.
+    pub fn apply_into(&self, out: &mut String) {
+        for (i, line) in self.lines.iter().chain(&self.suffix).enumerate() {
+            if i > 0 {
                 out.push('\n');
             }
-            out.push_str(&line.apply());
-            first = false;
+            line.apply_into(out);
         }
-        for line in &self.suffix {
-            if !first {
-                out.push('\n');
-            }
-            out.push_str(&line.apply());
-            first = false;
+    }
+
+    /// The text of the region without allocating, when it is held by a
+    /// single fragment (or none).
+    pub(crate) fn as_single_str(&self) -> Option<&str> {
+        match (self.lines.as_slice(), self.suffix.is_empty()) {
+            ([], true) => Some(""),
+            ([line], true) => line.as_single_str(),
+            _ => None,
         }
-        out
     }
 
     /// Convenience to check whether region is empty
@@ -142,12 +225,182 @@ impl Region {
         out
     }
 
-    /// Return a cloned Vec<Line> of this region's lines without consuming it.
-    /// Useful when callers need to iterate lines but want to keep the Region.
-    pub fn lines(&self) -> Vec<Line> {
-        let mut out = self.lines.clone();
-        out.extend(self.suffix.clone());
-        out
+    /// Move the lines of `other` to the end of the region. The suffix lines
+    /// of both regions become main lines, so the text of the result is the
+    /// text of `self` followed by the text of `other`.
+    pub fn append(&mut self, other: Region) -> &mut Self {
+        self.lines.append(&mut self.suffix);
+        self.lines.extend(other.lines);
+        self.lines.extend(other.suffix);
+        self
+    }
+
+    /// Insert a main line at `idx`, shifting the following lines down.
+    /// Panics if `idx` is greater than the number of main lines.
+    pub fn insert_line(&mut self, idx: usize, line: Line) -> &mut Self {
+        self.lines.insert(idx, line);
+        self
+    }
+
+    /// Remove and return the main line at `idx`. Panics if `idx` is out of
+    /// bounds.
+    pub fn remove_line(&mut self, idx: usize) -> Line {
+        self.lines.remove(idx)
+    }
+
+    /// Replace the main lines in `range` with `lines`, returning the
+    /// removed ones. Panics if the range is out of bounds.
+    pub fn splice(
+        &mut self,
+        range: impl std::ops::RangeBounds<usize>,
+        lines: impl IntoIterator<Item = Line>,
+    ) -> Vec<Line> {
+        self.lines.splice(range, lines).collect()
+    }
+
+    /// Replace every occurrence of `from` with `to` in every line, suffix
+    /// lines included (see `Line::replace_str`).
+    pub fn replace_str(&mut self, from: &str, to: &str) -> &mut Self {
+        for line in self.lines.iter_mut().chain(&mut self.suffix) {
+            line.replace_str(from, to);
+        }
+        self
+    }
+
+    /// Remove trailing whitespace from every line, suffix lines included.
+    /// This also removes markdown hard breaks written as two trailing
+    /// spaces.
+    pub fn trim_trailing_whitespace(&mut self) -> &mut Self {
+        for line in self.lines.iter_mut().chain(&mut self.suffix) {
+            line.trim_end();
+        }
+        self
+    }
+
+    /// Expand the tabs of every line, suffix lines included, like
+    /// `Line::expand_tabs`.
+    pub fn expand_tabs(&mut self, tab_width: usize) -> &mut Self {
+        for line in self.lines.iter_mut().chain(&mut self.suffix) {
+            line.expand_tabs(tab_width);
+        }
+        self
+    }
+
+    /// Remove the blank main lines at the start and at the end.
+    pub fn trim_blank_lines(&mut self) -> &mut Self {
+        let end = self
+            .lines
+            .iter()
+            .rposition(|l| !l.is_blank())
+            .map_or(0, |i| i + 1);
+        self.lines.truncate(end);
+        let start = self.lines.iter().position(|l| !l.is_blank()).unwrap_or(0);
+        self.lines.drain(..start);
+        self
+    }
+
+    /// Shorten every run of consecutive blank main lines to at most `max`
+    /// lines.
+    pub fn collapse_blank_runs(&mut self, max: usize) -> &mut Self {
+        let mut run = 0;
+        self.lines.retain(|l| {
+            run = if l.is_blank() { run + 1 } else { 0 };
+            run <= max
+        });
+        self
+    }
+
+    /// Push each line to the back, as `push_back_line` does.
+    pub fn extend_lines(&mut self, lines: impl IntoIterator<Item = Line>) -> &mut Self {
+        self.lines.extend(lines);
+        self
+    }
+
+    /// The main lines of the region, without the suffix lines.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    pub(super) fn lines_mut(&mut self) -> &mut Vec<Line> {
+        &mut self.lines
+    }
+
+    /// The suffix lines, written after the main lines.
+    pub fn suffix_lines(&self) -> &[Line] {
+        &self.suffix
+    }
+
+    /// Remove the suffix lines and return them, e.g. to hoist reference
+    /// definitions to the end of a document.
+    pub fn take_suffix(&mut self) -> Vec<Line> {
+        std::mem::take(&mut self.suffix)
+    }
+
+    /// Turn the suffix lines into main lines, after the existing ones. The
+    /// text of the region does not change.
+    pub fn merge_suffix_into_lines(&mut self) -> &mut Self {
+        self.lines.append(&mut self.suffix);
+        self
+    }
+
+    /// Iterate over every line without consuming the region: the main
+    /// lines, then the suffix lines, as `into_lines` returns them.
+    pub fn iter_lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter().chain(&self.suffix)
+    }
+
+    /// The last main line, to modify it in place.
+    pub fn last_line_mut(&mut self) -> Option<&mut Line> {
+        self.lines.last_mut()
+    }
+
+    /// Width of the widest line, suffix lines included.
+    pub fn max_width(&self) -> usize {
+        self.iter_lines().map(Line::width).max().unwrap_or(0)
+    }
+
+    /// Number of lines, suffix lines included.
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + self.suffix.len()
+    }
+}
+
+/// `s` with "\r\n" and lone "\r" line breaks turned into "\n".
+pub(super) fn normalize_newlines(s: &str) -> Cow<'_, str> {
+    if s.contains('\r') {
+        Cow::Owned(s.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+impl From<Vec<Line>> for Region {
+    fn from(lines: Vec<Line>) -> Self {
+        Region {
+            lines,
+            suffix: Vec::new(),
+        }
+    }
+}
+
+impl FromIterator<Line> for Region {
+    fn from_iter<I: IntoIterator<Item = Line>>(iter: I) -> Self {
+        Region::from_lines(iter)
+    }
+}
+
+impl Extend<Line> for Region {
+    fn extend<I: IntoIterator<Item = Line>>(&mut self, iter: I) {
+        self.extend_lines(iter);
+    }
+}
+
+/// Writing to a region appends text as `push_str` does: every '\n' starts
+/// a new main line. It never fails.
+impl fmt::Write for Region {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
     }
 }
 
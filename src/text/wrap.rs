@@ -0,0 +1,185 @@
+//! Word wrapping for regions.
+
+use super::{Fragment, Line, Region};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// A piece of a paragraph: an unbreakable word, or a break opportunity.
+enum Item {
+    Word(Vec<Fragment>, usize),
+    /// Whitespace: breakable, written as one space when not broken.
+    Space,
+    /// A break opportunity without whitespace, e.g. between CJK characters.
+    Break,
+}
+
+/// Splits the lines of a paragraph into items.
+#[derive(Default)]
+struct Tokenizer {
+    items: Vec<Item>,
+    word: Vec<Fragment>,
+    width: usize,
+    buf: String,
+}
+
+impl Tokenizer {
+    fn flush_buf(&mut self) {
+        if !self.buf.is_empty() {
+            self.width += UnicodeWidthStr::width(self.buf.as_str());
+            self.word
+                .push(Fragment::from_string(std::mem::take(&mut self.buf)));
+        }
+    }
+
+    fn end_word(&mut self) {
+        self.flush_buf();
+        if !self.word.is_empty() {
+            let word = std::mem::take(&mut self.word);
+            self.items
+                .push(Item::Word(word, std::mem::take(&mut self.width)));
+        }
+    }
+
+    /// End the current word with whitespace (`space`) or a plain break
+    /// opportunity.
+    fn separate(&mut self, space: bool) {
+        self.end_word();
+        match self.items.last_mut() {
+            // a paragraph never starts with a separator, and whitespace
+            // wins over a plain break opportunity
+            None | Some(Item::Space) => {}
+            Some(last @ Item::Break) => {
+                if space {
+                    *last = Item::Space;
+                }
+            }
+            Some(Item::Word(..)) => self
+                .items
+                .push(if space { Item::Space } else { Item::Break }),
+        }
+    }
+
+    fn push_fragment(&mut self, frag: &Fragment) {
+        if frag.is_atomic() {
+            self.flush_buf();
+            self.width += frag.width();
+            self.word.push(frag.clone());
+            return;
+        }
+        for c in frag.as_str().chars() {
+            if c.is_whitespace() {
+                self.separate(true);
+            } else if is_wide(c) {
+                // wide (CJK) characters can be broken around
+                self.separate(false);
+                self.buf.push(c);
+                self.separate(false);
+            } else {
+                self.buf.push(c);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<Item> {
+        self.end_word();
+        while matches!(self.items.last(), Some(Item::Space | Item::Break)) {
+            self.items.pop();
+        }
+        self.items
+    }
+}
+
+fn is_wide(c: char) -> bool {
+    UnicodeWidthChar::width(c) == Some(2)
+}
+
+/// Whether the line ends with a markdown hard break.
+fn ends_with_hard_break(line: &Line) -> bool {
+    let text = line.apply();
+    text.ends_with("  ") || text.ends_with('\\')
+}
+
+/// Fill lines of at most `width` columns with the items, greedily.
+fn fill(items: Vec<Item>, width: usize, out: &mut Vec<Line>) {
+    let mut line = Line::new();
+    let mut line_width = 0;
+    let mut sep = 0;
+    for item in items {
+        match item {
+            Item::Space => sep = 1,
+            Item::Break => sep = 0,
+            Item::Word(word, w) => {
+                if line_width > 0 && line_width + sep + w > width {
+                    out.push(std::mem::take(&mut line));
+                    line_width = 0;
+                } else if line_width > 0 && sep == 1 {
+                    line.push(Fragment::from_static(" "));
+                }
+                if line_width > 0 {
+                    line_width += sep;
+                }
+                line_width += w;
+                for frag in word {
+                    line.push(frag);
+                }
+            }
+        }
+    }
+    if line_width > 0 {
+        out.push(line);
+    }
+}
+
+impl Region {
+    /// Re-wrap the main lines at word boundaries so that no line is wider
+    /// than `width` columns, as measured by unicode-width. Runs of
+    /// non-blank lines are reflowed as paragraphs, blank lines are kept.
+    ///
+    /// Lines break at whitespace and around wide (CJK) characters; a source
+    /// line break between two wide characters is dropped rather than turned
+    /// into a space. Atomic fragments are never broken, and a word wider
+    /// than `width` gets a line of its own. A line ending with a markdown
+    /// hard break (two spaces or a backslash) keeps its break. Suffix lines
+    /// are left alone.
+    pub fn wrap(&mut self, width: usize) -> &mut Self {
+        let mut out = Vec::new();
+        let mut tokens = Tokenizer::default();
+        let mut prev_end: Option<char> = None;
+        for line in std::mem::take(self.lines_mut()) {
+            let text = line.apply();
+            if text.trim().is_empty() {
+                fill(std::mem::take(&mut tokens).finish(), width, &mut out);
+                out.push(Line::new());
+                prev_end = None;
+                continue;
+            }
+            if let Some(prev) = prev_end {
+                // the line break between two lines of a paragraph
+                let next = text.chars().next().filter(|c| !c.is_whitespace());
+                if is_wide(prev) && next.is_some_and(is_wide) {
+                    tokens.separate(false);
+                } else {
+                    tokens.separate(true);
+                }
+            }
+            for frag in line.fragments() {
+                tokens.push_fragment(frag);
+            }
+            if ends_with_hard_break(&line) {
+                fill(std::mem::take(&mut tokens).finish(), width, &mut out);
+                // a backslash stays with the last word; trailing spaces
+                // were dropped as whitespace
+                if !text.ends_with('\\')
+                    && let Some(last) = out.last_mut()
+                {
+                    last.push(Fragment::from_static("  "));
+                }
+                prev_end = None;
+            } else {
+                prev_end = text.chars().last();
+            }
+        }
+        fill(tokens.finish(), width, &mut out);
+        *self.lines_mut() = out;
+        self
+    }
+}
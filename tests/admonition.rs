@@ -0,0 +1,61 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::admonition::{Admonition, FencedDivs};
+use pulldown_cmark_writer::ast::{Block, ExtensionRegistry, ParserOptions, blocks_to_markdown};
+use std::any::Any;
+
+fn registry() -> ExtensionRegistry {
+    let mut extensions = ExtensionRegistry::new();
+    extensions.register(FencedDivs, ParserOptions::new());
+    extensions
+}
+
+fn admonition(b: &Block) -> &Admonition {
+    let Block::Custom(c) = b else {
+        panic!("expected an admonition, got {:?}", b);
+    };
+    let any: &dyn Any = &**c;
+    any.downcast_ref().expect("an admonition")
+}
+
+fn parse(src: &str) -> Vec<Block> {
+    let events: Vec<Event> = Parser::new(src).collect();
+    registry().parse(&events)
+}
+
+#[test]
+fn fenced_divs_parse_into_admonitions() {
+    let blocks = parse("Intro.\n\n::: warning\nBack up first.\n\n- a\n- b\n\n:::\n\nAfter.\n");
+    assert_eq!(blocks.len(), 3);
+    let node = admonition(&blocks[1]);
+    assert_eq!(node.kind, "warning");
+    assert_eq!(node.children.len(), 2);
+    assert_eq!(blocks[2], Block::paragraph(["After."]));
+
+    let blocks = parse("::: {.aside}\n::: tip\nNested.\n:::\n:::\n");
+    let outer = admonition(&blocks[0]);
+    assert_eq!(outer.kind, "aside");
+    let inner = admonition(&outer.children[0]);
+    assert_eq!(inner.kind, "tip");
+    assert_eq!(inner.children, [Block::paragraph(["Nested."])]);
+
+    // without a closing fence the text stays a paragraph
+    let blocks = parse("::: note\nNever closed.\n");
+    assert!(matches!(blocks[0], Block::Paragraph(_)));
+}
+
+#[test]
+fn admonitions_write_back_as_fences_and_html() {
+    let blocks = parse("::: tip\nNested ::: text.\n:::\n\n::: aside\n> Quoted.\n\n:::\n");
+    let md = registry().to_markdown(&blocks);
+    assert_eq!(
+        md,
+        "::: tip\n\n\nNested ::: text.\n\n\n:::\n\n\n::: aside\n\n\n> Quoted.\n\n\n:::\n"
+    );
+    assert_eq!(parse(&md), blocks);
+    assert_eq!(blocks_to_markdown(&blocks), md);
+
+    assert_eq!(
+        registry().to_html(&blocks),
+        "<blockquote class=\"markdown-alert-tip\">\n<p>Nested ::: text.</p>\n</blockquote>\n<div class=\"aside\">\n<blockquote>\n<p>Quoted.</p>\n</blockquote>\n</div>\n"
+    );
+}
@@ -0,0 +1,50 @@
+use pulldown_cmark::{BlockQuoteKind, Options};
+use pulldown_cmark_writer::ast::{Block, Inline, blocks_to_markdown};
+use pulldown_cmark_writer::md;
+
+mod common;
+
+fn parse(src: &str) -> Vec<Block> {
+    common::parse_with(src, Options::ENABLE_GFM)
+}
+
+#[test]
+fn alerts_parse_into_their_own_node() {
+    let blocks = parse("> [!TIP]\n> Use the *force*.\n\n> plain\n");
+    assert!(matches!(
+        &blocks[0],
+        Block::Alert { kind: BlockQuoteKind::Tip, children } if children.len() == 1
+    ));
+    assert!(matches!(blocks[1], Block::BlockQuote(_)));
+}
+
+#[test]
+fn alerts_write_their_marker() {
+    let blocks = parse("> [!WARNING]\n> Mind the gap.\n>\n> Twice.\n");
+    let md = blocks_to_markdown(&blocks);
+    assert_eq!(md, "> [!WARNING]\n> Mind the gap.\n>\n> Twice.\n");
+    assert_eq!(parse(&md), blocks);
+}
+
+#[test]
+fn alert_kinds_roundtrip() {
+    for kind in [
+        BlockQuoteKind::Note,
+        BlockQuoteKind::Tip,
+        BlockQuoteKind::Important,
+        BlockQuoteKind::Warning,
+        BlockQuoteKind::Caution,
+    ] {
+        let blocks = vec![Block::alert(
+            kind,
+            [Block::paragraph(vec![Inline::text("body")])],
+        )];
+        assert_eq!(parse(&blocks_to_markdown(&blocks)), blocks, "{:?}", kind);
+    }
+}
+
+#[test]
+fn md_macro_builds_alerts() {
+    let blocks = md! { alert Caution { p "hot" } };
+    assert_eq!(blocks_to_markdown(&blocks), "> [!CAUTION]\n> hot\n");
+}
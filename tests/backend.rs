@@ -0,0 +1,135 @@
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::custom::BlockNode;
+use pulldown_cmark_writer::ast::{Backend, Block, parse_events_to_blocks, render_with};
+use std::sync::Arc;
+
+/// A small Typst backend.
+struct Typst;
+
+impl Backend for Typst {
+    type Output = String;
+
+    fn blocks(&mut self, mut blocks: Vec<String>) -> String {
+        blocks.retain(|b| !b.is_empty());
+        blocks.join("\n\n")
+    }
+    fn inlines(&mut self, inlines: Vec<String>) -> String {
+        inlines.concat()
+    }
+    fn paragraph(&mut self, content: String) -> String {
+        content
+    }
+    fn heading(&mut self, level: HeadingLevel, _id: Option<&str>, content: String) -> String {
+        format!("{} {content}", "=".repeat(level as usize))
+    }
+    fn blockquote(&mut self, content: String) -> String {
+        format!("#quote[{content}]")
+    }
+    fn code_block(&mut self, info: Option<&str>, content: &str) -> String {
+        format!("```{}\n{content}```", info.unwrap_or(""))
+    }
+    fn html_block(&mut self, _html: &str) -> String {
+        String::new()
+    }
+    fn list(&mut self, start: Option<u64>, items: Vec<String>) -> String {
+        let marker = if start.is_some() { "+" } else { "-" };
+        let items: Vec<String> = items.iter().map(|i| format!("{marker} {i}")).collect();
+        items.join("\n")
+    }
+    fn rule(&mut self) -> String {
+        "#line(length: 100%)".to_string()
+    }
+    fn footnote_definition(&mut self, _label: &str, _content: String) -> String {
+        String::new()
+    }
+    fn table(
+        &mut self,
+        _aligns: &[pulldown_cmark::Alignment],
+        head: Vec<String>,
+        rows: Vec<Vec<String>>,
+    ) -> String {
+        let cells: Vec<String> = head
+            .into_iter()
+            .chain(rows.into_iter().flatten())
+            .map(|c| format!("[{c}]"))
+            .collect();
+        format!("#table({})", cells.join(", "))
+    }
+    fn text(&mut self, text: &str) -> String {
+        text.to_string()
+    }
+    fn code(&mut self, code: &str) -> String {
+        format!("`{code}`")
+    }
+    fn inline_html(&mut self, _html: &str) -> String {
+        String::new()
+    }
+    fn soft_break(&mut self) -> String {
+        " ".to_string()
+    }
+    fn hard_break(&mut self) -> String {
+        "\\\n".to_string()
+    }
+    fn emphasis(&mut self, content: String) -> String {
+        format!("_{content}_")
+    }
+    fn strong(&mut self, content: String) -> String {
+        format!("*{content}*")
+    }
+    fn strikethrough(&mut self, content: String) -> String {
+        format!("#strike[{content}]")
+    }
+    fn link(&mut self, dest: &str, _title: &str, content: String) -> String {
+        format!("#link(\"{dest}\")[{content}]")
+    }
+    fn image(&mut self, dest: &str, _title: &str, alt: String) -> String {
+        format!("#image(\"{dest}\", alt: \"{alt}\")")
+    }
+    fn footnote_reference(&mut self, label: &str) -> String {
+        format!("@{label}")
+    }
+}
+
+fn typst(src: &str) -> String {
+    let events: Vec<_> = Parser::new_ext(src, Options::all()).collect();
+    render_with(&mut Typst, &parse_events_to_blocks(&events))
+}
+
+#[test]
+fn backend_renders_every_node_kind() {
+    let src = "# Title\n\nSome *emphasis*, **strong** and [a link](https://x.y).\n\n> quoted\n\n1. one\n2. two\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+    assert_eq!(
+        typst(src),
+        "= Title\n\nSome _emphasis_, *strong* and #link(\"https://x.y\")[a link].\n\n#quote[quoted]\n\n+ one\n+ two\n\n#table([a], [b], [1], [2])"
+    );
+}
+
+#[test]
+fn defaults_cover_alerts_front_matter_and_math() {
+    let src = "---\ntitle: x\n---\n\n> [!NOTE]\n> noted $x^2$\n";
+    assert_eq!(typst(src), "#quote[noted `x^2`]");
+}
+
+#[derive(Clone, Debug)]
+struct Callout;
+impl BlockNode for Callout {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Paragraph),
+            Event::Start(Tag::Strong),
+            Event::Text(CowStr::from("careful")),
+            Event::End(TagEnd::Strong),
+            Event::End(TagEnd::Paragraph),
+        ]
+    }
+    fn to_region(&self) -> Region {
+        Region::from_str("**careful**")
+    }
+}
+
+#[test]
+fn custom_nodes_render_from_their_events() {
+    let blocks = vec![Block::Custom(Arc::new(Callout))];
+    assert_eq!(render_with(&mut Typst, &blocks), "*careful*");
+}
@@ -0,0 +1,74 @@
+use pulldown_cmark::{CowStr, Event, Options, Parser};
+use pulldown_cmark_writer::ast::borrowed::{self, Block, Inline};
+use pulldown_cmark_writer::ast::parse_events_to_blocks;
+
+const SRC: &str = "# Title {#top}\n\nSome *emphasis* and [a link](https://example.com \"t\").\n\n> quoted\n\n- [x] tight\n- item\n\n1. loose\n\n2. list\n\n```rust\nfn main() {}\n```\n\n| a | b |\n|---|:-:|\n| 1 | `2` |\n\n<div>\nhtml\n</div>\n\nnote[^1]\n\n[^1]: The note.\n\n---\n";
+
+fn opts() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+}
+
+#[test]
+fn events_roundtrip() {
+    let events: Vec<Event> = Parser::new_ext(SRC, opts()).collect();
+    let blocks = borrowed::parse_events(events.clone());
+    assert_eq!(borrowed::blocks_to_events(&blocks), events);
+}
+
+#[test]
+fn text_is_borrowed_from_source() {
+    let blocks = borrowed::parse_events(Parser::new_ext(SRC, opts()));
+    match &blocks[1] {
+        Block::Paragraph(inls) => match &inls[0] {
+            Inline::Text(t) => {
+                assert!(matches!(t, CowStr::Borrowed(_)));
+                assert_eq!(t.as_ref(), "Some ");
+            }
+            other => panic!("expected text, got {:?}", other),
+        },
+        other => panic!("expected paragraph, got {:?}", other),
+    }
+    // tight list items hold their inlines without a paragraph
+    assert!(matches!(
+        &blocks[3],
+        Block::List { start: None, items } if matches!(items[0][0], Block::Plain(_))
+    ));
+}
+
+#[test]
+fn into_static_outlives_source() {
+    let blocks: Vec<Block<'static>> = {
+        let src = SRC.to_string();
+        borrowed::parse_events(Parser::new_ext(&src, opts()))
+            .into_iter()
+            .map(Block::into_static)
+            .collect()
+    };
+    let events: Vec<Event> = Parser::new_ext(SRC, opts()).collect();
+    assert_eq!(borrowed::blocks_to_events(&blocks), events);
+}
+
+#[test]
+fn converts_to_owned_ast() {
+    let events: Vec<Event> = Parser::new_ext(SRC, opts()).collect();
+    let blocks = borrowed::parse_events(events.clone());
+    assert_eq!(
+        borrowed::to_owned_blocks(&blocks),
+        parse_events_to_blocks(&events)
+    );
+}
+
+#[test]
+fn stray_ends_are_skipped() {
+    let mut events: Vec<Event> = Parser::new("one\n\ntwo\n").collect();
+    events.insert(3, Event::End(pulldown_cmark::TagEnd::BlockQuote(None)));
+    let blocks = borrowed::parse_events(events.clone());
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(
+        borrowed::to_owned_blocks(&blocks),
+        parse_events_to_blocks(&events)
+    );
+}
@@ -0,0 +1,44 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::{Block, Inline, block_to_events, blocks_to_markdown};
+
+mod common;
+use common::parse;
+
+#[test]
+fn block_comment_is_a_dedicated_node() {
+    let src = "<!-- begin\ntemplate -->\n\ntext\n";
+    let blocks = parse(src);
+    match &blocks[0] {
+        Block::Comment(body) => assert_eq!(body.apply(), " begin\ntemplate "),
+        other => panic!("expected comment, got {:?}", other),
+    }
+    let events: Vec<Event> = Parser::new(src).collect();
+    let emitted: Vec<Event> = blocks.iter().flat_map(block_to_events).collect();
+    assert_eq!(events, emitted);
+
+    let md = blocks_to_markdown(&blocks);
+    assert!(md.starts_with("<!-- begin\ntemplate -->\n"));
+}
+
+#[test]
+fn inline_comment_is_a_dedicated_node() {
+    let blocks = parse("a <!-- note --> b\n");
+    match &blocks[0] {
+        Block::Paragraph(inls) => {
+            assert!(matches!(&inls[1], Inline::Comment(body) if body.apply() == " note "));
+        }
+        other => panic!("expected paragraph, got {:?}", other),
+    }
+    assert_eq!(blocks_to_markdown(&blocks), "a <!-- note --> b\n");
+}
+
+#[test]
+fn other_html_is_not_a_comment() {
+    let blocks = parse("<!-- a --> <div>\n");
+    assert!(matches!(blocks[0], Block::HtmlBlock(_)));
+    let blocks = parse("x <span>y</span>\n");
+    match &blocks[0] {
+        Block::Paragraph(inls) => assert!(matches!(inls[1], Inline::InlineHtml(_))),
+        other => panic!("expected paragraph, got {:?}", other),
+    }
+}
@@ -0,0 +1,23 @@
+//! Helpers shared by the integration tests.
+
+// every test crate compiles its own copy and uses only some of it
+#![allow(dead_code)]
+
+use pulldown_cmark::{Event, Options, Parser};
+use pulldown_cmark_writer::ast::{Block, parse_events_to_blocks};
+
+/// Parse `src` as plain CommonMark.
+pub fn parse(src: &str) -> Vec<Block> {
+    parse_with(src, Options::empty())
+}
+
+/// Parse `src` with every pulldown-cmark extension enabled.
+pub fn parse_all(src: &str) -> Vec<Block> {
+    parse_with(src, Options::all())
+}
+
+/// Parse `src` with the extensions in `options`.
+pub fn parse_with(src: &str, options: Options) -> Vec<Block> {
+    let events: Vec<Event> = Parser::new_ext(src, options).collect();
+    parse_events_to_blocks(&events)
+}
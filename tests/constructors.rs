@@ -0,0 +1,40 @@
+use pulldown_cmark::HeadingLevel;
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::{Block, Inline, blocks_to_markdown};
+
+mod common;
+use common::parse;
+
+#[test]
+fn inline_constructors() {
+    assert_eq!(Inline::text("x"), Inline::Text(Region::from_str("x")));
+    assert_eq!(Inline::code("x"), Inline::Code(Region::from_str("x")));
+    assert_eq!(Inline::from("x"), Inline::text("x"));
+    assert_eq!(
+        Inline::strong(["a"]),
+        Inline::Strong(vec![Inline::text("a")])
+    );
+}
+
+#[test]
+fn paragraph_accepts_strings_and_inlines() {
+    assert_eq!(
+        Block::paragraph(["a", "b"]),
+        Block::Paragraph(vec![Inline::text("a"), Inline::text("b")])
+    );
+    let p = Block::paragraph([Inline::text("see "), Inline::emphasis(["this"])]);
+    assert_eq!(p.to_string(), "see *this*");
+}
+
+#[test]
+fn constructed_blocks_match_parsed_markdown() {
+    let blocks = vec![
+        Block::heading(HeadingLevel::H2, ["Intro"]),
+        Block::block_quote([Block::paragraph([
+            Inline::text("a "),
+            Inline::link("https://example.com", [Inline::code("link")]),
+        ])]),
+        Block::code("rust", "fn main() {}"),
+    ];
+    assert_eq!(parse(&blocks_to_markdown(&blocks)), blocks);
+}
@@ -0,0 +1,55 @@
+use pulldown_cmark_writer::ast::{Block, Cursor, blocks_to_markdown};
+
+mod common;
+use common::parse_all as parse;
+
+const SRC: &str = "# Title\n\n> quoted\n>\n> twice\n\n- one\n\n- two\n\n  more\n";
+
+#[test]
+fn cursors_move_through_the_tree() {
+    let mut blocks = parse(SRC);
+    let mut cursor = Cursor::new(&mut blocks);
+    assert!(matches!(cursor.get(), Some(Block::Heading { .. })));
+    assert!(!cursor.prev_sibling());
+    assert!(!cursor.first_child());
+    assert!(cursor.next_sibling());
+    assert!(cursor.first_child());
+    assert_eq!(cursor.path(), [1, 0]);
+    assert!(cursor.next_sibling());
+    assert!(!cursor.next_sibling());
+    assert_eq!(cursor.get(), Some(&Block::paragraph(["twice"])));
+    assert!(cursor.parent());
+    assert!(!cursor.parent());
+    assert!(cursor.next_sibling());
+    assert!(cursor.first_child());
+    // list paths take the item, then the block within it
+    assert_eq!(cursor.path(), [2, 0, 0]);
+    assert_eq!(cursor.get(), Some(&Block::paragraph(["one"])));
+
+    let cursor = Cursor::at(&mut blocks, &[2, 1, 1]).unwrap();
+    assert_eq!(cursor.get(), Some(&Block::paragraph(["more"])));
+    assert!(Cursor::at(&mut blocks, &[2, 1]).is_none());
+    assert!(Cursor::at(&mut blocks, &[9]).is_none());
+}
+
+#[test]
+fn cursors_edit_around_themselves() {
+    let mut blocks = parse(SRC);
+    let mut cursor = Cursor::at(&mut blocks, &[1, 1]).unwrap();
+    cursor.insert_before(Block::Rule);
+    assert_eq!(cursor.path(), [1, 2]);
+    let old = cursor.replace(Block::paragraph(["thrice"])).unwrap();
+    assert_eq!(old, Block::paragraph(["twice"]));
+    cursor.insert_after(Block::paragraph(["end"]));
+
+    let mut cursor = Cursor::at(&mut blocks, &[2, 1, 0]).unwrap();
+    assert_eq!(cursor.delete(), Some(Block::paragraph(["two"])));
+    assert_eq!(cursor.get(), Some(&Block::paragraph(["more"])));
+    let mut cursor = Cursor::new(&mut blocks);
+    cursor.delete();
+
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "> quoted\n>\n> ---\n>\n> thrice\n>\n> end\n\n\n- one\n- more\n"
+    );
+}
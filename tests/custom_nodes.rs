@@ -1,7 +1,9 @@
-use pulldown_cmark::{CowStr, Event, Tag};
-use pulldown_cmark_writer::ast::custom::{BlockNode, InlineNode};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag};
+use pulldown_cmark_writer::ast::custom::{BlockNode, BlockParser, InlineNode};
+use pulldown_cmark_writer::ast::writer::block_to_region;
 use pulldown_cmark_writer::ast::{
-    Block, Inline, block_to_events, inline_to_events, writer::blocks_to_markdown,
+    Block, Inline, ParseContext, block_to_events, inline_to_events,
+    parse_events_to_blocks_with_parsers, writer::blocks_to_markdown,
 };
 use pulldown_cmark_writer::{Line, Region};
 use std::sync::Arc;
@@ -40,7 +42,7 @@ fn custom_nodes_roundtrip() {
     let inl = Inline::Custom(Arc::new(MyInline("hello".to_string())));
     // Convert inline to events and ensure we got Emphasis start/Text/End
     let evs = inline_to_events(&inl);
-    assert!(matches!(evs.get(0), Some(Event::Start(Tag::Emphasis))));
+    assert!(matches!(evs.first(), Some(Event::Start(Tag::Emphasis))));
     assert!(matches!(evs.get(1), Some(Event::Text(_))));
     assert!(matches!(evs.get(2), Some(Event::End(_))));
 
@@ -56,3 +58,286 @@ fn custom_nodes_roundtrip() {
     let events = block_to_events(&b);
     assert!(matches!(events.as_slice(), [Event::Html(_)]));
 }
+
+// A GitHub-style admonition (`> [!NOTE]`) implemented as a custom block.
+#[derive(Debug, Clone)]
+struct Admonition {
+    kind: pulldown_cmark::BlockQuoteKind,
+    children: Vec<Block>,
+}
+
+impl Admonition {
+    fn marker(&self) -> &'static str {
+        use pulldown_cmark::BlockQuoteKind::*;
+        match self.kind {
+            Note => "[!NOTE]",
+            Tip => "[!TIP]",
+            Important => "[!IMPORTANT]",
+            Warning => "[!WARNING]",
+            Caution => "[!CAUTION]",
+        }
+    }
+}
+
+impl BlockNode for Admonition {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        let mut out = vec![Event::Start(Tag::BlockQuote(Some(self.kind)))];
+        for b in &self.children {
+            out.extend(block_to_events(b));
+        }
+        out.push(Event::End(pulldown_cmark::TagEnd::BlockQuote(Some(
+            self.kind,
+        ))));
+        out
+    }
+    fn to_region(&self) -> Region {
+        let mut r = Region::new();
+        r.push_back_line(Line::from_str(self.marker()));
+        for b in &self.children {
+            for l in block_to_region(b).into_lines() {
+                r.push_back_line(l);
+            }
+        }
+        r.prefix_each_line("> ");
+        r
+    }
+}
+
+struct AdmonitionParser;
+impl BlockParser for AdmonitionParser {
+    fn try_parse(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        _ctx: &ParseContext,
+    ) -> Option<(usize, Block)> {
+        let kind = match events.first() {
+            Some(Event::Start(Tag::BlockQuote(Some(kind)))) => *kind,
+            _ => return None,
+        };
+        let mut depth = 0usize;
+        for (i, ev) in events.iter().enumerate() {
+            match ev {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let children = parse_events_to_blocks_with_parsers(&events[1..i], &[self]);
+                        let node = Admonition { kind, children };
+                        return Some((i + 1, Block::Custom(Arc::new(node))));
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn nested_custom_block_receives_container_prefixes() {
+    let admonition = Admonition {
+        kind: pulldown_cmark::BlockQuoteKind::Note,
+        children: vec![Block::Paragraph(vec![
+            Inline::Text(Region::from_str("see ")),
+            Inline::Link {
+                link_type: pulldown_cmark::LinkType::Reference,
+                dest: "https://example.com".to_string(),
+                title: String::new(),
+                id: "docs".to_string(),
+                children: vec![Inline::Text(Region::from_str("the docs"))],
+            },
+        ])],
+    };
+    let doc = vec![Block::BlockQuote(vec![Block::List {
+        start: None,
+        items: vec![vec![
+            Block::Paragraph(vec![Inline::Text(Region::from_str("item"))]),
+            Block::Custom(Arc::new(admonition)),
+        ]],
+    }])];
+
+    let md = blocks_to_markdown(&doc);
+    let lines: Vec<&str> = md.lines().collect();
+    assert_eq!(lines[0], "> - item");
+    // every line of the custom region, suffix lines included, carries the
+    // blockquote marker and the list continuation indent
+    for l in &lines[1..] {
+        assert!(
+            l.starts_with(">   ") || l.trim_end() == ">",
+            "bad prefix: {:?}",
+            l
+        );
+    }
+    assert!(lines.contains(&">   > [!NOTE]"));
+    assert!(lines.contains(&">   > [docs]: https://example.com"));
+
+    // parse the markdown back with the custom parser: the admonition is
+    // recovered two levels deep and renders identically
+    let events: Vec<Event> = Parser::new_ext(&md, Options::ENABLE_GFM).collect();
+    let reparsed = parse_events_to_blocks_with_parsers(&events, &[&AdmonitionParser]);
+    match &reparsed[..] {
+        [Block::BlockQuote(children)] => match &children[..] {
+            [Block::List { items, .. }] => {
+                assert!(matches!(items[0].last(), Some(Block::Custom(_))));
+            }
+            other => panic!("expected list, got {:?}", other),
+        },
+        other => panic!("expected blockquote, got {:?}", other),
+    }
+    assert_eq!(blocks_to_markdown(&reparsed), md);
+}
+
+#[test]
+fn custom_block_in_footnote_definition() {
+    let admonition = Admonition {
+        kind: pulldown_cmark::BlockQuoteKind::Tip,
+        children: vec![Block::Paragraph(vec![Inline::Text(Region::from_str(
+            "hint",
+        ))])],
+    };
+    let doc = vec![Block::FootnoteDefinition(
+        "1".to_string(),
+        vec![Block::Custom(Arc::new(admonition))],
+    )];
+    let md = blocks_to_markdown(&doc);
+    assert_eq!(md, "[^1]: > [!TIP]\n    > hint\n");
+}
+
+#[test]
+fn deep_clone_copies_custom_nodes() {
+    let node: Arc<dyn BlockNode> = Arc::new(MyBlock("<x/>".into()));
+    let inline: Arc<dyn InlineNode> = Arc::new(MyInline("y".into()));
+    let doc = Block::BlockQuote(vec![
+        Block::Custom(node.clone()),
+        Block::Paragraph(vec![Inline::Strong(vec![Inline::Custom(inline.clone())])]),
+    ]);
+
+    let shallow = doc.clone();
+    let deep = doc.deep_clone();
+    assert_eq!(deep, doc);
+
+    let (Block::BlockQuote(s), Block::BlockQuote(d)) = (&shallow, &deep) else {
+        unreachable!()
+    };
+    match (&s[0], &d[0]) {
+        (Block::Custom(a), Block::Custom(b)) => {
+            assert!(Arc::ptr_eq(a, &node));
+            assert!(!Arc::ptr_eq(b, &node));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    match &d[1] {
+        Block::Paragraph(inls) => match &inls[0] {
+            Inline::Strong(c) => match &c[0] {
+                Inline::Custom(b) => assert!(!Arc::ptr_eq(b, &inline)),
+                other => panic!("unexpected {:?}", other),
+            },
+            other => panic!("unexpected {:?}", other),
+        },
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // boxed trait objects are cloneable on their own
+    let boxed: Box<dyn BlockNode> = Box::new(MyBlock("<z/>".into()));
+    assert_eq!(boxed.clone().to_region().apply(), "<z/>");
+}
+
+// Nodes that only describe themselves through events.
+#[derive(Debug, Clone)]
+struct EventsOnlyBlock;
+impl BlockNode for EventsOnlyBlock {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Heading {
+                level: pulldown_cmark::HeadingLevel::H2,
+                id: None,
+                classes: vec![],
+                attrs: vec![],
+            }),
+            Event::Text(CowStr::from("from events")),
+            Event::End(pulldown_cmark::TagEnd::Heading(
+                pulldown_cmark::HeadingLevel::H2,
+            )),
+        ]
+    }
+    fn to_region(&self) -> Region {
+        Region::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EventsOnlyInline;
+impl InlineNode for EventsOnlyInline {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Strong),
+            Event::Text(CowStr::from("bold")),
+            Event::End(pulldown_cmark::TagEnd::Strong),
+        ]
+    }
+    fn to_line(&self) -> Line {
+        Line::new()
+    }
+}
+
+#[test]
+fn direct_rendering_wins_over_events() {
+    // MyInline's events are emphasis too, but its own line is used verbatim
+    let para = Block::Paragraph(vec![Inline::Custom(Arc::new(MyInline("x".into())))]);
+    assert_eq!(blocks_to_markdown(&[para]), "*x*\n");
+    let b = Block::Custom(Arc::new(MyBlock("<b>as is</b>".to_string())));
+    assert_eq!(blocks_to_markdown(&[b]), "<b>as is</b>\n");
+}
+
+#[test]
+fn empty_direct_rendering_falls_back_to_events() {
+    let doc = vec![
+        Block::Custom(Arc::new(EventsOnlyBlock)),
+        Block::Paragraph(vec![
+            Inline::Text(Region::from_str("a ")),
+            Inline::Custom(Arc::new(EventsOnlyInline)),
+        ]),
+    ];
+    assert_eq!(blocks_to_markdown(&doc), "## from events\n\n\na **bold**\n");
+}
+
+// A warning box described only by its events, holding arbitrary blocks.
+#[derive(Debug, Clone)]
+struct WarningBox(Vec<Block>);
+impl BlockNode for WarningBox {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        let mut out = vec![Event::Start(Tag::BlockQuote(None))];
+        for b in &self.0 {
+            out.extend(block_to_events(b));
+        }
+        out.push(Event::End(pulldown_cmark::TagEnd::BlockQuote(None)));
+        out
+    }
+    fn to_region(&self) -> Region {
+        Region::new()
+    }
+}
+
+#[test]
+fn complex_event_fallback_uses_the_full_writer() {
+    let src =
+        "- outer\n  - inner\n\n```rust\nfn main() {}\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+    let events: Vec<Event> = Parser::new_ext(src, Options::ENABLE_TABLES).collect();
+    let content = pulldown_cmark_writer::ast::parse_events_to_blocks(&events);
+    let boxed = vec![Block::Custom(Arc::new(WarningBox(content.clone())))];
+    let plain = vec![Block::BlockQuote(content)];
+    assert_eq!(blocks_to_markdown(&boxed), blocks_to_markdown(&plain));
+
+    // nested in a list item, the fallback still receives container prefixes
+    let nested = |b: Vec<Block>| {
+        vec![Block::List {
+            start: Some(1),
+            items: vec![b],
+        }]
+    };
+    assert_eq!(
+        blocks_to_markdown(&nested(boxed)),
+        blocks_to_markdown(&nested(plain))
+    );
+}
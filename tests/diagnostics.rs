@@ -0,0 +1,129 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use pulldown_cmark_writer::ast::{
+    Block, Inline, ParseDiagnostic, parse_events_to_blocks,
+    parse_events_to_blocks_with_diagnostics, try_parse_events_to_blocks,
+};
+
+#[test]
+fn well_formed_streams_parse_cleanly() {
+    let src = "# Title\n\n> quote\n\n- a\n- b\n\n```rust\nfn x() {}\n```\n\n| a |\n|---|\n| b |\n";
+    let events: Vec<Event> = Parser::new_ext(src, Options::ENABLE_TABLES).collect();
+    assert_eq!(
+        try_parse_events_to_blocks(&events),
+        Ok(parse_events_to_blocks(&events))
+    );
+}
+
+#[test]
+fn unbalanced_ends_are_reported() {
+    let events = vec![
+        Event::End(TagEnd::Paragraph),
+        Event::Start(Tag::BlockQuote(None)),
+        Event::Start(Tag::Paragraph),
+        Event::Text("a".into()),
+        Event::End(TagEnd::BlockQuote(None)),
+        Event::End(TagEnd::Heading(HeadingLevel::H1)),
+        Event::Start(Tag::Emphasis),
+    ];
+    let diags = try_parse_events_to_blocks(&events).unwrap_err();
+    assert_eq!(
+        diags,
+        vec![
+            ParseDiagnostic::UnmatchedEnd {
+                index: 0,
+                end: TagEnd::Paragraph
+            },
+            ParseDiagnostic::MismatchedEnd {
+                index: 4,
+                expected: TagEnd::Paragraph,
+                found: TagEnd::BlockQuote(None),
+            },
+            ParseDiagnostic::UnmatchedEnd {
+                index: 5,
+                end: TagEnd::Heading(HeadingLevel::H1)
+            },
+            ParseDiagnostic::UnclosedTag {
+                index: 6,
+                tag: Tag::Emphasis
+            },
+        ]
+    );
+    assert_eq!(
+        diags[1].to_string(),
+        "event 4: expected End(Paragraph), found End(BlockQuote(None))"
+    );
+}
+
+#[test]
+fn dangling_tags_are_closed_at_the_end() {
+    // a hand-assembled stream that never closes its paragraph
+    let events = vec![
+        Event::Start(Tag::BlockQuote(None)),
+        Event::Start(Tag::Paragraph),
+        Event::Text("kept".into()),
+        Event::Start(Tag::Strong),
+        Event::Text("too".into()),
+    ];
+    let (blocks, diags) = parse_events_to_blocks_with_diagnostics(&events);
+    assert_eq!(
+        blocks,
+        vec![Block::block_quote(vec![Block::paragraph(vec![
+            Inline::text("kept"),
+            Inline::strong(vec![Inline::text("too")]),
+        ])])]
+    );
+    let unclosed: Vec<usize> = diags.iter().map(|d| d.index()).collect();
+    assert_eq!(unclosed, vec![3, 1, 0]);
+}
+
+#[test]
+fn stray_ends_do_not_close_open_tags() {
+    let events = vec![
+        Event::Start(Tag::Paragraph),
+        Event::Text("a".into()),
+        Event::End(TagEnd::Emphasis),
+        Event::Text("b".into()),
+        Event::End(TagEnd::Paragraph),
+    ];
+    assert_eq!(
+        parse_events_to_blocks(&events),
+        vec![Block::paragraph(vec![Inline::text("ab")])]
+    );
+}
+
+#[test]
+fn guesses_and_drops_are_reported() {
+    let events = vec![
+        Event::Text("loose".into()),
+        Event::Start(Tag::Paragraph),
+        Event::Rule,
+        Event::End(TagEnd::Paragraph),
+        Event::Start(Tag::BlockQuote(None)),
+        Event::FootnoteReference("n".into()),
+        Event::End(TagEnd::BlockQuote(None)),
+    ];
+    let (blocks, diags) = parse_events_to_blocks_with_diagnostics(&events);
+    assert_eq!(blocks, parse_events_to_blocks(&events));
+    assert_eq!(
+        diags,
+        vec![
+            ParseDiagnostic::WrappedInline { index: 0 },
+            ParseDiagnostic::DroppedEvent { index: 2 },
+            ParseDiagnostic::DroppedEvent { index: 5 },
+        ]
+    );
+}
+
+#[test]
+fn unsupported_tags_are_reported() {
+    let src = "term\n: definition\n";
+    let events: Vec<Event> = Parser::new_ext(src, Options::ENABLE_DEFINITION_LIST).collect();
+    let (_, diags) = parse_events_to_blocks_with_diagnostics(&events);
+    assert!(matches!(
+        diags.first(),
+        Some(ParseDiagnostic::UnsupportedTag {
+            index: 0,
+            tag: Tag::DefinitionList
+        })
+    ));
+}
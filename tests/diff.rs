@@ -0,0 +1,212 @@
+use pulldown_cmark_writer::ast::diff::{
+    Change, ChangeMarkup, DiffOptions, MergeConflict, Node, NodePath, annotate, apply, diff_blocks,
+    diff_blocks_with, merge3, render_changes,
+};
+use pulldown_cmark_writer::ast::{Block, Inline, blocks_to_markdown};
+
+mod common;
+use common::parse;
+
+fn at(blocks: &[usize]) -> NodePath {
+    NodePath {
+        blocks: blocks.to_vec(),
+        inlines: Vec::new(),
+    }
+}
+
+#[test]
+fn equal_documents_have_no_changes() {
+    let blocks = parse("# A\n\ntext\n");
+    assert_eq!(diff_blocks(&blocks, &blocks), []);
+}
+
+#[test]
+fn blocks_are_inserted_deleted_and_modified() {
+    let old = parse("# A\n\none\n\ntwo\n\n---\n");
+    let new = parse("# A\n\nzero\n\ntwo\n\nthree\n");
+    assert_eq!(
+        diff_blocks(&old, &new),
+        [
+            Change::Modify {
+                path: at(&[1]),
+                old: Node::Block(Block::paragraph(["one"])),
+                new: Node::Block(Block::paragraph(["zero"])),
+            },
+            Change::Delete {
+                path: at(&[3]),
+                node: Node::Block(Block::Rule),
+            },
+            Change::Insert {
+                path: at(&[3]),
+                node: Node::Block(Block::paragraph(["three"])),
+            },
+        ]
+    );
+}
+
+#[test]
+fn paths_count_earlier_changes() {
+    let old = parse("a\n\nb\n\nc\n");
+    let new = parse("b\n\nx\n\ny\n\nc\n");
+    let paths: Vec<(&str, Vec<usize>)> = diff_blocks(&old, &new)
+        .iter()
+        .map(|c| {
+            let kind = match c {
+                Change::Insert { .. } => "insert",
+                Change::Delete { .. } => "delete",
+                Change::Modify { .. } => "modify",
+            };
+            (kind, c.path().blocks.clone())
+        })
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            ("delete", vec![0]),
+            ("insert", vec![1]),
+            ("insert", vec![2]),
+        ]
+    );
+}
+
+#[test]
+fn containers_are_compared_child_by_child() {
+    let old = parse("> - one\n>\n>   keep\n> - two\n");
+    let new = parse("> - one\n>\n>   kept\n> - two\n");
+    let changes = diff_blocks(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].path(), &at(&[0, 0, 0, 1]));
+}
+
+#[test]
+fn inline_changes_are_optional() {
+    let old = parse("Some *very* plain text.\n");
+    let new = parse("Some *really* plain text.\n");
+    let options = DiffOptions { inlines: true };
+    assert_eq!(
+        diff_blocks_with(&old, &new, &options),
+        [Change::Modify {
+            path: NodePath {
+                blocks: vec![0],
+                inlines: vec![1, 0],
+            },
+            old: Node::Inline(Inline::text("very")),
+            new: Node::Inline(Inline::text("really")),
+        }]
+    );
+    assert!(!diff_blocks(&old, &new)[0].path().is_inline());
+}
+
+#[test]
+fn applying_a_diff_gives_the_new_document() {
+    let old = parse("# A\n\n> one *two*\n>\n> - x\n> - y\n\nend\n");
+    let new = parse("# B\n\n> one *three*\n>\n> - x\n> - z\n\nnew\n\nend\n");
+    for options in [DiffOptions::default(), DiffOptions { inlines: true }] {
+        let changes = diff_blocks_with(&old, &new, &options);
+        assert_eq!(apply(&old, &changes).unwrap(), new);
+    }
+}
+
+#[test]
+fn changes_replay_onto_another_version() {
+    let base = parse("intro\n\nbody\n\nend\n");
+    let ours = parse("intro\n\nbody, edited\n\nend\n");
+    let theirs = parse("intro\n\nbody\n\nend\n\nappendix\n");
+    let changes = diff_blocks(&base, &ours);
+    assert_eq!(
+        apply(&theirs, &changes).unwrap(),
+        parse("intro\n\nbody, edited\n\nend\n\nappendix\n")
+    );
+}
+
+#[test]
+fn conflicting_changes_are_reported() {
+    let base = parse("intro\n\nbody\n");
+    let ours = parse("intro\n\nbody, edited\n");
+    let theirs = parse("intro\n\nbody, rewritten\n");
+    let changes = diff_blocks(&base, &ours);
+    let conflict = apply(&theirs, &changes).unwrap_err();
+    assert_eq!(conflict.index, 0);
+    assert_eq!(
+        conflict.found,
+        Some(Box::new(Node::Block(Block::paragraph(["body, rewritten"]))))
+    );
+    assert_eq!(
+        conflict.to_string(),
+        "change 0 conflicts at [1]: the node there differs"
+    );
+
+    let missing = parse("intro\n");
+    assert_eq!(apply(&missing, &changes).unwrap_err().found, None);
+}
+
+#[test]
+fn changes_render_as_critic_markup() {
+    let old = parse("# Title\n\nSome *very* plain text.\n\nGone.\n");
+    let new = parse("# Title\n\nSome *really* plain text, here.\n\nAdded.\n");
+    let changes = diff_blocks_with(&old, &new, &DiffOptions { inlines: true });
+    assert_eq!(
+        render_changes(&old, &changes, ChangeMarkup::CriticMarkup).unwrap(),
+        "# Title\n\n\nSome *{~~very~>really~~}*{~~ plain text.~> plain text, here.~~}\n\n\n{~~Gone.~>Added.~~}\n"
+    );
+}
+
+#[test]
+fn changes_render_as_html() {
+    let old = parse("one\n\n> two\n");
+    let new = parse("> two\n\n---\n");
+    let changes = diff_blocks(&old, &new);
+    let annotated = annotate(&old, &changes, ChangeMarkup::Html).unwrap();
+    assert_eq!(annotated.len(), 3);
+    assert_eq!(
+        render_changes(&old, &changes, ChangeMarkup::Html).unwrap(),
+        "<del>\n\none\n\n</del>\n\n\n> two\n\n\n<ins>\n\n---\n\n</ins>\n"
+    );
+}
+
+#[test]
+fn three_way_merge_takes_both_sides() {
+    let base = parse("intro\n\nmiddle\n\nend\n");
+    let ours = parse("intro, edited\n\nmiddle\n\nend\n");
+    let theirs = parse("intro\n\nmiddle\n\nend\n\nappendix\n");
+    let merged = merge3(&base, &ours, &theirs);
+    assert_eq!(merged.conflicts, 0);
+    assert_eq!(
+        merged.blocks,
+        parse("intro, edited\n\nmiddle\n\nend\n\nappendix\n")
+    );
+}
+
+#[test]
+fn three_way_merge_marks_conflicts() {
+    let base = parse("intro\n\nmiddle\n\nend\n");
+    let ours = parse("intro\n\nours\n\nend\n");
+    let theirs = parse("intro\n\ntheirs\n\nend\n");
+    let merged = merge3(&base, &ours, &theirs);
+    assert_eq!(merged.conflicts, 1);
+    let Block::Custom(node) = &merged.blocks[1] else {
+        panic!("expected a conflict block");
+    };
+    let any: &dyn std::any::Any = &**node;
+    assert_eq!(
+        any.downcast_ref::<MergeConflict>(),
+        Some(&MergeConflict {
+            base: parse("middle\n"),
+            ours: parse("ours\n"),
+            theirs: parse("theirs\n"),
+        })
+    );
+    assert_eq!(
+        blocks_to_markdown(&merged.blocks),
+        "intro\n\n\n<<<<<<< ours\nours\n||||||| base\nmiddle\n=======\ntheirs\n>>>>>>> theirs\n\n\nend\n"
+    );
+}
+
+#[test]
+fn identical_changes_merge_cleanly() {
+    let base = parse("a\n\nb\n");
+    let both = parse("a\n\nc\n");
+    let merged = merge3(&base, &both, &both);
+    assert_eq!(merged.conflicts, 0);
+    assert_eq!(merged.blocks, both);
+}
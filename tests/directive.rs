@@ -0,0 +1,160 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::directive::{
+    Directive, DirectiveKind, Directives, parse_directives, text_directives,
+};
+use pulldown_cmark_writer::ast::{
+    Block, ExtensionRegistry, Inline, ParserOptions, blocks_to_html, blocks_to_markdown,
+};
+use std::any::Any;
+use std::sync::Arc;
+
+fn registry() -> ExtensionRegistry {
+    let mut extensions = ExtensionRegistry::new();
+    extensions.register(Directives, ParserOptions::new());
+    extensions
+}
+
+fn parse(src: &str) -> Vec<Block> {
+    let events: Vec<Event> = Parser::new(src).collect();
+    let mut blocks = registry().parse(&events);
+    text_directives(&mut blocks);
+    blocks
+}
+
+fn block(b: &Block) -> &Directive {
+    let Block::Custom(c) = b else {
+        panic!("expected a directive, got {:?}", b);
+    };
+    let any: &dyn Any = &**c;
+    any.downcast_ref().expect("a directive")
+}
+
+fn inline(inl: &Inline) -> &Directive {
+    let Inline::Custom(c) = inl else {
+        panic!("expected a directive, got {:?}", inl);
+    };
+    let any: &dyn Any = &**c;
+    any.downcast_ref().expect("a directive")
+}
+
+#[test]
+fn directives_parse_into_structured_nodes() {
+    let blocks = parse(concat!(
+        "Press :kbd[Ctrl *C*]{.key} to copy, see http://x.org or Note:this.\n\n",
+        "::youtube[Intro]{#intro vid=123 title=\"A talk\" autoplay}\n\n",
+        ":::spoiler{.wide .dark}\nHe was :em[dead]{} all along.\n\n::::note\nNested.\n::::\n:::\n",
+    ));
+    assert_eq!(blocks.len(), 3);
+
+    let Block::Paragraph(inlines) = &blocks[0] else {
+        panic!("expected a paragraph");
+    };
+    assert_eq!(inlines.len(), 3);
+    assert_eq!(inlines[0], Inline::text("Press "));
+    let kbd = inline(&inlines[1]);
+    assert_eq!(kbd.kind, DirectiveKind::Text);
+    assert_eq!(kbd.name, "kbd");
+    assert_eq!(
+        kbd.label,
+        [Inline::text("Ctrl "), Inline::emphasis([Inline::text("C")])]
+    );
+    assert_eq!(kbd.classes, ["key"]);
+    assert_eq!(
+        inlines[2],
+        Inline::text(" to copy, see http://x.org or Note:this.")
+    );
+
+    let youtube = block(&blocks[1]);
+    assert_eq!(youtube.kind, DirectiveKind::Leaf);
+    assert_eq!(youtube.label, [Inline::text("Intro")]);
+    assert_eq!(youtube.id.as_deref(), Some("intro"));
+    assert_eq!(youtube.attr("vid"), Some("123"));
+    assert_eq!(youtube.attr("title"), Some("A talk"));
+    assert_eq!(youtube.attr("autoplay"), Some(""));
+
+    let spoiler = block(&blocks[2]);
+    assert_eq!(spoiler.kind, DirectiveKind::Container);
+    assert_eq!(spoiler.classes, ["wide", "dark"]);
+    let Block::Paragraph(inlines) = &spoiler.children[0] else {
+        panic!("expected a paragraph");
+    };
+    assert_eq!(inline(&inlines[1]).name, "em");
+    let note = block(&spoiler.children[1]);
+    assert_eq!(note.name, "note");
+    assert_eq!(note.children, [Block::paragraph(["Nested."])]);
+
+    assert_eq!(
+        parse_directives(&Parser::new("::x\n").collect::<Vec<_>>()).len(),
+        1
+    );
+}
+
+#[test]
+fn directives_write_back_as_written_and_as_html() {
+    let blocks = parse(concat!(
+        "Press :kbd[Ctrl]{.key} now.\n\n",
+        "::youtube[Intro]{#intro vid=\"123\"}\n\n",
+        ":::aside\nBody.\n:::\n",
+    ));
+    let md = registry().to_markdown(&blocks);
+    assert_eq!(
+        md,
+        concat!(
+            "Press :kbd[Ctrl]{.key} now.\n\n\n",
+            "::youtube[Intro]{#intro vid=\"123\"}\n\n\n",
+            ":::aside\n\n\nBody.\n\n\n:::\n",
+        )
+    );
+    assert_eq!(parse(&md), blocks);
+    assert_eq!(blocks_to_markdown(&blocks), md);
+
+    assert_eq!(
+        blocks_to_html(&blocks, &Default::default()),
+        concat!(
+            "<p>Press <span data-directive=\"kbd\" class=\"key\">Ctrl</span> now.</p>\n",
+            "<div data-directive=\"youtube\" id=\"intro\" vid=\"123\">Intro</div>\n",
+            "<div data-directive=\"aside\">\n<p>Body.</p>\n</div>\n",
+        )
+    );
+
+    // without a closing fence the text stays a paragraph
+    assert!(matches!(
+        parse(":::note\nNever closed.\n")[0],
+        Block::Paragraph(_)
+    ));
+}
+
+#[test]
+fn nested_containers_write_longer_outer_fences() {
+    let mut inner = Directive::new(DirectiveKind::Container, "inner");
+    inner.children = vec![Block::paragraph(["Deep."])];
+    let mut middle = Directive::new(DirectiveKind::Container, "middle");
+    middle.children = vec![Block::Custom(Arc::new(inner))];
+    let mut outer = Directive::new(DirectiveKind::Container, "outer");
+    outer.children = vec![
+        Block::Custom(Arc::new(middle)),
+        Block::paragraph(["After."]),
+    ];
+    let blocks = vec![Block::Custom(Arc::new(outer))];
+    let md = registry().to_markdown(&blocks);
+    assert_eq!(
+        md,
+        concat!(
+            ":::::outer\n\n\n",
+            "::::middle\n\n\n",
+            ":::inner\n\n\nDeep.\n\n\n:::\n\n\n",
+            "::::\n\n\n",
+            "After.\n\n\n",
+            ":::::\n",
+        )
+    );
+    assert_eq!(parse(&md), blocks);
+
+    // a fence shorter than the opening one does not close it
+    let blocks = parse("::::note\nText\n:::\n::::\n");
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(
+        block(&blocks[0]).children,
+        [Block::paragraph(["Text"]), Block::paragraph([":::"])]
+    );
+}
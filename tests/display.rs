@@ -0,0 +1,83 @@
+use pulldown_cmark::{Event, LinkType, Parser};
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::writer::block_to_region;
+use pulldown_cmark_writer::ast::{
+    Block, Inline, WriterOptions, block_to_markdown, inline_to_markdown, parse_events_to_blocks,
+};
+
+fn text(s: &str) -> Inline {
+    Inline::Text(Region::from_str(s))
+}
+
+#[test]
+fn block_display_matches_region() {
+    let events: Vec<Event> = Parser::new("# Title\n\n> quote *with* `code`\n").collect();
+    for b in parse_events_to_blocks(&events) {
+        assert_eq!(b.to_string(), block_to_region(&b).apply());
+    }
+}
+
+#[test]
+fn block_display_in_format() {
+    let b = Block::Paragraph(vec![text("Hello "), Inline::Strong(vec![text("world")])]);
+    assert_eq!(format!("{b}"), "Hello **world**");
+}
+
+#[test]
+fn inline_to_markdown_renders_single_inline() {
+    let link = Inline::Link {
+        link_type: LinkType::Inline,
+        dest: "https://example.com".into(),
+        title: String::new(),
+        children: vec![Inline::Emphasis(vec![text("site")])],
+        id: String::new(),
+    };
+    assert_eq!(
+        inline_to_markdown(&link, &WriterOptions::default()),
+        "[*site*](https://example.com)"
+    );
+    assert_eq!(
+        link.to_string(),
+        inline_to_markdown(&link, &WriterOptions::default())
+    );
+    assert_eq!(format!("{}", Inline::Code(Region::from_str("x"))), "`x`");
+}
+
+#[test]
+fn single_blocks_render_with_options() {
+    let options = WriterOptions::new().override_inline(|i, _| match i {
+        Inline::Strong(_) => Some(pulldown_cmark_writer::Line::from_str("__bold__")),
+        _ => None,
+    });
+    let b = Block::Paragraph(vec![text("a "), Inline::Strong(vec![text("b")])]);
+    assert_eq!(block_to_markdown(&b, &options), "a __bold__\n");
+    assert_eq!(
+        block_to_markdown(&b, &WriterOptions::default()),
+        format!("{b}\n")
+    );
+    assert_eq!(
+        inline_to_markdown(&Inline::Strong(vec![text("b")]), &options),
+        "__bold__"
+    );
+}
+
+#[test]
+fn list_item_paragraphs_are_written_apart() {
+    let list = Block::List {
+        start: None,
+        items: vec![vec![
+            Block::Paragraph(vec![text("a")]),
+            Block::Paragraph(vec![text("b")]),
+        ]],
+    };
+    assert_eq!(list.to_string(), "- a\n\n  b");
+}
+
+#[test]
+fn heading_attributes_are_written_after_the_text() {
+    let src = "# Title {#top .wide data=x}\n";
+    let events: Vec<Event> =
+        Parser::new_ext(src, pulldown_cmark::Options::ENABLE_HEADING_ATTRIBUTES).collect();
+    let blocks = parse_events_to_blocks(&events);
+    assert_eq!(blocks[0].to_string(), "# Title {#top .wide data=x}");
+}
@@ -0,0 +1,157 @@
+use pulldown_cmark::{MetadataBlockKind, Options, Parser};
+use pulldown_cmark_writer::ast::{
+    Block, DefinitionConflict, Document, Inline, LinkDefinition, blocks_to_markdown,
+    document_to_markdown, parse_markdown_to_document, parse_parser_to_blocks,
+    parse_parser_to_document,
+};
+
+fn opts() -> Options {
+    Options::ENABLE_FOOTNOTES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+}
+
+const SRC: &str = "---\ntitle: Doc\n---\n\nSee [the site][home] and a note[^n].\n\n[^n]: The note.\n\n[home]: https://example.com \"Home\"\n[unused]: https://unused.example\n";
+
+#[test]
+fn parse_splits_document_parts() {
+    let doc = parse_markdown_to_document(SRC, opts());
+    let (kind, content) = doc.metadata.as_ref().expect("front matter");
+    assert_eq!(*kind, MetadataBlockKind::YamlStyle);
+    assert_eq!(content.apply(), "title: Doc");
+
+    assert_eq!(doc.blocks.len(), 1);
+    assert!(matches!(doc.blocks[0], Block::Paragraph(_)));
+
+    assert_eq!(doc.footnotes.len(), 1);
+    assert_eq!(doc.footnotes[0].0, "n");
+
+    assert_eq!(
+        doc.link_definitions,
+        vec![
+            LinkDefinition {
+                id: "home".into(),
+                dest: "https://example.com".into(),
+                title: "Home".into(),
+            },
+            LinkDefinition {
+                id: "unused".into(),
+                dest: "https://unused.example".into(),
+                title: String::new(),
+            },
+        ]
+    );
+    assert_eq!(
+        doc.link_definition("HOME").map(|d| d.dest.as_str()),
+        Some("https://example.com")
+    );
+}
+
+#[test]
+fn link_definition_lookup_collapses_whitespace() {
+    let doc =
+        parse_markdown_to_document("See [the  Home page].\n\n[the home\npage]: /home\n", opts());
+    assert_eq!(
+        doc.link_definition("The Home  Page")
+            .map(|d| d.dest.as_str()),
+        Some("/home")
+    );
+}
+
+#[test]
+fn write_keeps_unused_definitions() {
+    let doc = parse_markdown_to_document(SRC, opts());
+    let md = document_to_markdown(&doc);
+    assert!(md.starts_with("---\ntitle: Doc\n---\n"));
+    assert_eq!(md.matches("[home]: ").count(), 1);
+    assert!(md.ends_with("[unused]: https://unused.example\n"));
+    assert_eq!(parse_markdown_to_document(&md, opts()), doc);
+}
+
+#[test]
+fn from_blocks_and_back() {
+    let doc = parse_markdown_to_document(SRC, opts());
+    let blocks = doc.clone().into_blocks();
+    assert!(matches!(blocks[0], Block::Metadata { .. }));
+    assert!(matches!(blocks[2], Block::FootnoteDefinition(..)));
+    assert!(matches!(blocks.last(), Some(Block::LinkDefinition { id, .. }) if id == "unused"));
+    assert_eq!(Document::from_blocks(blocks), doc);
+
+    // without definition blocks only the referenced one can be recovered
+    let mut body = doc.clone();
+    body.link_definitions.clear();
+    let rebuilt = Document::from_blocks(body.into_blocks());
+    assert_eq!(rebuilt.link_definitions, doc.link_definitions[..1]);
+}
+
+#[test]
+fn parser_entry_point_keeps_definitions() {
+    let parser = Parser::new_ext(SRC, opts());
+    let blocks = parse_parser_to_blocks(parser);
+    assert!(matches!(
+        blocks.last(),
+        Some(Block::LinkDefinition { id, .. }) if id == "unused"
+    ));
+    assert_eq!(
+        parse_parser_to_document(Parser::new_ext(SRC, opts())),
+        parse_markdown_to_document(SRC, opts())
+    );
+}
+
+#[test]
+fn parser_entry_point_writes_each_definition_once() {
+    let blocks = parse_parser_to_blocks(Parser::new_ext("[a][R]\n\n[r]: /u\n", opts()));
+    assert_eq!(blocks_to_markdown(&blocks), "[a][R]\n\n\n[r]: /u\n");
+}
+
+#[test]
+fn edited_definitions_carry_over_to_links() {
+    let mut doc = parse_markdown_to_document("[a][x] and [b][gone]\n\n[x]: /old\n", opts());
+    doc.link_definitions[0].dest = "/new".into();
+    let md = document_to_markdown(&doc);
+    assert!(md.contains("[x]: /new"), "{}", md);
+    assert!(!md.contains("/old"));
+
+    assert_eq!(doc.resolve_references(), Vec::<String>::new());
+    doc.link_definitions.clear();
+    assert_eq!(doc.resolve_references(), vec!["x".to_string()]);
+}
+
+#[test]
+fn shared_definitions_are_written_once() {
+    let doc = parse_markdown_to_document("[a][x]\n\n[b][X]\n\n[x]: /one\n", opts());
+    let md = document_to_markdown(&doc);
+    assert_eq!(md.matches("]: /one").count(), 1, "{}", md);
+    assert!(md.starts_with("[a][x]\n\n[x]: /one"), "{}", md);
+}
+
+#[test]
+fn conflicting_definitions_keep_the_first() {
+    let mut doc = parse_markdown_to_document("[a][x]\n\n[b][x]\n\n[x]: /one\n", opts());
+    let Block::Paragraph(inlines) = &mut doc.blocks[1] else {
+        panic!("expected a paragraph");
+    };
+    let Inline::Link { dest, .. } = &mut inlines[0] else {
+        panic!("expected a link");
+    };
+    *dest = "/two".into();
+    doc.link_definitions.clear();
+
+    let conflicts = doc.clone().dedupe_definitions();
+    assert_eq!(
+        conflicts,
+        vec![DefinitionConflict {
+            kept: LinkDefinition {
+                id: "x".into(),
+                dest: "/one".into(),
+                title: String::new(),
+            },
+            dropped: LinkDefinition {
+                id: "x".into(),
+                dest: "/two".into(),
+                title: String::new(),
+            },
+        }]
+    );
+    let md = document_to_markdown(&doc);
+    assert_eq!(md.matches("[x]: ").count(), 1, "{}", md);
+    assert!(!md.contains("/two"), "{}", md);
+}
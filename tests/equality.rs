@@ -0,0 +1,69 @@
+use pulldown_cmark::{CowStr, Event};
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::custom::BlockNode;
+use pulldown_cmark_writer::ast::{Block, Inline};
+use std::sync::Arc;
+
+mod common;
+use common::parse_all as parse;
+
+#[derive(Debug, Clone)]
+struct Marker(&'static str);
+impl BlockNode for Marker {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![Event::Html(CowStr::from(self.0))]
+    }
+    fn to_region(&self) -> Region {
+        Region::from_str(self.0)
+    }
+}
+
+#[test]
+fn parsed_documents_compare_structurally() {
+    let src = std::fs::read_to_string("src/fixtures/complex_mixed.md").unwrap();
+    assert_eq!(parse(&src), parse(&src));
+    assert_ne!(parse("# a\n"), parse("# b\n"));
+    assert_eq!(
+        parse("*hi*\n"),
+        vec![Block::Paragraph(vec![Inline::Emphasis(vec![
+            Inline::Text(Region::from_str("hi"))
+        ])])]
+    );
+}
+
+#[test]
+fn custom_nodes_compare_by_events() {
+    let a = Block::Custom(Arc::new(Marker("<x/>")));
+    let b = Block::Custom(Arc::new(Marker("<x/>")));
+    let c = Block::Custom(Arc::new(Marker("<y/>")));
+    assert_eq!(a, a.clone());
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn semantic_equality_ignores_formatting_details() {
+    use pulldown_cmark_writer::ast::{SemanticOptions, semantic_eq, semantic_eq_with};
+
+    let split = vec![Block::Paragraph(vec![
+        Inline::text("one "),
+        Inline::text("two"),
+    ])];
+    assert_ne!(split, parse("one two\n"));
+    assert!(semantic_eq(&split, &parse("one two\n")));
+
+    assert!(semantic_eq(&parse("one\ntwo\n"), &parse("one two\n")));
+    let strict = SemanticOptions {
+        soft_breaks_as_spaces: false,
+        ..SemanticOptions::default()
+    };
+    assert!(!semantic_eq_with(
+        &parse("one\ntwo\n"),
+        &parse("one two\n"),
+        &strict
+    ));
+
+    assert!(semantic_eq(&parse("- a\n- b\n"), &parse("- a\n\n- b\n")));
+    assert!(!semantic_eq(&parse("a\n\nb\n"), &parse("ab\n")));
+    assert!(!semantic_eq(&parse("*a*\n"), &parse("**a**\n")));
+}
@@ -0,0 +1,25 @@
+use pulldown_cmark::{CowStr, Event, Tag};
+use pulldown_cmark_writer::ast::{block_events_iter, block_to_events};
+
+mod common;
+use common::parse_all as parse;
+
+const SRC: &str = "---\ntitle: x\n---\n\n# Title {#top}\n\nSome *emphasis*, `code`, $x$ and [a link](https://x.y \"t\").[^1]\n\n> [!NOTE]\n> quoted\n\n1. one\n2. two\n   - nested\n\n| a | b |\n|---|:-:|\n| 1 | ~~2~~ |\n\n```rust\nfn main() {}\n```\n\n<!-- note -->\n\n[^1]: The note.\n";
+
+#[test]
+fn lazy_events_match_block_to_events() {
+    for b in parse(SRC) {
+        let lazy: Vec<Event> = block_events_iter(&b).collect();
+        assert_eq!(lazy, block_to_events(&b), "{b:?}");
+    }
+}
+
+#[test]
+fn strings_are_borrowed_from_the_ast() {
+    let blocks = parse("[a link](https://x.y)\n");
+    let dest = block_events_iter(&blocks[0]).find_map(|e| match e {
+        Event::Start(Tag::Link { dest_url, .. }) => Some(dest_url),
+        _ => None,
+    });
+    assert!(matches!(dest, Some(CowStr::Borrowed("https://x.y"))));
+}
@@ -0,0 +1,49 @@
+use pulldown_cmark::{Event, Options, Parser};
+use pulldown_cmark_writer::ast::{
+    Block, MdEvent, MdTag, MdTagEnd, block_to_events, blocks_to_markdown, blocks_to_md_events,
+    parse_events_to_blocks, parse_md_events_to_blocks,
+};
+use std::fs;
+
+#[test]
+fn pulldown_events_convert_losslessly() {
+    let src = fs::read_to_string("src/fixtures/complex_mixed.md").unwrap();
+    let events: Vec<Event> = Parser::new_ext(&src, Options::all()).collect();
+    let md: Vec<MdEvent> = events.iter().map(MdEvent::from).collect();
+    let back: Vec<Event<'static>> = md.iter().map(MdEvent::to_pulldown).collect();
+    assert_eq!(events, back);
+
+    let blocks = parse_events_to_blocks(&events);
+    assert_eq!(
+        blocks_to_markdown(&parse_md_events_to_blocks(&md)),
+        blocks_to_markdown(&blocks)
+    );
+    let emitted: Vec<MdEvent> = blocks
+        .iter()
+        .flat_map(block_to_events)
+        .map(MdEvent::from)
+        .collect();
+    assert_eq!(blocks_to_md_events(&blocks), emitted);
+}
+
+#[test]
+fn hand_built_events_parse_without_pulldown() {
+    let heading = MdTag::Heading {
+        level: pulldown_cmark::HeadingLevel::H2,
+        id: None,
+        classes: vec![],
+        attrs: vec![],
+    };
+    let end = heading.to_end();
+    let events = vec![
+        MdEvent::Start(heading),
+        MdEvent::Text("Title".to_string()),
+        MdEvent::End(end),
+        MdEvent::Start(MdTag::Paragraph),
+        MdEvent::Text("body".to_string()),
+        MdEvent::End(MdTagEnd::Paragraph),
+    ];
+    let blocks = parse_md_events_to_blocks(&events);
+    assert!(matches!(blocks[0], Block::Heading { .. }));
+    assert_eq!(blocks_to_markdown(&blocks), "## Title\n\n\nbody\n");
+}
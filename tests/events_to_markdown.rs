@@ -0,0 +1,24 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use pulldown_cmark_writer::ast::events_to_markdown;
+
+#[test]
+fn event_streams_write_in_one_call() {
+    // filtered parser output
+    let md = events_to_markdown(Parser::new("a *b* c\n").filter(|e| {
+        !matches!(
+            e,
+            Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis)
+        )
+    }));
+    assert_eq!(md, "a b c\n");
+
+    // a synthesized stream
+    let md = events_to_markdown([
+        Event::Start(Tag::BlockQuote(None)),
+        Event::Start(Tag::Paragraph),
+        Event::Text("quoted".into()),
+        Event::End(TagEnd::Paragraph),
+        Event::End(TagEnd::BlockQuote(None)),
+    ]);
+    assert_eq!(md, "> quoted\n");
+}
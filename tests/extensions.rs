@@ -0,0 +1,150 @@
+use pulldown_cmark::{CowStr, Event, Parser, Tag, TagEnd};
+use pulldown_cmark_writer::ast::custom::BlockNode;
+use pulldown_cmark_writer::ast::{
+    Block, Extension, ExtensionRegistry, ParseContext, ParserOptions, WriterOptions,
+    block_to_markdown, blocks_to_markdown,
+};
+use pulldown_cmark_writer::{Line, Region};
+
+// `::: kind` / `:::` around a single paragraph.
+#[derive(Clone, Debug)]
+struct Admonition {
+    kind: String,
+    body: String,
+}
+
+impl BlockNode for Admonition {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::BlockQuote(None)),
+            Event::Start(Tag::Paragraph),
+            Event::Text(CowStr::from(format!("{}: {}", self.kind, self.body))),
+            Event::End(TagEnd::Paragraph),
+            Event::End(TagEnd::BlockQuote(None)),
+        ]
+    }
+    fn to_region(&self) -> Region {
+        Region::from_str(&format!("> {}: {}", self.kind, self.body))
+    }
+}
+
+struct Admonitions;
+
+impl Extension for Admonitions {
+    type Node = Admonition;
+
+    fn try_parse(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        _ctx: &ParseContext,
+    ) -> Option<(usize, Admonition)> {
+        let kind = match events {
+            [Event::Start(Tag::Paragraph), Event::Text(t), ..] => t.strip_prefix("::: ")?,
+            _ => return None,
+        };
+        let end = events
+            .iter()
+            .position(|e| *e == Event::End(TagEnd::Paragraph))?;
+        let lines: Vec<&str> = events[1..end]
+            .iter()
+            .filter_map(|e| match e {
+                Event::Text(t) => Some(t.as_ref()),
+                _ => None,
+            })
+            .collect();
+        match lines.as_slice() {
+            [_, body @ .., ":::"] => Some((
+                end + 1,
+                Admonition {
+                    kind: kind.to_string(),
+                    body: body.join("\n"),
+                },
+            )),
+            _ => None,
+        }
+    }
+
+    fn write_markdown(&self, node: &Admonition) -> Region {
+        let mut r = Region::new();
+        r.push_back_line(Line::from_str(&format!("::: {}", node.kind)));
+        r.push_str("\n");
+        r.push_str(&node.body);
+        r.push_back_line(Line::from_str(":::"));
+        r
+    }
+
+    fn write_html(&self, node: &Admonition) -> Option<String> {
+        Some(format!(
+            "<div class=\"{}\">{}</div>\n",
+            node.kind, node.body
+        ))
+    }
+}
+
+fn registry() -> ExtensionRegistry {
+    let mut extensions = ExtensionRegistry::new();
+    extensions.register(Admonitions, ParserOptions::new());
+    extensions
+}
+
+fn parse(extensions: &ExtensionRegistry, src: &str) -> Vec<Block> {
+    let events: Vec<Event> = Parser::new(src).collect();
+    extensions.parse(&events)
+}
+
+const SRC: &str = "intro\n\n::: warning\nMind the gap.\n:::\n";
+
+#[test]
+fn extensions_parse_and_write_their_syntax() {
+    let extensions = registry();
+    let blocks = parse(&extensions, SRC);
+    assert!(matches!(blocks[1], Block::Custom(_)));
+    let md = extensions.to_markdown(&blocks);
+    assert!(md.ends_with("\n::: warning\nMind the gap.\n:::\n"));
+    assert_eq!(parse(&extensions, &md), blocks);
+    // outside the registry the node falls back to its own rendering
+    assert_eq!(
+        blocks_to_markdown(&blocks[1..]),
+        "> warning: Mind the gap.\n"
+    );
+}
+
+#[test]
+fn nested_extension_nodes_get_container_prefixes() {
+    let extensions = registry();
+    let src = "> ::: tip\n> Look up.\n> :::\n";
+    let blocks = parse(&extensions, src);
+    assert_eq!(extensions.to_markdown(&blocks), src);
+}
+
+#[test]
+fn writer_options_carry_extensions() {
+    let extensions = registry();
+    let blocks = parse(&extensions, SRC);
+    let options = WriterOptions::new().with_extensions(extensions);
+    assert_eq!(
+        block_to_markdown(&blocks[1], &options),
+        "::: warning\nMind the gap.\n:::\n"
+    );
+}
+
+#[test]
+fn extensions_render_html() {
+    let extensions = registry();
+    let blocks = parse(&extensions, SRC);
+    assert_eq!(
+        extensions.to_html(&blocks),
+        "<p>intro</p>\n<div class=\"warning\">Mind the gap.</div>\n"
+    );
+}
+
+#[test]
+fn extensions_apply_to_large_documents() {
+    let extensions = registry();
+    let src = SRC.repeat(100);
+    let blocks = parse(&extensions, &src);
+    let md = extensions.to_markdown(&blocks);
+    assert_eq!(md.matches("::: warning\n").count(), 100);
+    assert_eq!(parse(&extensions, &md), blocks);
+}
@@ -0,0 +1,70 @@
+use pulldown_cmark::{HeadingLevel, LinkType};
+use pulldown_cmark_writer::ast::extract::{SectionSelector, extract_section, images, links};
+use pulldown_cmark_writer::ast::{Block, Cursor, blocks_to_markdown};
+
+mod common;
+use common::parse_all as parse;
+
+const SRC: &str = "See [the *docs*](https://docs.example \"Docs\").\n\n> - item\n>\n> - [![logo](logo.png)][home] <https://auto.example>\n\n| a |\n|---|\n| ![cell](c.png) |\n\n[home]: https://home.example\n";
+
+#[test]
+fn links_come_with_text_and_path() {
+    let blocks = parse(SRC);
+    let found = links(&blocks);
+    let summary: Vec<_> = found
+        .iter()
+        .map(|l| (l.dest.as_str(), l.text.as_str(), l.path.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("https://docs.example", "the docs", vec![0]),
+            ("https://home.example", "logo", vec![1, 0, 1, 0]),
+            (
+                "https://auto.example",
+                "https://auto.example",
+                vec![1, 0, 1, 0]
+            ),
+        ]
+    );
+    assert_eq!(found[0].title, "Docs");
+    assert_eq!(found[1].link_type, LinkType::Reference);
+    assert_eq!(found[1].id, "home");
+}
+
+#[test]
+fn images_are_found_in_links_and_tables() {
+    let mut blocks = parse(SRC);
+    let found = images(&blocks);
+    let dests: Vec<&str> = found.iter().map(|i| i.dest.as_str()).collect();
+    assert_eq!(dests, ["logo.png", "c.png"]);
+    assert_eq!(found[0].text, "logo");
+    assert_eq!(found[1].path, [2]);
+
+    let cursor = Cursor::at(&mut blocks, &found[0].path).unwrap();
+    assert!(matches!(cursor.get(), Some(Block::Paragraph(_))));
+}
+
+const DOC: &str = "# Guide\n\n## Install\n\nRun it[^a], see [the site][home].\n\n### Linux\n\nUse apt.\n\n## Install\n\nAgain.\n\n# Other\n\n[^a]: Note[^b].\n\n[^b]: Inner.\n\n[^c]: Unused.\n\n[home]: https://home.example\n";
+
+#[test]
+fn sections_come_with_what_they_use() {
+    let mut blocks = parse(DOC);
+    blocks.push(Block::LinkDefinition {
+        id: "Home".into(),
+        dest: "https://home.example".into(),
+        title: String::new(),
+    });
+
+    let section = extract_section(&blocks, &SectionSelector::text("install"));
+    assert_eq!(
+        blocks_to_markdown(&section),
+        "## Install\n\n\nRun it[^a], see [the site][home].\n\n\n### Linux\n\n\nUse apt.\n\n\n[^a]: Note[^b].\n\n\n[^b]: Inner.\n\n\n[Home]: https://home.example\n"
+    );
+
+    let again = extract_section(&blocks, &SectionSelector::slug("install-1"));
+    assert_eq!(blocks_to_markdown(&again), "## Install\n\n\nAgain.\n");
+
+    let selector = SectionSelector::text("Linux").level(HeadingLevel::H2);
+    assert!(extract_section(&blocks, &selector).is_empty());
+}
@@ -0,0 +1,91 @@
+use pulldown_cmark_writer::ast::WriterOptions;
+use pulldown_cmark_writer::ast::format::{FormatError, FormatOptions, check, format_markdown};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn collect_md_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let p = entry.unwrap().path();
+        if p.is_dir() {
+            collect_md_files(&p, out);
+        } else if p.extension().is_some_and(|ext| ext == "md") {
+            out.push(p);
+        }
+    }
+}
+
+#[test]
+fn formatting_tidies_the_source() {
+    let options = FormatOptions::default();
+    let src = "Title\n=====\n\n* one\n* two\n\nSome *text* and __more__\nhere.\n";
+    let out = format_markdown(src, &options).unwrap();
+    assert_eq!(
+        out,
+        "# Title\n\n- one\n- two\n\nSome *text* and **more**\nhere.\n"
+    );
+    assert_eq!(check(src, &options), Ok(false));
+    assert_eq!(check(&out, &options), Ok(true));
+}
+
+#[test]
+fn formatting_writes_with_the_writer_options() {
+    let options = FormatOptions {
+        writer: WriterOptions {
+            blank_lines: 2,
+            ..WriterOptions::default()
+        },
+        ..FormatOptions::default()
+    };
+    let src = "# Title\n\nText with [a link][id].\n\n[id]: /url\n[unused]: /other\n";
+    assert_eq!(
+        format_markdown(src, &options).unwrap(),
+        "# Title\n\n\nText with [a link][id].\n\n[id]: /url\n\n\n[unused]: /other\n"
+    );
+}
+
+#[test]
+fn formatting_twice_changes_nothing() {
+    let mut files = Vec::new();
+    collect_md_files(Path::new("src/fixtures"), &mut files);
+    let options = FormatOptions::default();
+    for file in files {
+        let src = fs::read_to_string(&file).unwrap();
+        let once = match format_markdown(&src, &options) {
+            Ok(once) => once,
+            // some spec examples hold what the writer cannot express yet
+            Err(FormatError::Changed) if file.starts_with("src/fixtures/specs") => continue,
+            Err(e) => panic!("{}: {}", file.display(), e),
+        };
+        assert_eq!(
+            check(&once, &options),
+            Ok(true),
+            "{}:\n{}",
+            file.display(),
+            once
+        );
+    }
+}
+
+#[test]
+fn formatting_keeps_what_the_source_says() {
+    let options = FormatOptions::default();
+    let format = |src: &str| format_markdown(src, &options);
+    assert_eq!(format("* a\n* b\n\n  c\n").unwrap(), "- a\n- b\n\n  c\n");
+    assert_eq!(
+        format("- [ ] x\n- [x] *y*\n").unwrap(),
+        "- [ ] x\n- [x] *y*\n"
+    );
+    assert_eq!(
+        format("| a | b | c |\n|:-|-:|:-:|\n| d | e | f |\n").unwrap(),
+        "a  |  b |  c \n:- | -: | :-:\nd  |  e |  f \n"
+    );
+}
+
+#[test]
+fn formatting_fails_rather_than_change_the_document() {
+    let options = FormatOptions::default();
+    // the writer does not escape text, so this would become a list
+    let src = "1986\\. A good year.\n";
+    assert_eq!(format_markdown(src, &options), Err(FormatError::Changed));
+    assert_eq!(check(src, &options), Err(FormatError::Changed));
+}
@@ -0,0 +1,71 @@
+#![cfg(all(feature = "yaml", feature = "toml"))]
+
+use pulldown_cmark::{MetadataBlockKind, Options};
+use pulldown_cmark_writer::ast::frontmatter::{self, FrontMatterError};
+use pulldown_cmark_writer::ast::{Document, document_to_markdown, parse_markdown_to_document};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Page {
+    title: String,
+    draft: bool,
+}
+
+fn opts() -> Options {
+    Options::ENABLE_YAML_STYLE_METADATA_BLOCKS | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS
+}
+
+#[test]
+fn yaml_front_matter_reads_and_writes_back() {
+    let mut doc =
+        parse_markdown_to_document("---\ntitle: Home\ndraft: true\n---\n\nBody.\n", opts());
+    let mut page: Page = frontmatter::parse(&doc).unwrap();
+    assert_eq!(
+        page,
+        Page {
+            title: "Home".into(),
+            draft: true
+        }
+    );
+
+    page.draft = false;
+    frontmatter::set(&mut doc, &page).unwrap();
+    assert_eq!(
+        document_to_markdown(&doc),
+        "---\ntitle: Home\ndraft: false\n---\n\n\nBody.\n"
+    );
+}
+
+#[test]
+fn toml_front_matter_keeps_its_format() {
+    let mut doc = parse_markdown_to_document(
+        "+++\ntitle = \"Home\"\ndraft = true\n+++\n\nBody.\n",
+        opts(),
+    );
+    let mut page: Page = frontmatter::parse(&doc).unwrap();
+    assert_eq!(page.title, "Home");
+
+    page.title = "Start".into();
+    frontmatter::set(&mut doc, &page).unwrap();
+    let (kind, _) = doc.metadata.as_ref().unwrap();
+    assert_eq!(*kind, MetadataBlockKind::PlusesStyle);
+    assert_eq!(frontmatter::parse::<Page>(&doc).unwrap(), page);
+}
+
+#[test]
+fn missing_front_matter() {
+    let mut doc = Document::default();
+    assert!(matches!(
+        frontmatter::parse::<Page>(&doc),
+        Err(FrontMatterError::Missing)
+    ));
+    let page = Page {
+        title: "New".into(),
+        draft: true,
+    };
+    frontmatter::set(&mut doc, &page).unwrap();
+    assert_eq!(
+        document_to_markdown(&doc),
+        "---\ntitle: New\ndraft: true\n---\n"
+    );
+}
@@ -0,0 +1,56 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::github::GitHubReferences;
+use pulldown_cmark_writer::ast::{
+    Block, Inline, ParserOptions, ParserRegistry, blocks_to_markdown,
+};
+
+fn parse(references: GitHubReferences, src: &str) -> Vec<Block> {
+    let mut registry = ParserRegistry::new();
+    registry.register_inline(references, ParserOptions::new());
+    let events: Vec<Event> = Parser::new(src).collect();
+    registry.parse(&events)
+}
+
+#[test]
+fn references_become_links() {
+    let blocks = parse(
+        GitHubReferences::new("acme/app"),
+        "Thanks @alice-b for #12 and rust-lang/rust#99.\n",
+    );
+    assert_eq!(
+        blocks,
+        [Block::paragraph([
+            Inline::text("Thanks "),
+            Inline::link("https://github.com/alice-b", ["@alice-b"]),
+            Inline::text(" for "),
+            Inline::link("https://github.com/acme/app/issues/12", ["#12"]),
+            Inline::text(" and "),
+            Inline::link(
+                "https://github.com/rust-lang/rust/issues/99",
+                ["rust-lang/rust#99"]
+            ),
+            Inline::text("."),
+        ])]
+    );
+
+    let md = blocks_to_markdown(&parse(
+        GitHubReferences::default()
+            .user_url("https://git.example.com/{user}")
+            .issue_url("https://git.example.com/{owner}/{repo}/-/issues/{number}"),
+        "- @bob closed o/r#3\n- #4 needs a repository\n",
+    ));
+    assert_eq!(
+        md,
+        "- [@bob](https://git.example.com/bob) closed [o/r#3](https://git.example.com/o/r/-/issues/3)\n- #4 needs a repository\n"
+    );
+}
+
+#[test]
+fn references_in_words_links_and_code_stay_text() {
+    let src = "Mail a@b.com, see C#1, `#2` or [#3](x) and [@bob](y), not #0 or #07.\n";
+    let plain = Parser::new(src).collect::<Vec<_>>();
+    assert_eq!(
+        parse(GitHubReferences::new("o/r"), src),
+        pulldown_cmark_writer::ast::parse_events_to_blocks(&plain)
+    );
+}
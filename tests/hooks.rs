@@ -0,0 +1,152 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use pulldown_cmark_writer::ast::{
+    Block, HookResult, Inline, ParseContext, parse_events_to_blocks_with_hook,
+};
+
+fn figure_hook(evs: &[Event], _i: usize, ctx: &ParseContext) -> Option<(usize, HookResult)> {
+    match evs {
+        // `<!-- figure: cat.png | A cat -->` expands to an image and a caption
+        [
+            Event::Start(Tag::HtmlBlock),
+            Event::Html(h),
+            Event::End(TagEnd::HtmlBlock),
+            ..,
+        ] if !ctx.parent_collects_inlines => {
+            let body = h.trim().strip_prefix("<!-- figure:")?.strip_suffix("-->")?;
+            let (src, caption) = body.split_once('|')?;
+            let image = Block::paragraph(vec![Inline::Image {
+                link_type: pulldown_cmark::LinkType::Inline,
+                dest: src.trim().into(),
+                title: String::new(),
+                id: String::new(),
+                children: vec![Inline::text(caption.trim())],
+            }]);
+            let caption =
+                Block::paragraph(vec![Inline::emphasis(vec![Inline::text(caption.trim())])]);
+            Some((3, HookResult::Blocks(vec![image, caption])))
+        }
+        // `:kbd:` becomes inline code
+        [Event::Text(t), ..] if t.as_ref() == ":kbd:" => {
+            Some((1, HookResult::Inline(Inline::code("Ctrl"))))
+        }
+        _ => None,
+    }
+}
+
+fn parse(src: &str) -> Vec<Block> {
+    let events: Vec<Event> = Parser::new(src).collect();
+    parse_events_to_blocks_with_hook(&events, Some(&mut figure_hook))
+}
+
+#[test]
+fn hook_expands_to_sibling_blocks() {
+    let blocks = parse("intro\n\n<!-- figure: cat.png | A cat -->\n\nafter\n");
+    assert_eq!(blocks.len(), 4);
+    assert!(
+        matches!(&blocks[1], Block::Paragraph(inls) if matches!(inls[0], Inline::Image { .. }))
+    );
+    assert_eq!(
+        blocks[2],
+        Block::paragraph(vec![Inline::emphasis(vec![Inline::text("A cat")])])
+    );
+}
+
+#[test]
+fn hook_blocks_stay_inside_containers() {
+    let blocks = parse("> <!-- figure: cat.png | A cat -->\n");
+    match &blocks[..] {
+        [Block::BlockQuote(inner)] => assert_eq!(inner.len(), 2),
+        other => panic!("expected one block quote, got {:?}", other),
+    }
+}
+
+#[test]
+fn hook_inline_joins_surrounding_text() {
+    let events = vec![
+        Event::Start(Tag::Paragraph),
+        Event::Text("press ".into()),
+        Event::Text(":kbd:".into()),
+        Event::End(TagEnd::Paragraph),
+    ];
+    let blocks = parse_events_to_blocks_with_hook(&events, Some(&mut figure_hook));
+    assert_eq!(
+        blocks,
+        vec![Block::paragraph(vec![
+            Inline::text("press "),
+            Inline::code("Ctrl")
+        ])]
+    );
+}
+
+#[test]
+fn hook_inline_at_block_level_gets_a_paragraph() {
+    let events = vec![Event::Text(":kbd:".into())];
+    let blocks = parse_events_to_blocks_with_hook(&events, Some(&mut figure_hook));
+    assert_eq!(blocks, vec![Block::paragraph(vec![Inline::code("Ctrl")])]);
+}
+
+fn caption_hook(evs: &[Event], _i: usize, ctx: &ParseContext) -> Option<(usize, HookResult)> {
+    // `Caption: ...` right after an image paragraph is folded into it
+    let [
+        Event::Start(Tag::Paragraph),
+        Event::Text(t),
+        Event::End(TagEnd::Paragraph),
+        ..,
+    ] = evs
+    else {
+        return None;
+    };
+    let caption = t.strip_prefix("Caption: ")?;
+    let Some(Block::Paragraph(prev)) = ctx.preceding_blocks.last() else {
+        return None;
+    };
+    if !matches!(prev.as_slice(), [Inline::Image { .. }]) {
+        return None;
+    }
+    let mut merged = prev.clone();
+    merged.push(Inline::HardBreak);
+    merged.push(Inline::emphasis(vec![Inline::text(caption)]));
+    Some((3, HookResult::ReplacePrevious(Block::Paragraph(merged))))
+}
+
+#[test]
+fn hook_merges_caption_into_preceding_image() {
+    let events: Vec<Event> =
+        Parser::new("![cat](cat.png)\n\nCaption: A cat\n\nCaption: stray\n").collect();
+    let blocks = parse_events_to_blocks_with_hook(&events, Some(&mut caption_hook));
+    assert_eq!(blocks.len(), 2);
+    match &blocks[0] {
+        Block::Paragraph(inls) => {
+            assert!(matches!(inls[0], Inline::Image { .. }));
+            assert_eq!(inls[2], Inline::emphasis(vec![Inline::text("A cat")]));
+        }
+        other => panic!("expected paragraph, got {:?}", other),
+    }
+    // without an image before it the caption stays a plain paragraph
+    assert_eq!(
+        blocks[1],
+        Block::paragraph(vec![Inline::text("Caption: stray")])
+    );
+}
+
+#[test]
+fn hook_sees_preceding_inlines() {
+    let mut seen = Vec::new();
+    let mut hook = |evs: &[Event], _i: usize, ctx: &ParseContext| {
+        if let Some(Event::Text(t)) = evs.first()
+            && t.as_ref() == "b"
+        {
+            seen.extend(ctx.preceding_inlines.iter().cloned());
+        }
+        None
+    };
+    let events = vec![
+        Event::Start(Tag::Paragraph),
+        Event::Text("a".into()),
+        Event::Code("c".into()),
+        Event::Text("b".into()),
+        Event::End(TagEnd::Paragraph),
+    ];
+    parse_events_to_blocks_with_hook(&events, Some(&mut hook));
+    assert_eq!(seen, vec![Inline::text("a"), Inline::code("c")]);
+}
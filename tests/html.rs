@@ -0,0 +1,81 @@
+use pulldown_cmark::{CowStr, Event, Parser, Tag, TagEnd};
+use pulldown_cmark_writer::ast::custom::{BlockNode, InlineNode};
+use pulldown_cmark_writer::ast::{
+    Block, HtmlOptions, Inline, block_to_events, blocks_to_html, parse_events_to_blocks,
+};
+use pulldown_cmark_writer::{Line, Region};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+struct Badge(&'static str);
+impl InlineNode for Badge {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![Event::Text(CowStr::from(self.0))]
+    }
+    fn to_line(&self) -> Line {
+        Line::from_str(&format!("[{}]", self.0))
+    }
+    fn to_html(&self) -> Option<String> {
+        Some(format!("<span class=\"badge\">{}</span>", self.0))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Note(bool);
+impl BlockNode for Note {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Paragraph),
+            Event::Text(CowStr::from("note")),
+            Event::End(TagEnd::Paragraph),
+        ]
+    }
+    fn to_region(&self) -> Region {
+        Region::from_str("note")
+    }
+    fn to_html(&self) -> Option<String> {
+        self.0.then(|| "<aside>note</aside>\n".to_string())
+    }
+}
+
+#[test]
+fn plain_documents_render_like_pulldown() {
+    let src = "# Title\n\n> *quoted* text\n\n- a\n- b\n";
+    let events: Vec<Event> = Parser::new(src).collect();
+    let mut expected = String::new();
+    pulldown_cmark::html::push_html(&mut expected, Parser::new(src));
+    assert_eq!(
+        blocks_to_html(&parse_events_to_blocks(&events), &HtmlOptions::default()),
+        expected
+    );
+}
+
+#[test]
+fn custom_nodes_render_their_own_html() {
+    let doc = vec![
+        Block::Paragraph(vec![
+            Inline::Text(Region::from_str("status: ")),
+            Inline::Custom(Arc::new(Badge("ok"))),
+        ]),
+        Block::BlockQuote(vec![Block::Custom(Arc::new(Note(true)))]),
+        Block::Custom(Arc::new(Note(false))),
+    ];
+    assert_eq!(
+        blocks_to_html(&doc, &HtmlOptions::default()),
+        "<p>status: <span class=\"badge\">ok</span></p>\n\
+         <blockquote>\n<aside>note</aside>\n</blockquote>\n\
+         <p>note</p>\n"
+    );
+    // outside the HTML writer the nodes still produce their events
+    assert_eq!(block_to_events(&doc[2]), Note(true).to_events());
+}
+
+#[test]
+fn hard_breaks_keep_lines() {
+    let events: Vec<Event> = Parser::new("one\ntwo\n").collect();
+    let options = HtmlOptions { hard_breaks: true };
+    assert_eq!(
+        blocks_to_html(&parse_events_to_blocks(&events), &options),
+        "<p>one<br />\ntwo</p>\n"
+    );
+}
@@ -0,0 +1,80 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::jsx::{JsxElement, JsxElements, JsxValue};
+use pulldown_cmark_writer::ast::{Block, ExtensionRegistry, Inline, ParserOptions};
+use std::any::Any;
+
+fn registry() -> ExtensionRegistry {
+    let mut extensions = ExtensionRegistry::new();
+    extensions.register(JsxElements::default(), ParserOptions::new());
+    extensions
+}
+
+fn parse(src: &str) -> Vec<Block> {
+    let events: Vec<Event> = Parser::new(src).collect();
+    registry().parse(&events)
+}
+
+fn element(b: &Block) -> &JsxElement {
+    let Block::Custom(c) = b else {
+        panic!("expected an element, got {:?}", b);
+    };
+    let any: &dyn Any = &**c;
+    any.downcast_ref().expect("an element")
+}
+
+#[test]
+fn components_parse_into_elements() {
+    let blocks = parse(concat!(
+        "<Callout type=\"warning\" dismissible>\n\nBack up *first*.\n\n</Callout>\n\n",
+        "<Tabs>\n  <Tabs.Item label='a'>Apples</Tabs.Item>\n</Tabs>\n\n",
+        "<div>plain HTML</div>\n",
+    ));
+    assert_eq!(blocks.len(), 3);
+
+    let callout = element(&blocks[0]);
+    assert_eq!(callout.name, "Callout");
+    assert_eq!(
+        callout.attr("type"),
+        Some(&JsxValue::String("warning".into()))
+    );
+    assert_eq!(callout.attrs[1], ("dismissible".to_string(), None));
+    assert_eq!(
+        callout.children,
+        [Block::paragraph([
+            Inline::text("Back up "),
+            Inline::emphasis([Inline::text("first")]),
+            Inline::text(".")
+        ])]
+    );
+
+    let tabs = element(&blocks[1]);
+    let item = element(&tabs.children[0]);
+    assert_eq!(item.name, "Tabs.Item");
+    assert_eq!(item.children, [Block::paragraph(["Apples"])]);
+
+    assert!(matches!(blocks[2], Block::HtmlBlock(_)));
+
+    // without its closing tag the element stays HTML
+    assert!(matches!(parse("<Note>\n\nText.\n")[0], Block::HtmlBlock(_)));
+}
+
+#[test]
+fn elements_write_back_with_markdown_children() {
+    let blocks = parse("<Outer>\n\n<Chart data={[1, {a: \"}\"}]} />\n\nText.\n\n</Outer>\n");
+    let outer = element(&blocks[0]);
+    let chart = element(&outer.children[0]);
+    assert_eq!(
+        chart.attr("data"),
+        Some(&JsxValue::Expression("[1, {a: \"}\"}]".into()))
+    );
+    let md = registry().to_markdown(&blocks);
+    assert_eq!(
+        md,
+        "<Outer>\n\n\n<Chart data={[1, {a: \"}\"}]} />\n\n\nText.\n\n\n</Outer>\n"
+    );
+    assert_eq!(parse(&md), blocks);
+    assert_eq!(
+        registry().to_html(&blocks),
+        "<Outer>\n<Chart data={[1, {a: \"}\"}]} />\n<p>Text.</p>\n</Outer>\n"
+    );
+}
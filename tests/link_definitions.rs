@@ -0,0 +1,54 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::{
+    Block, block_to_events, blocks_to_markdown, link_definition_blocks, parse_events_to_blocks,
+};
+
+const SRC: &str = "[b]: /b \"B\"\n\nSee [x][a].\n\n[a]: /a\n";
+
+fn parse_with_definitions(src: &str) -> Vec<Block> {
+    let parser = Parser::new(src);
+    let defs = link_definition_blocks(parser.reference_definitions());
+    let events: Vec<Event> = parser.collect();
+    let mut blocks = parse_events_to_blocks(&events);
+    blocks.extend(defs);
+    blocks
+}
+
+#[test]
+fn recovers_definitions_in_source_order() {
+    let parser = Parser::new(SRC);
+    let defs = link_definition_blocks(parser.reference_definitions());
+    assert_eq!(
+        defs,
+        vec![
+            Block::LinkDefinition {
+                id: "b".into(),
+                dest: "/b".into(),
+                title: "B".into(),
+            },
+            Block::LinkDefinition {
+                id: "a".into(),
+                dest: "/a".into(),
+                title: String::new(),
+            },
+        ]
+    );
+    assert!(defs.iter().all(|d| block_to_events(d).is_empty()));
+}
+
+#[test]
+fn definitions_are_written_where_placed() {
+    let mut blocks = parse_with_definitions(SRC);
+    // move the definitions to the front, dropping the unused one
+    blocks.retain(|b| !matches!(b, Block::LinkDefinition { id, .. } if id == "b"));
+    let def = blocks.pop().unwrap();
+    blocks.insert(0, def);
+
+    let md = blocks_to_markdown(&blocks);
+    assert!(md.starts_with("[a]: /a\n"));
+    assert!(!md.contains("[b]:"));
+    assert_eq!(
+        parse_events_to_blocks(&Parser::new(&md).collect::<Vec<_>>()),
+        parse_events_to_blocks(&Parser::new(SRC).collect::<Vec<_>>())
+    );
+}
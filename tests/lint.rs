@@ -0,0 +1,89 @@
+use pulldown_cmark::Options;
+use pulldown_cmark_writer::ast::lint::{
+    BareUrls, HeadingIncrement, LineLength, LintContext, Linter, Rule,
+};
+use pulldown_cmark_writer::ast::{Block, Inline};
+
+const SRC: &str = "# Title\n\n### Skipped:\n\nSee https://example.com and <https://ok.example>.\n\n> - item\n>\n>   [https://in.link](https://in.link)\n";
+
+#[test]
+fn default_rules_report_with_spans() {
+    let mut linter = Linter::with_default_rules();
+    assert_eq!(
+        linter.rule_names(),
+        [
+            "heading-increment",
+            "heading-punctuation",
+            "bare-urls",
+            "line-length"
+        ]
+    );
+    let found: Vec<_> = linter
+        .lint_markdown(SRC, Options::empty())
+        .into_iter()
+        .map(|d| (d.rule, d.path, d.span.map(|s| &SRC[s])))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("heading-increment", vec![1], Some("### Skipped:\n")),
+            ("heading-punctuation", vec![1], Some("### Skipped:\n")),
+            (
+                "bare-urls",
+                vec![2],
+                Some("See https://example.com and <https://ok.example>.\n")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn rules_keep_no_state_between_runs() {
+    let blocks = vec![Block::heading(pulldown_cmark::HeadingLevel::H3, ["Deep"])];
+    let mut linter = Linter::new();
+    linter.add(HeadingIncrement::default());
+    assert_eq!(linter.lint(&blocks), []);
+    assert_eq!(linter.lint(&blocks), []);
+}
+
+#[test]
+fn long_lines_are_measured_as_written() {
+    let mut linter = Linter::new();
+    linter.add(LineLength { max: 10 }).add(BareUrls);
+    let blocks = vec![Block::BlockQuote(vec![Block::paragraph([
+        "short",
+        "and a much longer line",
+    ])])];
+    let diagnostics = linter.lint(&blocks);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, [0, 0]);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "[0, 0]: line-length: line 1 is 27 columns, over 10"
+    );
+}
+
+struct NoEmphasis;
+
+impl Rule for NoEmphasis {
+    fn name(&self) -> &'static str {
+        "no-emphasis"
+    }
+
+    fn check_inline(&mut self, inline: &Inline, cx: &mut LintContext<'_>) {
+        if matches!(inline, Inline::Emphasis(_)) {
+            cx.report("emphasis is not allowed");
+        }
+    }
+}
+
+#[test]
+fn custom_rules_plug_in() {
+    let mut linter = Linter::new();
+    linter.add(NoEmphasis);
+    let d = linter.lint_markdown("plain\n\n- *one*\n", Options::empty());
+    assert_eq!(d.len(), 1);
+    assert_eq!(d[0].rule, "no-emphasis");
+    assert_eq!(d[0].path, [1, 0, 0]);
+    assert_eq!(d[0].span, Some(9..14));
+}
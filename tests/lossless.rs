@@ -0,0 +1,50 @@
+use pulldown_cmark::Options;
+use pulldown_cmark_writer::ast::{
+    Block, Inline, SourceBlock, lossless_to_markdown, parse_markdown_lossless,
+};
+
+const SRC: &str = "Title\n=====\n\n*  one\n*  two\n\n\n[site]: https://example.com\n\nSee   [site][] __now__.\n\n    indented code\n";
+
+#[test]
+fn untouched_documents_are_written_byte_for_byte() {
+    let doc = parse_markdown_lossless(SRC, Options::empty());
+    assert_eq!(doc.blocks.len(), 4);
+    assert!(doc.blocks.iter().all(|b| !b.is_modified()));
+    assert_eq!(doc.blocks[1].source(), Some("*  one\n*  two\n"));
+    assert_eq!(lossless_to_markdown(&doc), SRC);
+}
+
+#[test]
+fn only_modified_blocks_are_reserialized() {
+    let mut doc = parse_markdown_lossless(SRC, Options::empty());
+    doc.blocks[1].block = Block::paragraph(["changed"]);
+    assert!(doc.blocks[1].is_modified());
+    assert_eq!(
+        lossless_to_markdown(&doc),
+        SRC.replace("*  one\n*  two\n", "changed\n")
+    );
+}
+
+#[test]
+fn inserted_and_removed_blocks() {
+    let mut doc = parse_markdown_lossless("a\n\nb\n\n\nc", Options::empty());
+    doc.blocks.remove(1);
+    doc.blocks
+        .insert(0, SourceBlock::new(Block::paragraph(["new"])));
+    doc.blocks.push(Block::Rule.into());
+    assert_eq!(lossless_to_markdown(&doc), "new\n\na\n\n\nc\n\n---\n");
+}
+
+#[test]
+fn edited_references_leave_their_definition_to_the_source() {
+    let src = "para [a][x]\n\n[x]: http://e.com\n\nother\n";
+    let mut doc = parse_markdown_lossless(src, Options::empty());
+    let Block::Paragraph(inls) = &mut doc.blocks[0].block else {
+        panic!("expected a paragraph");
+    };
+    inls[0] = Inline::text("edited ");
+    assert_eq!(
+        lossless_to_markdown(&doc),
+        "edited [a][x]\n\n[x]: http://e.com\n\nother\n"
+    );
+}
@@ -0,0 +1,60 @@
+use pulldown_cmark::{HeadingLevel, Options};
+use pulldown_cmark_writer::ast::{Block, Inline, blocks_to_markdown};
+use pulldown_cmark_writer::{Region, md};
+
+mod common;
+
+fn parse(src: &str) -> Vec<Block> {
+    common::parse_with(src, Options::ENABLE_STRIKETHROUGH)
+}
+
+#[test]
+fn builds_headings_and_paragraphs() {
+    let doc = md! {
+        h1 "Title";
+        p { "Hello " strong { "world" } "!" }
+    };
+    assert_eq!(
+        doc,
+        vec![
+            Block::Heading {
+                level: HeadingLevel::H1,
+                id: None,
+                classes: Vec::new(),
+                attrs: Vec::new(),
+                children: vec![Inline::Text(Region::from_str("Title"))],
+            },
+            Block::Paragraph(vec![
+                Inline::Text(Region::from_str("Hello ")),
+                Inline::Strong(vec![Inline::Text(Region::from_str("world"))]),
+                Inline::Text(Region::from_str("!")),
+            ]),
+        ]
+    );
+}
+
+#[test]
+fn matches_parsed_markdown() {
+    let doc = md! {
+        h2 { "Some " em { "styled" } " " code "text" };
+        quote { p { "quoted " del { "gone" } } }
+        ul ["one", { p { "two " link "https://example.com" "site" }; code "rust" "fn main() {}" }];
+        ol 3 ["three"];
+        hr
+    };
+    let md = blocks_to_markdown(&doc);
+    assert_eq!(parse(&md), doc);
+}
+
+#[test]
+fn accepts_expressions() {
+    let name = "dynamic";
+    let doc = md! {
+        p { "built " (Inline::Text(Region::from_str(name))) };
+        (Block::Rule)
+    };
+    assert_eq!(doc.len(), 2);
+    assert_eq!(doc[0].to_string(), "built dynamic");
+    assert_eq!(doc[1], Block::Rule);
+    assert!(md! {}.is_empty());
+}
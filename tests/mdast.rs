@@ -0,0 +1,161 @@
+#![cfg(feature = "mdast")]
+
+use pulldown_cmark::{Event, Options, Parser};
+use pulldown_cmark_writer::ast::mdast::{MdastError, from_mdast, from_mdast_json};
+use pulldown_cmark_writer::ast::{Block, blocks_to_markdown, parse_events_to_blocks};
+use serde_json::json;
+
+fn text(value: &str) -> serde_json::Value {
+    json!({ "type": "text", "value": value })
+}
+
+#[test]
+fn trees_read_as_their_markdown_parses() {
+    let md = concat!(
+        "---\ntitle: Doc\n---\n\n",
+        "# Hello *world*\n\n",
+        "Some **bold**, ~~gone~~, `code`, [a link](https://x.com \"T\") and ![alt](i.png).\n",
+        "Next line  \nafter a break.\n\n",
+        "> Quote\n\n",
+        "- one\n- two\n\n",
+        "3. three\n\n",
+        "```rust meta\nfn main() {}\n```\n\n",
+        "<div>raw</div>\n\n",
+        "| one | two |\n| :- | -: |\n| 1 | 2 |\n\n",
+        "[ref][Id] and[^n].\n\n",
+        "[Id]: /ref \"Ref\"\n\n",
+        "[^n]: Note.\n\n",
+        "***\n",
+    );
+    let tree = json!({
+        "type": "root",
+        "children": [
+            { "type": "yaml", "value": "title: Doc" },
+            { "type": "heading", "depth": 1, "children": [
+                text("Hello "),
+                { "type": "emphasis", "children": [text("world")] },
+            ]},
+            { "type": "paragraph", "children": [
+                text("Some "),
+                { "type": "strong", "children": [text("bold")] },
+                text(", "),
+                { "type": "delete", "children": [text("gone")] },
+                text(", "),
+                { "type": "inlineCode", "value": "code" },
+                text(", "),
+                { "type": "link", "url": "https://x.com", "title": "T", "children": [text("a link")] },
+                text(" and "),
+                { "type": "image", "url": "i.png", "title": null, "alt": "alt" },
+                text(".\nNext line"),
+                { "type": "break" },
+                text("after a break."),
+            ]},
+            { "type": "blockquote", "children": [
+                { "type": "paragraph", "children": [text("Quote")] },
+            ]},
+            { "type": "list", "ordered": false, "spread": false, "children": [
+                { "type": "listItem", "children": [{ "type": "paragraph", "children": [text("one")] }] },
+                { "type": "listItem", "children": [{ "type": "paragraph", "children": [text("two")] }] },
+            ]},
+            { "type": "list", "ordered": true, "start": 3, "children": [
+                { "type": "listItem", "children": [{ "type": "paragraph", "children": [text("three")] }] },
+            ]},
+            { "type": "code", "lang": "rust", "meta": "meta", "value": "fn main() {}" },
+            { "type": "html", "value": "<div>raw</div>" },
+            { "type": "table", "align": ["left", "right"], "children": [
+                { "type": "tableRow", "children": [
+                    { "type": "tableCell", "children": [text("one")] },
+                    { "type": "tableCell", "children": [text("two")] },
+                ]},
+                { "type": "tableRow", "children": [
+                    { "type": "tableCell", "children": [text("1")] },
+                    { "type": "tableCell", "children": [text("2")] },
+                ]},
+            ]},
+            { "type": "paragraph", "children": [
+                { "type": "linkReference", "identifier": "id", "label": "Id",
+                  "referenceType": "full", "children": [text("ref")] },
+                text(" and"),
+                { "type": "footnoteReference", "identifier": "n", "label": "n" },
+                text("."),
+            ]},
+            { "type": "definition", "identifier": "id", "label": "Id", "url": "/ref", "title": "Ref" },
+            { "type": "footnoteDefinition", "identifier": "n", "label": "n", "children": [
+                { "type": "paragraph", "children": [text("Note.")] },
+            ]},
+            { "type": "thematicBreak" },
+        ],
+    });
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let events: Vec<Event> = Parser::new_ext(md, options).collect();
+    let mut parsed = parse_events_to_blocks(&events);
+    // the event stream does not carry definitions, the tree does
+    let definition = Block::LinkDefinition {
+        id: "Id".into(),
+        dest: "/ref".into(),
+        title: "Ref".into(),
+    };
+    parsed.insert(10, definition);
+    let blocks = from_mdast_json(&tree.to_string()).unwrap();
+    assert_eq!(blocks, parsed);
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        concat!(
+            "---\ntitle: Doc\n---\n\n\n",
+            "# Hello *world*\n\n\n",
+            "Some **bold**, ~~gone~~, `code`, [a link](https://x.com \"T\") and ![alt](i.png).\n",
+            "Next line  \nafter a break.\n\n\n",
+            "> Quote\n\n\n",
+            "- one\n- two\n\n\n",
+            "3. three\n\n\n",
+            "```rust meta\nfn main() {}\n```\n\n\n",
+            "<div>raw</div>\n\n\n",
+            "one | two\n:-- | --:\n1   |   2\n\n\n",
+            "[ref][Id] and[^n].\n\n\n",
+            "[Id]: /ref \"Ref\"\n\n\n",
+            "[^n]: Note.\n\n\n",
+            "---\n",
+        )
+    );
+}
+
+#[test]
+fn code_meta_needs_a_language() {
+    let code = |lang: serde_json::Value| {
+        let tree = json!({ "type": "code", "lang": lang, "meta": "title=x", "value": "x" });
+        blocks_to_markdown(&from_mdast(&tree).unwrap())
+    };
+    assert_eq!(code(json!("sh")), "```sh title=x\nx\n```\n");
+    assert_eq!(code(json!(null)), "```\nx\n```\n");
+}
+
+#[test]
+fn checked_items_write_task_markers() {
+    let tree = json!({ "type": "list", "ordered": false, "children": [
+        { "type": "listItem", "checked": true, "children": [
+            { "type": "paragraph", "children": [text("done")] },
+        ]},
+        { "type": "listItem", "checked": false, "children": [
+            { "type": "paragraph", "children": [text("todo")] },
+        ]},
+    ]});
+    let md = blocks_to_markdown(&from_mdast(&tree).unwrap());
+    assert_eq!(md, "- [x] done\n- [ ] todo\n");
+}
+
+#[test]
+fn unknown_and_malformed_nodes_are_errors() {
+    assert!(matches!(from_mdast_json("{"), Err(MdastError::Json(_))));
+    let err = from_mdast(&json!({ "type": "root", "children": [{ "type": "mdxJsxFlowElement" }] }));
+    assert!(matches!(err, Err(MdastError::Unsupported(t)) if t == "mdxJsxFlowElement"));
+    let err = from_mdast(&json!({ "type": "heading", "depth": 7, "children": [] }));
+    assert!(matches!(
+        err,
+        Err(MdastError::Malformed { field: "depth", .. })
+    ));
+    let err = from_mdast(&json!({ "type": "paragraph", "children": [{ "type": "paragraph" }] }));
+    assert!(matches!(err, Err(MdastError::Unsupported(t)) if t == "paragraph"));
+}
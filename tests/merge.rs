@@ -0,0 +1,50 @@
+use pulldown_cmark::Options;
+use pulldown_cmark_writer::ast::{
+    Block, MergeOptions, blocks_to_markdown, merge_documents, merge_documents_with,
+};
+
+mod common;
+
+fn parse(src: &str) -> Vec<Block> {
+    common::parse_with(
+        src,
+        Options::ENABLE_FOOTNOTES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS,
+    )
+}
+
+#[test]
+fn colliding_footnotes_are_renamed() {
+    let a = parse("A[^1].\n\n[^1]: First.\n");
+    let b = parse("B[^1] and C[^1-2].\n\n[^1]: Second.\n\n[^1-2]: Third.\n");
+    let md = blocks_to_markdown(&merge_documents(vec![a, b]));
+    assert_eq!(
+        md,
+        "A[^1].\n\n\n[^1]: First.\n\n\nB[^1-3] and C[^1-2].\n\n\n[^1-3]: Second.\n\n\n[^1-2]: Third.\n"
+    );
+}
+
+#[test]
+fn colliding_references_are_renamed() {
+    let a = parse("See [docs].\n\n[docs]: https://a.example\n");
+    let b = parse("Read [the docs][docs] or [Docs].\n\n[docs]: https://b.example\n");
+    let c = parse("Same [docs].\n\n[docs]: https://a.example\n");
+    let md = blocks_to_markdown(&merge_documents(vec![a, b, c]));
+    assert_eq!(
+        md,
+        "See [docs].\n\n[docs]: https://a.example\n\n\nRead [the docs][docs-2] or [Docs][docs-2].\n\n[docs-2]: https://b.example\n\n\nSame [docs].\n\n[docs]: https://a.example\n"
+    );
+}
+
+#[test]
+fn front_matter_is_merged_and_separators_inserted() {
+    let a = parse("---\ntitle: A\ntags:\n- x\n---\n\nOne.\n");
+    let b = parse("---\ntitle: B\nauthor: Me\n---\n\nTwo.\n");
+    let options = MergeOptions {
+        separator: Some(Block::Rule),
+    };
+    let md = blocks_to_markdown(&merge_documents_with(vec![a, b], &options));
+    assert_eq!(
+        md,
+        "---\ntitle: A\ntags:\n- x\nauthor: Me\n---\n\n\nOne.\n\n\n---\n\n\nTwo.\n"
+    );
+}
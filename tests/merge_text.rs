@@ -0,0 +1,96 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::{
+    Block, Inline, ParseOptions, blocks_to_markdown, parse_events_to_blocks,
+    parse_events_to_blocks_with_options,
+};
+
+// escapes and entities make pulldown-cmark split the text
+const SRC: &str = "a \\*b\\* &amp; c\nnext *em*\n";
+
+fn events() -> Vec<Event<'static>> {
+    Parser::new(SRC).collect()
+}
+
+#[test]
+fn adjacent_text_is_merged_by_default() {
+    let events = events();
+    assert!(
+        events
+            .iter()
+            .filter(|e| matches!(e, Event::Text(_)))
+            .count()
+            > 4
+    );
+    let blocks = parse_events_to_blocks(&events);
+    assert_eq!(
+        blocks,
+        vec![Block::paragraph(vec![
+            Inline::text("a *b* & c"),
+            Inline::SoftBreak,
+            Inline::text("next "),
+            Inline::emphasis(vec![Inline::text("em")]),
+        ])]
+    );
+}
+
+#[test]
+fn merging_can_be_disabled() {
+    let options = ParseOptions {
+        merge_text: false,
+        ..ParseOptions::default()
+    };
+    let blocks = parse_events_to_blocks_with_options(&events(), &options);
+    match &blocks[0] {
+        Block::Paragraph(inls) => {
+            assert!(inls.iter().filter(|i| matches!(i, Inline::Text(_))).count() > 3)
+        }
+        other => panic!("expected paragraph, got {:?}", other),
+    }
+}
+
+#[test]
+fn soft_breaks_can_be_folded_into_text() {
+    let options = ParseOptions {
+        merge_soft_breaks: true,
+        ..ParseOptions::default()
+    };
+    let blocks = parse_events_to_blocks_with_options(&events(), &options);
+    assert_eq!(
+        blocks,
+        vec![Block::paragraph(vec![
+            Inline::text("a *b* & c\nnext "),
+            Inline::emphasis(vec![Inline::text("em")]),
+        ])]
+    );
+    // the paragraph still writes as two lines
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        blocks_to_markdown(&parse_events_to_blocks(&events()))
+    );
+}
+
+#[test]
+fn tight_list_items_gather_their_text_in_one_paragraph() {
+    let events: Vec<Event> = Parser::new_ext(
+        "- a &amp; *b* c\n- [ ] d\n",
+        pulldown_cmark::Options::ENABLE_TASKLISTS,
+    )
+    .collect();
+    let blocks = parse_events_to_blocks(&events);
+    let Block::List { items, .. } = &blocks[0] else {
+        panic!("expected list, got {:?}", blocks[0]);
+    };
+    assert_eq!(
+        items[0],
+        vec![Block::paragraph(vec![
+            Inline::text("a & "),
+            Inline::emphasis(vec![Inline::text("b")]),
+            Inline::text(" c"),
+        ])]
+    );
+    // the task marker stays in front of the item's text
+    assert_eq!(
+        items[1],
+        vec![Block::paragraph(vec![Inline::text("[ ] d")])]
+    );
+}
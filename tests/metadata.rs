@@ -0,0 +1,62 @@
+use pulldown_cmark::{Event, MetadataBlockKind, Options, Parser};
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::{
+    Block, block_to_events, blocks_to_markdown, parse_events_to_blocks,
+};
+
+fn parse(s: &str) -> (Vec<Event<'static>>, Vec<Block>) {
+    let opts = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    let events: Vec<Event<'static>> = Parser::new_ext(s, opts).map(|e| e.into_static()).collect();
+    let blocks = parse_events_to_blocks(&events);
+    (events, blocks)
+}
+
+#[test]
+fn yaml_front_matter_roundtrip() {
+    let src = "---\ntitle: Hello\ntags: [a, b]\n---\n\n# Heading\n";
+    let (events, blocks) = parse(src);
+    match &blocks[0] {
+        Block::Metadata { kind, content } => {
+            assert_eq!(*kind, MetadataBlockKind::YamlStyle);
+            assert_eq!(content.apply(), "title: Hello\ntags: [a, b]");
+        }
+        other => panic!("expected metadata block, got {:?}", other),
+    }
+
+    let out_events: Vec<Event<'static>> = blocks.iter().flat_map(block_to_events).collect();
+    assert_eq!(events, out_events);
+
+    let md = blocks_to_markdown(&blocks);
+    assert!(md.starts_with("---\ntitle: Hello\ntags: [a, b]\n---\n"));
+    let (reparsed, _) = parse(&md);
+    assert_eq!(events, reparsed);
+}
+
+#[test]
+fn toml_front_matter_roundtrip() {
+    let src = "+++\ntitle = \"Hello\"\n+++\n\nbody\n";
+    let (_, blocks) = parse(src);
+    assert!(matches!(
+        blocks[0],
+        Block::Metadata {
+            kind: MetadataBlockKind::PlusesStyle,
+            ..
+        }
+    ));
+    let md = blocks_to_markdown(&blocks);
+    assert!(md.starts_with("+++\ntitle = \"Hello\"\n+++\n"));
+    assert_eq!(parse(&md).0, parse(src).0);
+}
+
+#[test]
+fn metadata_is_written_first() {
+    let (_, mut blocks) = parse("para\n");
+    blocks.push(Block::Metadata {
+        kind: MetadataBlockKind::YamlStyle,
+        content: Region::from_str("draft: true"),
+    });
+    let md = blocks_to_markdown(&blocks);
+    assert!(md.starts_with("---\ndraft: true\n---\n"));
+    assert!(matches!(parse(&md).1[0], Block::Metadata { .. }));
+}
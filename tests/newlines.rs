@@ -0,0 +1,30 @@
+use pulldown_cmark::{Event, Options, Parser};
+use pulldown_cmark_writer::ast::{blocks_to_markdown, parse_events_to_blocks};
+
+const LF: &str = "# Title\n\nSome *text*\nover two lines.\n\n> quoted\n> twice\n\n```rust\nfn main() {\n    run();\n}\n```\n\n<div>\nhtml\n</div>\n\n- a\n- b\n";
+
+fn write(src: &str) -> String {
+    let events: Vec<Event> = Parser::new_ext(src, Options::all()).collect();
+    blocks_to_markdown(&parse_events_to_blocks(&events))
+}
+
+#[test]
+fn crlf_documents_write_like_lf_ones() {
+    let crlf = LF.replace('\n', "\r\n");
+    let md = write(&crlf);
+    assert!(!md.contains('\r'), "{md:?}");
+    assert_eq!(md, write(LF));
+}
+
+#[test]
+fn lone_carriage_returns_break_code_lines() {
+    let md = write("```\r\none\rtwo\r\n```\r\n");
+    assert_eq!(md, write("```\none\ntwo\n```\n"));
+}
+
+#[test]
+fn html_blocks_keep_their_lines() {
+    let src = "<div>\r\nhtml\r\n</div>\r\n";
+    assert_eq!(write(src), "<div>\nhtml\n</div>\n");
+    assert_eq!(write(&src.replace('\r', "")), "<div>\nhtml\n</div>\n");
+}
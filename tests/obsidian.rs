@@ -0,0 +1,95 @@
+use pulldown_cmark_writer::ast::obsidian::{Callout, Fold, Tag, parse_note};
+use pulldown_cmark_writer::ast::{Block, HtmlOptions, Inline, blocks_to_html, blocks_to_markdown};
+use std::any::Any;
+
+fn callout(b: &Block) -> &Callout {
+    let Block::Custom(c) = b else {
+        panic!("expected a callout, got {:?}", b);
+    };
+    let any: &dyn Any = &**c;
+    any.downcast_ref().expect("a callout")
+}
+
+fn tag(i: &Inline) -> &str {
+    let Inline::Custom(c) = i else {
+        panic!("expected a tag, got {:?}", i);
+    };
+    let any: &dyn Any = &**c;
+    &any.downcast_ref::<Tag>().expect("a tag").name
+}
+
+#[test]
+fn notes_parse_callouts_and_tags() {
+    let blocks = parse_note(concat!(
+        "Filed under #project/alpha, not C#1 or #12.\n\n",
+        "> [!tip]- Folded *title*\n",
+        "> Body with [[Other note|a link]].\n\n",
+        "> [!NOTE]\n",
+        "> Plain.\n\n",
+        "> Just a quote.\n",
+    ));
+    assert_eq!(blocks.len(), 4);
+
+    let Block::Paragraph(text) = &blocks[0] else {
+        panic!("expected a paragraph");
+    };
+    assert_eq!(tag(&text[1]), "project/alpha");
+    assert_eq!(text[2], Inline::text(", not C#1 or #12."));
+
+    let tip = callout(&blocks[1]);
+    assert_eq!(tip.kind, "tip");
+    assert_eq!(tip.fold, Some(Fold::Closed));
+    assert_eq!(
+        tip.title,
+        [
+            Inline::text("Folded "),
+            Inline::emphasis([Inline::text("title")])
+        ]
+    );
+    assert_eq!(tip.children.len(), 1);
+
+    let note = callout(&blocks[2]);
+    assert_eq!((note.kind.as_str(), note.fold), ("NOTE", None));
+    assert!(note.title.is_empty());
+    assert_eq!(note.children, [Block::paragraph(["Plain."])]);
+
+    assert!(matches!(blocks[3], Block::BlockQuote(_)));
+}
+
+#[test]
+fn notes_roundtrip() {
+    let src = concat!(
+        "# Note #inbox\n\n\n",
+        "See [[Other note#Heading|the heading]], [[Plain]] and ![[diagram.png]].\n\n\n",
+        "> [!warning]+ Mind the gap\n",
+        "> First line.\n",
+        ">\n",
+        "> > [!quote]\n",
+        "> > Nested.\n\n\n",
+        "- Item #todo\n",
+        "- [ ] Open task\n",
+        "- [x] Done task\n\n\n",
+        "$$\n",
+        "e^{i\\pi} + 1 = 0\n",
+        "$$\n",
+    );
+    let blocks = parse_note(src);
+    let md = blocks_to_markdown(&blocks);
+    assert_eq!(md, src);
+    assert_eq!(parse_note(&md), blocks);
+
+    let html = blocks_to_html(
+        &parse_note("> [!info] Title\n> Text #tag\n"),
+        &HtmlOptions::default(),
+    );
+    assert_eq!(
+        html,
+        concat!(
+            "<div class=\"callout\" data-callout=\"info\">\n",
+            "<div class=\"callout-title\">Title</div>\n",
+            "<div class=\"callout-content\">\n",
+            "<p>Text <a class=\"tag\" href=\"#tag\">#tag</a></p>\n",
+            "</div>\n</div>\n"
+        )
+    );
+}
@@ -0,0 +1,45 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::{
+    Block, SpannedBlock, parse_events_to_blocks, parse_offset_events_to_blocks,
+};
+
+const SRC: &str = "# Title\n\n> quoted\n>\n> more\n\n- a *b*\n- c\n\n  d\n\n---\n";
+
+fn spanned() -> Vec<SpannedBlock> {
+    let events: Vec<_> = Parser::new(SRC).into_offset_iter().collect();
+    parse_offset_events_to_blocks(&events)
+}
+
+#[test]
+fn top_level_blocks_match_plain_parse() {
+    let events: Vec<Event> = Parser::new(SRC).collect();
+    let plain = parse_events_to_blocks(&events);
+    let spanned = spanned();
+    assert_eq!(
+        spanned.iter().map(|s| s.block.clone()).collect::<Vec<_>>(),
+        plain
+    );
+}
+
+#[test]
+fn spans_cover_source_text() {
+    let spanned = spanned();
+    let text = |s: &SpannedBlock| &SRC[s.span.clone()];
+    assert_eq!(text(&spanned[0]), "# Title\n");
+    assert_eq!(text(&spanned[1]), "> quoted\n>\n> more\n");
+    assert_eq!(text(&spanned[3]), "---\n");
+
+    let quote = &spanned[1].children;
+    assert_eq!(quote.len(), 2);
+    assert_eq!(text(&quote[0]), "quoted\n");
+    assert_eq!(text(&quote[1]), "more\n");
+
+    let items = &spanned[2].children;
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[0].block, Block::Item(_)));
+    // tight item content forms a single paragraph child
+    assert_eq!(items[0].children.len(), 1);
+    assert_eq!(text(&items[0].children[0]).trim_end(), "a *b*");
+    assert_eq!(items[1].children.len(), 2);
+    assert_eq!(text(&items[1].children[1]), "d\n");
+}
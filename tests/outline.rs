@@ -0,0 +1,100 @@
+use pulldown_cmark::{HeadingLevel, Options};
+use pulldown_cmark_writer::ast::outline::{
+    OutlineEntry, build_sections, flatten_sections, outline, outline_markdown,
+};
+use pulldown_cmark_writer::ast::{Block, blocks_to_markdown};
+
+mod common;
+use common::parse;
+
+const SRC: &str = "intro\n\n# One\n\na\n\n## One.1\n\nb\n\n### One.1.1\n\n## One.2\n\n# Two\n\nc\n";
+
+fn title(b: &Option<Block>) -> String {
+    b.as_ref().map(|b| b.to_string()).unwrap_or_default()
+}
+
+#[test]
+fn builds_nested_sections() {
+    let sections = build_sections(&parse(SRC));
+    assert_eq!(sections.len(), 3);
+
+    assert!(sections[0].heading.is_none());
+    assert_eq!(sections[0].children_blocks.len(), 1);
+
+    let one = &sections[1];
+    assert_eq!(title(&one.heading), "# One");
+    assert_eq!(one.children_blocks.len(), 1);
+    assert_eq!(one.subsections.len(), 2);
+    assert_eq!(title(&one.subsections[0].heading), "## One.1");
+    assert_eq!(one.subsections[0].subsections.len(), 1);
+    assert_eq!(title(&one.subsections[1].heading), "## One.2");
+
+    assert_eq!(title(&sections[2].heading), "# Two");
+    assert!(sections[2].subsections.is_empty());
+}
+
+#[test]
+fn flatten_restores_blocks() {
+    let blocks = parse(SRC);
+    assert_eq!(flatten_sections(&build_sections(&blocks)), blocks);
+    assert!(flatten_sections(&build_sections(&[])).is_empty());
+}
+
+#[test]
+fn move_and_renumber_chapters() {
+    let mut sections = build_sections(&parse(SRC));
+    // swap the chapters and demote the second one under the first
+    let two = sections.pop().unwrap();
+    let mut one = sections.pop().unwrap();
+    one.shift_level(1);
+    let mut two = two;
+    two.subsections.push(one);
+    sections.push(two);
+
+    assert_eq!(sections[1].level(), Some(HeadingLevel::H1));
+    assert_eq!(
+        sections[1].subsections[0].subsections[0].level(),
+        Some(HeadingLevel::H3)
+    );
+
+    let md = blocks_to_markdown(&flatten_sections(&sections));
+    let reparsed = build_sections(&parse(&md));
+    assert_eq!(reparsed, sections);
+}
+
+#[test]
+fn outline_lists_headings_in_order() {
+    let src = "# Intro\n\n> ## Quoted *heading*\n\n# Intro\n\n## Setup {#install}\n";
+    let entries = outline_markdown(src, Options::ENABLE_HEADING_ATTRIBUTES);
+    let summary: Vec<_> = entries
+        .iter()
+        .map(|e| (e.level, e.text.as_str(), e.slug.as_str(), e.path.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (HeadingLevel::H1, "Intro", "intro", vec![0]),
+            (
+                HeadingLevel::H2,
+                "Quoted heading",
+                "quoted-heading",
+                vec![1, 0]
+            ),
+            (HeadingLevel::H1, "Intro", "intro-1", vec![2]),
+            (HeadingLevel::H2, "Setup", "install", vec![3]),
+        ]
+    );
+    assert_eq!(
+        &src[entries[1].span.clone().unwrap()],
+        "## Quoted *heading*\n"
+    );
+    assert_eq!(
+        &src[entries[3].span.clone().unwrap()],
+        "## Setup {#install}\n"
+    );
+
+    let blocks = parse(src);
+    let plain: Vec<OutlineEntry> = outline(&blocks);
+    assert_eq!(plain.len(), 4);
+    assert!(plain.iter().all(|e| e.span.is_none()));
+}
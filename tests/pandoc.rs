@@ -0,0 +1,113 @@
+#![cfg(feature = "pandoc")]
+
+use pulldown_cmark::{Event, Options, Parser};
+use pulldown_cmark_writer::ast::parse_events_to_blocks;
+use pulldown_cmark_writer::ast::writer::pandoc::{PANDOC_API_VERSION, blocks_to_pandoc};
+use serde_json::json;
+
+fn to_pandoc(s: &str) -> serde_json::Value {
+    let events: Vec<Event> = Parser::new_ext(s, Options::all()).collect();
+    blocks_to_pandoc(&parse_events_to_blocks(&events))
+}
+
+#[test]
+fn document_envelope() {
+    let doc = to_pandoc("");
+    assert_eq!(doc["pandoc-api-version"], json!(PANDOC_API_VERSION));
+    assert_eq!(doc["meta"], json!({}));
+    assert_eq!(doc["blocks"], json!([]));
+}
+
+#[test]
+fn headings_paragraphs_and_inlines() {
+    let doc = to_pandoc("# Hi there\n\nsome *em* and [a link](http://x \"t\")\nnext\n");
+    assert_eq!(
+        doc["blocks"][0],
+        json!({"t": "Header", "c": [1, ["", [], []], [
+            {"t": "Str", "c": "Hi"}, {"t": "Space"}, {"t": "Str", "c": "there"}
+        ]]})
+    );
+    assert_eq!(
+        doc["blocks"][1],
+        json!({"t": "Para", "c": [
+            {"t": "Str", "c": "some"}, {"t": "Space"},
+            {"t": "Emph", "c": [{"t": "Str", "c": "em"}]}, {"t": "Space"},
+            {"t": "Str", "c": "and"}, {"t": "Space"},
+            {"t": "Link", "c": [["", [], []], [{"t": "Str", "c": "a"}, {"t": "Space"}, {"t": "Str", "c": "link"}], ["http://x", "t"]]},
+            {"t": "SoftBreak"},
+            {"t": "Str", "c": "next"}
+        ]})
+    );
+}
+
+#[test]
+fn code_lists_and_footnotes() {
+    let doc = to_pandoc("```rust\nfn main() {}\n```\n\n3. a\n4. b\n\nnote[^n]\n\n[^n]: the note\n");
+    let blocks = doc["blocks"].as_array().unwrap();
+    assert_eq!(
+        blocks[0],
+        json!({"t": "CodeBlock", "c": [["", ["rust"], []], "fn main() {}"]})
+    );
+    assert_eq!(blocks[1]["t"], "OrderedList");
+    assert_eq!(
+        blocks[1]["c"][0],
+        json!([3, {"t": "Decimal"}, {"t": "Period"}])
+    );
+    // the definition is folded into the reference
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(
+        blocks[2]["c"][1],
+        json!({"t": "Note", "c": [{"t": "Para", "c": [
+            {"t": "Str", "c": "the"}, {"t": "Space"}, {"t": "Str", "c": "note"}
+        ]}]})
+    );
+}
+
+#[test]
+fn footnote_cycles_are_not_expanded_again() {
+    let doc = to_pandoc("a[^x]\n\n[^x]: see[^x]\n");
+    assert_eq!(
+        doc["blocks"][0]["c"][1],
+        json!({"t": "Note", "c": [{"t": "Para", "c": [
+            {"t": "Str", "c": "see"}, {"t": "Str", "c": "[^x]"}
+        ]}]})
+    );
+
+    let doc = to_pandoc("a[^x]\n\n[^x]: x[^y]\n\n[^y]: y[^x]\n");
+    let y = json!({"t": "Note", "c": [{"t": "Para", "c": [
+        {"t": "Str", "c": "y"}, {"t": "Str", "c": "[^x]"}
+    ]}]});
+    assert_eq!(
+        doc["blocks"][0]["c"][1],
+        json!({"t": "Note", "c": [{"t": "Para", "c": [{"t": "Str", "c": "x"}, y]}]})
+    );
+}
+
+#[test]
+fn tables() {
+    let doc = to_pandoc("| a | b |\n|:--|--:|\n| 1 | 2 |\n");
+    let table = &doc["blocks"][0];
+    assert_eq!(table["t"], "Table");
+    assert_eq!(
+        table["c"][2],
+        json!([[{"t": "AlignLeft"}, {"t": "ColWidthDefault"}], [{"t": "AlignRight"}, {"t": "ColWidthDefault"}]])
+    );
+    assert_eq!(table["c"][3][1].as_array().unwrap().len(), 1);
+    assert_eq!(table["c"][4][0][3].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn alerts_become_divs() {
+    let doc = to_pandoc("> [!NOTE]\n> Read me.\n");
+    assert_eq!(
+        doc["blocks"][0],
+        json!({"t": "Div", "c": [["", ["note"], []], [
+            {"t": "Div", "c": [["", ["title"], []], [
+                {"t": "Para", "c": [{"t": "Str", "c": "Note"}]}
+            ]]},
+            {"t": "Para", "c": [
+                {"t": "Str", "c": "Read"}, {"t": "Space"}, {"t": "Str", "c": "me."}
+            ]}
+        ]]})
+    );
+}
@@ -0,0 +1,155 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use pulldown_cmark_writer::ast::custom::{BlockParser, InlineParser};
+use pulldown_cmark_writer::ast::{Block, Inline, ParseContext, ParserOptions, ParserRegistry};
+
+fn events(src: &str) -> Vec<Event<'_>> {
+    Parser::new_ext(src, Options::ENABLE_TABLES).collect()
+}
+
+/// Turns a paragraph whose only text is `marker` into a rule.
+struct MarkerRule(&'static str);
+impl BlockParser for MarkerRule {
+    fn try_parse(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        _ctx: &ParseContext,
+    ) -> Option<(usize, Block)> {
+        match events {
+            [
+                Event::Start(Tag::Paragraph),
+                Event::Text(t),
+                Event::End(_),
+                ..,
+            ] if t.as_ref() == self.0 => Some((3, Block::Rule)),
+            _ => None,
+        }
+    }
+}
+
+/// Turns `word` into inline code carrying `label`.
+struct Word(&'static str, &'static str);
+impl InlineParser for Word {
+    fn try_parse(
+        &self,
+        events: &[Event],
+        _idx: usize,
+        _ctx: &ParseContext,
+    ) -> Option<(usize, Inline)> {
+        match events.first()? {
+            Event::Text(t) if t.as_ref() == self.0 => Some((1, Inline::code(self.1))),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn higher_priority_parsers_win() {
+    let mut registry = ParserRegistry::new();
+    registry
+        .register_inline(Word("x", "low"), ParserOptions::new())
+        .register_inline(Word("x", "high"), ParserOptions::new().priority(5));
+    assert_eq!(registry.len(), 2);
+    assert_eq!(
+        registry.parse(&events("x\n")),
+        vec![Block::paragraph(vec![Inline::code("high")])]
+    );
+}
+
+#[test]
+fn equal_priorities_keep_registration_order() {
+    let mut registry = ParserRegistry::new();
+    registry
+        .register_inline(Word("x", "first"), ParserOptions::new())
+        .register_inline(Word("x", "second"), ParserOptions::new());
+    assert_eq!(
+        registry.parse(&events("x\n")),
+        vec![Block::paragraph(vec![Inline::code("first")])]
+    );
+}
+
+#[test]
+fn inline_parsers_run_in_tight_list_items() {
+    let mut registry = ParserRegistry::new();
+    registry.register_inline(Word("x", "item"), ParserOptions::new());
+    assert_eq!(
+        registry.parse(&events("- x\n- y\n")),
+        vec![Block::List {
+            start: None,
+            items: vec![
+                vec![Block::paragraph(vec![Inline::code("item")])],
+                vec![Block::paragraph(["y"])],
+            ],
+        }]
+    );
+}
+
+#[test]
+fn within_limits_parsers_to_containers() {
+    let mut registry = ParserRegistry::new();
+    registry.register_block(
+        MarkerRule("---x"),
+        ParserOptions::new().within(|t| matches!(t, Tag::BlockQuote(_))),
+    );
+    let blocks = registry.parse(&events("---x\n\n> ---x\n"));
+    assert!(matches!(blocks[0], Block::Paragraph(_)));
+    assert_eq!(blocks[1], Block::BlockQuote(vec![Block::Rule]));
+
+    let mut registry = ParserRegistry::new();
+    registry.register_inline(
+        Word("x", "cell"),
+        ParserOptions::new().within(|t| matches!(t, Tag::TableCell)),
+    );
+    let blocks = registry.parse(&events("x\n\n| a |\n|---|\n| x |\n"));
+    assert_eq!(blocks[0], Block::paragraph(vec![Inline::text("x")]));
+    match &blocks[1] {
+        Block::Table { rows, .. } => {
+            assert_eq!(rows.last().unwrap()[0], vec![Inline::code("cell")])
+        }
+        other => panic!("expected table, got {:?}", other),
+    }
+}
+
+#[test]
+fn block_parsers_do_not_run_inside_inline_content() {
+    let mut registry = ParserRegistry::new();
+    registry.register_inline(Word("x", "inline"), ParserOptions::new());
+    registry.register_block(MarkerRule("x"), ParserOptions::new().priority(9));
+    assert_eq!(
+        registry.parse(&events("x\n")),
+        vec![Block::Rule],
+        "the block parser claims the paragraph first"
+    );
+}
+
+#[test]
+fn exclusive_parsers_shadow_lower_priorities() {
+    let mut registry = ParserRegistry::new();
+    registry
+        .register_inline(Word("x", "fallback"), ParserOptions::new())
+        .register_inline(
+            Word("y", "quoted"),
+            ParserOptions::new()
+                .priority(1)
+                .within(|t| matches!(t, Tag::BlockQuote(_)))
+                .exclusive(),
+        );
+    // inside the quote the exclusive parser declines "x" and nothing else runs
+    let blocks = registry.parse(&events("x\n\n> x\n"));
+    assert_eq!(blocks[0], Block::paragraph(vec![Inline::code("fallback")]));
+    assert_eq!(
+        blocks[1],
+        Block::BlockQuote(vec![Block::paragraph(vec![Inline::text("x")])])
+    );
+}
+
+#[test]
+fn registry_is_reusable() {
+    let mut registry = ParserRegistry::new();
+    registry.register_inline(Word("x", "code"), ParserOptions::new());
+    let again = registry.clone();
+    for src in ["x\n", "# x\n"] {
+        assert_eq!(registry.parse(&events(src)), again.parse(&events(src)));
+    }
+    assert!(ParserRegistry::new().is_empty());
+}
@@ -0,0 +1,73 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::{Block, Inline, Pipeline, Transform};
+
+fn rewrite_links(blocks: &mut Vec<Block>) {
+    fn walk(inls: &mut [Inline]) {
+        for inl in inls {
+            if let Inline::Link { dest, children, .. } = inl {
+                if let Some(rest) = dest.strip_prefix("http://") {
+                    *dest = format!("https://{}", rest);
+                }
+                walk(children);
+            }
+        }
+    }
+    for b in blocks {
+        if let Block::Paragraph(inls) = b {
+            walk(inls);
+        }
+    }
+}
+
+/// Drops every thematic break.
+struct DropRules;
+impl Transform for DropRules {
+    fn apply(&self, blocks: &mut Vec<Block>) {
+        blocks.retain(|b| !matches!(b, Block::Rule));
+    }
+}
+
+#[test]
+fn transforms_run_in_order() {
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add(|blocks: &mut Vec<Block>| blocks.push(Block::paragraph(vec![Inline::text("one")])))
+        .add(|blocks: &mut Vec<Block>| {
+            if let Some(Block::Paragraph(inls)) = blocks.last_mut() {
+                inls.push(Inline::text(" two"));
+            }
+        });
+    assert_eq!(pipeline.len(), 2);
+    let mut blocks = Vec::new();
+    pipeline.run(&mut blocks);
+    assert_eq!(
+        blocks,
+        vec![Block::paragraph(vec![
+            Inline::text("one"),
+            Inline::text(" two")
+        ])]
+    );
+}
+
+#[test]
+fn pipeline_parses_and_writes() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(rewrite_links).add(DropRules);
+    let events: Vec<Event> = Parser::new("[site](http://example.com)\n\n---\n\nend\n").collect();
+    let blocks = pipeline.parse(&events);
+    assert_eq!(blocks.len(), 2);
+    let md = pipeline.markdown(&events);
+    assert!(md.starts_with("[site](https://example.com)\n"));
+    assert!(!md.contains("---"));
+}
+
+#[test]
+fn empty_pipeline_leaves_blocks_alone() {
+    let pipeline = Pipeline::default();
+    assert!(pipeline.is_empty());
+    let events: Vec<Event> = Parser::new("# hi\n").collect();
+    assert_eq!(
+        pipeline.parse(&events),
+        pulldown_cmark_writer::ast::parse_events_to_blocks(&events)
+    );
+}
@@ -0,0 +1,28 @@
+use pulldown_cmark::{Options, Parser};
+use pulldown_cmark_writer::ast::{blocks_to_plaintext, parse_events_to_blocks};
+
+fn plaintext(src: &str) -> String {
+    let events: Vec<_> = Parser::new_ext(src, Options::all()).collect();
+    blocks_to_plaintext(&parse_events_to_blocks(&events))
+}
+
+#[test]
+fn markup_is_stripped() {
+    let src = "# The *title*\n\nSome **bold** and [linked](https://x.y \"t\") text\nwith `code` and ![an image](i.png).\n\n<div>html</div>\n\n---\n";
+    assert_eq!(
+        plaintext(src),
+        "The title\n\nSome bold and linked text with code and an image."
+    );
+}
+
+#[test]
+fn containers_keep_their_text() {
+    let src = "> quoted\n\n- one\n- two\n  - nested\n\n```rust\nfn main() {}\n```\n";
+    assert_eq!(plaintext(src), "quoted\n\none\ntwo\nnested\n\nfn main() {}");
+}
+
+#[test]
+fn tables_are_tab_separated() {
+    let src = "| a | *b* |\n|---|---|\n| 1 | `2` |\n| 3 | 4 |\n";
+    assert_eq!(plaintext(src), "a\tb\n1\t2\n3\t4");
+}
@@ -0,0 +1,46 @@
+use pulldown_cmark::{Event, Tag};
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::{
+    Block, Inline, block_to_events, blocks_to_markdown, inline_to_events,
+};
+
+#[test]
+fn raw_block_is_written_verbatim() {
+    let raw = Block::Raw(Region::from_str("{% include note.md %}\n*kept* \\as-is"));
+    assert_eq!(
+        blocks_to_markdown(std::slice::from_ref(&raw)),
+        "{% include note.md %}\n*kept* \\as-is\n"
+    );
+
+    // inside a container only the container prefix is added
+    let quoted = Block::BlockQuote(vec![raw]);
+    assert_eq!(
+        blocks_to_markdown(&[quoted]),
+        "> {% include note.md %}\n> *kept* \\as-is\n"
+    );
+}
+
+#[test]
+fn raw_inline_is_written_verbatim() {
+    let p = Block::Paragraph(vec![
+        Inline::Text(Region::from_str("a ")),
+        Inline::Raw(Region::from_str("<kbd>Ctrl</kbd>+**C**")),
+    ]);
+    assert_eq!(blocks_to_markdown(&[p]), "a <kbd>Ctrl</kbd>+**C**\n");
+}
+
+#[test]
+fn raw_nodes_produce_parsed_events() {
+    let evs = block_to_events(&Block::Raw(Region::from_str("# Title")));
+    assert!(matches!(
+        evs.first(),
+        Some(Event::Start(Tag::Heading { .. }))
+    ));
+
+    let evs = inline_to_events(&Inline::Raw(Region::from_str("**bold**")));
+    assert!(matches!(evs.first(), Some(Event::Start(Tag::Strong))));
+    assert!(
+        !evs.iter()
+            .any(|e| matches!(e, Event::Start(Tag::Paragraph)))
+    );
+}
@@ -0,0 +1,71 @@
+use pulldown_cmark_writer::ast::{Block, RenderCache, WriterOptions, blocks_to_markdown};
+
+mod common;
+use common::parse_all as parse;
+
+#[test]
+fn unchanged_blocks_are_reused() {
+    let mut blocks = parse("---\ntitle: x\n---\n\n# Title\n\nfirst\n\n- a\n- b\n\nlast\n");
+    let mut cache = RenderCache::new(WriterOptions::default());
+    assert_eq!(cache.render(&blocks), blocks_to_markdown(&blocks));
+    assert_eq!(cache.rendered_last(), blocks.len());
+
+    blocks[2] = Block::paragraph(["edited"]);
+    assert_eq!(cache.render(&blocks), blocks_to_markdown(&blocks));
+    assert_eq!(cache.rendered_last(), 1);
+
+    assert_eq!(cache.render(&blocks), blocks_to_markdown(&blocks));
+    assert_eq!(cache.rendered_last(), 0);
+}
+
+#[test]
+fn inserted_and_moved_blocks_keep_the_others_cached() {
+    let mut blocks = parse("one\n\ntwo\n\nthree\n");
+    let mut cache = RenderCache::new(WriterOptions::default());
+    cache.render(&blocks);
+
+    blocks.insert(1, Block::Rule);
+    blocks.swap(0, 3);
+    assert_eq!(cache.render(&blocks), blocks_to_markdown(&blocks));
+    assert_eq!(cache.rendered_last(), 1);
+
+    cache.clear();
+    cache.render(&blocks);
+    assert_eq!(cache.rendered_last(), blocks.len());
+}
+
+#[test]
+fn large_documents_match_blocks_by_hash() {
+    let src: String = (0..5000)
+        .map(|i| format!("paragraph {}\n\n", i % 2500))
+        .collect();
+    let mut blocks = parse(&src);
+    let mut cache = RenderCache::new(WriterOptions::default());
+    cache.render(&blocks);
+
+    blocks.reverse();
+    blocks.push(Block::paragraph(["new"]));
+    assert_eq!(cache.render(&blocks), blocks_to_markdown(&blocks));
+    assert_eq!(cache.rendered_last(), 1);
+}
+
+#[test]
+fn definitions_held_as_blocks_are_written_once() {
+    let mut blocks = parse("see [docs][d]\n\n[d]: https://docs.example\n");
+    let definition = Block::LinkDefinition {
+        id: "d".to_string(),
+        dest: "https://docs.example".to_string(),
+        title: String::new(),
+    };
+    let mut cache = RenderCache::new(WriterOptions::default());
+    assert_eq!(cache.render(&blocks), blocks_to_markdown(&blocks));
+
+    // the paragraph leaves its definition to the new block
+    blocks.push(definition);
+    let md = cache.render(&blocks);
+    assert_eq!(md, blocks_to_markdown(&blocks));
+    assert_eq!(md.matches("[d]: https://docs.example").count(), 1);
+
+    blocks.pop();
+    assert_eq!(cache.render(&blocks), blocks_to_markdown(&blocks));
+}
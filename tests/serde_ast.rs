@@ -0,0 +1,156 @@
+#![cfg(feature = "serde")]
+
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use pulldown_cmark_writer::ast::custom::{BlockNode, InlineNode, OpaqueBlock, OpaqueInline};
+use pulldown_cmark_writer::ast::registry::NodeRegistry;
+use pulldown_cmark_writer::ast::{Block, Inline, blocks_to_markdown};
+use pulldown_cmark_writer::{Line, Region};
+use std::any::Any;
+use std::sync::Arc;
+
+mod common;
+use common::parse_all as parse;
+
+#[test]
+fn ast_roundtrips_through_json() {
+    for name in ["complex_mixed.md", "code.md", "tables.md", "lists.md"] {
+        let src = std::fs::read_to_string(format!("src/fixtures/{}", name)).unwrap();
+        let blocks = parse(&src);
+        let json = serde_json::to_string(&blocks).unwrap();
+        let back: Vec<Block> = serde_json::from_str(&json).unwrap();
+        assert_eq!(blocks, back, "json roundtrip mismatch for {}", name);
+    }
+}
+
+#[test]
+fn text_types_serialize_as_strings() {
+    let mut l = Line::new();
+    l.push("a").push("b");
+    assert_eq!(serde_json::to_string(&l).unwrap(), "\"ab\"");
+    let r = Region::from_str("x\ny");
+    assert_eq!(serde_json::to_string(&r).unwrap(), r#"{"lines":["x","y"]}"#);
+}
+
+#[derive(Debug, Clone)]
+struct Kbd(String);
+impl InlineNode for Kbd {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Strong),
+            Event::Text(CowStr::from(self.0.clone())),
+            Event::End(TagEnd::Strong),
+        ]
+    }
+    fn to_line(&self) -> Line {
+        Line::from_str(&format!("<kbd>{}</kbd>", self.0))
+    }
+}
+
+#[test]
+fn custom_nodes_keep_their_output() {
+    let blocks = vec![Block::Paragraph(vec![
+        Inline::Text(Region::from_str("press ")),
+        Inline::Custom(Arc::new(Kbd("Ctrl".to_string()))),
+    ])];
+    let json = serde_json::to_string(&blocks).unwrap();
+    let back: Vec<Block> = serde_json::from_str(&json).unwrap();
+    assert_eq!(blocks, back);
+    assert_eq!(blocks_to_markdown(&back), "press <kbd>Ctrl</kbd>\n");
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Callout {
+    kind: String,
+    text: String,
+}
+impl BlockNode for Callout {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Paragraph),
+            Event::Text(CowStr::from(format!("{}: {}", self.kind, self.text))),
+            Event::End(TagEnd::Paragraph),
+        ]
+    }
+    fn to_region(&self) -> Region {
+        Region::from_str(&format!("{}: {}", self.kind, self.text))
+    }
+}
+
+fn callout_doc() -> Vec<Block> {
+    vec![
+        Block::Custom(Arc::new(Callout {
+            kind: "note".into(),
+            text: "hi".into(),
+        })),
+        Block::Paragraph(vec![Inline::Custom(Arc::new(Kbd("K".into())))]),
+    ]
+}
+
+#[test]
+fn registered_nodes_roundtrip_with_payload() {
+    let mut registry = NodeRegistry::new();
+    registry.register_block::<Callout>("callout");
+
+    let blocks = callout_doc();
+    let json = registry.with(|| serde_json::to_string(&blocks)).unwrap();
+    assert!(json.contains(r#""type":"callout","payload":{"kind":"note","text":"hi"}"#));
+
+    let back: Vec<Block> = registry.with(|| serde_json::from_str(&json)).unwrap();
+    assert_eq!(back, blocks);
+    match &back[0] {
+        Block::Custom(node) => {
+            let any: &dyn Any = &**node;
+            assert_eq!(
+                any.downcast_ref::<Callout>().map(|c| c.kind.as_str()),
+                Some("note")
+            );
+        }
+        other => panic!("expected custom block, got {:?}", other),
+    }
+    // the unregistered inline still comes back as an opaque node
+    match &back[1] {
+        Block::Paragraph(inls) => match &inls[0] {
+            Inline::Custom(node) => {
+                let any: &dyn Any = &**node;
+                assert!(any.is::<OpaqueInline>());
+            }
+            other => panic!("expected custom inline, got {:?}", other),
+        },
+        other => panic!("expected paragraph, got {:?}", other),
+    }
+}
+
+#[test]
+fn payload_is_ignored_without_registry() {
+    let mut registry = NodeRegistry::new();
+    registry.register_block::<Callout>("callout");
+    let json = registry
+        .with(|| serde_json::to_string(&callout_doc()))
+        .unwrap();
+
+    let back: Vec<Block> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, callout_doc());
+    match &back[0] {
+        Block::Custom(node) => {
+            let any: &dyn Any = &**node;
+            assert!(any.is::<OpaqueBlock>());
+        }
+        other => panic!("expected custom block, got {:?}", other),
+    }
+    assert_eq!(blocks_to_markdown(&back[..1]), "note: hi\n");
+}
+
+#[test]
+fn text_types_roundtrip() {
+    use pulldown_cmark_writer::{BigRegion, Fragment};
+    let f = Fragment::from_str("frag");
+    let back: Fragment = serde_json::from_str(&serde_json::to_string(&f).unwrap()).unwrap();
+    assert_eq!(back, f);
+
+    let mut r = Region::from_str("a\nb");
+    r.push_back_suffix_line(Line::from_str("[x]: y"));
+    let json = serde_json::to_string(&r).unwrap();
+    assert_eq!(serde_json::from_str::<Region>(&json).unwrap(), r);
+    let big: BigRegion = serde_json::from_str(&json).unwrap();
+    assert_eq!(big, BigRegion::from(r));
+}
@@ -0,0 +1,68 @@
+use pulldown_cmark::Options;
+use pulldown_cmark_writer::ast::shortcode::{Shortcode, ShortcodeDelimiter, parse_shortcodes};
+use pulldown_cmark_writer::ast::{Block, Inline, blocks_to_markdown};
+use std::any::Any;
+
+fn block(b: &Block) -> &Shortcode {
+    let Block::Custom(c) = b else {
+        panic!("expected a shortcode, got {:?}", b);
+    };
+    let any: &dyn Any = &**c;
+    any.downcast_ref().expect("a shortcode")
+}
+
+fn inline(inl: &Inline) -> &Shortcode {
+    let Inline::Custom(c) = inl else {
+        panic!("expected a shortcode, got {:?}", inl);
+    };
+    let any: &dyn Any = &**c;
+    any.downcast_ref().expect("a shortcode")
+}
+
+#[test]
+fn shortcodes_parse_into_nodes() {
+    let code = Shortcode::parse("{{< figure src=\"a_b.png\" >}}").unwrap();
+    assert_eq!(code.delimiter(), ShortcodeDelimiter::HugoAngle);
+    assert_eq!(code.name(), "figure");
+    assert_eq!(code.args(), "src=\"a_b.png\"");
+    let end = Shortcode::parse("{{% /notice %}}").unwrap();
+    assert_eq!((end.name(), end.is_closing()), ("notice", true));
+    let zola = Shortcode::parse("{{ youtube(id=\"x\") }}").unwrap();
+    assert_eq!((zola.name(), zola.args()), ("youtube", "id=\"x\""));
+    assert!(Shortcode::parse("{% end %}").unwrap().is_closing());
+    assert_eq!(Shortcode::parse("{{ title }}"), None);
+    assert_eq!(Shortcode::parse("{{< a >}} {{< b >}}"), None);
+
+    let blocks = parse_shortcodes(
+        "Hi {{< figure alt=\"_x_ <b>\" >}} and {{% notice %}}*bold*{{% /notice %}}.\n\n{{<youtube id=\"w7\">}}\n\n`{{< code >}}`\n",
+        Options::empty(),
+    );
+    assert_eq!(blocks.len(), 3);
+    let Block::Paragraph(inlines) = &blocks[0] else {
+        panic!("expected a paragraph");
+    };
+    assert_eq!(inlines[0], Inline::text("Hi "));
+    assert_eq!(inline(&inlines[1]).raw(), "{{< figure alt=\"_x_ <b>\" >}}");
+    assert_eq!(inline(&inlines[3]).name(), "notice");
+    assert_eq!(inlines[4], Inline::emphasis([Inline::text("bold")]));
+    assert!(inline(&inlines[5]).is_closing());
+    assert_eq!(block(&blocks[1]).raw(), "{{<youtube id=\"w7\">}}");
+    // shortcodes in code stay code
+    assert_eq!(blocks[2], Block::paragraph([Inline::code("{{< code >}}")]));
+}
+
+#[test]
+fn shortcodes_write_back_verbatim() {
+    let src = concat!(
+        "# Title {{< ref \"a_b_c.md\" >}}\n\n\n",
+        "{{< details summary=\"*Click*\" >}}\n\n\n",
+        "- item {{% badge %}}**new**{{% /badge %}}\n\n\n",
+        "{{< /details >}}\n\n\n",
+        "{% quote(author=\"Ann_B\") %}\n\n\n",
+        "Quoted.\n\n\n",
+        "{% end %}\n",
+    );
+    let blocks = parse_shortcodes(src, Options::empty());
+    assert_eq!(blocks.len(), 7);
+    assert_eq!(blocks_to_markdown(&blocks), src);
+}
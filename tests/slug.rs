@@ -0,0 +1,55 @@
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_writer::ast::{Block, SlugOptions, Slugger, parse_events_to_blocks, slugify};
+
+fn heading(src: &str) -> Block {
+    let events: Vec<Event> = Parser::new(src).collect();
+    parse_events_to_blocks(&events).remove(0)
+}
+
+fn heading_slug(src: &str) -> String {
+    match heading(src) {
+        Block::Heading { children, .. } => slugify(&children),
+        b => panic!("not a heading: {b:?}"),
+    }
+}
+
+#[test]
+fn slugs_follow_github() {
+    assert_eq!(heading_slug("# Hello, *World*!"), "hello-world");
+    assert_eq!(
+        heading_slug("## `snake_case` and kebab-case"),
+        "snake_case-and-kebab-case"
+    );
+    assert_eq!(heading_slug("# a - b"), "a---b");
+    assert_eq!(heading_slug("# Ünïcode 中文"), "ünïcode-中文");
+    assert_eq!(
+        heading_slug("# [Linked](https://x.y) title"),
+        "linked-title"
+    );
+}
+
+#[test]
+fn sluggers_number_repeated_slugs() {
+    let mut slugger = Slugger::default();
+    let slugs: Vec<String> = ["Intro", "Intro", "Intro 1", "Intro"]
+        .iter()
+        .map(|t| slugger.slug_str(t))
+        .collect();
+    // "intro-1" is taken by the second heading, so the third moves on
+    assert_eq!(slugs, ["intro", "intro-1", "intro-1-1", "intro-2"]);
+    slugger.reset();
+    assert_eq!(slugger.slug_str("Intro"), "intro");
+}
+
+#[test]
+fn slug_options_customize_the_algorithm() {
+    let options = SlugOptions {
+        keep_unicode: false,
+        separator: '_',
+        collapse_separators: true,
+        dedupe: false,
+    };
+    assert_eq!(options.slug_str("  Café -- au  lait "), "caf_--_au_lait");
+    let mut slugger = Slugger::new(options);
+    assert_eq!(slugger.slug_str("Same"), slugger.slug_str("Same"));
+}
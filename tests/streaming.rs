@@ -0,0 +1,145 @@
+use pulldown_cmark::{MetadataBlockKind, Options};
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::{
+    Block, MarkdownWriter, WriterOptions, blocks_to_markdown, blocks_to_markdown_into,
+    write_blocks, write_blocks_io,
+};
+use std::io;
+
+mod common;
+
+fn parse(src: &str) -> Vec<Block> {
+    common::parse_with(src, Options::ENABLE_TABLES)
+}
+
+const SRC: &str = "# Title\n\n> quoted\n\n- a\n- b\n\n| x | y |\n|---|---|\n| 1 | 2 |\n";
+
+#[test]
+fn streamed_output_matches_blocks_to_markdown() {
+    let blocks = parse(SRC);
+    let mut out = String::new();
+    write_blocks(&mut out, &blocks, &WriterOptions::default()).unwrap();
+    assert_eq!(out, blocks_to_markdown(&blocks));
+
+    let mut bytes = Vec::new();
+    write_blocks_io(&mut bytes, &blocks, &WriterOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), out);
+}
+
+#[test]
+fn blank_lines_between_blocks_are_configurable() {
+    let blocks = parse("a\n\nb\n");
+    let mut out = String::new();
+    let options = WriterOptions {
+        blank_lines: 1,
+        ..Default::default()
+    };
+    write_blocks(&mut out, &blocks, &options).unwrap();
+    assert_eq!(out, "a\n\nb\n");
+}
+
+#[test]
+fn io_errors_are_passed_through() {
+    struct Full;
+    impl io::Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::StorageFull, "full"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let err = write_blocks_io(&mut Full, &parse(SRC), &WriterOptions::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+}
+
+#[test]
+fn markdown_writer_emits_blocks_as_they_come() {
+    let blocks = parse(
+        "# Title\n\nSee [the docs][d].\n\n> Also [the docs][d] and [more][m].\n\n[d]: /docs\n[m]: /more\n",
+    );
+    let mut writer = MarkdownWriter::new(
+        String::new(),
+        WriterOptions {
+            blank_lines: 1,
+            ..Default::default()
+        },
+    );
+    writer.push_block(&blocks[0]).unwrap();
+    writer.push_block(&blocks[1]).unwrap();
+    assert_eq!(writer.pending_definitions(), 1);
+    writer.push_block(&blocks[2]).unwrap();
+    assert_eq!(writer.pending_definitions(), 2);
+    let md = writer.finish().unwrap();
+    assert_eq!(
+        md,
+        "# Title\n\nSee [the docs][d].\n\n> Also [the docs][d] and [more][m].\n\n[d]: /docs\n[m]: /more\n"
+    );
+    assert_eq!(parse(&md), blocks);
+}
+
+#[test]
+fn incremental_definitions_match_labels_without_case() {
+    let blocks = parse("See [the docs][D].\n\nAlso [the docs][d].\n\n[d]: /docs\n");
+    let mut writer = MarkdownWriter::new(String::new(), WriterOptions::default());
+    for b in &blocks {
+        writer.push_block(b).unwrap();
+    }
+    assert_eq!(writer.pending_definitions(), 1);
+    assert_eq!(
+        writer.finish().unwrap(),
+        "See [the docs][D].\n\n\nAlso [the docs][d].\n\n\n[D]: /docs\n"
+    );
+}
+
+#[test]
+fn large_documents_keep_block_order() {
+    // large enough for `blocks_to_markdown` to render in parallel with the
+    // `rayon` feature
+    let src: String = (0..300)
+        .map(|i| format!("{SRC}\nparagraph {i}\n\n"))
+        .collect();
+    let mut blocks = parse(&src);
+    // front matter is hoisted above the rest
+    blocks.push(Block::Metadata {
+        kind: MetadataBlockKind::YamlStyle,
+        content: Region::from_str("title: x"),
+    });
+    let mut out = String::new();
+    write_blocks(&mut out, &blocks, &WriterOptions::default()).unwrap();
+    assert_eq!(blocks_to_markdown(&blocks), out);
+    assert!(out.starts_with("---\ntitle: x\n---\n"));
+}
+
+#[test]
+fn large_documents_write_definitions_like_small_ones() {
+    // the definitions are blocks of the document, which its paragraphs
+    // leave to them, in parallel too
+    let src = "See [the docs][D] and [more][m].\n\n".repeat(80) + "[d]: /docs\n[m]: /more\n";
+    let mut blocks = parse(&src);
+    for (id, dest) in [("d", "/docs"), ("m", "/more")] {
+        blocks.push(Block::LinkDefinition {
+            id: id.into(),
+            dest: dest.into(),
+            title: String::new(),
+        });
+    }
+    let mut out = String::new();
+    write_blocks(&mut out, &blocks, &WriterOptions::default()).unwrap();
+    assert_eq!(blocks_to_markdown(&blocks), out);
+    assert_eq!(out.matches("[d]: /docs").count(), 1);
+}
+
+#[test]
+fn buffers_can_be_reused() {
+    let blocks = parse(SRC);
+    let mut buf = String::from("stale");
+    buf.clear();
+    blocks_to_markdown_into(&blocks, &mut buf);
+    assert_eq!(buf, blocks_to_markdown(&blocks));
+
+    let region = Region::from_str("a\nb");
+    let mut buf = String::from("> ");
+    region.apply_into(&mut buf);
+    assert_eq!(buf, "> a\nb");
+}
@@ -0,0 +1,184 @@
+use pulldown_cmark::{Alignment, Event, Options, Tag};
+use pulldown_cmark_writer::ast::{
+    Block, CellKey, Inline, SortOrder, WriterOptions, block_to_events, blocks_to_markdown,
+    parse_events_to_blocks, write_blocks,
+};
+
+mod common;
+
+fn parse(src: &str) -> Vec<Block> {
+    common::parse_with(src, Options::ENABLE_TABLES)
+}
+
+#[test]
+fn header_is_kept_apart_from_body_rows() {
+    let blocks = parse("| a | b |\n|---|---|\n| 1 | 2 |\n| 3 | 4 |\n");
+    match &blocks[0] {
+        Block::Table { head, rows, .. } => {
+            assert_eq!(
+                head,
+                &vec![vec![Inline::text("a")], vec![Inline::text("b")]]
+            );
+            assert_eq!(rows.len(), 2);
+            assert_eq!(rows[0][0], vec![Inline::text("1")]);
+        }
+        other => panic!("expected a table, got {:?}", other),
+    }
+}
+
+#[test]
+fn tables_roundtrip_through_events() {
+    let src = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+    let blocks = parse(src);
+    let events = block_to_events(&blocks[0]);
+    assert_eq!(events[1], Event::Start(Tag::TableHead));
+    assert_eq!(parse_events_to_blocks(&events), blocks);
+    assert_eq!(parse(&blocks_to_markdown(&blocks)), blocks);
+}
+
+#[test]
+fn aligned_narrow_columns_keep_a_dash_next_to_their_colons() {
+    let src = "| a | b | c |\n|:-|-:|:-:|\n| d | e | f |\n";
+    let blocks = parse(src);
+    let out = blocks_to_markdown(&blocks);
+    assert_eq!(out, "a  |  b |  c \n:- | -: | :-:\nd  |  e |  f \n");
+    assert_eq!(parse(&out), blocks);
+}
+
+#[test]
+fn dropping_the_header_keeps_every_body_row() {
+    let mut blocks = parse("| a | b |\n|---|---|\n| 1 | 2 |\n");
+    if let Block::Table { head, .. } = &mut blocks[0] {
+        head.clear();
+    }
+    let md = blocks_to_markdown(&blocks);
+    match &parse(&md)[0] {
+        Block::Table { head, rows, .. } => {
+            assert!(head.iter().all(|cell| cell.is_empty()));
+            assert_eq!(
+                rows,
+                &vec![vec![vec![Inline::text("1")], vec![Inline::text("2")]]]
+            );
+        }
+        other => panic!("expected a table, got {:?}", other),
+    }
+}
+
+#[test]
+fn single_column_tables_stay_tables() {
+    let blocks = parse("| a |\n|---|\n| 1 |\n");
+    let md = blocks_to_markdown(&blocks);
+    assert_eq!(md, "| a |\n| - |\n| 1 |\n");
+    assert_eq!(parse(&md), blocks);
+}
+
+fn grid(blocks: &[Block]) -> String {
+    let options = WriterOptions {
+        grid_tables: true,
+        ..Default::default()
+    };
+    let mut out = String::new();
+    write_blocks(&mut out, blocks, &options).unwrap();
+    out
+}
+
+#[test]
+fn multi_line_cells_make_grid_tables() {
+    let blocks = vec![Block::Table {
+        aligns: vec![Alignment::None, Alignment::Right],
+        head: vec![vec![Inline::text("a")], vec![Inline::text("b")]],
+        rows: vec![vec![
+            vec![Inline::text("1")],
+            vec![
+                Inline::text("two"),
+                Inline::HardBreak,
+                Inline::text("lines"),
+            ],
+        ]],
+    }];
+    assert_eq!(
+        grid(&blocks),
+        "+---+-------+\n\
+         | a |     b |\n\
+         +===+======:+\n\
+         | 1 |  two\\ |\n\
+         |   | lines |\n\
+         +---+-------+\n"
+    );
+}
+
+#[test]
+fn single_line_cells_stay_pipe_tables() {
+    let blocks = parse("| a | b |\n|---|---|\n| 1 | 2 |\n");
+    assert_eq!(grid(&blocks), blocks_to_markdown(&blocks));
+}
+
+#[test]
+fn rows_sort_by_column() {
+    let mut blocks = parse(
+        "| crate | time |\n|---|---|\n| b | 10 ms |\n| a | 9.5 ms |\n| c | n/a |\n| d | 1,024 ms |\n",
+    );
+    let column = |blocks: &[Block], i: usize| match &blocks[0] {
+        Block::Table { rows, .. } => rows
+            .iter()
+            .map(|r| r[i].iter().map(|inl| format!("{inl}")).collect::<String>())
+            .collect::<Vec<_>>(),
+        other => panic!("expected a table, got {:?}", other),
+    };
+
+    blocks[0].sort_by_column(1, SortOrder::Ascending, CellKey::numeric);
+    assert_eq!(column(&blocks, 0), ["a", "b", "d", "c"]);
+
+    blocks[0].sort_by_column(1, SortOrder::Descending, CellKey::text);
+    assert_eq!(column(&blocks, 1), ["n/a", "9.5 ms", "10 ms", "1,024 ms"]);
+
+    blocks[0].sort_by_column(0, SortOrder::Ascending, |cell| cell.len());
+    assert_eq!(column(&blocks, 0), ["c", "a", "b", "d"]);
+}
+
+#[test]
+fn cell_keys_are_equal_when_they_sort_equal() {
+    let keys: Vec<CellKey> = ["-0", "0", "0", "1,024", "1024", "n/a"]
+        .into_iter()
+        .map(|t| CellKey::numeric(&[Inline::text(t)]))
+        .collect();
+    for a in &keys {
+        for b in &keys {
+            assert_eq!(a == b, a.cmp(b) == std::cmp::Ordering::Equal, "{a:?} {b:?}");
+        }
+    }
+    assert_eq!(keys[1], keys[2]);
+    assert_ne!(keys[0], keys[1]);
+}
+
+#[test]
+fn tables_from_rows() {
+    let table = Block::table_from_rows([
+        vec!["crate", "version"],
+        vec!["serde", "1.0"],
+        vec!["rayon"],
+    ]);
+    assert_eq!(
+        blocks_to_markdown(&[table]),
+        "crate | version\n----- | -------\nserde | 1.0    \nrayon |        \n"
+    );
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn tables_from_csv_and_tsv() {
+    let table = Block::table_from_csv("name,notes\nfoo,\"a|b\nc\"\n".as_bytes(), true).unwrap();
+    assert_eq!(
+        blocks_to_markdown(&[table]),
+        "name | notes \n---- | ------\nfoo  | a\\|b c\n"
+    );
+
+    let table = Block::table_from_tsv("1\t2\n3\t4\n".as_bytes(), false).unwrap();
+    match table {
+        Block::Table { head, rows, .. } => {
+            assert!(head.is_empty());
+            assert_eq!(rows.len(), 2);
+        }
+        other => panic!("expected a table, got {:?}", other),
+    }
+}
@@ -0,0 +1,309 @@
+use pulldown_cmark::{Alignment, Event, Parser};
+use pulldown_cmark_writer::ast::transform::{
+    EmbedImages, HeadingOverflow, IncludeError, IncludeOptions, LinkKind, NormalizeOptions,
+    TemplateEscape, embed_images, include, normalize, renumber_footnotes, rewrite_links,
+    shift_headings, substitute,
+};
+use pulldown_cmark_writer::ast::{Block, Inline, blocks_to_markdown, parse_events_to_blocks};
+use std::collections::HashMap;
+
+mod common;
+use common::parse;
+
+const SRC: &str = "# One\n\n> ## Two\n\n###### Six\n";
+
+#[test]
+fn headings_shift_at_any_depth() {
+    let mut blocks = parse(SRC);
+    shift_headings(&mut blocks, 1, HeadingOverflow::Clamp).unwrap();
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "## One\n\n\n> ### Two\n\n\n###### Six\n"
+    );
+    shift_headings(&mut blocks, -4, HeadingOverflow::Clamp).unwrap();
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "# One\n\n\n> # Two\n\n\n## Six\n"
+    );
+}
+
+#[test]
+fn overflowing_headings_follow_the_policy() {
+    let mut blocks = parse(SRC);
+    shift_headings(&mut blocks, 1, HeadingOverflow::Bold).unwrap();
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "## One\n\n\n> ### Two\n\n\n**Six**\n"
+    );
+
+    let mut blocks = parse(SRC);
+    let err = shift_headings(&mut blocks, 5, HeadingOverflow::Error).unwrap_err();
+    assert_eq!(err.level, 7);
+    assert_eq!(err.to_string(), "heading shifted to level 7, past h6");
+    assert_eq!(blocks, parse(SRC));
+}
+
+#[test]
+fn link_destinations_are_rewritten() {
+    let src = "> [a](docs/a.md) and ![b](img/b.png)\n\n- [c][ref] <https://x.example>\n\n[ref]: docs/c.md\n";
+    let mut blocks = parse(src);
+    // parsing resolves references; a definition block is built by hand
+    blocks.push(Block::LinkDefinition {
+        id: "extra".into(),
+        dest: "docs/d.md".into(),
+        title: String::new(),
+    });
+    let mut seen = Vec::new();
+    rewrite_links(&mut blocks, |dest, kind| {
+        seen.push(kind);
+        match kind {
+            LinkKind::Image => Some(format!("https://cdn.example/{dest}")),
+            _ if dest.starts_with("docs/") => Some(dest.replace(".md", ".html")),
+            _ => None,
+        }
+    });
+    assert_eq!(
+        seen,
+        [
+            LinkKind::Link,
+            LinkKind::Image,
+            LinkKind::Link,
+            LinkKind::Link,
+            LinkKind::Definition,
+        ]
+    );
+    let out = blocks_to_markdown(&blocks);
+    assert!(out.contains("[a](docs/a.html)"), "{out}");
+    assert!(out.contains("![b](https://cdn.example/img/b.png)"), "{out}");
+    assert!(out.contains("<https://x.example>"), "{out}");
+    assert!(out.contains("[ref]: docs/c.html"), "{out}");
+    assert!(out.contains("[extra]: docs/d.html"), "{out}");
+    assert!(!out.contains(".md"), "{out}");
+}
+
+fn definition(id: &str) -> Block {
+    Block::LinkDefinition {
+        id: id.into(),
+        dest: format!("https://{id}.example"),
+        title: String::new(),
+    }
+}
+
+#[test]
+fn normalize_gives_a_canonical_form() {
+    let mut blocks = vec![
+        definition("b"),
+        Block::Paragraph(vec![
+            Inline::text("one "),
+            Inline::text(""),
+            Inline::text("two"),
+            Inline::Emphasis(vec![]),
+            Inline::Strong(vec![Inline::Strong(vec![Inline::text("x")])]),
+        ]),
+        Block::BlockQuote(vec![
+            Block::Paragraph(vec![Inline::text("")]),
+            Block::TableRow(vec![vec![Inline::text("a")], vec![Inline::text("b")]]),
+        ]),
+        definition("A"),
+    ];
+    normalize(&mut blocks, NormalizeOptions::default());
+    assert_eq!(
+        blocks[0],
+        Block::Paragraph(vec![
+            Inline::text("one two"),
+            Inline::Strong(vec![Inline::text("x")]),
+        ])
+    );
+    let Block::BlockQuote(quote) = &blocks[1] else {
+        panic!("expected a block quote");
+    };
+    assert_eq!(quote.len(), 1);
+    assert!(
+        matches!(&quote[0], Block::Table { aligns, rows, .. } if aligns.len() == 2 && rows.len() == 1)
+    );
+    assert_eq!(blocks[2..], [definition("A"), definition("b")]);
+
+    assert_eq!(
+        blocks_to_markdown(&quote[..]),
+        "|   |   |\n| - | - |\n| a | b |\n"
+    );
+}
+
+#[test]
+fn normalize_steps_can_be_turned_off() {
+    let original = vec![Block::Paragraph(vec![Inline::text("a"), Inline::text("b")])];
+    let mut blocks = original.clone();
+    let options = NormalizeOptions {
+        merge_text: false,
+        ..NormalizeOptions::default()
+    };
+    normalize(&mut blocks, options);
+    assert_eq!(blocks, original);
+}
+
+#[test]
+fn table_pieces_are_gathered() {
+    let row = |s: &str| vec![vec![Inline::text(s)]];
+    let mut blocks = vec![
+        Block::TablePlaceholder(vec![Alignment::Left]),
+        Block::TableHead(row("h")),
+        Block::TableRow(row("1")),
+        Block::TableRow(row("2")),
+        Block::TableHead(row("next")),
+        Block::Rule,
+    ];
+    normalize(&mut blocks, NormalizeOptions::default());
+    assert_eq!(
+        blocks,
+        [
+            Block::Table {
+                aligns: vec![Alignment::Left],
+                head: row("h"),
+                rows: vec![row("1"), row("2")],
+            },
+            Block::Table {
+                aligns: vec![Alignment::None],
+                head: row("next"),
+                rows: Vec::new(),
+            },
+            Block::Rule,
+        ]
+    );
+}
+
+#[test]
+fn footnotes_are_renumbered_in_reference_order() {
+    let src = "Second[^b] then first[^A] and again[^b].\n\n> quoted[^z]\n\n[^a]: First note.\n\n[^b]: Second note.\n\n[^unused]: Never cited.\n\n[^z]: Quoted note.\n";
+    let events: Vec<Event> = Parser::new_ext(src, pulldown_cmark::Options::all()).collect();
+    let mut blocks = parse_events_to_blocks(&events);
+    renumber_footnotes(&mut blocks);
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "Second[^1] then first[^2] and again[^1].\n\n\n> quoted[^3]\n\n\n[^1]: Second note.\n\n\n[^2]: First note.\n\n\n[^3]: Quoted note.\n\n\n[^4]: Never cited.\n"
+    );
+}
+
+#[test]
+fn placeholders_are_substituted() {
+    let mut blocks = parse(
+        "# Hi {{ first_name }}\n\nSee [docs]({{base}}/docs) and `{{cmd}}`, {{missing}}.\n\n```\n{{cmd}} --help\n```\n",
+    );
+    let vars = HashMap::from([
+        ("first_name", "*Ada*"),
+        ("base", "https://example.com"),
+        ("cmd", "run"),
+    ]);
+    let unresolved = substitute(&mut blocks, &vars, TemplateEscape::Markdown);
+    assert_eq!(unresolved, vec!["missing".to_string()]);
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "# Hi \\*Ada\\*\n\n\nSee [docs](https://example.com/docs) and `run`, {{missing}}.\n\n\n```\nrun --help\n```\n"
+    );
+
+    let mut blocks = parse("{{a}} & {{{a}}}\n");
+    let vars = HashMap::from([("a".to_string(), "<b>".to_string())]);
+    assert!(substitute(&mut blocks, &vars, TemplateEscape::Html).is_empty());
+    assert_eq!(blocks_to_markdown(&blocks), "&lt;b&gt; & {&lt;b&gt;}\n");
+}
+
+#[test]
+fn includes_are_resolved_in_place() {
+    let files = HashMap::from([
+        ("intro.md", "Intro.\n\n<!-- include: note.md -->\n"),
+        ("note.md", "*Note.*\n"),
+        ("loop.md", "<!-- include: loop.md -->\n"),
+    ]);
+    let load = |path: &str| {
+        files
+            .get(path)
+            .map(|src| parse(src))
+            .ok_or_else(|| format!("no such file: {path}"))
+    };
+
+    let mut blocks = parse("# Doc\n\n> <!-- include: intro.md -->\n\nEnd.\n");
+    include(&mut blocks, &IncludeOptions::default(), load).unwrap();
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "# Doc\n\n\n> Intro.\n>\n> *Note.*\n\n\nEnd.\n"
+    );
+
+    let mut blocks = parse("<!-- include: loop.md -->\n");
+    match include(&mut blocks, &IncludeOptions::default(), load) {
+        Err(IncludeError::Cycle { chain }) => assert_eq!(chain, ["loop.md", "loop.md"]),
+        other => panic!("expected a cycle, got {other:?}"),
+    }
+
+    let options = IncludeOptions {
+        max_depth: 1,
+        ..IncludeOptions::default()
+    };
+    let mut blocks = parse("<!-- include: intro.md -->\n");
+    assert!(matches!(
+        include(&mut blocks, &options, load),
+        Err(IncludeError::TooDeep { target, .. }) if target == "note.md"
+    ));
+
+    let mut blocks = parse("<!-- include: gone.md -->\n");
+    let err = include(&mut blocks, &IncludeOptions::default(), load).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot include gone.md: no such file: gone.md"
+    );
+}
+
+#[test]
+fn images_are_embedded_or_copied() {
+    let src = "![a](img/a.png) ![b](https://example.com/b.png)\n\n![again](img/a.png) ![c](other/a.png?v=2)\n";
+    let load = |dest: &str| -> std::io::Result<Option<Vec<u8>>> {
+        Ok(match dest {
+            "img/a.png" => Some(b"hi!".to_vec()),
+            "other/a.png?v=2" => Some(b"hi".to_vec()),
+            _ => None,
+        })
+    };
+
+    let mut blocks = parse(src);
+    let found = embed_images(&mut blocks, &EmbedImages::DataUri, load).unwrap();
+    assert_eq!(
+        found,
+        ["img/a.png", "https://example.com/b.png", "other/a.png?v=2"]
+    );
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "![a](data:image/png;base64,aGkh) ![b](https://example.com/b.png)\n\n\n![again](data:image/png;base64,aGkh) ![c](data:image/png;base64,aGk=)\n"
+    );
+
+    let dir = std::env::temp_dir().join(format!("embed-images-{}", std::process::id()));
+    let mode = EmbedImages::CopyTo {
+        dir: dir.clone(),
+        prefix: "assets/".into(),
+    };
+    let mut blocks = parse(src);
+    embed_images(&mut blocks, &mode, load).unwrap();
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "![a](assets/a.png) ![b](https://example.com/b.png)\n\n\n![again](assets/a.png) ![c](assets/a-1.png)\n"
+    );
+    assert_eq!(std::fs::read(dir.join("a.png")).unwrap(), b"hi!");
+    assert_eq!(std::fs::read(dir.join("a-1.png")).unwrap(), b"hi");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reference_images_take_their_definition_along() {
+    let load = |dest: &str| -> std::io::Result<Option<Vec<u8>>> {
+        Ok((dest == "img/a.png").then(|| b"hi!".to_vec()))
+    };
+    let mut blocks = parse("![logo][Logo] is [the logo][logo].\n\n[logo]: img/a.png\n");
+    blocks.push(Block::LinkDefinition {
+        id: "logo".into(),
+        dest: "img/a.png".into(),
+        title: String::new(),
+    });
+    let found = embed_images(&mut blocks, &EmbedImages::DataUri, load).unwrap();
+    assert_eq!(found, ["img/a.png"]);
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "![logo][Logo] is [the logo][logo].\n\n\n[logo]: data:image/png;base64,aGkh\n"
+    );
+}
@@ -0,0 +1,128 @@
+use pulldown_cmark::{Alignment, HeadingLevel, LinkType};
+use pulldown_cmark_writer::ast::{Block, Inline, Issue, validate};
+
+mod common;
+use common::parse_all as parse;
+
+fn heading(id: &str) -> Block {
+    Block::Heading {
+        level: HeadingLevel::H2,
+        id: Some(id.into()),
+        classes: Vec::new(),
+        attrs: Vec::new(),
+        children: vec!["Title".into()],
+    }
+}
+
+fn link(link_type: LinkType, id: &str, dest: &str) -> Inline {
+    Inline::Link {
+        link_type,
+        dest: dest.into(),
+        title: String::new(),
+        id: id.into(),
+        children: vec!["text".into()],
+    }
+}
+
+#[test]
+fn parsed_documents_are_valid() {
+    let src = "# Intro {#intro}\n\nSee [docs](https://docs.example)[^n].\n\n[^n]: A note.\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+    assert_eq!(validate(&parse(src)), []);
+}
+
+#[test]
+fn footnotes_must_pair_up() {
+    let blocks = vec![
+        Block::paragraph([Inline::FootnoteReference("Missing".into())]),
+        Block::BlockQuote(vec![Block::FootnoteDefinition(
+            "unused".into(),
+            vec![Block::paragraph(["x"])],
+        )]),
+        Block::paragraph([Inline::FootnoteReference("Used".into())]),
+        Block::FootnoteDefinition("used".into(), vec![Block::paragraph(["y"])]),
+    ];
+    assert_eq!(
+        validate(&blocks),
+        [
+            Issue::MissingFootnote {
+                label: "Missing".into(),
+                path: vec![0],
+            },
+            Issue::UnusedFootnote {
+                label: "unused".into(),
+                path: vec![1, 0],
+            },
+        ]
+    );
+}
+
+#[test]
+fn headings_links_and_tables_are_checked() {
+    let blocks = vec![
+        heading("intro"),
+        Block::List {
+            start: None,
+            items: vec![vec![heading("intro")]],
+        },
+        Block::Paragraph(vec![
+            link(LinkType::Reference, "nowhere", ""),
+            link(LinkType::Reference, "defined", ""),
+            link(LinkType::Shortcut, "carried", "https://x.example"),
+            link(LinkType::Inline, "", ""),
+        ]),
+        Block::LinkDefinition {
+            id: "Defined".into(),
+            dest: "https://d.example".into(),
+            title: String::new(),
+        },
+        Block::Table {
+            aligns: vec![Alignment::None; 2],
+            head: vec![vec!["a".into()], vec!["b".into()]],
+            rows: vec![vec![vec!["1".into()]; 3]],
+        },
+    ];
+    let issues = validate(&blocks);
+    assert_eq!(
+        issues,
+        [
+            Issue::DuplicateHeadingId {
+                id: "intro".into(),
+                path: vec![1, 0, 0],
+            },
+            Issue::UnknownReference {
+                id: "nowhere".into(),
+                path: vec![2],
+            },
+            Issue::EmptyDestination { path: vec![2] },
+            Issue::TableColumns {
+                path: vec![4],
+                row: 1,
+                expected: 2,
+                found: 3,
+            },
+        ]
+    );
+    assert_eq!(issues[3].path(), [4]);
+    assert_eq!(
+        issues[3].to_string(),
+        "[4]: table row 1 has 3 cells, expected 2"
+    );
+}
+
+#[test]
+fn heading_ids_collide_with_generated_slugs() {
+    let src = "# Setup\n\n## Install {#setup}\n\n## Usage {#notes}\n\n## Notes\n";
+    assert_eq!(
+        validate(&parse(src)),
+        [
+            Issue::DuplicateHeadingId {
+                id: "setup".into(),
+                path: vec![1],
+            },
+            Issue::DuplicateHeadingId {
+                id: "notes".into(),
+                path: vec![3],
+            },
+        ]
+    );
+}
@@ -0,0 +1,138 @@
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use pulldown_cmark_writer::Region;
+use pulldown_cmark_writer::ast::{
+    Block, BlockNode, Edit, Inline, VisitMut, Visitor, blocks_to_markdown, parse_events_to_blocks,
+    transform_blocks, transform_inlines, walk_block, walk_block_mut, walk_blocks, walk_blocks_mut,
+    walk_inline, walk_inline_mut,
+};
+use std::sync::Arc;
+
+mod common;
+use common::parse_all as parse;
+
+#[derive(Default)]
+struct Links(Vec<String>);
+
+impl Visitor for Links {
+    fn visit_inline(&mut self, inl: &Inline) {
+        if let Inline::Link { dest, .. } = inl {
+            self.0.push(dest.clone());
+        }
+        walk_inline(self, inl);
+    }
+}
+
+#[test]
+fn links_are_found_at_any_depth() {
+    let src = "[a](1)\n\n> - [b](2)\n>   - *[c](3)*\n\n| x |\n|---|\n| [d](4) |\n\nnote[^n]\n\n[^n]: [e](5)\n";
+    let mut links = Links::default();
+    walk_blocks(&mut links, &parse(src));
+    assert_eq!(links.0, ["1", "2", "3", "4", "5"]);
+}
+
+/// Counts blocks, but does not look inside block quotes.
+#[derive(Default)]
+struct Shallow {
+    paragraphs: usize,
+}
+
+impl Visitor for Shallow {
+    fn visit_block(&mut self, b: &Block) {
+        match b {
+            Block::BlockQuote(_) => {}
+            Block::Paragraph(_) => self.paragraphs += 1,
+            _ => walk_block(self, b),
+        }
+    }
+}
+
+#[test]
+fn overrides_decide_whether_to_descend() {
+    let mut v = Shallow::default();
+    walk_blocks(&mut v, &parse("one\n\n> two\n\n- three\n"));
+    assert_eq!(v.paragraphs, 2);
+}
+
+#[derive(Debug, Clone)]
+struct Card;
+impl BlockNode for Card {
+    fn to_events(&self) -> Vec<Event<'static>> {
+        vec![
+            Event::Start(Tag::Paragraph),
+            Event::Start(Tag::Link {
+                link_type: pulldown_cmark::LinkType::Inline,
+                dest_url: CowStr::from("card"),
+                title: CowStr::from(""),
+                id: CowStr::from(""),
+            }),
+            Event::Text(CowStr::from("open")),
+            Event::End(TagEnd::Link),
+            Event::End(TagEnd::Paragraph),
+        ]
+    }
+    fn to_region(&self) -> Region {
+        Region::from_str("[open](card)")
+    }
+}
+
+#[test]
+fn custom_nodes_are_walked_through_their_events() {
+    let mut links = Links::default();
+    walk_blocks(&mut links, &[Block::Custom(Arc::new(Card))]);
+    assert_eq!(links.0, ["card"]);
+}
+
+/// Deepens headings, unwraps emphasis, drops images and puts a rule after
+/// every top-level heading.
+struct Edits;
+
+impl VisitMut for Edits {
+    fn visit_block_mut(&mut self, b: &mut Block) -> Edit<Block> {
+        walk_block_mut(self, b);
+        match b {
+            Block::Heading { level, .. } => {
+                *level = pulldown_cmark::HeadingLevel::H2;
+                Edit::Replace(vec![b.clone(), Block::Rule])
+            }
+            _ => Edit::Keep,
+        }
+    }
+
+    fn visit_inline_mut(&mut self, inl: &mut Inline) -> Edit<Inline> {
+        walk_inline_mut(self, inl);
+        match inl {
+            Inline::Emphasis(children) => Edit::Replace(std::mem::take(children)),
+            Inline::Image { .. } => Edit::Remove,
+            _ => Edit::Keep,
+        }
+    }
+}
+
+#[test]
+fn mutable_visits_edit_siblings_in_place() {
+    let mut blocks = parse("# Title\n\nSome *very **bold*** text ![x](y.png)\n\n> # Quoted\n");
+    walk_blocks_mut(&mut Edits, &mut blocks);
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "## Title\n\n\n---\n\n\nSome very **bold** text \n\n\n> ## Quoted\n>\n> ---\n"
+    );
+}
+
+#[test]
+fn transforms_map_nodes_to_replacements() {
+    let mut blocks = parse("<div>x</div>\n\n> <p>y</p>\n>\n> kept `code`\n");
+    blocks.push(Block::Custom(Arc::new(Card)));
+    transform_blocks(&mut blocks, |b| match b {
+        Block::HtmlBlock(_) => Some(vec![]),
+        Block::Custom(node) => Some(parse_events_to_blocks(&node.to_events())),
+        _ => None,
+    });
+    transform_inlines(&mut blocks, |inl| match inl {
+        Inline::Code(r) => Some(vec![Inline::Text(r.clone())]),
+        _ => None,
+    });
+    assert_eq!(
+        blocks_to_markdown(&blocks),
+        "> kept code\n\n\n[open](card)\n"
+    );
+}
@@ -0,0 +1,148 @@
+use pulldown_cmark::{CodeBlockKind, Event, Parser};
+use pulldown_cmark_writer::ast::{
+    Block, Inline, WriteContext, WriterOptions, parse_events_to_blocks, write_blocks,
+};
+use pulldown_cmark_writer::{Line, Region};
+
+fn write(src: &str, options: &WriterOptions) -> String {
+    let events: Vec<Event> = Parser::new(src).collect();
+    let mut out = String::new();
+    write_blocks(&mut out, &parse_events_to_blocks(&events), options).unwrap();
+    out
+}
+
+fn tildes(b: &Block, _: &WriteContext) -> Option<Region> {
+    match b {
+        Block::CodeBlock {
+            kind: CodeBlockKind::Fenced(lang),
+            content,
+        } => {
+            let mut r = Region::new();
+            r.push_back_line(Line::from_str(&format!("~~~{}", lang)));
+            for l in content.apply().lines() {
+                r.push_back_line(Line::from_str(l));
+            }
+            r.push_back_line(Line::from_str("~~~"));
+            Some(r)
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn block_overrides_replace_matching_blocks_at_any_depth() {
+    let options = WriterOptions {
+        blank_lines: 1,
+        ..Default::default()
+    }
+    .override_block(tildes);
+    assert_eq!(
+        write(
+            "```rust\nlet x = 1;\n```\n\n> ```\n> quoted\n> ```\n",
+            &options
+        ),
+        "~~~rust\nlet x = 1;\n~~~\n\n> ~~~\n> quoted\n> ~~~\n"
+    );
+}
+
+#[test]
+fn first_matching_override_wins() {
+    let options = WriterOptions::new()
+        .override_block(|b, _| matches!(b, Block::Rule).then(|| Region::from_str("***")))
+        .override_block(|_, _| Some(Region::from_str("never for rules")));
+    assert_eq!(write("---\n", &options), "***\n");
+}
+
+#[test]
+fn inline_overrides_see_the_nesting_depth() {
+    let options = WriterOptions::new().override_inline(|i, ctx| match i {
+        Inline::Emphasis(children) => Some(Line::from_str(&format!(
+            "_{}_@{}",
+            children.iter().map(|c| c.to_string()).collect::<String>(),
+            ctx.depth
+        ))),
+        _ => None,
+    });
+    assert_eq!(write("*a*\n\n> *b*\n", &options), "_a_@0\n\n\n> _b_@1\n");
+}
+
+#[test]
+fn context_tracks_lists_quotes_and_cells() {
+    fn describe(i: &Inline, ctx: &WriteContext) -> Option<Line> {
+        match i {
+            Inline::Code(_) => Some(Line::from_str(&format!(
+                "quote={} items={:?} cell={}",
+                ctx.inside_blockquote,
+                ctx.list_stack
+                    .iter()
+                    .map(|l| (l.start, l.index))
+                    .collect::<Vec<_>>(),
+                ctx.table_cell
+            ))),
+            _ => None,
+        }
+    }
+    let options = WriterOptions::new().override_inline(describe);
+    let md = write("1. a\n2. > - `x`\n", &options);
+    assert!(md.contains("quote=true items=[(Some(1), 1), (None, 0)] cell=false"));
+
+    let events: Vec<Event> =
+        Parser::new_ext("| `x` |\n|---|\n", pulldown_cmark::Options::ENABLE_TABLES).collect();
+    let mut out = String::new();
+    write_blocks(&mut out, &parse_events_to_blocks(&events), &options).unwrap();
+    assert!(out.contains("quote=false items=[] cell=true"));
+}
+
+#[test]
+fn custom_nodes_render_with_the_context() {
+    use pulldown_cmark_writer::ast::custom::BlockNode;
+    use std::sync::Arc;
+
+    #[derive(Clone, Debug)]
+    struct Fence;
+    impl BlockNode for Fence {
+        fn to_events(&self) -> Vec<Event<'static>> {
+            Vec::new()
+        }
+        fn to_region(&self) -> Region {
+            Region::from_str("```\ncode\n```")
+        }
+        fn to_region_in(&self, ctx: &WriteContext) -> Region {
+            // indented code is shorter inside list items
+            if ctx.list_stack.is_empty() {
+                self.to_region()
+            } else {
+                Region::from_str("    code")
+            }
+        }
+    }
+
+    let doc = vec![
+        Block::Custom(Arc::new(Fence)),
+        Block::List {
+            start: None,
+            items: vec![vec![
+                Block::paragraph(["item"]),
+                Block::Custom(Arc::new(Fence)),
+            ]],
+        },
+    ];
+    let mut out = String::new();
+    write_blocks(&mut out, &doc, &WriterOptions::new()).unwrap();
+    assert_eq!(out, "```\ncode\n```\n\n\n- item\n\n      code\n");
+}
+
+#[test]
+fn code_tabs_are_kept_or_expanded() {
+    let src = "```\nfn f() {\n\tx\ta\n}\n```\n";
+    assert_eq!(write(src, &WriterOptions::default()), src);
+    let options = WriterOptions {
+        code_tab_width: Some(4),
+        ..Default::default()
+    };
+    assert_eq!(
+        write("- item\n\n  ```\n  \tx\n  ```\n", &options),
+        "- item\n\n  ```\n      x\n  ```\n"
+    );
+    assert_eq!(write(src, &options), "```\nfn f() {\n    x   a\n}\n```\n");
+}